@@ -0,0 +1,135 @@
+use crate::log_entry::LogEntry;
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use streaming_iterator::StreamingIterator;
+
+// bytes are those read from the inputs, whether or not their entries pass the filters, while
+// entries are those coming out of the filters
+pub struct Throughput {
+    entries: usize,
+    bytes: u64,
+    elapsed: Duration,
+}
+
+impl Throughput {
+    pub fn measure(
+        mut log_entries: impl StreamingIterator<Item = LogEntry>,
+        input_bytes: &Cell<u64>,
+    ) -> Self {
+        let start = Instant::now();
+        let mut entries = 0;
+
+        while log_entries.next().is_some() {
+            entries += 1;
+        }
+
+        Throughput {
+            entries,
+            bytes: input_bytes.get(),
+            elapsed: start.elapsed(),
+        }
+    }
+
+    pub fn entries_per_sec(&self) -> f64 {
+        self.entries as f64 / self.elapsed_secs()
+    }
+
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.elapsed_secs()
+    }
+
+    fn elapsed_secs(&self) -> f64 {
+        self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes read, {} entries output in {:.3}s ({:.0} bytes/s, {:.0} entries/s)",
+            self.bytes,
+            self.entries,
+            self.elapsed.as_secs_f64(),
+            self.bytes_per_sec(),
+            self.entries_per_sec()
+        )
+    }
+}
+
+// passes all reads through, adding the number of bytes read to the count (if any)
+pub struct CountingReader<R> {
+    inner: R,
+    count: Option<Rc<Cell<u64>>>,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R, count: Option<Rc<Cell<u64>>>) -> Self {
+        CountingReader { inner, count }
+    }
+
+    fn add(&self, bytes: usize) {
+        if let Some(count) = &self.count {
+            count.set(count.get() + bytes as u64);
+        }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.add(bytes_read);
+        Ok(bytes_read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.add(amt);
+        self.inner.consume(amt);
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eol;
+    use crate::log_entry_reader::LogEntryReader;
+
+    #[test]
+    fn throughput_positive() {
+        let input: Vec<u8> = (0..1000)
+            .flat_map(|i| {
+                let level = if i % 2 == 0 { "info" } else { "debug" };
+                format!(
+                    "-{}:<1> 2020-01-01 20:00:00.000 UTC [A]: Text{}\n\n",
+                    level, i
+                )
+                .into_bytes()
+            })
+            .collect();
+        let input_bytes = Rc::new(Cell::new(0));
+        let reader = CountingReader::new(input.as_slice(), Some(input_bytes.clone()));
+        let entries = LogEntryReader::new(reader, eol::EOL)
+            .filter(|entry| entry.contents().starts_with(b"-info"));
+        let throughput = Throughput::measure(entries, &input_bytes);
+
+        assert_eq!(throughput.entries, 500);
+        assert_eq!(throughput.bytes, input.len() as u64);
+        assert!(throughput.entries_per_sec() > 0.0);
+        assert!(throughput.bytes_per_sec() > 0.0);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::eol;
+use crate::error::Error;
+use crate::log_entry::LogEntry;
+use crate::log_entry_reader::EntrySeparator;
+use crate::pattern::Pattern;
+use crate::result::Result;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use streaming_iterator::StreamingIterator;
+
+pub const DEFAULT_MEMORY_LIMIT: usize = 512 * 1024 * 1024;
+
+// line ending every entry in a spill file, so that entries containing blank lines, e.g. read with
+// a custom separator, are read back whole
+const SPILL_SEPARATOR: &[u8] = b"\x1E";
+
+static SPILL_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub enum Buffered {
+    Memory(Vec<LogEntry>),
+    Spilled(SpillFile),
+}
+
+// temporary file holding entries which did not fit in memory, removed when dropped
+pub struct SpillFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl SpillFile {
+    fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "riolog-{}-{}.spill",
+            std::process::id(),
+            SPILL_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| Error::CannotCreateFile(path.clone(), e))?;
+        Ok(SpillFile { file, path })
+    }
+}
+
+impl Read for SpillFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for SpillFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// collects entries in memory, moving all of them to a temporary file
+// once they do not fit within the limit
+pub fn buffer_entries(
+    mut log_entries: impl StreamingIterator<Item = LogEntry>,
+    limit: usize,
+) -> Result<Buffered> {
+    let mut entries = Vec::new();
+    let mut size = 0;
+
+    while let Some(entry) = log_entries.next() {
+        size += entry.contents().len();
+        if size > limit {
+            let mut spill_file = SpillFile::create()?;
+            {
+                let mut writer = BufWriter::new(&mut spill_file.file);
+                for entry in entries.iter().chain(std::iter::once(entry)) {
+                    write_spilled(entry, &mut writer)?;
+                }
+                while let Some(entry) = log_entries.next() {
+                    write_spilled(entry, &mut writer)?;
+                }
+                writer.flush()?;
+            }
+            spill_file.seek(SeekFrom::Start(0))?;
+            return Ok(Buffered::Spilled(spill_file));
+        }
+        entries.push(entry.clone());
+    }
+
+    Ok(Buffered::Memory(entries))
+}
+
+// writes entries to a temporary file, positioned at the beginning to read them back
+pub fn spill_entries(entries: &[LogEntry]) -> Result<SpillFile> {
+    let mut spill_file = SpillFile::create()?;
+    {
+        let mut writer = BufWriter::new(&mut spill_file.file);
+        for entry in entries {
+            write_spilled(entry, &mut writer)?;
+        }
+        writer.flush()?;
+    }
+    spill_file.seek(SeekFrom::Start(0))?;
+    Ok(spill_file)
+}
+
+// separator of entries to read a spill file with
+pub fn spill_separator() -> EntrySeparator {
+    EntrySeparator::Line(Pattern::new("^\x1E$").expect("Invalid spill separator pattern"))
+}
+
+// entries are written with a separator line after them so that they can be read back
+fn write_spilled(entry: &LogEntry, writer: &mut impl Write) -> Result<()> {
+    writer.write_all(eol::trim_eol(entry.contents()))?;
+    writer.write_all(eol::EOL)?;
+    writer.write_all(SPILL_SEPARATOR)?;
+    writer.write_all(eol::EOL)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG_INPUT: &[&[u8]] = &[
+        b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1",
+        b"-info:<16866> 2020-01-01 21:00:00.000 UTC [A]: Text2",
+    ];
+
+    fn to_log_iter() -> impl StreamingIterator<Item = LogEntry> {
+        streaming_iterator::convert(
+            LOG_INPUT
+                .iter()
+                .map(|lines| LogEntry::from_contents(lines.to_vec())),
+        )
+    }
+
+    #[test]
+    fn buffer_entries_within_limit() -> Result<()> {
+        match buffer_entries(to_log_iter(), 1024)? {
+            Buffered::Memory(entries) => assert_eq!(entries, LOG_INPUT.to_vec()),
+            Buffered::Spilled(_) => panic!("Expected entries buffered in memory"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn buffer_entries_over_limit() -> Result<()> {
+        match buffer_entries(to_log_iter(), 60)? {
+            Buffered::Memory(_) => panic!("Expected entries spilled to a file"),
+            Buffered::Spilled(mut spill_file) => {
+                let path = spill_file.path.clone();
+                let mut contents = Vec::new();
+                spill_file.read_to_end(&mut contents)?;
+                let expected: Vec<u8> = LOG_INPUT
+                    .iter()
+                    .flat_map(|entry| [*entry, eol::EOL, SPILL_SEPARATOR, eol::EOL].concat())
+                    .collect();
+                assert_eq!(contents, expected);
+
+                drop(spill_file);
+                assert!(!path.exists());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+use crate::cli::parse_date_time_arg;
+use crate::direction::Direction;
+use crate::error::Error;
+use crate::log_entry::LogEntry;
+use crate::result::Result;
+use chrono::NaiveDateTime;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use streaming_iterator::StreamingIterator;
+
+const CHECKPOINT_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+// The newest timestamp written and the number of entries written with it, which tells apart
+// the entries sharing it that were written from those appended later. A date/time given
+// without the number stands for all the entries with it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub timestamp: NaiveDateTime,
+    pub count: usize,
+}
+
+// Reads a date/time bound from the file, e.g. saved by a previous run. A missing or empty file
+// is no bound, so that the first run of a checkpointed pipeline reads the whole log. Contents
+// that cannot be parsed are an invalid value of given option.
+pub fn read_bound(path: &Path, arg: &'static str) -> Result<Option<NaiveDateTime>> {
+    Ok(read_position(path, arg)?.map(|position| position.timestamp))
+}
+
+// like `read_bound`, also with the number of entries written with the timestamp on the next line
+pub fn read_position(path: &Path, arg: &'static str) -> Result<Option<Position>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::CannotOpenFile(path.to_path_buf(), e)),
+    };
+    let mut lines = contents.trim().lines().map(str::trim);
+    let timestamp = match lines.next() {
+        Some(line) => parse_date_time_arg(line).ok_or(Error::InvalidCliOptionValue(arg))?,
+        None => return Ok(None),
+    };
+    let count = match lines.next() {
+        Some(line) => line
+            .parse()
+            .map_err(|_| Error::InvalidCliOptionValue(arg))?,
+        None => usize::MAX,
+    };
+    Ok(Some(Position { timestamp, count }))
+}
+
+// The position of the newest entry written, saved to a file on exit so that the next run can
+// continue from there with --since-file. Starting from the position read by --since-file keeps
+// counting the entries with its timestamp that were written by the previous runs.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    newest: Cell<Option<Position>>,
+    recorded: Cell<bool>,
+}
+
+impl Checkpoint {
+    pub fn new(path: PathBuf) -> Self {
+        Checkpoint {
+            path,
+            newest: Cell::new(None),
+            recorded: Cell::new(false),
+        }
+    }
+
+    pub fn with_start(self, start: Option<Position>) -> Self {
+        self.newest.set(start);
+        self
+    }
+
+    pub fn record(&self, timestamp: Option<NaiveDateTime>) {
+        let timestamp = match timestamp {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+        self.recorded.set(true);
+        let newest = match self.newest.get() {
+            Some(newest) if newest.timestamp == timestamp => Position {
+                count: newest.count.saturating_add(1),
+                ..newest
+            },
+            Some(newest) if newest.timestamp > timestamp => newest,
+            _ => Position {
+                timestamp,
+                count: 1,
+            },
+        };
+        self.newest.set(Some(newest));
+    }
+
+    // leaves the file as it is when no entry with a timestamp was written
+    pub fn save(&self) -> Result<()> {
+        if let (Some(newest), true) = (self.newest.get(), self.recorded.get()) {
+            let mut contents = format!("{}\n", newest.timestamp.format(CHECKPOINT_FORMAT));
+            if newest.count < usize::MAX {
+                contents.push_str(&format!("{}\n", newest.count));
+            }
+            fs::write(&self.path, contents)
+                .map_err(|e| Error::CannotCreateFile(self.path.clone(), e))?;
+        }
+        Ok(())
+    }
+}
+
+// Drops the entries already written according to the position read by --since-file: those older
+// than it and the first ones of its timestamp in the input order. Reading newest first, these are
+// the last ones, so the entries from the first one not newer than the position on are held until
+// the input ends.
+pub struct SkipWritten<I> {
+    input: I,
+    position: Option<Position>,
+    direction: Direction,
+    skipped: usize,
+    holding: bool,
+    held: VecDeque<LogEntry>,
+    current: Option<LogEntry>,
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> SkipWritten<I> {
+    pub fn new(input: I, position: Option<Position>, direction: Direction) -> Self {
+        SkipWritten {
+            input,
+            position,
+            direction,
+            skipped: 0,
+            holding: false,
+            held: VecDeque::new(),
+            current: None,
+        }
+    }
+
+    fn hold_rest(&mut self, position: Position) {
+        let mut rest = Vec::new();
+        rest.extend(self.input.get().cloned());
+        while let Some(entry) = self.input.next() {
+            rest.push(entry.clone());
+        }
+        // the oldest first
+        for entry in rest.into_iter().rev() {
+            if !is_written(position, &mut self.skipped, &entry) {
+                self.held.push_front(entry);
+            }
+        }
+        self.holding = true;
+    }
+}
+
+fn is_written(position: Position, skipped: &mut usize, entry: &LogEntry) -> bool {
+    match entry.timestamp() {
+        Some(timestamp) if timestamp < position.timestamp => true,
+        Some(timestamp) if timestamp == position.timestamp && *skipped < position.count => {
+            *skipped += 1;
+            true
+        }
+        _ => false,
+    }
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for SkipWritten<I> {
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        let position = match self.position {
+            Some(position) => position,
+            None => return self.input.advance(),
+        };
+        if !self.holding {
+            match self.direction {
+                Direction::Forward => loop {
+                    self.input.advance();
+                    match self.input.get() {
+                        Some(entry) if is_written(position, &mut self.skipped, entry) => {}
+                        _ => return,
+                    }
+                },
+                Direction::Reverse => {
+                    self.input.advance();
+                    match self.input.get() {
+                        Some(entry)
+                            if entry
+                                .timestamp()
+                                .is_some_and(|timestamp| timestamp <= position.timestamp) =>
+                        {
+                            self.hold_rest(position)
+                        }
+                        _ => return,
+                    }
+                }
+            }
+        }
+        self.current = self.held.pop_front();
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.holding {
+            self.current.as_ref()
+        } else {
+            self.input.get()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn checkpoint_round_trip() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("riolog-{}-checkpoint.txt", std::process::id()));
+        assert_eq!(read_bound(&path, "since-file")?, None);
+
+        let checkpoint = Checkpoint::new(path.clone());
+        checkpoint.save()?;
+        assert!(!path.exists());
+
+        let newest = NaiveDate::from_ymd(2020, 1, 1).and_hms_milli(20, 0, 1, 250);
+        checkpoint.record(Some(newest));
+        checkpoint.record(None);
+        checkpoint.record(Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(20, 0, 0)));
+        checkpoint.record(Some(newest));
+        checkpoint.save()?;
+        let saved = fs::read_to_string(&path)?;
+        let bound = read_bound(&path, "since-file")?;
+        let position = read_position(&path, "since-file")?;
+
+        fs::write(&path, "2020-01-01 20:00:01.250\n")?;
+        let without_count = read_position(&path, "since-file")?;
+        fs::write(&path, "2020-01-01 20:00:01.250\nmany\n")?;
+        let invalid_count = read_position(&path, "since-file");
+
+        fs::write(&path, " \n")?;
+        let empty = read_bound(&path, "since-file")?;
+        fs::write(&path, "yesterday")?;
+        let invalid = read_bound(&path, "since-file");
+        fs::remove_file(&path)?;
+
+        assert_eq!(saved, "2020-01-01 20:00:01.250\n2\n");
+        assert_eq!(bound, Some(newest));
+        assert_eq!(
+            position,
+            Some(Position {
+                timestamp: newest,
+                count: 2
+            })
+        );
+        assert_eq!(
+            without_count.map(|position| position.count),
+            Some(usize::MAX)
+        );
+        assert!(matches!(
+            invalid_count,
+            Err(Error::InvalidCliOptionValue("since-file"))
+        ));
+        assert_eq!(empty, None);
+        assert!(matches!(
+            invalid,
+            Err(Error::InvalidCliOptionValue("since-file"))
+        ));
+        Ok(())
+    }
+
+    fn skip_written(input: &[&'static [u8]], direction: Direction) -> Vec<Vec<u8>> {
+        let entries = input
+            .iter()
+            .map(|entry| LogEntry::from_contents(entry.to_vec()));
+        let position = Position {
+            timestamp: NaiveDate::from_ymd(2020, 1, 1).and_hms_milli(20, 0, 1, 250),
+            count: 2,
+        };
+        SkipWritten::new(
+            streaming_iterator::convert(entries),
+            Some(position),
+            direction,
+        )
+        .map(|entry| entry.contents().to_vec())
+        .cloned()
+        .collect()
+    }
+
+    const OLDER: &[u8] = b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Older\n\n";
+    const FIRST: &[u8] = b"-info:<1> 2020-01-01 20:00:01.250 UTC [A]: First\n\n";
+    const SECOND: &[u8] = b"-info:<1> 2020-01-01 20:00:01.250 UTC [A]: Second\n\n";
+    const THIRD: &[u8] = b"-info:<1> 2020-01-01 20:00:01.250 UTC [A]: Third\n\n";
+    const NEWER: &[u8] = b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: Newer\n\n";
+
+    #[test]
+    fn skip_written_entries_of_saved_timestamp() {
+        assert_eq!(
+            skip_written(&[OLDER, FIRST, SECOND, THIRD, NEWER], Direction::Forward),
+            vec![THIRD.to_vec(), NEWER.to_vec()]
+        );
+    }
+
+    #[test]
+    fn skip_written_entries_of_saved_timestamp_rev() {
+        assert_eq!(
+            skip_written(&[NEWER, THIRD, SECOND, FIRST, OLDER], Direction::Reverse),
+            vec![NEWER.to_vec(), THIRD.to_vec()]
+        );
+        assert_eq!(
+            skip_written(&[NEWER, b"No header\n\n"], Direction::Reverse),
+            vec![NEWER.to_vec(), b"No header\n\n".to_vec()]
+        );
+    }
+}
@@ -1,42 +1,384 @@
-use crate::error::Error::InvalidCliOptionValue;
-use crate::log_entry::LogLevel;
+use crate::checkpoint::{read_bound, read_position, Checkpoint, Position};
+use crate::error::Error::{self, InvalidCliOptionValue};
+use crate::formatting::EscapeTable;
+use crate::interrupt::Interrupt;
+use crate::json_lines::JsonFields;
+use crate::limit::ByteLimit;
+use crate::log_entry::{LevelMap, LogLevel};
+use crate::log_entry_reader::EntrySeparator;
+use crate::notify::LevelNotifier;
+use crate::order::OrderCheck;
+use crate::pattern::Pattern;
 use crate::result::Result;
-use chrono::{NaiveDate, NaiveDateTime};
+use crate::resume::{ResumeToken, ResumeTracker};
+use crate::summary::RateSummary;
+use crate::template::Template;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 use clap::{App, Arg, crate_version};
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 const ARG_FILE_NAMES: &str = "FILE";
+const ARG_FILES_FROM: &str = "files-from";
 const ARG_COLOR: &str = "color";
+const ARG_COLOR_BY: &str = "color-by";
+const ARG_COLOR_BY_SOURCE: &str = "color-by-source";
+const ARG_KEEP_ANSI: &str = "keep-ansi";
+const ARG_ENCODING: &str = "encoding";
 const ARG_FORMATTING: &str = "formatting";
+const ARG_ESCAPE_MODE: &str = "escape-mode";
+const ARG_ESCAPE_CHAR: &str = "escape-char";
+const ARG_ESCAPE_MAP: &str = "escape-map";
 const ARG_PAGER: &str = "pager";
 const ARG_WRAP: &str = "wrap";
 const ARG_OUTPUT: &str = "output";
+const ARG_OUTPUT_APPEND: &str = "output-append";
+const ARG_TEE: &str = "tee";
+const ARG_EXEC: &str = "exec";
+const ADDRESS_PATTERN: &str = "0x[0-9a-fA-F]+";
+const ADDRESS_MASK: &str = "0xADDR";
 const ARG_VALUES_TRUE: [&str; 3] = ["yes", "true", "on"];
 const ARG_VALUES_FALSE: [&str; 3] = ["no", "false", "off"];
 const ARG_SINCE: &str = "since";
 const ARG_UNTIL: &str = "until";
+const ARG_SINCE_FILE: &str = "since-file";
+const ARG_UNTIL_FILE: &str = "until-file";
+const ARG_WRITE_UNTIL_FILE: &str = "write-until-file";
+const ARG_TIME_RESOLUTION: &str = "time-resolution";
+const ARG_MAX_AGE: &str = "max-age";
+const ARG_SCAN_WINDOW: &str = "scan-window";
+const ARG_LEVEL_MAP: &str = "level-map";
+const ARG_INPUT_FORMAT: &str = "input-format";
+const ARG_JSONL_FIELDS: &str = "jsonl-fields";
+const ARG_JSONL_STRICT: &str = "jsonl-strict";
+const ARG_SPLIT_ON_HEADER: &str = "split-on-header";
+const ARG_SEPARATOR: &str = "separator";
 const ARG_LEVEL: &str = "level";
+const ARG_LEVEL_FOR: &str = "level-for";
 const ARG_CONTAINS: &str = "contains";
+const ARG_CATEGORY_REGEX: &str = "category-regex";
+const ARG_BEFORE_CONTEXT: &str = "before-context";
+const ARG_AFTER_CONTEXT: &str = "after-context";
+const ARG_CONTEXT: &str = "context";
+const ARG_CONTEXT_SEPARATOR: &str = "context-separator";
+const ARG_CONTEXT_TIME: &str = "context-time";
+const ARG_NO_CONTEXT_SEPARATOR: &str = "no-context-separator";
+const ARG_DISTINCT_BY: &str = "distinct-by";
+const ARG_KV_FILTER: &str = "kv-filter";
+const ARG_WELL_FORMED_ONLY: &str = "well-formed-only";
+const ARG_CONTAINS_UNICODE: &str = "contains-unicode";
 const ARG_REVERSE: &str = "reverse";
+const ARG_OUTPUT_FORMAT: &str = "format";
+const ARG_JSON_SHAPE: &str = "json-shape";
+const ARG_PARSE_EMBEDDED_JSON: &str = "parse-embedded-json";
+const ARG_EXTRACT_KV: &str = "extract-kv";
+const ARG_FILL_TIMESTAMPS: &str = "fill-timestamps";
+const ARG_ANNOTATE_NEXT_GAP: &str = "annotate-next-gap";
+const ARG_JOIN_CONTINUATIONS: &str = "join-continuations";
+const ARG_HISTOGRAM: &str = "histogram";
+const ARG_WINDOW_STATS: &str = "window-stats";
+const ARG_CARDINALITY: &str = "cardinality";
+const ARG_CARDINALITY_LIST: &str = "cardinality-list";
+const ARG_COUNT_MATCHES: &str = "count-matches";
+const ARG_LIST_CATEGORIES: &str = "list-categories";
+const ARG_ONLY: &str = "only";
+const ARG_ONLY_BLANK_MISSING: &str = "only-blank-missing";
+const ARG_INTERACTIVE: &str = "interactive";
+const ARG_PROGRESS_BAR: &str = "progress-bar";
+const ARG_BENCHMARK: &str = "benchmark";
+const ARG_SELFCHECK: &str = "selfcheck";
+const ARG_EXPLAIN_FILTERS: &str = "explain-filters";
+const ARG_EXPLAIN: &str = "explain";
+const ARG_QUIET: &str = "quiet";
+const ARG_DRY_RUN: &str = "dry-run";
+const ARG_FROM_PATTERN: &str = "from-pattern";
+const ARG_PAIR_REGEX: &str = "pair-regex";
+const ARG_MIN_DURATION: &str = "min-duration";
+const ARG_TO_PATTERN: &str = "to-pattern";
+const ARG_ALIGN: &str = "align";
+const ARG_SUMMARY_LINE: &str = "summary-line";
+const ARG_MARK_RESTARTS: &str = "mark-restarts";
+const ARG_FLAG_ESCALATION: &str = "flag-escalation";
+const ARG_RESTART_PATTERN: &str = "restart-pattern";
+const ARG_CUT_AFTER: &str = "cut-after";
+const ARG_REPLACE: &str = "replace";
+const ARG_DIFFABLE: &str = "diffable";
+const ARG_COMPACT_TIME: &str = "compact-time";
+const ARG_MASK_ADDRESSES: &str = "mask-addresses";
+const ARG_HOST_FROM: &str = "host-from";
+const ARG_EMPHASIZE_AFTER_GAP: &str = "emphasize-after-gap";
+const ARG_COLLAPSE_TRACES: &str = "collapse-traces";
+const ARG_FOLD_MARKERS: &str = "fold-markers";
+const ARG_FLATTEN: &str = "flatten";
+const ARG_MESSAGE_ONLY_OUTPUT: &str = "message-only-output";
+const ARG_SHOW_SIZE: &str = "show-size";
+const ARG_DEBUG_PARSE: &str = "debug-parse";
+const ARG_MAX_LINES_PER_ENTRY: &str = "max-lines-per-entry";
+const ARG_WRAP_COLUMNS: &str = "wrap-columns";
+const ARG_FOLLOW: &str = "follow";
+const ARG_IDLE_MARKER: &str = "idle-marker";
+const ARG_RATE_SUMMARY: &str = "rate-summary";
+const ARG_NOTIFY_ON_LEVEL: &str = "notify-on-level";
+const ARG_LAST_PER_LEVEL: &str = "last-per-level";
+const ARG_SORT_BY: &str = "sort-by";
+const ARG_HEAD: &str = "head";
+const ARG_TAIL: &str = "tail";
+const ARG_LIMIT_BYTES: &str = "limit-bytes";
+const ARG_MERGE_PRECISION: &str = "merge-precision";
+const ARG_MERGE_DEDUPE: &str = "merge-dedupe";
+const ARG_VERIFY_ORDER: &str = "verify-order";
+const ARG_STRICT: &str = "strict";
+const ARG_INDEX: &str = "index";
+const ARG_RESUME: &str = "resume";
+const ARG_SAVE_RESUME_TOKEN: &str = "save-resume-token";
+const ARG_MEMORY_LIMIT: &str = "memory-limit";
+const ARG_MAX_ENTRY_SIZE: &str = "max-entry-size";
+const ARG_AT_OFFSET: &str = "at-offset";
+const ARG_AT_ENTRY: &str = "at-entry";
+const ARG_REVERSE_BUFFER: &str = "reverse-buffer";
+const ARG_TEMPLATE: &str = "template";
+const ARG_TEMPLATE_FALLBACK: &str = "template-fallback";
 
-#[derive(Clone)]
+const DEFAULT_CONTEXT_SEPARATOR: &str = "--";
+const DEFAULT_FLATTEN_MARKER: &str = "⏎";
+
+#[derive(Clone, Default)]
 pub struct Options {
-    pub color_enabled: bool,
-    pub formatting_enabled: bool,
     pub pager: bool,
     pub wrap: bool,
     pub reverse: bool,
+    pub reverse_buffer: bool,
+    pub memory_limit: Option<usize>,
+    pub max_entry_size: Option<usize>,
+    pub follow: bool,
+    pub idle_marker: Option<Duration>,
+    pub rate_summary: Option<Rc<RateSummary>>,
+    pub notifier: Option<Rc<LevelNotifier>>,
+    pub interrupt: Interrupt,
+    pub entry_selector: Option<EntrySelector>,
+    pub last_per_level: Option<usize>,
+    pub sort_by: Option<SortKey>,
+    pub head: Option<usize>,
+    pub tail: Option<usize>,
+    pub merge_precision: Option<Duration>,
+    pub merge_dedupe: bool,
+    pub verify_order: bool,
+    pub strict: bool,
+    pub order_check: Option<Rc<OrderCheck>>,
+    pub index: bool,
+    // numbers of entries of each level in the input, if known from indexes
+    pub level_counts: Option<[usize; LogLevel::ALL.len()]>,
+    pub resume: ResumeToken,
+    pub resume_token_file: Option<PathBuf>,
+    pub resume_tracker: Option<Rc<ResumeTracker>>,
+    pub since_position: Option<Position>,
+    pub level_map: Rc<LevelMap>,
+    pub input_format: InputFormat,
+    pub jsonl_fields: JsonFields,
+    pub jsonl_strict: bool,
+    pub separator: EntrySeparator,
+    pub explain_filters: bool,
+    pub explain: bool,
+    // Suppresses everything written to stderr except errors, which end the program. The rest is
+    // informational, even if explicitly requested (e.g. --explain-filters), and may be silenced
+    // at once for scripts. The --benchmark report is its only output, so both conflict.
+    pub quiet: bool,
+    pub dry_run: bool,
+    pub benchmark: bool,
+    // bytes read from the inputs, counted for the --benchmark report
+    pub input_bytes: Option<Rc<Cell<u64>>>,
+    pub selfcheck: bool,
+    pub progress_bar: bool,
+    pub histogram: Option<Duration>,
+    pub window_stats: Option<Duration>,
+    pub cardinality: Option<DistinctField>,
+    pub cardinality_list: bool,
+    pub count_matches: bool,
+    pub list_categories: bool,
+    pub only: Option<OnlyField>,
+    pub only_blank_missing: bool,
+    pub interactive: bool,
     pub filtering_options: FilteringOptions,
+    pub transforming_options: TransformingOptions,
+    pub output_options: OutputOptions,
     pub input_files: Vec<PathBuf>,
     pub output_file: Option<PathBuf>,
+    pub output_append: bool,
+    pub exec: Option<String>,
+    pub tee_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntrySelector {
+    Offset(u64),
+    Number(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistinctField {
+    Level,
+    Category,
+    Pid,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnlyField {
+    Timestamps,
+    Levels,
+    Categories,
+    Messages,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Timestamp,
+    Level,
+    Category,
+    Size,
+}
+
+#[derive(Clone, Default)]
 pub struct FilteringOptions {
     pub since: Option<NaiveDateTime>,
     pub until: Option<NaiveDateTime>,
+    pub time_resolution: TimeResolution,
     pub contains: Option<String>,
+    pub contains_unicode: bool,
     pub min_level: Option<LogLevel>,
+    pub category_levels: HashMap<Vec<u8>, LogLevel>,
+    pub from_pattern: Option<Pattern>,
+    pub to_pattern: Option<Pattern>,
+    pub well_formed_only: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub context_separator: Option<String>,
+    pub context_time: Option<Duration>,
+    pub distinct_by: Option<DistinctField>,
+    pub kv_filters: Vec<(String, String)>,
+    pub max_age: Option<Duration>,
+    pub category_pattern: Option<Pattern>,
+    pub scan_window: Option<Duration>,
+    pub pair_pattern: Option<Pattern>,
+    pub min_duration: Option<Duration>,
+}
+
+#[derive(Clone, Default)]
+pub struct TransformingOptions {
+    pub fill_timestamps: bool,
+    pub annotate_next_gap: bool,
+    pub join_continuations: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorBy {
+    #[default]
+    Level,
+    Category,
+    Pid,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EscapeMode {
+    #[default]
+    None,
+    Standard,
+    Aggressive,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FoldMarkers {
+    #[default]
+    None,
+    Vim,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TimeResolution {
+    Seconds,
+    #[default]
+    Millis,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InputFormat {
+    #[default]
+    Native,
+    JsonLines,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Passthrough,
+    Utf8Lossy,
+    Latin1,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    JsonPretty,
+    Tsv,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum JsonShape {
+    #[default]
+    Flat,
+    Nested,
+}
+
+#[derive(Clone, Default)]
+pub struct OutputOptions {
+    pub color_enabled: bool,
+    pub escape_mode: EscapeMode,
+    pub escape_table: EscapeTable,
+    pub color_by: ColorBy,
+    pub color_by_source: bool,
+    pub keep_ansi: bool,
+    pub encoding: Encoding,
+    pub format: OutputFormat,
+    pub json_shape: JsonShape,
+    pub parse_embedded_json: bool,
+    pub extract_kv: Vec<String>,
+    pub max_lines_per_entry: Option<usize>,
+    pub wrap_columns: Option<usize>,
+    pub collapse_traces: bool,
+    pub fold_markers: FoldMarkers,
+    pub flatten: Option<String>,
+    pub align: bool,
+    pub summary_line: bool,
+    pub emphasize_after_gap: Option<Duration>,
+    pub mark_restarts: bool,
+    pub flag_escalation: bool,
+    pub restart_pattern: Option<Pattern>,
+    pub cut_pattern: Option<Pattern>,
+    pub replacements: Vec<(Pattern, String)>,
+    pub diffable: bool,
+    pub compact_time: bool,
+    pub host_pattern: Option<Pattern>,
+    pub message_only_output: bool,
+    pub show_size: bool,
+    pub debug_parse: bool,
+    pub template: Option<Template>,
+    pub template_fallback: String,
+    pub byte_limit: Option<Rc<ByteLimit>>,
+    pub checkpoint: Option<Rc<Checkpoint>>,
+    pub interrupt: Interrupt,
 }
 
 impl Options {
@@ -46,19 +388,50 @@ impl Options {
             .about("RIO log filter & viewer")
             .arg(
                 Arg::with_name(ARG_FILE_NAMES)
-                    .help("path to a log file(s). With no FILE, or when FILE is -, read standard input")
+                    .help("path to a log file(s). With no FILE, or when FILE is -, read standard input. Standard input given together with other files is merged with them chronologically")
                     .index(1)
                     .multiple(true),
             )
+            .arg(Arg::with_name(ARG_FILES_FROM)
+                .long(ARG_FILES_FROM)
+                .value_name("LIST")
+                .help("read paths of log files to read from LIST, one per line, or from standard input when LIST is -. Blank lines and lines starting with # are skipped. The paths are read after any FILE"))
             .arg(Arg::with_name(ARG_COLOR)
                 .long(ARG_COLOR)
                 .short("c")
-                .value_name("BOOLEAN")
-                .help("turn on/off colorized output. Default: enabled for interactive mode, disabled for file output mode (-o)"))
+                .value_name("WHEN")
+                .help("colorize output: auto (when writing to a terminal, not to a file given by -o), always or never. Boolean values turn it on/off, like always and never. Default: auto"))
+            .arg(Arg::with_name(ARG_COLOR_BY)
+                .long(ARG_COLOR_BY)
+                .value_name("FIELD")
+                .help("choose what entries are colored by: level, category or pid. Every category or pid gets its own stable color, entries without it are not colored. Default: level"))
+            .arg(Arg::with_name(ARG_COLOR_BY_SOURCE)
+                .long(ARG_COLOR_BY_SOURCE)
+                .help("when reading several files, color the file path or host label prefixing each entry by its input file, each file in its own color, instead of all in cyan. Colors repeat after six files"))
+            .arg(Arg::with_name(ARG_ENCODING)
+                .long(ARG_ENCODING)
+                .value_name("NAME")
+                .help("encoding of the input, converted to UTF-8 in text output. Allowed values: passthrough (bytes written unchanged), utf8-lossy (invalid UTF-8 sequences replaced with \"\u{FFFD}\"), latin1. Default: passthrough"))
+            .arg(Arg::with_name(ARG_KEEP_ANSI)
+                .long(ARG_KEEP_ANSI)
+                .help("keep colors embedded in the input working together with colorized output: the entry color is restored after every embedded reset sequence. Has no effect with --escape-mode aggressive, which shows escape characters as ^["))
             .arg(Arg::with_name(ARG_FORMATTING)
                 .long(ARG_FORMATTING)
                 .value_name("BOOLEAN")
                 .help("turn on/off special characters formatting. Default: true"))
+            .arg(Arg::with_name(ARG_ESCAPE_MODE)
+                .long(ARG_ESCAPE_MODE)
+                .value_name("MODE")
+                .conflicts_with(ARG_FORMATTING)
+                .help("choose how escape sequences are formatted: none (same as --formatting no), standard (\\n, \\t, quotes and backslashes) or aggressive (also \\xHH, \\uHHHH and octal escapes, with other control characters shown as ^A). Default: standard"))
+            .arg(Arg::with_name(ARG_ESCAPE_CHAR)
+                .long(ARG_ESCAPE_CHAR)
+                .value_name("CHAR")
+                .help("character starting escape sequences in the standard escape mode, e.g. '~' for ~n. Default: \\"))
+            .arg(Arg::with_name(ARG_ESCAPE_MAP)
+                .long(ARG_ESCAPE_MAP)
+                .value_name("MAPPING")
+                .help("escape sequences decoded in the standard escape mode instead of the default ones, as comma separated CHAR=REPLACEMENT pairs, e.g. 'n=\\n,t=\\t,a=@'. Replacements may contain \\n, \\t, \\r, \\0, \\\\ and \\, escapes"))
             .arg(Arg::with_name(ARG_PAGER)
                 .long(ARG_PAGER)
                 .value_name("BOOLEAN")
@@ -71,40 +444,500 @@ impl Options {
                 .long(ARG_REVERSE)
                 .short("r")
                 .help("reverse output so that the newest entries are displayed first"))
+            .arg(Arg::with_name(ARG_REVERSE_BUFFER)
+                .long(ARG_REVERSE_BUFFER)
+                .requires(ARG_REVERSE)
+                .help("reverse standard input by buffering all of its entries in memory"))
+            .arg(Arg::with_name(ARG_MAX_ENTRY_SIZE)
+                .long(ARG_MAX_ENTRY_SIZE)
+                .value_name("SIZE")
+                .help("cut entries longer than given size, e.g. binary data without line breaks, so that they are not read into memory as a whole. A cut entry ends with a marker and continues in the next one. Accepts K, M and G suffixes. Default: unlimited"))
+            .arg(Arg::with_name(ARG_MEMORY_LIMIT)
+                .long(ARG_MEMORY_LIMIT)
+                .value_name("SIZE")
+                .help("maximum size of entries buffered in memory (e.g. by --reverse-buffer) before they are moved to a temporary file. Accepts K, M and G suffixes. Default: 512M"))
+            .arg(Arg::with_name(ARG_FOLLOW)
+                .long(ARG_FOLLOW)
+                .short("f")
+                .conflicts_with_all(&[ARG_REVERSE, ARG_BENCHMARK])
+                .help("keep reading the input file as it grows, reopening it when it gets rotated (renamed and recreated, or truncated). Requires a single input file. Turns off pager by default"))
+            .arg(Arg::with_name(ARG_IDLE_MARKER)
+                .long(ARG_IDLE_MARKER)
+                .value_name("DURATION")
+                .requires(ARG_FOLLOW)
+                .help("with --follow, print an idle marker such as \"-- idle 30s --\" to standard error every time given duration passes without new data, e.g. \"30s\", \"5m\""))
+            .arg(Arg::with_name(ARG_RATE_SUMMARY)
+                .long(ARG_RATE_SUMMARY)
+                .requires(ARG_FOLLOW)
+                .help("with --follow, print a summary of shown entries to standard error on exit (also on Ctrl-C): their total number, the duration of the session, the average rate and the numbers of entries of each level"))
+            .arg(Arg::with_name(ARG_NOTIFY_ON_LEVEL)
+                .long(ARG_NOTIFY_ON_LEVEL)
+                .value_name("NAME")
+                .requires(ARG_FOLLOW)
+                .help("with --follow, ring the terminal bell on standard error when an entry with equal or higher level is shown, at most once in 5 seconds. Allowed values: debug, info, warning, critical, fatal"))
+            .arg(Arg::with_name(ARG_AT_OFFSET)
+                .long(ARG_AT_OFFSET)
+                .value_name("N")
+                .conflicts_with_all(&[ARG_AT_ENTRY, ARG_REVERSE, ARG_FOLLOW])
+                .help("show only the entry starting at byte offset N of the input"))
+            .arg(Arg::with_name(ARG_AT_ENTRY)
+                .long(ARG_AT_ENTRY)
+                .value_name("N")
+                .conflicts_with_all(&[ARG_REVERSE, ARG_FOLLOW])
+                .help("show only the N-th entry of the input, counting from 1"))
+            .arg(Arg::with_name(ARG_LAST_PER_LEVEL)
+                .long(ARG_LAST_PER_LEVEL)
+                .value_name("N")
+                .conflicts_with_all(&[ARG_AT_OFFSET, ARG_AT_ENTRY, ARG_FOLLOW])
+                .help("show only the N latest entries of each level, in chronological order. Entries without a level are skipped"))
+            .arg(Arg::with_name(ARG_RESUME)
+                .long(ARG_RESUME)
+                .value_name("TOKEN")
+                .conflicts_with_all(&[ARG_FOLLOW, ARG_REVERSE, ARG_AT_OFFSET, ARG_AT_ENTRY, ARG_LAST_PER_LEVEL, ARG_SORT_BY])
+                .help("start reading each input file at the offset recorded in given resume token, e.g. \"0:1024,1:512\" (pairs of input file index and offset). Files shorter than their offset, e.g. rotated ones, are read from the start. Not supported for standard input"))
+            .arg(Arg::with_name(ARG_SAVE_RESUME_TOKEN)
+                .long(ARG_SAVE_RESUME_TOKEN)
+                .value_name("FILE")
+                .conflicts_with_all(&[ARG_FOLLOW, ARG_REVERSE, ARG_AT_OFFSET, ARG_AT_ENTRY, ARG_LAST_PER_LEVEL, ARG_SORT_BY])
+                .help("on exit, write a resume token recording how far each input file has been processed to the file, to be passed to --resume by the next run so that no entry is processed twice. Not supported for standard input"))
+            .arg(Arg::with_name(ARG_SORT_BY)
+                .long(ARG_SORT_BY)
+                .value_name("KEY")
+                .conflicts_with_all(&[ARG_FOLLOW, ARG_BENCHMARK])
+                .help("sort entries by given key, keeping the input order of entries with equal keys. Entries missing the key are put last. All entries passing the filters are held in memory at once. Allowed values: timestamp, level, category, size"))
+            .arg(Arg::with_name(ARG_HEAD)
+                .long(ARG_HEAD)
+                .value_name("N")
+                .help("show only the first N entries of the output and stop reading the input. Together with --reverse it shows the N newest entries across all files, reading only the ends of the files"))
+            .arg(Arg::with_name(ARG_TAIL)
+                .long(ARG_TAIL)
+                .value_name("M")
+                .conflicts_with(ARG_FOLLOW)
+                .help("show only the last M entries of the output, holding them in memory until the input ends. Together with --head it shows the first N and the last M entries with a line counting the entries elided between them"))
+            .arg(Arg::with_name(ARG_LIMIT_BYTES)
+                .long(ARG_LIMIT_BYTES)
+                .value_name("SIZE")
+                .help("stop after the entry with which the output reaches given size, noting it on standard error. Unlike --head, it counts written bytes rather than entries. Accepts K, M and G suffixes"))
+            .arg(Arg::with_name(ARG_MERGE_PRECISION)
+                .long(ARG_MERGE_PRECISION)
+                .value_name("DURATION")
+                .help("when merging multiple files, compare timestamps rounded down to given precision, e.g. \"1s\", so that entries within the same period are shown grouped by file instead of interleaved by clock jitter. Shown timestamps are not changed"))
+            .arg(Arg::with_name(ARG_MERGE_DEDUPE)
+                .long(ARG_MERGE_DEDUPE)
+                .help("when merging multiple files, skip an entry with the same timestamp and contents as the entry shown just before it from another file, e.g. an event logged to two files"))
+            .arg(Arg::with_name(ARG_VERIFY_ORDER)
+                .long(ARG_VERIFY_ORDER)
+                .conflicts_with(ARG_SELFCHECK)
+                .help("while reading, check that the entries of each input file are sorted by their timestamps, as merging assumes, and print the file, offset and both timestamps of every entry going back in time to standard error"))
+            .arg(Arg::with_name(ARG_STRICT)
+                .long(ARG_STRICT)
+                .requires(ARG_VERIFY_ORDER)
+                .help("with --verify-order, stop reading and fail at the first entry out of order"))
+            .arg(Arg::with_name(ARG_INDEX)
+                .long(ARG_INDEX)
+                .conflicts_with(ARG_FOLLOW)
+                .help("keep offsets, timestamps and levels of entries of each input file in an index FILE.riolog-idx next to it, built on first use and rebuilt when the file changes. With --since, reading forward starts at the first entry not skipped by it instead of the beginning of the file, and reading newest first stops there. With --last-per-level, reading stops once it has all entries of each level the file has, even if there are fewer than requested"))
             .arg(Arg::with_name(ARG_OUTPUT)
                 .long(ARG_OUTPUT)
                 .short("o")
                 .value_name("FILE")
                 .help("write the log to the output file"))
+            .arg(Arg::with_name(ARG_OUTPUT_APPEND)
+                .long(ARG_OUTPUT_APPEND)
+                .alias("append")
+                .requires(ARG_OUTPUT)
+                .help("append the log to the output file instead of replacing its contents, e.g. to accumulate results of many runs"))
+            .arg(Arg::with_name(ARG_EXEC)
+                .long(ARG_EXEC)
+                .value_name("CMD")
+                .conflicts_with_all(&[ARG_INTERACTIVE, ARG_BENCHMARK])
+                .help("pipe the output through given shell command, e.g. 'grep -v noise', whose output is shown in the pager, written to the output file or to stdout instead. Colors are turned off by default, like when writing to a pipe"))
+            .arg(Arg::with_name(ARG_TEE)
+                .long(ARG_TEE)
+                .value_name("FILE")
+                .conflicts_with_all(&[ARG_OUTPUT, ARG_BENCHMARK])
+                .help("also save the output shown in the pager (or written to stdout) to the file, which is completed even if the pager is closed early"))
             .arg(Arg::with_name(ARG_SINCE)
                 .long(ARG_SINCE)
                 .short("S")
                 .value_name("DATE_TIME")
-                .help("show only entries later than provided date/time. Accepted formats: \"2020-01-10\", \"2020-01-10 18:00\", \"2020-01-10 18:33:19\""))
+                .help("show only entries later than provided date/time. Accepted formats: \"2020-01-10\", \"2020-01-10 18:00\", \"2020-01-10 18:33:19\", or UTC epoch seconds (10 digits) or milliseconds (13 digits)"))
             .arg(Arg::with_name(ARG_UNTIL)
                 .long(ARG_UNTIL)
                 .short("U")
                 .value_name("DATE_TIME")
-                .help("show only entries earlier than provided date/time. Accepted formats: \"2020-01-10\", \"2020-01-10 18:00\", \"2020-01-10 18:33:19\""))
+                .help("show only entries earlier than provided date/time. Accepted formats: \"2020-01-10\", \"2020-01-10 18:00\", \"2020-01-10 18:33:19\", or UTC epoch seconds (10 digits) or milliseconds (13 digits)"))
+            .arg(Arg::with_name(ARG_SINCE_FILE)
+                .long(ARG_SINCE_FILE)
+                .value_name("FILE")
+                .conflicts_with(ARG_SINCE)
+                .help("show only entries later than the date/time read from the file. When written by --write-until-file of the previous run, the file also holds the number of entries it wrote with that date/time, so that only those appended after them are shown. A missing or empty file means no bound"))
+            .arg(Arg::with_name(ARG_UNTIL_FILE)
+                .long(ARG_UNTIL_FILE)
+                .value_name("FILE")
+                .conflicts_with(ARG_UNTIL)
+                .help("like --until, with the date/time read from the file. A missing or empty file means no bound"))
+            .arg(Arg::with_name(ARG_WRITE_UNTIL_FILE)
+                .long(ARG_WRITE_UNTIL_FILE)
+                .value_name("FILE")
+                .help("on exit, write the timestamp of the newest entry written and the number of entries written with it to the file, to be read by --since-file of the next run. The file is left as it is when no entry was written"))
+            .arg(Arg::with_name(ARG_TIME_RESOLUTION)
+                .long(ARG_TIME_RESOLUTION)
+                .value_name("RESOLUTION")
+                .help("compare timestamps with --since and --until with given resolution: seconds or millis. With seconds, sub-second parts are ignored, so e.g. --until \"2020-01-10 18:33:19\" includes all entries of that second. Default: millis"))
+            .arg(Arg::with_name(ARG_MAX_AGE)
+                .long(ARG_MAX_AGE)
+                .value_name("DURATION")
+                .help("show only entries not older than given duration relative to the newest entry, e.g. \"1h\" or \"30m\". When following a file the newest entry is the newest one received so far, so the cutoff only moves forward as new entries arrive and already printed entries are kept"))
+            .arg(Arg::with_name(ARG_SCAN_WINDOW)
+                .long(ARG_SCAN_WINDOW)
+                .value_name("DURATION")
+                .help("stop reading the input after the first entry later than given duration from the first read entry (earlier, with --reverse), e.g. \"10m\""))
             .arg(Arg::with_name(ARG_LEVEL)
                 .long(ARG_LEVEL)
                 .short("L")
                 .value_name("NAME")
                 .help("show only entries with equal or higher level. Allowed values: debug, info, warning, critical, fatal"))
+            .arg(Arg::with_name(ARG_LEVEL_FOR)
+                .long(ARG_LEVEL_FOR)
+                .value_name("CATEGORY=LEVEL")
+                .multiple(true)
+                .number_of_values(1)
+                .help("show only entries of given category with equal or higher level, overriding --level for it, e.g. 'db=debug'. Can be given multiple times, for different categories"))
+            .arg(Arg::with_name(ARG_LEVEL_MAP)
+                .long(ARG_LEVEL_MAP)
+                .value_name("MAPPING")
+                .help("recognize additional level letters in headers or change meaning of the standard ones, e.g. \"v=debug,e=critical\""))
+            .arg(Arg::with_name(ARG_INPUT_FORMAT)
+                .long(ARG_INPUT_FORMAT)
+                .value_name("NAME")
+                .conflicts_with_all(&[ARG_REVERSE, ARG_LAST_PER_LEVEL, ARG_MAX_AGE, ARG_AT_OFFSET, ARG_AT_ENTRY, ARG_SELFCHECK, ARG_INDEX])
+                .help("format of input files. Allowed values: native, jsonl (one JSON object per line with level, timestamp and message members, see --jsonl-fields). Default: native"))
+            .arg(Arg::with_name(ARG_JSONL_FIELDS)
+                .long(ARG_JSONL_FIELDS)
+                .value_name("MAPPING")
+                .requires(ARG_INPUT_FORMAT)
+                .help("names of JSON members holding the level, the timestamp and the message with --input-format jsonl. Default: \"level=level,ts=ts,msg=msg\""))
+            .arg(Arg::with_name(ARG_JSONL_STRICT)
+                .long(ARG_JSONL_STRICT)
+                .requires(ARG_INPUT_FORMAT)
+                .help("with --input-format jsonl, drop lines which are not JSON objects instead of showing them as entries without a header"))
+            .arg(Arg::with_name(ARG_SPLIT_ON_HEADER)
+                .long(ARG_SPLIT_ON_HEADER)
+                .conflicts_with_all(&[ARG_SEPARATOR, ARG_INPUT_FORMAT])
+                .help("start a new entry at every line beginning with a header, not only after blank lines, e.g. to separate continuation lines left at the top of a truncated file from the first entry. Same as --separator header"))
+            .arg(Arg::with_name(ARG_SEPARATOR)
+                .long(ARG_SEPARATOR)
+                .value_name("SEPARATOR")
+                .conflicts_with_all(&[ARG_INPUT_FORMAT, ARG_INDEX])
+                .help("what ends an entry: \"blank-line\" (default), \"header\" (also a line beginning with a header starts a new entry) or a pattern matching separator lines, e.g. \"^-{5,}$\", which are not shown. With a pattern, blank lines are a part of entries"))
+            .arg(Arg::with_name(ARG_DISTINCT_BY)
+                .long(ARG_DISTINCT_BY)
+                .value_name("FIELD")
+                .help("show only the first entry for each distinct value of given header field. Allowed values: level, category, pid"))
+            .arg(Arg::with_name(ARG_KV_FILTER)
+                .long(ARG_KV_FILTER)
+                .value_name("KEY=VALUE")
+                .multiple(true)
+                .number_of_values(1)
+                .help("show only entries whose message contains the key=value pair, e.g. 'userID=42'. Values may be quoted in the message. Can be given multiple times, all pairs must be present"))
+            .arg(Arg::with_name(ARG_WELL_FORMED_ONLY)
+                .long(ARG_WELL_FORMED_ONLY)
+                .help("show only entries with a complete header: level, pid, timestamp and category"))
             .arg(Arg::with_name(ARG_CONTAINS)
                 .long(ARG_CONTAINS)
                 .short("C")
                 .value_name("STRING")
                 .help("show only entries containing given string. Search is case-sensitive"))
+            .arg(Arg::with_name(ARG_CONTAINS_UNICODE)
+                .long(ARG_CONTAINS_UNICODE)
+                .requires(ARG_CONTAINS)
+                .help("make --contains search case-insensitive, including non-ASCII letters. Entries which are not valid UTF-8 are searched case-sensitively"))
+            .arg(Arg::with_name(ARG_BEFORE_CONTEXT)
+                .long(ARG_BEFORE_CONTEXT)
+                .short("B")
+                .value_name("N")
+                .help("show also N entries preceding each entry matching level, contents and region filters"))
+            .arg(Arg::with_name(ARG_AFTER_CONTEXT)
+                .long(ARG_AFTER_CONTEXT)
+                .short("A")
+                .value_name("N")
+                .help("show also N entries following each entry matching level, contents and region filters"))
+            .arg(Arg::with_name(ARG_CONTEXT)
+                .long(ARG_CONTEXT)
+                .value_name("N")
+                .help("show also N entries preceding and following each matching entry. Overridden by -A and -B"))
+            .arg(Arg::with_name(ARG_CONTEXT_TIME)
+                .long(ARG_CONTEXT_TIME)
+                .value_name("DURATION")
+                .conflicts_with_all(&[ARG_BEFORE_CONTEXT, ARG_AFTER_CONTEXT, ARG_CONTEXT])
+                .help("show also entries with timestamps within given duration before and after each matching entry, e.g. \"30s\". Entries without a timestamp go with the nearest preceding entry which has one"))
+            .arg(Arg::with_name(ARG_CONTEXT_SEPARATOR)
+                .long(ARG_CONTEXT_SEPARATOR)
+                .value_name("STRING")
+                .help("line shown between non-adjacent groups of context entries. Default: --"))
+            .arg(Arg::with_name(ARG_NO_CONTEXT_SEPARATOR)
+                .long(ARG_NO_CONTEXT_SEPARATOR)
+                .conflicts_with(ARG_CONTEXT_SEPARATOR)
+                .help("do not show any line between non-adjacent groups of context entries"))
+            .arg(Arg::with_name(ARG_CATEGORY_REGEX)
+                .long(ARG_CATEGORY_REGEX)
+                .value_name("REGEX")
+                .help("show only entries with category matching given pattern, e.g. \"^DB\". Entries without a category are dropped"))
+            .arg(Arg::with_name(ARG_FROM_PATTERN)
+                .long(ARG_FROM_PATTERN)
+                .value_name("REGEX")
+                .requires(ARG_TO_PATTERN)
+                .help("show only regions of entries starting with an entry matching given pattern and ending with the next entry matching --to-pattern, or at the end of the input. Entries starting and ending regions are found before other filters apply, which then select entries inside regions"))
+            .arg(Arg::with_name(ARG_TO_PATTERN)
+                .long(ARG_TO_PATTERN)
+                .value_name("REGEX")
+                .requires(ARG_FROM_PATTERN)
+                .help("pattern matching the last entry of a region started by --from-pattern"))
+            .arg(Arg::with_name(ARG_PAIR_REGEX)
+                .long(ARG_PAIR_REGEX)
+                .value_name("REGEX")
+                .conflicts_with(ARG_REVERSE)
+                .help("show only entries ending operations, annotated with their duration. Operations are identified by the text captured by the group named \"id\" of given pattern and ended by entries in which its group named \"end\" matches, e.g. \"(start|(?P<end>end)) (?P<id>\\w+)\": other matching entries start operations, and an entry ending one is paired with the latest start with the same id before it. Ends without a start are ignored"))
+            .arg(Arg::with_name(ARG_MIN_DURATION)
+                .long(ARG_MIN_DURATION)
+                .value_name("DURATION")
+                .requires(ARG_PAIR_REGEX)
+                .help("show only operations found by --pair-regex which took at least given duration, e.g. \"500ms\""))
+            .arg(Arg::with_name(ARG_EXPLAIN_FILTERS)
+                .long(ARG_EXPLAIN_FILTERS)
+                .help("print number of entries dropped by each filter to standard error"))
+            .arg(Arg::with_name(ARG_EXPLAIN)
+                .long(ARG_EXPLAIN)
+                .conflicts_with(ARG_QUIET)
+                .help("print to standard error why each dropped entry was removed, with the first line of the entry, e.g. \"dropped: level debug < min warning: ...\""))
+            .arg(Arg::with_name(ARG_BENCHMARK)
+                .long(ARG_BENCHMARK)
+                .conflicts_with(ARG_OUTPUT)
+                .help("read and filter the log discarding the output, then print the throughput of reading the input and the number of entries passing the filters to standard error"))
+            .arg(Arg::with_name(ARG_DRY_RUN)
+                .long(ARG_DRY_RUN)
+                .help("instead of the log, print how it would be read: the reading mode, the input files and whether they are seekable, and the active filters"))
+            .arg(Arg::with_name(ARG_QUIET)
+                .long(ARG_QUIET)
+                .short("q")
+                .conflicts_with(ARG_BENCHMARK)
+                .help("write nothing but errors to standard error, i.e. no progress bar, idle markers, bell notifications, rate summary or filter report"))
+            .arg(Arg::with_name(ARG_SELFCHECK)
+                .long(ARG_SELFCHECK)
+                .hidden(true)
+                .conflicts_with_all(&[ARG_FOLLOW, ARG_AT_OFFSET, ARG_AT_ENTRY, ARG_RESUME, ARG_SAVE_RESUME_TOKEN])
+                .help("merge the input files by both the linear scan and the heap-based merging, failing when their entries differ"))
+            .arg(Arg::with_name(ARG_HISTOGRAM)
+                .long(ARG_HISTOGRAM)
+                .value_name("INTERVAL")
+                .conflicts_with(ARG_BENCHMARK)
+                .help("instead of entries, show numbers of entries in consecutive time intervals of given length, e.g. \"1m\", \"1h\""))
+            .arg(Arg::with_name(ARG_WINDOW_STATS)
+                .long(ARG_WINDOW_STATS)
+                .value_name("INTERVAL")
+                .conflicts_with_all(&[ARG_BENCHMARK, ARG_HISTOGRAM])
+                .help("instead of entries, show the total number of entries and the numbers of entries of each level in consecutive time intervals of given length, e.g. \"1m\", \"1h\""))
+            .arg(Arg::with_name(ARG_CARDINALITY)
+                .long(ARG_CARDINALITY)
+                .value_name("FIELD")
+                .conflicts_with_all(&[ARG_BENCHMARK, ARG_HISTOGRAM, ARG_WINDOW_STATS])
+                .help("instead of entries, show the number of distinct values of given header field among shown entries. Allowed values: level, category, pid"))
+            .arg(Arg::with_name(ARG_CARDINALITY_LIST)
+                .long(ARG_CARDINALITY_LIST)
+                .requires(ARG_CARDINALITY)
+                .help("with --cardinality, also list the distinct values in sorted order, one per line"))
+            .arg(Arg::with_name(ARG_COUNT_MATCHES)
+                .long(ARG_COUNT_MATCHES)
+                .requires(ARG_CONTAINS)
+                .conflicts_with_all(&[ARG_BENCHMARK, ARG_HISTOGRAM, ARG_WINDOW_STATS, ARG_CARDINALITY])
+                .help("instead of entries, show the total number of occurrences of the text given by --contains in shown entries, counting each one within an entry. With multiple input files, also show the number for each of them"))
+            .arg(Arg::with_name(ARG_LIST_CATEGORIES)
+                .long(ARG_LIST_CATEGORIES)
+                .conflicts_with_all(&[ARG_BENCHMARK, ARG_HISTOGRAM, ARG_WINDOW_STATS, ARG_CARDINALITY, ARG_COUNT_MATCHES])
+                .help("instead of entries, show the distinct categories of shown entries with the number of entries of each, most frequent first. Entries without a category are counted as \"(none)\""))
+            .arg(Arg::with_name(ARG_ONLY)
+                .long(ARG_ONLY)
+                .value_name("FIELD")
+                .conflicts_with_all(&[ARG_BENCHMARK, ARG_HISTOGRAM, ARG_WINDOW_STATS, ARG_CARDINALITY, ARG_COUNT_MATCHES, ARG_LIST_CATEGORIES, ARG_OUTPUT_FORMAT, ARG_TEMPLATE])
+                .help("instead of entries, show only given field of each shown entry, one per line, e.g. to plot a timeline. Timestamps are shown like \"2020-01-01 20:00:00.000\" in UTC and messages with line breaks and runs of whitespace replaced by single spaces. Entries without the field are skipped. Allowed values: timestamps, levels, categories, messages"))
+            .arg(Arg::with_name(ARG_ONLY_BLANK_MISSING)
+                .long(ARG_ONLY_BLANK_MISSING)
+                .requires(ARG_ONLY)
+                .help("with --only, show a blank line for entries without the field instead of skipping them"))
+            .arg(Arg::with_name(ARG_INTERACTIVE)
+                .long(ARG_INTERACTIVE)
+                .conflicts_with_all(&[ARG_BENCHMARK, ARG_HISTOGRAM, ARG_WINDOW_STATS, ARG_CARDINALITY, ARG_COUNT_MATCHES, ARG_LIST_CATEGORIES, ARG_OUTPUT, ARG_TEE, ARG_FOLLOW])
+                .help("instead of the pager, load shown entries into memory (up to --memory-limit) and explore them on the terminal, showing only entries containing the text typed at the prompt"))
+            .arg(Arg::with_name(ARG_PROGRESS_BAR)
+                .long(ARG_PROGRESS_BAR)
+                .help("show progress of reading input files with estimated remaining time on standard error. Ignored for standard input, --follow and when standard error is not a terminal"))
+            .arg(Arg::with_name(ARG_OUTPUT_FORMAT)
+                .long(ARG_OUTPUT_FORMAT)
+                .value_name("NAME")
+                .help("output format. Allowed values: text, json (one JSON object per entry), json-pretty (indented JSON objects, colorized when writing to a terminal), tsv (tab-separated timestamp, level, category, pid and message). Default: text"))
+            .arg(Arg::with_name(ARG_JSON_SHAPE)
+                .long(ARG_JSON_SHAPE)
+                .value_name("SHAPE")
+                .help("structure of JSON objects. Allowed values: flat (all fields at the top level), nested (header fields grouped under \"header\"). Default: flat"))
+            .arg(Arg::with_name(ARG_EXTRACT_KV)
+                .long(ARG_EXTRACT_KV)
+                .value_name("KEY")
+                .multiple(true)
+                .number_of_values(1)
+                .help("in JSON output, add values of key=value pairs with given key found in the message under \"kv\", e.g. {\"kv\":{\"userID\":42}}. Can be given multiple times"))
+            .arg(Arg::with_name(ARG_PARSE_EMBEDDED_JSON)
+                .long(ARG_PARSE_EMBEDDED_JSON)
+                .help("in JSON output, put messages which are valid JSON documents under \"data\" field instead of \"message\" string"))
+            .arg(Arg::with_name(ARG_FILL_TIMESTAMPS)
+                .long(ARG_FILL_TIMESTAMPS)
+                .help("assign entries without a timestamp the timestamp of the most recent entry that had one"))
+            .arg(Arg::with_name(ARG_JOIN_CONTINUATIONS)
+                .long(ARG_JOIN_CONTINUATIONS)
+                .help("append entries without a timestamp and level to the preceding entry, e.g. stack trace lines split off from their entry"))
+            .arg(Arg::with_name(ARG_ANNOTATE_NEXT_GAP)
+                .long(ARG_ANNOTATE_NEXT_GAP)
+                .help("prefix each entry with the time until the next entry, e.g. \"(+1.250s)\"; the final entry is marked \"(last)\""))
+            .arg(Arg::with_name(ARG_MAX_LINES_PER_ENTRY)
+                .long(ARG_MAX_LINES_PER_ENTRY)
+                .value_name("N")
+                .help("show at most N lines of each entry followed by a number of omitted lines. The first line is always shown"))
+            .arg(Arg::with_name(ARG_WRAP_COLUMNS)
+                .long(ARG_WRAP_COLUMNS)
+                .value_name("N")
+                .help("break lines of text output longer than N columns, e.g. when saving to a file. Unlike --wrap, it also works without the pager"))
+            .arg(Arg::with_name(ARG_COLLAPSE_TRACES)
+                .long(ARG_COLLAPSE_TRACES)
+                .help("show only the first line of entries looking like stack traces (followed by indented lines) together with a number of omitted frames"))
+            .arg(Arg::with_name(ARG_FOLD_MARKERS)
+                .long(ARG_FOLD_MARKERS)
+                .value_name("STYLE")
+                .help("wrap multiline entries with editor fold markers: vim ({{{ and }}}) or none. Default: none"))
+            .arg(Arg::with_name(ARG_FLATTEN)
+                .long(ARG_FLATTEN)
+                .value_name("MARKER")
+                .min_values(0)
+                .require_equals(true)
+                .help("show every entry on a single line, replacing line breaks inside entries, also those decoded from escaped \\n, with MARKER, e.g. --flatten=\" \". Default: ⏎"))
+            .arg(Arg::with_name(ARG_EMPHASIZE_AFTER_GAP)
+                .long(ARG_EMPHASIZE_AFTER_GAP)
+                .value_name("DURATION")
+                .help("emphasize entries following a period without any entries longer than given duration, e.g. \"30s\", \"5m\", \"1h\". Requires colored output"))
+            .arg(Arg::with_name(ARG_FLAG_ESCALATION)
+                .long(ARG_FLAG_ESCALATION)
+                .conflicts_with(ARG_REVERSE)
+                .help("show an escalation marker before the first critical or fatal entry, and again before the first one after the log recovers to debug, info or warning entries"))
+            .arg(Arg::with_name(ARG_MARK_RESTARTS)
+                .long(ARG_MARK_RESTARTS)
+                .conflicts_with(ARG_REVERSE)
+                .help("show a restart marker before entries with a timestamp earlier than the previous entry's one"))
+            .arg(Arg::with_name(ARG_RESTART_PATTERN)
+                .long(ARG_RESTART_PATTERN)
+                .value_name("REGEX")
+                .help("show a restart marker before entries matching given pattern, e.g. a startup banner"))
+            .arg(Arg::with_name(ARG_CUT_AFTER)
+                .long(ARG_CUT_AFTER)
+                .value_name("REGEX")
+                .help("cut each entry at the first match of given pattern in its message (after replacing escaped control characters), e.g. \"stacktrace=\". The header is always kept"))
+            .arg(Arg::with_name(ARG_REPLACE)
+                .long(ARG_REPLACE)
+                .value_name("REGEX=REPLACEMENT")
+                .multiple(true)
+                .number_of_values(1)
+                .help("replace all matches of the pattern in entry messages (after replacing escaped control characters) with the replacement text, e.g. 'req-[0-9a-f]{8}=REQ'. Can be given multiple times, replacements are applied in order. A '=' in the pattern must be escaped as '\\='"))
+            .arg(Arg::with_name(ARG_DIFFABLE)
+                .long(ARG_DIFFABLE)
+                .conflicts_with(ARG_TEMPLATE)
+                .help("mask volatile header fields so that outputs of two runs can be compared with diff: timestamps are replaced with a placeholder and pids with 0. Combine with --mask-addresses to also mask hexadecimal addresses"))
+            .arg(Arg::with_name(ARG_COMPACT_TIME)
+                .long(ARG_COMPACT_TIME)
+                .conflicts_with_all(&[ARG_TEMPLATE, ARG_DIFFABLE])
+                .help("show the full timestamp only when the hour differs from the previous entry, otherwise only minutes, seconds and milliseconds, e.g. \":00:00.000\". A line with the new date is shown when the date changes"))
+            .arg(Arg::with_name(ARG_MASK_ADDRESSES)
+                .long(ARG_MASK_ADDRESSES)
+                .help("replace hexadecimal addresses such as 0x7ffd5e8c in entry messages with 0xADDR. Applied before the --replace substitutions"))
+            .arg(Arg::with_name(ARG_HOST_FROM)
+                .long(ARG_HOST_FROM)
+                .value_name("REGEX")
+                .help("prefix entries with a host label extracted from the path of their input file by given pattern, e.g. '([^/]+)\\.log$'. The label is the first capture group, or the whole match if the pattern has no groups. Replaces the file path prefix of inputs the pattern matches"))
+            .arg(Arg::with_name(ARG_ALIGN)
+                .long(ARG_ALIGN)
+                .help("pad level, pid and category of each header to fixed widths so that messages start in the same column. Overlong fields are shortened"))
+            .arg(Arg::with_name(ARG_SUMMARY_LINE)
+                .long(ARG_SUMMARY_LINE)
+                .conflicts_with_all(&[ARG_ALIGN, ARG_TEMPLATE, ARG_MESSAGE_ONLY_OUTPUT])
+                .help("show each entry as a single line with its timestamp, level and the first 80 characters of its message, whitespace including line breaks collapsed into single spaces"))
+            .arg(Arg::with_name(ARG_SHOW_SIZE)
+                .long(ARG_SHOW_SIZE)
+                .help("prefix each entry with its size in bytes. JSON output gets a \"bytes\" field instead"))
+            .arg(Arg::with_name(ARG_DEBUG_PARSE)
+                .long(ARG_DEBUG_PARSE)
+                .help("prefix each entry with flags showing which header fields were recognized: T (timestamp), L (level), C (category) and P (pid), lowercase when missing, e.g. \"[TLcp]\""))
+            .arg(Arg::with_name(ARG_MESSAGE_ONLY_OUTPUT)
+                .long(ARG_MESSAGE_ONLY_OUTPUT)
+                .conflicts_with_all(&[ARG_ALIGN, ARG_TEMPLATE])
+                .help("strip headers and show only message bodies of entries. Entries without a header are shown in full"))
+            .arg(Arg::with_name(ARG_TEMPLATE)
+                .long(ARG_TEMPLATE)
+                .short("t")
+                .value_name("TEMPLATE")
+                .help("render each entry using given template. Placeholders: {ts}, {level}, {category}, {pid}, {source}, {message}, {raw}. Use {{ and }} for literal braces"))
+            .arg(Arg::with_name(ARG_TEMPLATE_FALLBACK)
+                .long(ARG_TEMPLATE_FALLBACK)
+                .value_name("STRING")
+                .requires(ARG_TEMPLATE)
+                .help("text rendered in place of template fields missing in an entry. Default: empty"))
             .get_matches();
 
         let output_file = matches.value_of_os(ARG_OUTPUT).map(PathBuf::from);
+        let output_append = matches.is_present(ARG_OUTPUT_APPEND);
+        let exec = matches.value_of(ARG_EXEC).map(String::from);
+        let tee_file = matches.value_of_os(ARG_TEE).map(PathBuf::from);
 
-        let color_enabled = matches
+        let format = matches
+            .value_of(ARG_OUTPUT_FORMAT)
+            .map(|input| parse_format_arg(input).ok_or(InvalidCliOptionValue(ARG_OUTPUT_FORMAT)))
+            .transpose()?
+            .unwrap_or(OutputFormat::Text);
+
+        let color_choice = matches
             .value_of(ARG_COLOR)
-            .map(|input| parse_bool_arg(input).ok_or(InvalidCliOptionValue(ARG_COLOR)))
+            .map(|input| parse_color_choice_arg(input).ok_or(InvalidCliOptionValue(ARG_COLOR)))
             .transpose()?
-            .unwrap_or_else(|| output_file.is_none());
+            .unwrap_or_default();
+
+        let color_enabled = (format == OutputFormat::Text || format == OutputFormat::JsonPretty)
+            && resolve_color(
+                color_choice,
+                output_file.is_some() || exec.is_some(),
+                atty::is(atty::Stream::Stdout),
+            );
+
+        let color_by = matches
+            .value_of(ARG_COLOR_BY)
+            .map(|input| parse_color_by_arg(input).ok_or(InvalidCliOptionValue(ARG_COLOR_BY)))
+            .transpose()?
+            .unwrap_or_default();
+
+        let flatten = if matches.is_present(ARG_FLATTEN) {
+            Some(
+                matches
+                    .value_of(ARG_FLATTEN)
+                    .unwrap_or(DEFAULT_FLATTEN_MARKER)
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+
+        let color_by_source = matches.is_present(ARG_COLOR_BY_SOURCE);
+        let keep_ansi = matches.is_present(ARG_KEEP_ANSI);
+
+        let encoding = matches
+            .value_of(ARG_ENCODING)
+            .map(|input| parse_encoding_arg(input).ok_or(InvalidCliOptionValue(ARG_ENCODING)))
+            .transpose()?
+            .unwrap_or_default();
 
         let formatting_enabled = matches
             .value_of(ARG_FORMATTING)
@@ -112,64 +945,795 @@ impl Options {
             .transpose()?
             .unwrap_or(true);
 
+        let escape_mode = matches
+            .value_of(ARG_ESCAPE_MODE)
+            .map(|input| parse_escape_mode_arg(input).ok_or(InvalidCliOptionValue(ARG_ESCAPE_MODE)))
+            .transpose()?
+            .unwrap_or(if formatting_enabled {
+                EscapeMode::Standard
+            } else {
+                EscapeMode::None
+            });
+
+        let escape_table = matches
+            .value_of(ARG_ESCAPE_MAP)
+            .map(|input| parse_escape_map_arg(input).ok_or(InvalidCliOptionValue(ARG_ESCAPE_MAP)))
+            .transpose()?
+            .map_or_else(EscapeTable::default, |escapes| {
+                EscapeTable::default().with_escapes(escapes)
+            });
+
+        let escape_table = match matches.value_of(ARG_ESCAPE_CHAR) {
+            Some(input) => escape_table.with_lead(
+                parse_escape_char_arg(input).ok_or(InvalidCliOptionValue(ARG_ESCAPE_CHAR))?,
+            ),
+            None => escape_table,
+        };
+
+        let follow = matches.is_present(ARG_FOLLOW);
+
+        let idle_marker = matches
+            .value_of(ARG_IDLE_MARKER)
+            .map(|input| {
+                parse_duration_arg(input)
+                    .filter(|interval| interval.num_milliseconds() > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_IDLE_MARKER))
+            })
+            .transpose()?;
+
+        let rate_summary = if matches.is_present(ARG_RATE_SUMMARY) {
+            Some(Rc::new(RateSummary::new()))
+        } else {
+            None
+        };
+
+        let notifier = matches
+            .value_of(ARG_NOTIFY_ON_LEVEL)
+            .map(|input| {
+                parse_level_arg(input)
+                    .map(|level| Rc::new(LevelNotifier::new(level)))
+                    .ok_or(InvalidCliOptionValue(ARG_NOTIFY_ON_LEVEL))
+            })
+            .transpose()?;
+
+        let at_offset = matches
+            .value_of(ARG_AT_OFFSET)
+            .map(|input| {
+                input
+                    .parse()
+                    .map(EntrySelector::Offset)
+                    .map_err(|_| InvalidCliOptionValue(ARG_AT_OFFSET))
+            })
+            .transpose()?;
+
+        let at_entry = matches
+            .value_of(ARG_AT_ENTRY)
+            .map(|input| {
+                input
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .map(EntrySelector::Number)
+                    .ok_or(InvalidCliOptionValue(ARG_AT_ENTRY))
+            })
+            .transpose()?;
+
+        let entry_selector = at_offset.or(at_entry);
+
+        let last_per_level = matches
+            .value_of(ARG_LAST_PER_LEVEL)
+            .map(|input| {
+                input
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_LAST_PER_LEVEL))
+            })
+            .transpose()?;
+
+        let level_map = matches
+            .value_of(ARG_LEVEL_MAP)
+            .map(|input| parse_level_map_arg(input).ok_or(InvalidCliOptionValue(ARG_LEVEL_MAP)))
+            .transpose()?
+            .unwrap_or_default();
+
+        let input_format = matches
+            .value_of(ARG_INPUT_FORMAT)
+            .map(|input| {
+                parse_input_format_arg(input).ok_or(InvalidCliOptionValue(ARG_INPUT_FORMAT))
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let jsonl_fields = matches
+            .value_of(ARG_JSONL_FIELDS)
+            .map(|input| {
+                parse_jsonl_fields_arg(input).ok_or(InvalidCliOptionValue(ARG_JSONL_FIELDS))
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let jsonl_strict = matches.is_present(ARG_JSONL_STRICT);
+
+        let separator = if matches.is_present(ARG_SPLIT_ON_HEADER) {
+            EntrySeparator::Header
+        } else {
+            matches
+                .value_of(ARG_SEPARATOR)
+                .map(|input| parse_separator_arg(input).ok_or(InvalidCliOptionValue(ARG_SEPARATOR)))
+                .transpose()?
+                .unwrap_or_default()
+        };
+
         let pager = matches
             .value_of(ARG_PAGER)
             .map(|input| parse_bool_arg(input).ok_or(InvalidCliOptionValue(ARG_PAGER)))
             .transpose()?
-            .unwrap_or(true);
+            .unwrap_or(!follow);
 
         let wrap = matches.is_present(ARG_WRAP);
 
         let reverse = matches.is_present(ARG_REVERSE);
 
+        let reverse_buffer = matches.is_present(ARG_REVERSE_BUFFER);
+
+        let max_entry_size = matches
+            .value_of(ARG_MAX_ENTRY_SIZE)
+            .map(|input| {
+                parse_size_arg(input)
+                    .filter(|&size| size > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_MAX_ENTRY_SIZE))
+            })
+            .transpose()?;
+
+        let memory_limit = matches
+            .value_of(ARG_MEMORY_LIMIT)
+            .map(|input| parse_size_arg(input).ok_or(InvalidCliOptionValue(ARG_MEMORY_LIMIT)))
+            .transpose()?;
+
+        let sort_by = matches
+            .value_of(ARG_SORT_BY)
+            .map(|input| parse_sort_key_arg(input).ok_or(InvalidCliOptionValue(ARG_SORT_BY)))
+            .transpose()?;
+
+        let head = matches
+            .value_of(ARG_HEAD)
+            .map(|input| input.parse().map_err(|_| InvalidCliOptionValue(ARG_HEAD)))
+            .transpose()?;
+
+        let tail = matches
+            .value_of(ARG_TAIL)
+            .map(|input| input.parse().map_err(|_| InvalidCliOptionValue(ARG_TAIL)))
+            .transpose()?;
+
+        let resume = matches
+            .value_of(ARG_RESUME)
+            .map(|input| ResumeToken::parse(input).ok_or(InvalidCliOptionValue(ARG_RESUME)))
+            .transpose()?
+            .unwrap_or_default();
+
+        let resume_token_file = matches
+            .value_of_os(ARG_SAVE_RESUME_TOKEN)
+            .map(PathBuf::from);
+
+        let index = matches.is_present(ARG_INDEX);
+
+        let merge_precision = matches
+            .value_of(ARG_MERGE_PRECISION)
+            .map(|input| {
+                parse_duration_arg(input)
+                    .filter(|precision| precision.num_milliseconds() > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_MERGE_PRECISION))
+            })
+            .transpose()?;
+
+        let merge_dedupe = matches.is_present(ARG_MERGE_DEDUPE);
+
+        let verify_order = matches.is_present(ARG_VERIFY_ORDER);
+
+        let strict = matches.is_present(ARG_STRICT);
+
+        let explain_filters = matches.is_present(ARG_EXPLAIN_FILTERS);
+
+        let explain = matches.is_present(ARG_EXPLAIN);
+
+        let quiet = matches.is_present(ARG_QUIET);
+
+        let dry_run = matches.is_present(ARG_DRY_RUN);
+
+        let benchmark = matches.is_present(ARG_BENCHMARK);
+        let input_bytes = if benchmark {
+            Some(Rc::new(Cell::new(0)))
+        } else {
+            None
+        };
+
+        let selfcheck = matches.is_present(ARG_SELFCHECK);
+
+        let progress_bar = matches.is_present(ARG_PROGRESS_BAR);
+
+        let histogram = matches
+            .value_of(ARG_HISTOGRAM)
+            .map(|input| {
+                parse_duration_arg(input)
+                    .filter(|interval| interval.num_milliseconds() > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_HISTOGRAM))
+            })
+            .transpose()?;
+
+        let window_stats = matches
+            .value_of(ARG_WINDOW_STATS)
+            .map(|input| {
+                parse_duration_arg(input)
+                    .filter(|interval| interval.num_milliseconds() > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_WINDOW_STATS))
+            })
+            .transpose()?;
+
+        let cardinality = matches
+            .value_of(ARG_CARDINALITY)
+            .map(|input| {
+                parse_distinct_field_arg(input).ok_or(InvalidCliOptionValue(ARG_CARDINALITY))
+            })
+            .transpose()?;
+
+        let cardinality_list = matches.is_present(ARG_CARDINALITY_LIST);
+
+        let count_matches = matches.is_present(ARG_COUNT_MATCHES);
+
+        let list_categories = matches.is_present(ARG_LIST_CATEGORIES);
+
+        let only = matches
+            .value_of(ARG_ONLY)
+            .map(|input| parse_only_field_arg(input).ok_or(InvalidCliOptionValue(ARG_ONLY)))
+            .transpose()?;
+        let only_blank_missing = matches.is_present(ARG_ONLY_BLANK_MISSING);
+
+        let interactive = matches.is_present(ARG_INTERACTIVE);
+
         let since = matches
             .value_of(ARG_SINCE)
             .map(|input| parse_date_time_arg(input).ok_or(InvalidCliOptionValue(ARG_SINCE)))
             .transpose()?;
-
         let until = matches
             .value_of(ARG_UNTIL)
             .map(|input| parse_date_time_arg(input).ok_or(InvalidCliOptionValue(ARG_UNTIL)))
             .transpose()?;
+        let until = match matches.value_of_os(ARG_UNTIL_FILE) {
+            Some(path) => read_bound(Path::new(path), ARG_UNTIL_FILE)?,
+            None => until,
+        };
+
+        let time_resolution = matches
+            .value_of(ARG_TIME_RESOLUTION)
+            .map(|input| {
+                parse_time_resolution_arg(input).ok_or(InvalidCliOptionValue(ARG_TIME_RESOLUTION))
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let since_position = match matches.value_of_os(ARG_SINCE_FILE) {
+            Some(path) => read_position(Path::new(path), ARG_SINCE_FILE)?,
+            None => None,
+        };
+        let since = since_position.map(|position| position.timestamp).or(since);
 
         let min_level = matches
             .value_of(ARG_LEVEL)
             .map(|input| parse_level_arg(input).ok_or(InvalidCliOptionValue(ARG_LEVEL)))
             .transpose()?;
 
+        let category_levels = matches
+            .values_of(ARG_LEVEL_FOR)
+            .map(|inputs| {
+                inputs
+                    .map(|input| {
+                        parse_category_level_arg(input).ok_or(InvalidCliOptionValue(ARG_LEVEL_FOR))
+                    })
+                    .collect::<Result<HashMap<_, _>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
         let contains = matches.value_of(ARG_CONTAINS).map(String::from);
 
-        let input_files = matches
+        let contains_unicode = matches.is_present(ARG_CONTAINS_UNICODE);
+
+        let well_formed_only = matches.is_present(ARG_WELL_FORMED_ONLY);
+
+        let distinct_by = matches
+            .value_of(ARG_DISTINCT_BY)
+            .map(|input| {
+                parse_distinct_field_arg(input).ok_or(InvalidCliOptionValue(ARG_DISTINCT_BY))
+            })
+            .transpose()?;
+
+        let kv_filters = matches
+            .values_of(ARG_KV_FILTER)
+            .map(|inputs| {
+                inputs
+                    .map(|input| {
+                        parse_kv_filter_arg(input).ok_or(InvalidCliOptionValue(ARG_KV_FILTER))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let max_age = matches
+            .value_of(ARG_MAX_AGE)
+            .map(|input| parse_duration_arg(input).ok_or(InvalidCliOptionValue(ARG_MAX_AGE)))
+            .transpose()?;
+
+        let scan_window = matches
+            .value_of(ARG_SCAN_WINDOW)
+            .map(|input| parse_duration_arg(input).ok_or(InvalidCliOptionValue(ARG_SCAN_WINDOW)))
+            .transpose()?;
+
+        let parse_context_arg = |arg| {
+            matches
+                .value_of(arg)
+                .map(|input| input.parse().map_err(|_| InvalidCliOptionValue(arg)))
+                .transpose()
+        };
+
+        let context = parse_context_arg(ARG_CONTEXT)?.unwrap_or(0);
+
+        let before_context = parse_context_arg(ARG_BEFORE_CONTEXT)?.unwrap_or(context);
+
+        let after_context = parse_context_arg(ARG_AFTER_CONTEXT)?.unwrap_or(context);
+
+        let context_time = matches
+            .value_of(ARG_CONTEXT_TIME)
+            .map(|input| {
+                parse_duration_arg(input)
+                    .filter(|window| window.num_milliseconds() > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_CONTEXT_TIME))
+            })
+            .transpose()?;
+
+        let context_separator = if matches.is_present(ARG_NO_CONTEXT_SEPARATOR) {
+            None
+        } else {
+            Some(
+                matches
+                    .value_of(ARG_CONTEXT_SEPARATOR)
+                    .unwrap_or(DEFAULT_CONTEXT_SEPARATOR)
+                    .to_string(),
+            )
+        };
+
+        let from_pattern = matches
+            .value_of(ARG_FROM_PATTERN)
+            .map(|input| Pattern::new(input).ok_or(InvalidCliOptionValue(ARG_FROM_PATTERN)))
+            .transpose()?;
+
+        let category_pattern = matches
+            .value_of(ARG_CATEGORY_REGEX)
+            .map(|input| Pattern::new(input).ok_or(InvalidCliOptionValue(ARG_CATEGORY_REGEX)))
+            .transpose()?;
+
+        let pair_pattern = matches
+            .value_of(ARG_PAIR_REGEX)
+            .map(|input| {
+                Pattern::new(input)
+                    .filter(|pattern| pattern.has_group("id") && pattern.has_group("end"))
+                    .ok_or(InvalidCliOptionValue(ARG_PAIR_REGEX))
+            })
+            .transpose()?;
+
+        let min_duration = matches
+            .value_of(ARG_MIN_DURATION)
+            .map(|input| parse_duration_arg(input).ok_or(InvalidCliOptionValue(ARG_MIN_DURATION)))
+            .transpose()?;
+
+        let to_pattern = matches
+            .value_of(ARG_TO_PATTERN)
+            .map(|input| Pattern::new(input).ok_or(InvalidCliOptionValue(ARG_TO_PATTERN)))
+            .transpose()?;
+
+        let max_lines_per_entry = matches
+            .value_of(ARG_MAX_LINES_PER_ENTRY)
+            .map(|input| {
+                input
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_MAX_LINES_PER_ENTRY))
+            })
+            .transpose()?;
+
+        let byte_limit = matches
+            .value_of(ARG_LIMIT_BYTES)
+            .map(|input| {
+                parse_size_arg(input)
+                    .filter(|&size| size > 0)
+                    .map(|size| Rc::new(ByteLimit::new(size as u64)))
+                    .ok_or(InvalidCliOptionValue(ARG_LIMIT_BYTES))
+            })
+            .transpose()?;
+
+        let checkpoint = matches
+            .value_of_os(ARG_WRITE_UNTIL_FILE)
+            .map(|path| Rc::new(Checkpoint::new(PathBuf::from(path)).with_start(since_position)));
+
+        let wrap_columns = matches
+            .value_of(ARG_WRAP_COLUMNS)
+            .map(|input| {
+                input
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or(InvalidCliOptionValue(ARG_WRAP_COLUMNS))
+            })
+            .transpose()?;
+
+        let collapse_traces = matches.is_present(ARG_COLLAPSE_TRACES);
+
+        let fold_markers = matches
+            .value_of(ARG_FOLD_MARKERS)
+            .map(|input| {
+                parse_fold_markers_arg(input).ok_or(InvalidCliOptionValue(ARG_FOLD_MARKERS))
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let align = matches.is_present(ARG_ALIGN);
+
+        let summary_line = matches.is_present(ARG_SUMMARY_LINE);
+
+        let mark_restarts = matches.is_present(ARG_MARK_RESTARTS);
+
+        let flag_escalation = matches.is_present(ARG_FLAG_ESCALATION);
+
+        let restart_pattern = matches
+            .value_of(ARG_RESTART_PATTERN)
+            .map(|input| Pattern::new(input).ok_or(InvalidCliOptionValue(ARG_RESTART_PATTERN)))
+            .transpose()?;
+
+        let cut_pattern = matches
+            .value_of(ARG_CUT_AFTER)
+            .map(|input| Pattern::new(input).ok_or(InvalidCliOptionValue(ARG_CUT_AFTER)))
+            .transpose()?;
+
+        let mut replacements = Vec::new();
+        if matches.is_present(ARG_MASK_ADDRESSES) {
+            let pattern = Pattern::new(ADDRESS_PATTERN).expect("Invalid address pattern");
+            replacements.push((pattern, ADDRESS_MASK.to_string()));
+        }
+        if let Some(inputs) = matches.values_of(ARG_REPLACE) {
+            for input in inputs {
+                replacements
+                    .push(parse_replacement_arg(input).ok_or(InvalidCliOptionValue(ARG_REPLACE))?);
+            }
+        }
+
+        let diffable = matches.is_present(ARG_DIFFABLE);
+
+        let compact_time = matches.is_present(ARG_COMPACT_TIME);
+
+        let host_pattern = matches
+            .value_of(ARG_HOST_FROM)
+            .map(|input| Pattern::new(input).ok_or(InvalidCliOptionValue(ARG_HOST_FROM)))
+            .transpose()?;
+
+        let emphasize_after_gap = matches
+            .value_of(ARG_EMPHASIZE_AFTER_GAP)
+            .map(|input| {
+                parse_duration_arg(input).ok_or(InvalidCliOptionValue(ARG_EMPHASIZE_AFTER_GAP))
+            })
+            .transpose()?;
+
+        let message_only_output = matches.is_present(ARG_MESSAGE_ONLY_OUTPUT);
+
+        let show_size = matches.is_present(ARG_SHOW_SIZE);
+
+        let debug_parse = matches.is_present(ARG_DEBUG_PARSE);
+
+        let json_shape = matches
+            .value_of(ARG_JSON_SHAPE)
+            .map(|input| parse_json_shape_arg(input).ok_or(InvalidCliOptionValue(ARG_JSON_SHAPE)))
+            .transpose()?
+            .unwrap_or_default();
+
+        let parse_embedded_json = matches.is_present(ARG_PARSE_EMBEDDED_JSON);
+
+        let extract_kv = matches
+            .values_of(ARG_EXTRACT_KV)
+            .map(|keys| keys.map(String::from).collect())
+            .unwrap_or_default();
+
+        let fill_timestamps = matches.is_present(ARG_FILL_TIMESTAMPS);
+
+        let annotate_next_gap = matches.is_present(ARG_ANNOTATE_NEXT_GAP);
+
+        let join_continuations = matches.is_present(ARG_JOIN_CONTINUATIONS);
+
+        let template = matches
+            .value_of(ARG_TEMPLATE)
+            .map(|input| Template::parse(input).ok_or(InvalidCliOptionValue(ARG_TEMPLATE)))
+            .transpose()?;
+
+        let template_fallback = matches
+            .value_of(ARG_TEMPLATE_FALLBACK)
+            .map(String::from)
+            .unwrap_or_default();
+
+        let mut input_files: Vec<PathBuf> = matches
             .values_of_os(ARG_FILE_NAMES)
             .map(|os_values| os_values.map(PathBuf::from).collect())
             .unwrap_or_else(Vec::new);
 
+        if let Some(list_file) = matches.value_of_os(ARG_FILES_FROM).map(PathBuf::from) {
+            // standard input cannot provide both the list and a log
+            if list_file == Path::new("-") && input_files.iter().any(|file| file == Path::new("-"))
+            {
+                return Err(InvalidCliOptionValue(ARG_FILES_FROM));
+            }
+            input_files.extend(read_file_list(&list_file)?);
+        }
+
+        if follow && !is_single_file(&input_files) {
+            return Err(InvalidCliOptionValue(ARG_FOLLOW));
+        }
+
+        // standard input can be merged with files only while reading forward
+        let stdin_count = input_files
+            .iter()
+            .filter(|file| *file == Path::new("-"))
+            .count();
+        if stdin_count > 1 {
+            return Err(InvalidCliOptionValue(ARG_FILE_NAMES));
+        }
+        if stdin_count == 1 && input_files.len() > 1 && reverse {
+            return Err(InvalidCliOptionValue(ARG_REVERSE));
+        }
+
+        // input files are read once per merging implementation
+        if (stdin_count == 1 || input_files.is_empty()) && selfcheck {
+            return Err(InvalidCliOptionValue(ARG_SELFCHECK));
+        }
+
+        // offsets are recorded for input files only
+        if (stdin_count == 1 || input_files.is_empty()) && resume_token_file.is_some() {
+            return Err(InvalidCliOptionValue(ARG_SAVE_RESUME_TOKEN));
+        }
+        if (stdin_count == 1 || input_files.is_empty() || resume.sources() > input_files.len())
+            && resume != ResumeToken::default()
+        {
+            return Err(InvalidCliOptionValue(ARG_RESUME));
+        }
+
+        match entry_selector {
+            Some(EntrySelector::Offset(_)) if input_files.len() > 1 => {
+                return Err(InvalidCliOptionValue(ARG_AT_OFFSET))
+            }
+            Some(EntrySelector::Number(_)) if input_files.len() > 1 => {
+                return Err(InvalidCliOptionValue(ARG_AT_ENTRY))
+            }
+            _ => {}
+        }
+
         let filtering_options = FilteringOptions {
             since,
             until,
+            time_resolution,
             min_level,
+            category_levels,
             contains,
+            contains_unicode,
+            from_pattern,
+            to_pattern,
+            well_formed_only,
+            before_context,
+            after_context,
+            context_separator,
+            context_time,
+            distinct_by,
+            kv_filters,
+            max_age,
+            category_pattern,
+            scan_window,
+            pair_pattern,
+            min_duration,
         };
 
-        Ok(Options {
+        let transforming_options = TransformingOptions {
+            fill_timestamps,
+            annotate_next_gap,
+            join_continuations,
+        };
+
+        let output_options = OutputOptions {
             color_enabled,
-            formatting_enabled,
+            escape_mode,
+            escape_table,
+            color_by,
+            color_by_source,
+            keep_ansi,
+            encoding,
+            format,
+            json_shape,
+            parse_embedded_json,
+            extract_kv,
+            max_lines_per_entry,
+            wrap_columns,
+            collapse_traces,
+            fold_markers,
+            flatten,
+            align,
+            summary_line,
+            emphasize_after_gap,
+            mark_restarts,
+            flag_escalation,
+            restart_pattern,
+            cut_pattern,
+            replacements,
+            diffable,
+            compact_time,
+            host_pattern,
+            message_only_output,
+            show_size,
+            debug_parse,
+            template,
+            template_fallback,
+            byte_limit,
+            checkpoint,
+            interrupt: Interrupt::default(),
+        };
+
+        Ok(Options {
             pager,
             wrap,
             reverse,
+            reverse_buffer,
+            memory_limit,
+            max_entry_size,
+            follow,
+            idle_marker,
+            rate_summary,
+            notifier,
+            interrupt: Interrupt::default(),
+            entry_selector,
+            last_per_level,
+            sort_by,
+            head,
+            tail,
+            merge_precision,
+            merge_dedupe,
+            verify_order,
+            strict,
+            order_check: None,
+            index,
+            level_counts: None,
+            resume,
+            resume_token_file,
+            resume_tracker: None,
+            since_position,
+            level_map: Rc::new(level_map),
+            input_format,
+            jsonl_fields,
+            jsonl_strict,
+            separator,
+            explain_filters,
+            explain,
+            quiet,
+            dry_run,
+            benchmark,
+            input_bytes,
+            selfcheck,
+            progress_bar,
+            histogram,
+            window_stats,
+            cardinality,
+            cardinality_list,
+            count_matches,
+            list_categories,
+            only,
+            only_blank_missing,
+            interactive,
             filtering_options,
+            transforming_options,
+            output_options,
             input_files,
             output_file,
+            output_append,
+            exec,
+            tee_file,
         })
     }
 
     pub fn is_filtering_or_coloring(&self) -> bool {
-        self.color_enabled
+        self.has_entry_hooks()
+            || self.output_options.color_enabled
+            || self.benchmark
+            || self.verify_order
+            || self.histogram.is_some()
+            || self.window_stats.is_some()
+            || self.cardinality.is_some()
+            || self.count_matches
+            || self.list_categories
+            || self.only.is_some()
+            || self.interactive
+            || self.input_format != InputFormat::Native
+            || !matches!(self.separator, EntrySeparator::BlankLine)
+            || self.max_entry_size.is_some()
+            || self.output_options.template.is_some()
+            || self.output_options.max_lines_per_entry.is_some()
+            || self.output_options.wrap_columns.is_some()
+            || self.output_options.byte_limit.is_some()
+            || self.output_options.collapse_traces
+            || self.output_options.fold_markers != FoldMarkers::None
+            || self.output_options.flatten.is_some()
+            || self.output_options.align
+            || self.output_options.summary_line
+            || self.output_options.message_only_output
+            || self.output_options.show_size
+            || self.output_options.debug_parse
+            || self.output_options.escape_mode == EscapeMode::Aggressive
+            || self.output_options.mark_restarts
+            || self.output_options.flag_escalation
+            || self.output_options.restart_pattern.is_some()
+            || self.output_options.cut_pattern.is_some()
+            || !self.output_options.replacements.is_empty()
+            || self.output_options.diffable
+            || self.output_options.compact_time
+            || self.output_options.host_pattern.is_some()
+            || self.output_options.format != OutputFormat::Text
             || self.filtering_options.since.is_some()
             || self.filtering_options.until.is_some()
             || self.filtering_options.min_level.is_some()
+            || !self.filtering_options.category_levels.is_empty()
             || self.filtering_options.contains.is_some()
+            || self.filtering_options.from_pattern.is_some()
+            || self.filtering_options.well_formed_only
+            || self.filtering_options.distinct_by.is_some()
+            || !self.filtering_options.kv_filters.is_empty()
+            || self.filtering_options.max_age.is_some()
+            || self.filtering_options.category_pattern.is_some()
+            || self.filtering_options.scan_window.is_some()
+            || self.filtering_options.pair_pattern.is_some()
+            || self.transforming_options.annotate_next_gap
+            || self.transforming_options.join_continuations
+            || self.last_per_level.is_some()
+            || self.sort_by.is_some()
+            || self.head.is_some()
+            || self.tail.is_some()
+            || self.resume != ResumeToken::default()
     }
+
+    // whether something records each entry written, which the fast path does not parse
+    fn has_entry_hooks(&self) -> bool {
+        self.resume_token_file.is_some()
+            || self.rate_summary.is_some()
+            || self.notifier.is_some()
+            || self.output_options.checkpoint.is_some()
+    }
+}
+
+fn read_file_list(list_file: &Path) -> Result<Vec<PathBuf>> {
+    let file_list = if list_file == Path::new("-") {
+        parse_file_list(io::stdin().lock())
+    } else {
+        File::open(list_file).and_then(|file| parse_file_list(BufReader::new(file)))
+    };
+    file_list.map_err(|e| Error::CannotOpenFile(list_file.to_path_buf(), e))
+}
+
+fn parse_file_list(reader: impl BufRead) -> io::Result<Vec<PathBuf>> {
+    let mut file_list = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let path = line.trim();
+        if !path.is_empty() && !path.starts_with('#') {
+            file_list.push(PathBuf::from(path));
+        }
+    }
+    Ok(file_list)
+}
+
+fn is_single_file(input_files: &[PathBuf]) -> bool {
+    input_files.len() == 1 && input_files[0] != Path::new("-")
 }
 
 fn parse_bool_arg(input: &str) -> Option<bool> {
@@ -183,6 +1747,84 @@ fn parse_bool_arg(input: &str) -> Option<bool> {
     }
 }
 
+fn parse_color_choice_arg(input: &str) -> Option<ColorChoice> {
+    match input.to_lowercase().as_str() {
+        "auto" => Some(ColorChoice::Auto),
+        "always" => Some(ColorChoice::Always),
+        "never" => Some(ColorChoice::Never),
+        _ => parse_bool_arg(input).map(|enabled| {
+            if enabled {
+                ColorChoice::Always
+            } else {
+                ColorChoice::Never
+            }
+        }),
+    }
+}
+
+// auto colors output to a terminal, including the pager, but not to a file, a pipe or a command
+fn resolve_color(choice: ColorChoice, redirected: bool, stdout_is_tty: bool) -> bool {
+    match choice {
+        ColorChoice::Auto => !redirected && stdout_is_tty,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+fn parse_time_resolution_arg(input: &str) -> Option<TimeResolution> {
+    match input.to_lowercase().as_str() {
+        "seconds" => Some(TimeResolution::Seconds),
+        "millis" => Some(TimeResolution::Millis),
+        _ => None,
+    }
+}
+
+fn parse_input_format_arg(input: &str) -> Option<InputFormat> {
+    match input.to_lowercase().as_str() {
+        "native" => Some(InputFormat::Native),
+        "jsonl" => Some(InputFormat::JsonLines),
+        _ => None,
+    }
+}
+
+// e.g. "level=severity,msg=message", with fields not mentioned keeping their default names
+fn parse_jsonl_fields_arg(input: &str) -> Option<JsonFields> {
+    let mut fields = JsonFields::default();
+    for mapping in input.split(',') {
+        let (field, name) = mapping.split_once('=')?;
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return None;
+        }
+        match field.trim() {
+            "level" => fields.level = name,
+            "ts" => fields.timestamp = name,
+            "msg" => fields.message = name,
+            _ => return None,
+        }
+    }
+    Some(fields)
+}
+
+fn parse_encoding_arg(input: &str) -> Option<Encoding> {
+    match input.to_lowercase().as_str() {
+        "passthrough" => Some(Encoding::Passthrough),
+        "utf8-lossy" => Some(Encoding::Utf8Lossy),
+        "latin1" => Some(Encoding::Latin1),
+        _ => None,
+    }
+}
+
+fn parse_format_arg(input: &str) -> Option<OutputFormat> {
+    match input.to_lowercase().as_str() {
+        "text" => Some(OutputFormat::Text),
+        "json" => Some(OutputFormat::Json),
+        "json-pretty" => Some(OutputFormat::JsonPretty),
+        "tsv" => Some(OutputFormat::Tsv),
+        _ => None,
+    }
+}
+
 pub fn parse_level_arg(input: &str) -> Option<LogLevel> {
     match input.to_lowercase().as_str() {
         "debug" => Some(LogLevel::Debug),
@@ -194,17 +1836,203 @@ pub fn parse_level_arg(input: &str) -> Option<LogLevel> {
     }
 }
 
+// splits "PATTERN=REPLACEMENT" at the first '=' not escaped with a backslash
+fn parse_replacement_arg(input: &str) -> Option<(Pattern, String)> {
+    let separator = input
+        .char_indices()
+        .find(|&(i, c)| c == '=' && !input[..i].ends_with('\\'))
+        .map(|(i, _)| i)?;
+    let pattern = Pattern::new(&input[..separator])?;
+    Some((pattern, input[separator + 1..].to_string()))
+}
+
+fn parse_category_level_arg(input: &str) -> Option<(Vec<u8>, LogLevel)> {
+    let (category, level) = input.rsplit_once('=')?;
+    Some((category.as_bytes().to_vec(), parse_level_arg(level)?))
+}
+
+fn parse_kv_filter_arg(input: &str) -> Option<(String, String)> {
+    let (key, value) = input.split_once('=')?;
+    Some((key.to_string(), value.to_string())).filter(|(key, _)| !key.is_empty())
+}
+
+fn parse_color_by_arg(input: &str) -> Option<ColorBy> {
+    match input.to_lowercase().as_str() {
+        "level" => Some(ColorBy::Level),
+        "category" => Some(ColorBy::Category),
+        "pid" => Some(ColorBy::Pid),
+        _ => None,
+    }
+}
+
+fn parse_fold_markers_arg(input: &str) -> Option<FoldMarkers> {
+    match input.to_lowercase().as_str() {
+        "none" => Some(FoldMarkers::None),
+        "vim" => Some(FoldMarkers::Vim),
+        _ => None,
+    }
+}
+
+fn parse_escape_char_arg(input: &str) -> Option<u8> {
+    match input.as_bytes() {
+        &[lead] if lead.is_ascii() => Some(lead),
+        _ => None,
+    }
+}
+
+// parses comma separated "CHAR=REPLACEMENT" pairs, e.g. "n=\\n,a=@"
+fn parse_escape_map_arg(input: &str) -> Option<Vec<(u8, Vec<u8>)>> {
+    let mut escapes = Vec::new();
+    let mut bytes = input.bytes();
+
+    loop {
+        let key = bytes.next()?;
+        if bytes.next()? != b'=' {
+            return None;
+        }
+
+        let mut replacement = Vec::new();
+        loop {
+            match bytes.next() {
+                None => {
+                    escapes.push((key, replacement));
+                    return Some(escapes);
+                }
+                Some(b',') => break,
+                Some(b'\\') => replacement.push(match bytes.next()? {
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    b'r' => b'\r',
+                    b'0' => b'\0',
+                    c @ (b'\\' | b',') => c,
+                    _ => return None,
+                }),
+                Some(c) => replacement.push(c),
+            }
+        }
+        escapes.push((key, replacement));
+    }
+}
+
+fn parse_escape_mode_arg(input: &str) -> Option<EscapeMode> {
+    match input.to_lowercase().as_str() {
+        "none" => Some(EscapeMode::None),
+        "standard" => Some(EscapeMode::Standard),
+        "aggressive" => Some(EscapeMode::Aggressive),
+        _ => None,
+    }
+}
+
+fn parse_separator_arg(input: &str) -> Option<EntrySeparator> {
+    match input {
+        "blank-line" => Some(EntrySeparator::BlankLine),
+        "header" => Some(EntrySeparator::Header),
+        _ => Pattern::new(input).map(EntrySeparator::Line),
+    }
+}
+
+fn parse_sort_key_arg(input: &str) -> Option<SortKey> {
+    match input.to_lowercase().as_str() {
+        "timestamp" => Some(SortKey::Timestamp),
+        "level" => Some(SortKey::Level),
+        "category" => Some(SortKey::Category),
+        "size" => Some(SortKey::Size),
+        _ => None,
+    }
+}
+
+fn parse_distinct_field_arg(input: &str) -> Option<DistinctField> {
+    match input.to_lowercase().as_str() {
+        "level" => Some(DistinctField::Level),
+        "category" => Some(DistinctField::Category),
+        "pid" => Some(DistinctField::Pid),
+        _ => None,
+    }
+}
+
+fn parse_only_field_arg(input: &str) -> Option<OnlyField> {
+    match input.to_lowercase().as_str() {
+        "timestamps" => Some(OnlyField::Timestamps),
+        "levels" => Some(OnlyField::Levels),
+        "categories" => Some(OnlyField::Categories),
+        "messages" => Some(OnlyField::Messages),
+        _ => None,
+    }
+}
+
+fn parse_json_shape_arg(input: &str) -> Option<JsonShape> {
+    match input.to_lowercase().as_str() {
+        "flat" => Some(JsonShape::Flat),
+        "nested" => Some(JsonShape::Nested),
+        _ => None,
+    }
+}
+
+fn parse_duration_arg(input: &str) -> Option<Duration> {
+    let input = input.trim();
+    let unit_pos = input.find(|c: char| !c.is_ascii_digit())?;
+    let value: i64 = input[..unit_pos].parse().ok()?;
+    match &input[unit_pos..] {
+        "ms" => Some(Duration::milliseconds(value)),
+        "s" => Some(Duration::seconds(value)),
+        "m" => Some(Duration::minutes(value)),
+        "h" => Some(Duration::hours(value)),
+        "d" => Some(Duration::days(value)),
+        _ => None,
+    }
+}
+
+fn parse_size_arg(input: &str) -> Option<usize> {
+    let input = input.trim();
+    let (digits, multiplier) = match input.chars().last()?.to_ascii_uppercase() {
+        'K' => (&input[..input.len() - 1], 1024),
+        'M' => (&input[..input.len() - 1], 1024 * 1024),
+        'G' => (&input[..input.len() - 1], 1024 * 1024 * 1024),
+        _ => (input, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+fn parse_level_map_arg(input: &str) -> Option<LevelMap> {
+    let mut level_map = LevelMap::default();
+    for mapping in input.split(',') {
+        let mut parts = mapping.splitn(2, '=');
+        let letter = match parts.next()?.trim().as_bytes() {
+            &[letter] if letter.is_ascii_alphabetic() => letter,
+            _ => return None,
+        };
+        let level = parse_level_arg(parts.next()?.trim())?;
+        level_map.insert(letter, level);
+    }
+    Some(level_map)
+}
+
 pub fn parse_date_time_arg(input: &str) -> Option<NaiveDateTime> {
     NaiveDateTime::parse_from_str(&input, "%F %T.%3f")
         .or_else(|_| NaiveDateTime::parse_from_str(&input, "%F %T"))
         .or_else(|_| NaiveDateTime::parse_from_str(&input, "%F %R"))
         .or_else(|_| NaiveDate::parse_from_str(&input, "%F").map(|d| d.and_hms(0, 0, 0)))
         .ok()
+        .or_else(|| parse_epoch_arg(input))
+}
+
+// seconds (10 digits) or milliseconds (13 digits) since the epoch, other lengths being ambiguous
+fn parse_epoch_arg(input: &str) -> Option<NaiveDateTime> {
+    if !input.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let value: i64 = input.parse().ok()?;
+    match input.len() {
+        10 => NaiveDateTime::from_timestamp_opt(value, 0),
+        13 => NaiveDateTime::from_timestamp_opt(value / 1000, (value % 1000) as u32 * 1_000_000),
+        _ => None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::log_entry::LogEntry;
     use chrono::NaiveDate;
 
     #[test]
@@ -238,4 +2066,181 @@ mod tests {
             NaiveDate::from_ymd(2020, 01, 10).and_hms(0, 0, 0)
         );
     }
+
+    #[test]
+    fn parse_date_time_arg_epoch_seconds() {
+        assert_eq!(
+            parse_date_time_arg("1578681199").unwrap(),
+            NaiveDate::from_ymd(2020, 1, 10).and_hms(18, 33, 19)
+        );
+    }
+
+    #[test]
+    fn parse_date_time_arg_epoch_millis() {
+        assert_eq!(
+            parse_date_time_arg("1578681199244").unwrap(),
+            NaiveDate::from_ymd(2020, 1, 10).and_hms_milli(18, 33, 19, 244)
+        );
+        assert_eq!(parse_date_time_arg("157868119924"), None);
+        assert_eq!(parse_date_time_arg("-157868119"), None);
+        assert_eq!(parse_date_time_arg("20200110"), None);
+    }
+
+    #[test]
+    fn parse_category_level_arg_values() {
+        assert_eq!(
+            parse_category_level_arg("db=debug"),
+            Some((b"db".to_vec(), LogLevel::Debug))
+        );
+        assert_eq!(
+            parse_category_level_arg("a=b=Warning"),
+            Some((b"a=b".to_vec(), LogLevel::Warning))
+        );
+        assert_eq!(parse_category_level_arg("db"), None);
+        assert_eq!(parse_category_level_arg("db=verbose"), None);
+    }
+
+    #[test]
+    fn parse_color_choice_arg_values() {
+        assert_eq!(parse_color_choice_arg("auto"), Some(ColorChoice::Auto));
+        assert_eq!(parse_color_choice_arg("Always"), Some(ColorChoice::Always));
+        assert_eq!(parse_color_choice_arg("never"), Some(ColorChoice::Never));
+        assert_eq!(parse_color_choice_arg("yes"), Some(ColorChoice::Always));
+        assert_eq!(parse_color_choice_arg("off"), Some(ColorChoice::Never));
+        assert_eq!(parse_color_choice_arg("sometimes"), None);
+    }
+
+    #[test]
+    fn resolve_color_tty() {
+        assert!(resolve_color(ColorChoice::Auto, false, true));
+        assert!(!resolve_color(ColorChoice::Auto, false, false));
+        assert!(!resolve_color(ColorChoice::Auto, true, true));
+        assert!(resolve_color(ColorChoice::Always, true, false));
+        assert!(resolve_color(ColorChoice::Always, false, false));
+        assert!(!resolve_color(ColorChoice::Never, false, true));
+    }
+
+    #[test]
+    fn parse_duration_arg_units() {
+        assert_eq!(
+            parse_duration_arg("500ms"),
+            Some(Duration::milliseconds(500))
+        );
+        assert_eq!(parse_duration_arg("30s"), Some(Duration::seconds(30)));
+        assert_eq!(parse_duration_arg("5m"), Some(Duration::minutes(5)));
+        assert_eq!(parse_duration_arg("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_duration_arg("30"), None);
+        assert_eq!(parse_duration_arg("s"), None);
+        assert_eq!(parse_duration_arg("3w"), None);
+    }
+
+    #[test]
+    fn read_file_list_skips_blank_lines_and_comments() -> Result<()> {
+        let list_path =
+            std::env::temp_dir().join(format!("riolog-{}-files-from.list", std::process::id()));
+        std::fs::write(
+            &list_path,
+            "# nightly logs\n/var/log/a.log\n\n  /var/log/b c.log  \r\n#/var/log/skipped.log\nrelative/d.log",
+        )?;
+
+        let file_list = read_file_list(&list_path);
+        std::fs::remove_file(&list_path)?;
+
+        assert_eq!(
+            file_list?,
+            [
+                PathBuf::from("/var/log/a.log"),
+                PathBuf::from("/var/log/b c.log"),
+                PathBuf::from("relative/d.log"),
+            ]
+        );
+        assert!(matches!(
+            read_file_list(Path::new("/nonexistent/riolog.list")),
+            Err(Error::CannotOpenFile(_, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_escape_map_arg_pairs() {
+        assert_eq!(
+            parse_escape_map_arg("n=\\n,t=\\t,a=@,c=\\,,r="),
+            Some(vec![
+                (b'n', b"\n".to_vec()),
+                (b't', b"\t".to_vec()),
+                (b'a', b"@".to_vec()),
+                (b'c', b",".to_vec()),
+                (b'r', b"".to_vec()),
+            ])
+        );
+        assert_eq!(parse_escape_map_arg(""), None);
+        assert_eq!(parse_escape_map_arg("n"), None);
+        assert_eq!(parse_escape_map_arg("nn=x"), None);
+        assert_eq!(parse_escape_map_arg("n=\\q"), None);
+        assert_eq!(parse_escape_char_arg("~"), Some(b'~'));
+        assert_eq!(parse_escape_char_arg("~~"), None);
+        assert_eq!(parse_escape_char_arg("§"), None);
+    }
+
+    #[test]
+    fn parse_size_arg_suffixes() {
+        assert_eq!(parse_size_arg("100"), Some(100));
+        assert_eq!(parse_size_arg("4k"), Some(4 * 1024));
+        assert_eq!(parse_size_arg("64M"), Some(64 * 1024 * 1024));
+        assert_eq!(parse_size_arg("2G"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size_arg("M"), None);
+        assert_eq!(parse_size_arg("1T"), None);
+    }
+
+    #[test]
+    fn parse_separator_arg_values() {
+        assert!(matches!(
+            parse_separator_arg("blank-line"),
+            Some(EntrySeparator::BlankLine)
+        ));
+        assert!(matches!(
+            parse_separator_arg("header"),
+            Some(EntrySeparator::Header)
+        ));
+        match parse_separator_arg("^-{5,}$") {
+            Some(EntrySeparator::Line(pattern)) => {
+                assert!(pattern.is_match(b"-----"));
+                assert!(!pattern.is_match(b"----"));
+            }
+            _ => panic!("Expected a separator line pattern"),
+        }
+        assert!(parse_separator_arg("(").is_none());
+    }
+
+    #[test]
+    fn parse_level_map_arg_letters() {
+        let level_map = Rc::new(parse_level_map_arg("v=debug, e=Critical").unwrap());
+        let entry = LogEntry::from_contents(b"-e:<1> 2020-01-01 20:00:00.000 UTC [A]: B".to_vec())
+            .with_level_map(level_map.clone());
+        assert_eq!(entry.level(), Some(LogLevel::Critical));
+        assert_eq!(level_map.get(b'v'), Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn parse_jsonl_fields_arg_names() {
+        assert_eq!(
+            parse_jsonl_fields_arg("level=severity, msg=message"),
+            Some(JsonFields {
+                level: "severity".to_string(),
+                timestamp: "ts".to_string(),
+                message: "message".to_string(),
+            })
+        );
+        assert_eq!(parse_jsonl_fields_arg("time=t"), None);
+        assert_eq!(parse_jsonl_fields_arg("ts="), None);
+        assert_eq!(parse_jsonl_fields_arg("ts"), None);
+    }
+
+    #[test]
+    fn parse_level_map_arg_invalid() {
+        assert_eq!(parse_level_map_arg("e"), None);
+        assert_eq!(parse_level_map_arg("e=error"), None);
+        assert_eq!(parse_level_map_arg("ee=debug"), None);
+        assert_eq!(parse_level_map_arg("v=debug,"), None);
+    }
 }
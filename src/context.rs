@@ -0,0 +1,280 @@
+use crate::log_entry::LogEntry;
+use chrono::{Duration, NaiveDateTime};
+use std::collections::VecDeque;
+use streaming_iterator::StreamingIterator;
+
+// Passes through entries accepted by the matcher together with up to `before` preceding and
+// `after` following entries. Disjoint windows are divided by the separator entry (if any).
+// With a time window, the surrounding entries are instead those with timestamps within
+// the window of the match. Entries without a timestamp count as having the timestamp of
+// the last entry before them (in the reading direction) which has one.
+pub struct ContextIter<I, F> {
+    input: I,
+    matcher: F,
+    before: usize,
+    after: usize,
+    time_window: Option<Duration>,
+    separator: Option<LogEntry>,
+    history: VecDeque<(LogEntry, Option<NaiveDateTime>)>,
+    pending: VecDeque<LogEntry>,
+    current: Option<LogEntry>,
+    input_is_current: bool,
+    after_left: usize,
+    last_timestamp: Option<NaiveDateTime>,
+    match_timestamp: Option<NaiveDateTime>,
+    printed_any: bool,
+    gap: bool,
+}
+
+impl<I, F> ContextIter<I, F>
+where
+    I: StreamingIterator<Item = LogEntry>,
+    F: FnMut(&LogEntry) -> bool,
+{
+    pub fn new(
+        input: I,
+        matcher: F,
+        before: usize,
+        after: usize,
+        separator: Option<LogEntry>,
+    ) -> Self {
+        ContextIter {
+            input,
+            matcher,
+            before,
+            after,
+            time_window: None,
+            separator,
+            history: VecDeque::with_capacity(before + 1),
+            pending: VecDeque::new(),
+            current: None,
+            input_is_current: false,
+            after_left: 0,
+            last_timestamp: None,
+            match_timestamp: None,
+            printed_any: false,
+            gap: false,
+        }
+    }
+
+    // replaces the numbers of preceding and following entries
+    pub fn with_time_window(mut self, time_window: Option<Duration>) -> Self {
+        self.time_window = time_window;
+        self
+    }
+
+    fn yield_pending(&mut self) {
+        self.current = self.pending.pop_front();
+        self.input_is_current = false;
+    }
+}
+
+// drops entries too far from the newest one to precede a match, returns whether any was dropped
+fn trim_history(
+    history: &mut VecDeque<(LogEntry, Option<NaiveDateTime>)>,
+    before: usize,
+    time_window: Option<Duration>,
+    newest: Option<NaiveDateTime>,
+) -> bool {
+    let len = history.len();
+    match time_window {
+        Some(window) => {
+            while let Some(&(_, timestamp)) = history.front() {
+                if is_within(timestamp, newest, window) {
+                    break;
+                }
+                history.pop_front();
+            }
+        }
+        None => {
+            while history.len() > before {
+                history.pop_front();
+            }
+        }
+    }
+    history.len() < len
+}
+
+fn is_within(
+    timestamp: Option<NaiveDateTime>,
+    other: Option<NaiveDateTime>,
+    window: Duration,
+) -> bool {
+    match (timestamp, other) {
+        (Some(timestamp), Some(other)) if timestamp > other => timestamp - other <= window,
+        (Some(timestamp), Some(other)) => other - timestamp <= window,
+        _ => false,
+    }
+}
+
+impl<I, F> StreamingIterator for ContextIter<I, F>
+where
+    I: StreamingIterator<Item = LogEntry>,
+    F: FnMut(&LogEntry) -> bool,
+{
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        if !self.pending.is_empty() {
+            self.yield_pending();
+            return;
+        }
+
+        self.current = None;
+        self.input_is_current = false;
+
+        loop {
+            self.input.advance();
+            let entry = match self.input.get() {
+                Some(entry) => entry,
+                None => return,
+            };
+            let timestamp = entry.timestamp().or(self.last_timestamp);
+            self.last_timestamp = timestamp;
+
+            if (self.matcher)(entry) {
+                self.gap |=
+                    trim_history(&mut self.history, self.before, self.time_window, timestamp);
+                if self.printed_any && self.gap {
+                    self.pending.extend(self.separator.clone());
+                }
+                self.printed_any = true;
+                self.gap = false;
+                self.after_left = self.after;
+                self.match_timestamp = timestamp;
+
+                if self.pending.is_empty() && self.history.is_empty() {
+                    self.input_is_current = true;
+                } else {
+                    self.pending
+                        .extend(self.history.drain(..).map(|(entry, _)| entry));
+                    self.pending.push_back(entry.clone());
+                    self.yield_pending();
+                }
+                return;
+            } else if match self.time_window {
+                Some(window) => is_within(self.match_timestamp, timestamp, window),
+                None => self.after_left > 0,
+            } {
+                self.after_left = self.after_left.saturating_sub(1);
+                self.input_is_current = true;
+                return;
+            } else if self.before > 0 || self.time_window.is_some() {
+                self.history.push_back((entry.clone(), timestamp));
+                self.gap |=
+                    trim_history(&mut self.history, self.before, self.time_window, timestamp);
+            } else {
+                self.gap = true;
+            }
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.input_is_current {
+            self.input.get()
+        } else {
+            self.current.as_ref()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(input: &'static [&'static [u8]]) -> impl StreamingIterator<Item = LogEntry> {
+        streaming_iterator::convert(
+            input
+                .iter()
+                .map(|contents| LogEntry::from_contents(contents.to_vec())),
+        )
+    }
+
+    fn run(input: &'static [&'static [u8]], before: usize, after: usize) -> Vec<Vec<u8>> {
+        let separator = LogEntry::separator(b"--\n".to_vec());
+        let matcher = |entry: &LogEntry| entry.contents().starts_with(b"match");
+        ContextIter::new(entries(input), matcher, before, after, Some(separator))
+            .map(|entry| entry.contents().to_vec())
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn context_disjoint_windows_single_separator() {
+        let input: &'static [&'static [u8]] =
+            &[b"a", b"match 1", b"b", b"c", b"d", b"match 2", b"e", b"f"];
+        assert_eq!(
+            run(input, 1, 1),
+            vec![
+                b"a".to_vec(),
+                b"match 1".to_vec(),
+                b"b".to_vec(),
+                b"--\n".to_vec(),
+                b"d".to_vec(),
+                b"match 2".to_vec(),
+                b"e".to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn context_adjacent_windows_no_separator() {
+        let input: &'static [&'static [u8]] = &[b"match 1", b"a", b"b", b"match 2"];
+        assert_eq!(
+            run(input, 1, 1),
+            vec![
+                b"match 1".to_vec(),
+                b"a".to_vec(),
+                b"b".to_vec(),
+                b"match 2".to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn context_time_window() {
+        let input: &'static [&'static [u8]] = &[
+            b"No header",
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: a",
+            b"-info:<1> 2020-01-01 20:00:50.000 UTC [A]: b",
+            b"  continuation of b",
+            b"-info:<1> 2020-01-01 20:01:00.000 UTC [A]: match 1",
+            b"-info:<1> 2020-01-01 20:01:30.000 UTC [A]: c",
+            b"  continuation of c",
+            b"-info:<1> 2020-01-01 20:02:01.000 UTC [A]: d",
+            b"-info:<1> 2020-01-01 20:05:00.000 UTC [A]: match 2",
+            b"-info:<1> 2020-01-01 20:05:10.000 UTC [A]: e",
+        ];
+        let separator = LogEntry::separator(b"--\n".to_vec());
+        let matcher = |entry: &LogEntry| entry.message().unwrap_or_default().starts_with(b"match");
+        let output: Vec<Vec<u8>> = ContextIter::new(entries(input), matcher, 0, 0, Some(separator))
+            .with_time_window(Some(Duration::seconds(30)))
+            .map(|entry| entry.contents().to_vec())
+            .cloned()
+            .collect();
+        assert_eq!(
+            output,
+            vec![
+                input[2].to_vec(),
+                input[3].to_vec(),
+                input[4].to_vec(),
+                input[5].to_vec(),
+                input[6].to_vec(),
+                b"--\n".to_vec(),
+                input[8].to_vec(),
+                input[9].to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn context_without_windows_is_filter() {
+        let input: &'static [&'static [u8]] = &[b"a", b"match 1", b"b", b"match 2"];
+        let matcher = |entry: &LogEntry| entry.contents().starts_with(b"match");
+        let output: Vec<Vec<u8>> = ContextIter::new(entries(input), matcher, 0, 0, None)
+            .map(|entry| entry.contents().to_vec())
+            .cloned()
+            .collect();
+        assert_eq!(output, vec![b"match 1".to_vec(), b"match 2".to_vec()]);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::cli::Encoding;
+use std::io::{self, Write};
+
+const REPLACEMENT_CHARACTER: &[u8] = "\u{FFFD}".as_bytes();
+
+// Converts the output to UTF-8. A sequence cut off at the end of a write is completed by
+// the next one, so callers may write in arbitrary chunks, but have to call `finish` at the end.
+pub struct TranscodingWriter<W: Write> {
+    inner: W,
+    encoding: Encoding,
+    pending: Vec<u8>, // start of a UTF-8 sequence continued by the next write
+}
+
+impl<W: Write> TranscodingWriter<W> {
+    pub fn new(inner: W, encoding: Encoding) -> Self {
+        TranscodingWriter {
+            inner,
+            encoding,
+            pending: Vec::new(),
+        }
+    }
+
+    // replaces a sequence left incomplete at the end of the output
+    pub fn finish(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            self.pending.clear();
+            self.inner.write_all(REPLACEMENT_CHARACTER)?;
+        }
+        self.inner.flush()
+    }
+
+    fn write_lossy(&mut self, buf: &[u8]) -> io::Result<()> {
+        let joined;
+        let mut input = if self.pending.is_empty() {
+            buf
+        } else {
+            self.pending.extend_from_slice(buf);
+            joined = std::mem::take(&mut self.pending);
+            &joined[..]
+        };
+
+        loop {
+            match std::str::from_utf8(input) {
+                Ok(_) => return self.inner.write_all(input),
+                Err(error) => {
+                    let (valid, rest) = input.split_at(error.valid_up_to());
+                    self.inner.write_all(valid)?;
+                    match error.error_len() {
+                        Some(len) => {
+                            self.inner.write_all(REPLACEMENT_CHARACTER)?;
+                            input = &rest[len..];
+                        }
+                        None => {
+                            self.pending = rest.to_vec();
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_latin1(&mut self, buf: &[u8]) -> io::Result<()> {
+        let mut transcoded = Vec::with_capacity(buf.len() * 2);
+        for &c in buf {
+            if c.is_ascii() {
+                transcoded.push(c);
+            } else {
+                transcoded.push(0xC0 | (c >> 6));
+                transcoded.push(0x80 | (c & 0x3F));
+            }
+        }
+        self.inner.write_all(&transcoded)
+    }
+}
+
+impl<W: Write> Write for TranscodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.encoding {
+            Encoding::Passthrough => return self.inner.write(buf),
+            Encoding::Utf8Lossy => self.write_lossy(buf)?,
+            Encoding::Latin1 => self.write_latin1(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcode(chunks: &[&[u8]], encoding: Encoding) -> io::Result<String> {
+        let mut out_buf = Vec::new();
+        let mut writer = TranscodingWriter::new(&mut out_buf, encoding);
+        for chunk in chunks {
+            writer.write_all(chunk)?;
+        }
+        writer.finish()?;
+        Ok(String::from_utf8(out_buf).expect("Invalid UTF-8 output"))
+    }
+
+    #[test]
+    fn transcoding_writer_utf8_lossy() -> io::Result<()> {
+        assert_eq!(
+            transcode(
+                &[b"Za\xC5\xBC\xC3\xB3\xC5", b"\x82\xFF ok\n"],
+                Encoding::Utf8Lossy
+            )?,
+            "Zażół\u{FFFD} ok\n"
+        );
+        assert_eq!(
+            transcode(&[b"cut \xE2\x82"], Encoding::Utf8Lossy)?,
+            "cut \u{FFFD}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn transcoding_writer_latin1() -> io::Result<()> {
+        assert_eq!(
+            transcode(&[b"Gr\xFC\xDFe, ", b"caf\xE9\n"], Encoding::Latin1)?,
+            "Grüße, café\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn transcoding_writer_passthrough() -> io::Result<()> {
+        let mut out_buf = Vec::new();
+        let mut writer = TranscodingWriter::new(&mut out_buf, Encoding::Passthrough);
+        writer.write_all(b"raw \xFF")?;
+        writer.finish()?;
+        assert_eq!(out_buf, b"raw \xFF");
+        Ok(())
+    }
+}
@@ -3,3 +3,11 @@ pub const EOL: &[u8] = b"\r\n";
 
 #[cfg(not(target_os = "windows"))]
 pub const EOL: &[u8] = b"\n";
+
+pub fn trim_eol(buf: &[u8]) -> &[u8] {
+    let len = buf
+        .iter()
+        .rposition(|&c| c != b'\n' && c != b'\r')
+        .map_or(0, |pos| pos + 1);
+    &buf[..len]
+}
@@ -7,8 +7,14 @@ pub enum Error {
     Io(io::Error),
     CannotOpenFile(PathBuf, io::Error),
     CannotCreateFile(PathBuf, io::Error),
-    CannotUseLessStdin,
+    CannotUseStdin(String),
+    CannotRunCommand(String, io::Error),
     InvalidCliOptionValue(&'static str),
+    Interrupted,
+    SelfCheckFailed(usize),
+    NotATerminal,
+    TooLargeToExplore(usize),
+    OutOfOrder(String),
 }
 
 impl std::error::Error for Error {}
@@ -29,12 +35,30 @@ impl fmt::Display for Error {
             Error::CannotCreateFile(file, error) => {
                 write!(f, "Cannot create file {}: {}", file.display(), error)
             }
-            Error::CannotUseLessStdin => write!(f, "Cannot open stdin stream for 'less' process"),
+            Error::CannotUseStdin(command) => {
+                write!(f, "Cannot open stdin stream for '{}' process", command)
+            }
+            Error::CannotRunCommand(command, error) => {
+                write!(f, "Cannot run command '{}': {}", command, error)
+            }
             Error::InvalidCliOptionValue(opt) => write!(
                 f,
                 "Invalid value provided for command line option '{}'",
                 opt
             ),
+            Error::Interrupted => write!(f, "Interrupted"),
+            Error::SelfCheckFailed(position) => write!(
+                f,
+                "Self-check failed: merged entries diverge at entry {}",
+                position
+            ),
+            Error::NotATerminal => write!(f, "Interactive mode requires a terminal"),
+            Error::TooLargeToExplore(limit) => write!(
+                f,
+                "Shown entries take more than {} bytes, too many to explore interactively; narrow them down with filters or raise --memory-limit",
+                limit
+            ),
+            Error::OutOfOrder(violation) => write!(f, "{}", violation),
         }
     }
 }
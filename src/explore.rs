@@ -0,0 +1,335 @@
+use crate::cli::FilteringOptions;
+use crate::direction::Direction;
+use crate::error::Error;
+use crate::filtering::filtering_iter;
+use crate::log_entry::LogEntry;
+use crate::result::Result;
+#[cfg(unix)]
+use crate::tty::RawTerminal;
+#[cfg(unix)]
+use std::io::Read;
+use std::io::Write;
+use streaming_iterator::StreamingIterator;
+
+const ESC: u8 = 0x1B;
+const CLEAR_SCREEN: &[u8] = b"\x1B[H\x1B[2J";
+const CODE_PROMPT: &[u8] = b"\x1B[7m";
+const CODE_NORMAL: &[u8] = b"\x1B[0m";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Key {
+    Text(String),
+    Backspace,
+    ClearQuery,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Flow {
+    Continue,
+    Refilter,
+    Quit,
+}
+
+// keys read from the terminal at once, e.g. typed text or a single escape sequence of a key
+fn parse_keys(input: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let rest = &input[pos..];
+        let (key, len) = match rest {
+            [ESC] => (Some(Key::Quit), 1),
+            [ESC, b'[', b'A', ..] => (Some(Key::Up), 3),
+            [ESC, b'[', b'B', ..] => (Some(Key::Down), 3),
+            [ESC, b'[', b'5', b'~', ..] => (Some(Key::PageUp), 4),
+            [ESC, b'[', b'6', b'~', ..] => (Some(Key::PageDown), 4),
+            [ESC, b'[', sequence @ ..] => {
+                // other keys, skipped up to the final byte of the sequence
+                let len = sequence
+                    .iter()
+                    .position(|c| (0x40..=0x7E).contains(c))
+                    .map_or(sequence.len(), |pos| pos + 1);
+                (None, 2 + len)
+            }
+            [ESC, ..] => (None, 1),
+            [0x03, ..] | [0x04, ..] => (Some(Key::Quit), 1),
+            [0x7F, ..] | [0x08, ..] => (Some(Key::Backspace), 1),
+            [0x15, ..] => (Some(Key::ClearQuery), 1),
+            [c, ..] if *c < 0x20 => (None, 1),
+            _ => {
+                let len = rest
+                    .iter()
+                    .position(|&c| c < 0x20 || c == 0x7F)
+                    .unwrap_or(rest.len());
+                let text = String::from_utf8_lossy(&rest[..len]).into_owned();
+                (Some(Key::Text(text)), len)
+            }
+        };
+        keys.extend(key);
+        pos += len;
+    }
+
+    keys
+}
+
+// collects the entries to explore, failing if they take more memory than the limit
+pub fn load_entries(
+    mut log_entries: impl StreamingIterator<Item = LogEntry>,
+    limit: usize,
+) -> Result<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    let mut size = 0;
+    while let Some(entry) = log_entries.next() {
+        size += entry.contents().len();
+        if size > limit {
+            return Err(Error::TooLargeToExplore(limit));
+        }
+        entries.push(entry.clone());
+    }
+    Ok(entries)
+}
+
+// Loaded entries containing the text typed so far, regardless of case, rendered as lines fitting the screen width.
+struct Explorer {
+    entries: Vec<LogEntry>,
+    query: String,
+    lines: Vec<Vec<u8>>,
+    matches: usize,
+    scroll: usize, // number of lines below the screen
+    width: usize,
+}
+
+impl Explorer {
+    fn new(entries: Vec<LogEntry>) -> Self {
+        Explorer {
+            entries,
+            query: String::new(),
+            lines: Vec::new(),
+            matches: 0,
+            scroll: 0,
+            width: 0,
+        }
+    }
+
+    fn refilter(
+        &mut self,
+        render: &mut impl FnMut(Vec<LogEntry>, usize) -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        let options = FilteringOptions {
+            contains: Some(self.query.clone()).filter(|query| !query.is_empty()),
+            contains_unicode: true,
+            ..Default::default()
+        };
+        let input = streaming_iterator::convert(self.entries.iter().cloned());
+        let matching: Vec<_> = filtering_iter(input, options, Direction::Forward)
+            .cloned()
+            .collect();
+        self.matches = matching.len();
+
+        let rendered = render(matching, self.width)?;
+        let rendered = rendered.strip_suffix(b"\n").unwrap_or(&rendered);
+        self.lines = rendered
+            .split(|&c| c == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line).to_vec())
+            .collect();
+        self.scroll = 0;
+        Ok(())
+    }
+
+    // handles the key on a screen with given number of rows for entries
+    fn handle(&mut self, key: Key, rows: usize) -> Flow {
+        let max_scroll = self.lines.len().saturating_sub(rows);
+        match key {
+            Key::Text(text) => {
+                self.query.push_str(&text);
+                return Flow::Refilter;
+            }
+            Key::Backspace if self.query.pop().is_some() => return Flow::Refilter,
+            Key::ClearQuery if !self.query.is_empty() => {
+                self.query.clear();
+                return Flow::Refilter;
+            }
+            Key::Up => self.scroll = (self.scroll + 1).min(max_scroll),
+            Key::Down => self.scroll = self.scroll.saturating_sub(1),
+            Key::PageUp => self.scroll = (self.scroll + rows).min(max_scroll),
+            Key::PageDown => self.scroll = self.scroll.saturating_sub(rows),
+            Key::Quit => return Flow::Quit,
+            Key::Backspace | Key::ClearQuery => {}
+        }
+        Flow::Continue
+    }
+
+    // the newest lines, unless scrolled back
+    fn visible(&self, rows: usize) -> &[Vec<u8>] {
+        let end = self.lines.len() - self.scroll.min(self.lines.len());
+        &self.lines[end.saturating_sub(rows)..end]
+    }
+
+    fn draw(&self, writer: &mut impl Write, rows: usize) -> Result<()> {
+        let mut screen = CLEAR_SCREEN.to_vec();
+        for line in self.visible(rows) {
+            screen.extend_from_slice(line);
+            screen.extend_from_slice(b"\n");
+        }
+        screen.extend_from_slice(format!("\x1B[{};1H", rows + 1).as_bytes());
+        screen.extend_from_slice(CODE_PROMPT);
+        screen.extend_from_slice(format!("/{}", self.query).as_bytes());
+        screen.extend_from_slice(CODE_NORMAL);
+        screen.extend_from_slice(
+            format!(
+                "  {} of {} entries (arrows scroll, Esc quits)",
+                self.matches,
+                self.entries.len()
+            )
+            .as_bytes(),
+        );
+        writer.write_all(&screen)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// Shows the entries on the terminal with a prompt, showing only the entries containing the typed
+// text. Entries are rendered for the width of the screen, in lines ending with line feeds.
+#[cfg(unix)]
+pub fn explore(
+    entries: Vec<LogEntry>,
+    mut render: impl FnMut(Vec<LogEntry>, usize) -> Result<Vec<u8>>,
+) -> Result<()> {
+    let mut terminal = RawTerminal::open().map_err(|_| Error::NotATerminal)?;
+    let mut explorer = Explorer::new(entries);
+    let mut input = [0; 64];
+
+    loop {
+        let (width, height) = terminal.size();
+        let rows = height.saturating_sub(1).max(1);
+        if width != explorer.width {
+            explorer.width = width;
+            explorer.refilter(&mut render)?;
+        }
+        explorer.draw(&mut terminal, rows)?;
+
+        let len = terminal.read(&mut input)?;
+        if len == 0 {
+            return Ok(());
+        }
+        for key in parse_keys(&input[..len]) {
+            match explorer.handle(key, rows) {
+                Flow::Continue => {}
+                Flow::Refilter => explorer.refilter(&mut render)?,
+                Flow::Quit => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn explore(
+    _entries: Vec<LogEntry>,
+    _render: impl FnMut(Vec<LogEntry>, usize) -> Result<Vec<u8>>,
+) -> Result<()> {
+    Err(Error::NotATerminal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<LogEntry> {
+        [
+            &b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Connected\n\n"[..],
+            b"-warning:<1> 2020-01-01 20:00:01.000 UTC [A]: Timeout\n\n",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: Disconnected\n\n",
+        ]
+        .iter()
+        .map(|contents| LogEntry::from_contents(contents.to_vec()))
+        .collect()
+    }
+
+    // one line per entry
+    fn render(entries: Vec<LogEntry>, _width: usize) -> Result<Vec<u8>> {
+        Ok(entries
+            .iter()
+            .flat_map(|entry| {
+                [entry.message().unwrap_or_default().trim_ascii_end(), b"\n"].concat()
+            })
+            .collect())
+    }
+
+    #[test]
+    fn parse_keys_text_and_sequences() {
+        assert_eq!(
+            parse_keys("zał\x1B[A\x1B[6~\x1B[1;5C\x7F\r".as_bytes()),
+            vec![
+                Key::Text("zał".to_string()),
+                Key::Up,
+                Key::PageDown,
+                Key::Backspace
+            ]
+        );
+        assert_eq!(parse_keys(b"\x1B"), vec![Key::Quit]);
+        assert_eq!(parse_keys(b"\x03"), vec![Key::Quit]);
+    }
+
+    #[test]
+    fn explorer_refilters_by_query() -> Result<()> {
+        let mut explorer = Explorer::new(entries());
+        let mut render = render;
+        explorer.refilter(&mut render)?;
+        assert_eq!(explorer.matches, 3);
+
+        for key in parse_keys(b"time") {
+            assert_eq!(explorer.handle(key, 10), Flow::Refilter);
+        }
+        explorer.refilter(&mut render)?;
+        assert_eq!(explorer.visible(10), &[b"Timeout".to_vec()][..]);
+        assert_eq!(explorer.matches, 1);
+
+        assert_eq!(explorer.handle(Key::ClearQuery, 10), Flow::Refilter);
+        explorer.refilter(&mut render)?;
+        assert_eq!(explorer.matches, 3);
+        assert_eq!(explorer.handle(Key::Backspace, 10), Flow::Continue);
+        Ok(())
+    }
+
+    #[test]
+    fn explorer_scrolls_within_lines() -> Result<()> {
+        let mut explorer = Explorer::new(entries());
+        explorer.refilter(&mut render)?;
+
+        assert_eq!(
+            explorer.visible(2),
+            &[b"Timeout".to_vec(), b"Disconnected".to_vec()][..]
+        );
+        explorer.handle(Key::PageUp, 2);
+        assert_eq!(
+            explorer.visible(2),
+            &[b"Connected".to_vec(), b"Timeout".to_vec()][..]
+        );
+        explorer.handle(Key::Down, 2);
+        assert_eq!(explorer.visible(2).len(), 2);
+        assert_eq!(explorer.visible(2)[1], b"Disconnected".to_vec());
+        Ok(())
+    }
+
+    #[test]
+    fn load_entries_limit() {
+        let size = entries().iter().map(|entry| entry.contents().len()).sum();
+        let input = || streaming_iterator::convert(entries());
+        assert_eq!(
+            load_entries(input(), size)
+                .map(|entries| entries.len())
+                .ok(),
+            Some(3)
+        );
+        assert!(matches!(
+            load_entries(input(), size - 1),
+            Err(Error::TooLargeToExplore(_))
+        ));
+    }
+}
@@ -1,59 +1,758 @@
-use crate::cli::FilteringOptions;
+use crate::cli::{DistinctField, FilteringOptions, TimeResolution};
+use crate::context::ContextIter;
 use crate::direction::Direction;
-use crate::log_entry::LogEntry;
+use crate::eol;
+use crate::key_values::find_value;
+use crate::log_entry::{LogEntry, LogLevel};
+use crate::pattern::Pattern;
+use chrono::{Duration, NaiveDateTime, Timelike};
+use memchr::memmem;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::rc::Rc;
 use streaming_iterator::StreamingIterator;
-use subslice::SubsliceExt;
 
 pub fn filtering_iter(
+    input: impl StreamingIterator<Item = LogEntry>,
+    options: FilteringOptions,
+    direction: Direction,
+) -> impl StreamingIterator<Item = LogEntry> {
+    filtering_iter_with_stats(input, options, direction, Rc::default())
+}
+
+pub fn filtering_iter_with_stats(
     input: impl StreamingIterator<Item = LogEntry>,
     FilteringOptions {
         since,
         until,
+        time_resolution,
         min_level,
+        category_levels,
         contains,
+        contains_unicode,
+        from_pattern,
+        to_pattern,
+        well_formed_only,
+        before_context,
+        after_context,
+        context_separator,
+        context_time,
+        distinct_by,
+        kv_filters,
+        max_age,
+        category_pattern,
+        scan_window,
+        pair_pattern,
+        min_duration,
     }: FilteringOptions,
     direction: Direction,
+    stats: Rc<FilterStats>,
 ) -> impl StreamingIterator<Item = LogEntry> {
-    input
+    let scanning_stats = stats.clone();
+    let skipping_stats = stats.clone();
+    let taking_stats = stats.clone();
+    let max_age_stats = stats.clone();
+    let level_stats = stats.clone();
+    let well_formed_stats = stats.clone();
+    let category_stats = stats.clone();
+    let contains_stats = stats.clone();
+    let kv_stats = stats.clone();
+    let region_stats = stats.clone();
+    let distinct_stats = stats.clone();
+    let pair_stats = stats;
+
+    // as given, for explaining
+    let (since_bound, until_bound) = (since, until);
+    // with seconds resolution, --until includes the whole second it falls in
+    let since = since.map(|since| truncate_timestamp(since, time_resolution));
+    let until = until.map(|until| match time_resolution {
+        TimeResolution::Seconds => {
+            truncate_timestamp(until, time_resolution) + Duration::seconds(1)
+        }
+        TimeResolution::Millis => truncate_timestamp(until, time_resolution),
+    });
+    let truncate = move |timestamp| truncate_timestamp(timestamp, time_resolution);
+
+    let mut first_timestamp = None;
+    let bounded = input
+        .take_while(move |entry| {
+            let scan = match (scan_window, entry.timestamp()) {
+                (Some(scan_window), Some(timestamp)) => {
+                    let first_timestamp = *first_timestamp.get_or_insert(timestamp);
+                    match direction {
+                        Direction::Forward => timestamp <= first_timestamp + scan_window,
+                        Direction::Reverse => timestamp >= first_timestamp - scan_window,
+                    }
+                }
+                _ => true,
+            };
+            if !scan {
+                scanning_stats.reject(&scanning_stats.scan_window, entry, || {
+                    "beyond --scan-window from the first entry".to_string()
+                });
+            }
+            scan
+        })
         .skip_while(move |entry| {
-            entry
+            let skip = entry
                 .timestamp()
+                .map(truncate)
                 .and_then(|timestamp| match direction {
                     Direction::Forward => since.map(|since| timestamp < since),
                     Direction::Reverse => until.map(|until| timestamp >= until),
                 })
-                .unwrap_or(false)
+                .unwrap_or(false);
+            if skip {
+                match direction {
+                    Direction::Forward => {
+                        skipping_stats.reject(&skipping_stats.since, entry, || {
+                            before_since(entry, since_bound)
+                        })
+                    }
+                    Direction::Reverse => {
+                        skipping_stats.reject(&skipping_stats.until, entry, || {
+                            past_until(entry, until_bound)
+                        })
+                    }
+                }
+            }
+            skip
         })
         .take_while(move |entry| {
-            entry
+            let take = entry
                 .timestamp()
+                .map(truncate)
                 .and_then(|timestamp| match direction {
                     Direction::Forward => until.map(|until| timestamp < until),
                     Direction::Reverse => since.map(|since| timestamp >= since),
                 })
-                .unwrap_or(true)
-        })
-        .filter(move |entry| {
-            if let (Some(min_level), Some(level)) = (min_level, entry.level()) {
-                (level as i32) >= (min_level as i32)
-            } else {
-                true
+                .unwrap_or(true);
+            if !take {
+                match direction {
+                    Direction::Forward => taking_stats.reject(&taking_stats.until, entry, || {
+                        past_until(entry, until_bound)
+                    }),
+                    Direction::Reverse => taking_stats.reject(&taking_stats.since, entry, || {
+                        before_since(entry, since_bound)
+                    }),
+                }
             }
-        })
-        .filter(move |entry| {
-            if let Some(contains) = &contains {
-                entry.contents().find(contains.as_ref()).is_some()
-            } else {
-                true
+            take
+        });
+
+    // region markers are found among all entries, also those dropped by other filters
+    let region = RegionIter::new(
+        bounded,
+        from_pattern.zip(to_pattern),
+        direction,
+        region_stats,
+    );
+
+    let context = ContextIter::new(
+        region,
+        {
+            let mut newest = None;
+            let mut max_age = move |entry: &LogEntry| {
+                let keep = match (max_age, entry.timestamp()) {
+                    (Some(max_age), Some(timestamp)) => {
+                        let newest = newest.get_or_insert(timestamp);
+                        *newest = timestamp.max(*newest);
+                        timestamp >= *newest - max_age
+                    }
+                    _ => true,
+                };
+                max_age_stats.check(&max_age_stats.max_age, entry, keep, || {
+                    format!(
+                        "timestamp {} older than --max-age before the newest entry {}",
+                        format_timestamp(entry.timestamp()),
+                        format_timestamp(newest)
+                    )
+                })
+            };
+            let level = move |entry: &LogEntry| {
+                // categories given their own level use it instead of the global one
+                let min_level = entry
+                    .category()
+                    .and_then(|category| category_levels.get(category))
+                    .copied()
+                    .or(min_level);
+                let keep = if let (Some(min_level), Some(level)) = (min_level, entry.level()) {
+                    (level as i32) >= (min_level as i32)
+                } else {
+                    true
+                };
+                level_stats.check(&level_stats.level, entry, keep, || {
+                    format!(
+                        "level {} < min {}",
+                        entry.level().map_or("none", LogLevel::name),
+                        min_level.map_or("none", LogLevel::name)
+                    )
+                })
+            };
+            let well_formed = move |entry: &LogEntry| {
+                let keep = !well_formed_only
+                    || (entry.timestamp().is_some()
+                        && entry.level().is_some()
+                        && entry.category().is_some()
+                        && entry.pid().is_some());
+                well_formed_stats.check(&well_formed_stats.well_formed, entry, keep, || {
+                    "incomplete header".to_string()
+                })
+            };
+            let category = move |entry: &LogEntry| {
+                let keep = category_pattern.as_ref().is_none_or(|pattern| {
+                    entry
+                        .category()
+                        .is_some_and(|category| pattern.is_match(category))
+                });
+                category_stats.check(&category_stats.category, entry, keep, || {
+                    match entry.category() {
+                        Some(category) => format!(
+                            "category {} does not match --category-regex",
+                            String::from_utf8_lossy(category)
+                        ),
+                        None => "no category".to_string(),
+                    }
+                })
+            };
+            let contains_lowercase = contains
+                .as_ref()
+                .filter(|_| contains_unicode)
+                .map(|contains| contains.to_lowercase());
+            // built once so that the needle is not preprocessed again for every entry
+            let finder = contains
+                .as_ref()
+                .map(|contains| memmem::Finder::new(contains.as_bytes()).into_owned());
+            let needle = contains.clone().unwrap_or_default();
+            let contains = move |entry: &LogEntry| {
+                let keep = match (&finder, &contains_lowercase) {
+                    (Some(finder), Some(contains_lowercase)) => {
+                        match std::str::from_utf8(entry.contents()) {
+                            Ok(text) if text.is_ascii() => {
+                                contains_ascii_lowercase(text.as_bytes(), contains_lowercase)
+                            }
+                            Ok(text) => text.to_lowercase().contains(contains_lowercase.as_str()),
+                            Err(_) => finder.find(entry.contents()).is_some(),
+                        }
+                    }
+                    (Some(finder), None) => finder.find(entry.contents()).is_some(),
+                    (None, _) => true,
+                };
+                contains_stats.check(&contains_stats.contains, entry, keep, || {
+                    format!("does not contain \"{}\"", needle)
+                })
+            };
+            let kv = move |entry: &LogEntry| {
+                let message = entry.message().unwrap_or_else(|| entry.contents());
+                let keep = kv_filters.iter().all(|(key, value)| {
+                    find_value(message, key.as_bytes()) == Some(value.as_bytes())
+                });
+                kv_stats.check(&kv_stats.kv, entry, keep, || {
+                    let (key, value) = kv_filters
+                        .iter()
+                        .find(|(key, value)| {
+                            find_value(message, key.as_bytes()) != Some(value.as_bytes())
+                        })
+                        .expect("No failed key-value filter");
+                    format!("{} is not {}", key, value)
+                })
+            };
+            let mut seen = HashSet::new();
+            let mut distinct = move |entry: &LogEntry| {
+                let key = distinct_by.and_then(|field| field_value(entry, field));
+                let keep = key.is_none_or(|key| seen.insert(key));
+                distinct_stats.check(&distinct_stats.distinct, entry, keep, || {
+                    let field = distinct_by.expect("No field to be distinct by");
+                    let name = match field {
+                        DistinctField::Level => "level",
+                        DistinctField::Category => "category",
+                        DistinctField::Pid => "pid",
+                    };
+                    let value = field_value(entry, field).unwrap_or_default();
+                    format!("repeated {} {}", name, String::from_utf8_lossy(&value))
+                })
+            };
+            move |entry: &LogEntry| {
+                max_age(entry)
+                    && level(entry)
+                    && well_formed(entry)
+                    && category(entry)
+                    && contains(entry)
+                    && kv(entry)
+                    && distinct(entry)
             }
-        })
+        },
+        before_context,
+        after_context,
+        context_separator
+            .filter(|_| before_context > 0 || after_context > 0 || context_time.is_some())
+            .map(|separator| LogEntry::separator([separator.as_bytes(), eol::EOL].concat())),
+    )
+    .with_time_window(context_time);
+
+    PairIter::new(
+        context,
+        pair_pattern.map(|pattern| Pairing::new(pattern, min_duration)),
+        pair_stats,
+    )
+}
+
+// whether ASCII text contains the lowercase needle regardless of case, without allocating
+fn contains_ascii_lowercase(text: &[u8], needle: &str) -> bool {
+    needle.is_empty()
+        || text
+            .windows(needle.len())
+            .any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn format_timestamp(timestamp: Option<NaiveDateTime>) -> String {
+    timestamp.map_or_else(|| "none".to_string(), |timestamp| timestamp.to_string())
+}
+
+fn before_since(entry: &LogEntry, since: Option<NaiveDateTime>) -> String {
+    format!(
+        "timestamp {} before since {}",
+        format_timestamp(entry.timestamp()),
+        format_timestamp(since)
+    )
+}
+
+fn past_until(entry: &LogEntry, until: Option<NaiveDateTime>) -> String {
+    format!(
+        "timestamp {} past until {}",
+        format_timestamp(entry.timestamp()),
+        format_timestamp(until)
+    )
+}
+
+// drops the part of the timestamp below the resolution
+pub fn truncate_timestamp(timestamp: NaiveDateTime, resolution: TimeResolution) -> NaiveDateTime {
+    let nanos = timestamp.nanosecond();
+    let truncated = match resolution {
+        TimeResolution::Seconds => 0,
+        TimeResolution::Millis => nanos - nanos % 1_000_000,
+    };
+    timestamp.with_nanosecond(truncated).unwrap_or(timestamp)
+}
+
+// Number of entries dropped by each filtering stage. Since `--until` (or `--since` in reverse
+// mode) and `--scan-window` stop reading the input, only the entry that ended reading is counted
+// for them. With --explain, the reason for dropping each entry is also written.
+#[derive(Default)]
+pub struct FilterStats {
+    since: Cell<usize>,
+    until: Cell<usize>,
+    scan_window: Cell<usize>,
+    max_age: Cell<usize>,
+    level: Cell<usize>,
+    well_formed: Cell<usize>,
+    category: Cell<usize>,
+    contains: Cell<usize>,
+    kv: Cell<usize>,
+    region: Cell<usize>,
+    distinct: Cell<usize>,
+    pair: Cell<usize>,
+    explain: Option<RefCell<Box<dyn Write>>>,
+}
+
+impl FilterStats {
+    pub fn explaining(writer: impl Write + 'static) -> Self {
+        FilterStats {
+            explain: Some(RefCell::new(Box::new(writer))),
+            ..Default::default()
+        }
+    }
+
+    // counts the entry as dropped by the stage, the reason is only built when explaining
+    fn reject(&self, counter: &Cell<usize>, entry: &LogEntry, reason: impl FnOnce() -> String) {
+        counter.set(counter.get() + 1);
+        if let Some(writer) = &self.explain {
+            let first_line = entry
+                .contents()
+                .split(|&c| c == b'\n')
+                .next()
+                .unwrap_or_default();
+            let first_line = first_line.strip_suffix(b"\r").unwrap_or(first_line);
+            // explaining is best effort, like other reports on standard error
+            let _ = writeln!(
+                writer.borrow_mut(),
+                "dropped: {}: {}",
+                reason(),
+                String::from_utf8_lossy(first_line)
+            );
+        }
+    }
+
+    fn check(
+        &self,
+        counter: &Cell<usize>,
+        entry: &LogEntry,
+        keep: bool,
+        reason: impl FnOnce() -> String,
+    ) -> bool {
+        if !keep {
+            self.reject(counter, entry, reason);
+        }
+        keep
+    }
+
+    pub fn report(&self, options: &FilteringOptions) -> String {
+        let counters = [
+            &self.since,
+            &self.until,
+            &self.scan_window,
+            &self.max_age,
+            &self.level,
+            &self.well_formed,
+            &self.category,
+            &self.contains,
+            &self.kv,
+            &self.region,
+            &self.distinct,
+            &self.pair,
+        ];
+
+        filter_stages(options)
+            .iter()
+            .zip(counters.iter())
+            .filter(|((_, active), _)| *active)
+            .map(|((name, _), counter)| format!("{}: dropped {}", name, counter.get()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// names of filtering stages in the order of `FilterStats` counters, with whether they are active
+fn filter_stages(options: &FilteringOptions) -> [(&'static str, bool); 12] {
+    [
+        // apart from follow mode, --max-age is applied as --since
+        (
+            "since",
+            options.since.is_some() || options.max_age.is_some(),
+        ),
+        ("until", options.until.is_some()),
+        ("scan-window", options.scan_window.is_some()),
+        ("max-age", options.max_age.is_some()),
+        (
+            "level",
+            options.min_level.is_some() || !options.category_levels.is_empty(),
+        ),
+        ("well-formed", options.well_formed_only),
+        ("category", options.category_pattern.is_some()),
+        ("contains", options.contains.is_some()),
+        ("kv-filter", !options.kv_filters.is_empty()),
+        ("region", options.from_pattern.is_some()),
+        ("distinct", options.distinct_by.is_some()),
+        ("pair-regex", options.pair_pattern.is_some()),
+    ]
+}
+
+pub fn active_filters(options: &FilteringOptions) -> Vec<&'static str> {
+    filter_stages(options)
+        .iter()
+        .filter(|(_, active)| *active)
+        .map(|&(name, _)| name)
+        .collect()
+}
+
+// Passes only entries in regions starting at an entry matching the first pattern and ending at
+// the next one matching the second pattern, or at the end of the input. Read newest first,
+// entries are held until an older one matching just one of the patterns tells whether the region
+// is open before them, so that the output is the reverse of the one read oldest first.
+struct RegionIter<I> {
+    input: I,
+    patterns: Option<(Pattern, Pattern)>,
+    direction: Direction,
+    inside: bool,
+    pending: Vec<(LogEntry, bool, bool)>, // newest first, with whether they match each pattern
+    ready: VecDeque<LogEntry>,
+    current: Option<LogEntry>,
+    done: bool,
+    stats: Rc<FilterStats>,
+}
+
+impl<I> RegionIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    fn new(
+        input: I,
+        patterns: Option<(Pattern, Pattern)>,
+        direction: Direction,
+        stats: Rc<FilterStats>,
+    ) -> Self {
+        RegionIter {
+            input,
+            patterns,
+            direction,
+            inside: false,
+            pending: Vec::new(),
+            ready: VecDeque::new(),
+            current: None,
+            done: false,
+            stats,
+        }
+    }
+
+    // whether the entry is inside a region, updating whether the next one is, when read oldest
+    // first
+    fn step(inside: &mut bool, starts: bool, ends: bool) -> bool {
+        let keep = *inside || starts;
+        *inside = if *inside { !ends } else { starts };
+        keep
+    }
+
+    // decides pending entries, given whether a region is open before the oldest of them
+    fn resolve(&mut self, mut inside: bool) {
+        let kept: Vec<_> = self
+            .pending
+            .iter()
+            .rev()
+            .map(|&(_, starts, ends)| Self::step(&mut inside, starts, ends))
+            .collect();
+        let pending = std::mem::take(&mut self.pending);
+        for ((entry, _, _), keep) in pending.into_iter().zip(kept.into_iter().rev()) {
+            if check_region(&self.stats, &entry, keep) {
+                self.ready.push_back(entry);
+            }
+        }
+    }
+}
+
+impl<I> StreamingIterator for RegionIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        if self.patterns.is_none() {
+            self.input.advance();
+            return;
+        }
+
+        loop {
+            if let Some(entry) = self.ready.pop_front() {
+                self.current = Some(entry);
+                return;
+            }
+            let next = if self.done { None } else { self.input.next() };
+            let entry = match next {
+                Some(entry) => entry,
+                None => {
+                    // nothing is open before the oldest entry
+                    self.done = true;
+                    if self.pending.is_empty() {
+                        self.current = None;
+                        return;
+                    }
+                    self.resolve(false);
+                    continue;
+                }
+            };
+            let (start, end) = self.patterns.as_ref().expect("No region patterns");
+            let starts = start.is_match(entry.contents());
+            let ends = end.is_match(entry.contents());
+
+            match self.direction {
+                Direction::Forward => {
+                    let keep = Self::step(&mut self.inside, starts, ends);
+                    if check_region(&self.stats, entry, keep) {
+                        self.current = Some(entry.clone());
+                        return;
+                    }
+                }
+                Direction::Reverse => {
+                    let entry = entry.clone();
+                    // an entry matching just one pattern opens or closes a region after it
+                    if starts != ends {
+                        self.resolve(starts);
+                    }
+                    self.pending.push((entry, starts, ends));
+                }
+            }
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.patterns.is_some() {
+            self.current.as_ref()
+        } else {
+            self.input.get()
+        }
+    }
+}
+
+fn check_region(stats: &FilterStats, entry: &LogEntry, keep: bool) -> bool {
+    stats.check(&stats.region, entry, keep, || {
+        "outside the --from/--to region".to_string()
+    })
+}
+
+// Starts of operations not ended yet are forgotten beyond this number, oldest first, so that
+// lines never followed by their end do not accumulate.
+const MAX_PENDING_PAIRS: usize = 10_000;
+
+// Operations delimited by pairs of entries matching a pattern, identified by the text captured
+// by its group named "id". Entries in which its group named "end" takes part in the match end
+// operations and the other ones start them. An end ends the latest start with the same id before
+// it; an end whose start was not seen is ignored, and so is a start never ended.
+struct Pairing {
+    pattern: Pattern,
+    min_duration: Option<Duration>,
+    pending: HashMap<Vec<u8>, (u64, NaiveDateTime)>, // by id: sequence number, start timestamp
+    order: BTreeMap<u64, Vec<u8>>,                   // ids of pending starts by sequence number
+    sequence: u64,
+}
+
+impl Pairing {
+    fn new(pattern: Pattern, min_duration: Option<Duration>) -> Self {
+        Pairing {
+            pattern,
+            min_duration,
+            pending: HashMap::new(),
+            order: BTreeMap::new(),
+            sequence: 0,
+        }
+    }
+
+    // time since the start of the operation ended by the entry, if it lasted at least
+    // the minimum duration. Entries without a timestamp are never paired.
+    fn accept(&mut self, entry: &LogEntry) -> Option<Duration> {
+        let timestamp = entry.timestamp()?;
+        let contents = entry.contents();
+        let captures = self.pattern.captures(contents)?;
+        let id = &contents[captures.name("id")?];
+
+        if captures.name("end").is_some() {
+            let (sequence, start) = self.pending.remove(id)?;
+            self.order.remove(&sequence);
+            let elapsed = timestamp - start;
+            return Some(elapsed).filter(|&elapsed| {
+                self.min_duration
+                    .is_none_or(|min_duration| elapsed >= min_duration)
+            });
+        }
+
+        self.sequence += 1;
+        if let Some((sequence, _)) = self.pending.insert(id.to_vec(), (self.sequence, timestamp)) {
+            self.order.remove(&sequence);
+        }
+        self.order.insert(self.sequence, id.to_vec());
+        if self.pending.len() > MAX_PENDING_PAIRS {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.pending.remove(&oldest);
+            }
+        }
+        None
+    }
+}
+
+// Passes through only entries ending an operation, annotated with its duration. Separators of
+// context groups are dropped along with the entries they divided. Entries are passed through
+// untouched when pairing is disabled.
+struct PairIter<I> {
+    input: I,
+    pairing: Option<Pairing>,
+    current: Option<LogEntry>,
+    stats: Rc<FilterStats>,
+}
+
+impl<I> PairIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    fn new(input: I, pairing: Option<Pairing>, stats: Rc<FilterStats>) -> Self {
+        PairIter {
+            input,
+            pairing,
+            current: None,
+            stats,
+        }
+    }
+}
+
+impl<I> StreamingIterator for PairIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        let pairing = match &mut self.pairing {
+            Some(pairing) => pairing,
+            None => {
+                self.input.advance();
+                return;
+            }
+        };
+
+        self.current = None;
+        while let Some(entry) = self.input.next() {
+            if entry.is_separator() {
+                continue;
+            }
+            if let Some(elapsed) = pairing.accept(entry) {
+                let mut entry = entry.clone();
+                entry.set_elapsed(Some(elapsed));
+                self.current = Some(entry);
+                return;
+            }
+            self.stats.reject(&self.stats.pair, entry, || {
+                "not ending a --pair-regex operation long enough".to_string()
+            });
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.pairing.is_some() {
+            self.current.as_ref()
+        } else {
+            self.input.get()
+        }
+    }
+}
+
+// Counts all occurrences of a needle in entry contents, also several within one entry.
+// Occurrences do not overlap.
+pub struct MatchCounter {
+    finder: memmem::Finder<'static>,
+    lowercase_finder: Option<memmem::Finder<'static>>, // when ignoring case
+}
+
+impl MatchCounter {
+    pub fn new(needle: &str, ignore_case: bool) -> Self {
+        MatchCounter {
+            finder: memmem::Finder::new(needle.as_bytes()).into_owned(),
+            lowercase_finder: Some(needle)
+                .filter(|_| ignore_case)
+                .map(|needle| memmem::Finder::new(needle.to_lowercase().as_bytes()).into_owned()),
+        }
+    }
+
+    pub fn count(&self, contents: &[u8]) -> usize {
+        match (&self.lowercase_finder, std::str::from_utf8(contents)) {
+            (Some(finder), Ok(text)) => finder.find_iter(text.to_lowercase().as_bytes()).count(),
+            _ => self.finder.find_iter(contents).count(),
+        }
+    }
+}
+
+pub fn field_value(entry: &LogEntry, field: DistinctField) -> Option<Vec<u8>> {
+    match field {
+        DistinctField::Level => entry.level().map(|level| level.name().as_bytes().to_vec()),
+        DistinctField::Category => entry.category().map(<[u8]>::to_vec),
+        DistinctField::Pid => entry.pid().map(|pid| pid.to_string().into_bytes()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::log_entry::LogLevel;
-    use chrono::NaiveDate;
+    use chrono::{Duration, NaiveDate};
+    use std::alloc::{GlobalAlloc, Layout, System};
 
     const LOG_INPUT: &[&[u8]] = &[
         b"-debug:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1",
@@ -79,6 +778,7 @@ mod tests {
                 until: None,
                 contains: None,
                 min_level: None,
+                ..Default::default()
             },
             Direction::Forward,
         );
@@ -96,6 +796,7 @@ mod tests {
                 until: None,
                 contains: None,
                 min_level: None,
+                ..Default::default()
             },
             Direction::Reverse,
         );
@@ -116,6 +817,7 @@ mod tests {
                 until: None,
                 contains: None,
                 min_level: None,
+                ..Default::default()
             },
             Direction::Forward,
         );
@@ -136,6 +838,7 @@ mod tests {
                 until: None,
                 contains: None,
                 min_level: None,
+                ..Default::default()
             },
             Direction::Reverse,
         );
@@ -156,6 +859,7 @@ mod tests {
                 until: Some(NaiveDate::from_ymd(2020, 01, 01).and_hms(21, 30, 00)),
                 contains: None,
                 min_level: None,
+                ..Default::default()
             },
             Direction::Forward,
         );
@@ -176,6 +880,7 @@ mod tests {
                 until: Some(NaiveDate::from_ymd(2020, 01, 01).and_hms(21, 30, 00)),
                 contains: None,
                 min_level: None,
+                ..Default::default()
             },
             Direction::Reverse,
         );
@@ -196,6 +901,7 @@ mod tests {
                 until: None,
                 contains: None,
                 min_level: Some(LogLevel::Critical),
+                ..Default::default()
             },
             Direction::Forward,
         );
@@ -216,6 +922,7 @@ mod tests {
                 until: None,
                 contains: None,
                 min_level: Some(LogLevel::Critical),
+                ..Default::default()
             },
             Direction::Reverse,
         );
@@ -226,6 +933,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn filtering_iter_category_levels() {
+        const INPUT: &[&[u8]] = &[
+            b"-debug:<1> 2020-01-01 20:00:00.000 UTC [db]: Query",
+            b"-debug:<1> 2020-01-01 20:00:01.000 UTC [net]: Packet",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [net]: Connected",
+            b"-warning:<1> 2020-01-01 20:00:03.000 UTC [net]: Slow",
+            b"-debug:<1> 2020-01-01 20:00:04.000 No category",
+            b"-warning:<1> 2020-01-01 20:00:05.000 UTC [db]: Lock wait",
+        ];
+        let log_iter = filtering_iter(
+            to_log_iter(INPUT.iter().copied()),
+            FilteringOptions {
+                min_level: Some(LogLevel::Warning),
+                category_levels: vec![(b"db".to_vec(), LogLevel::Debug)]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            vec![INPUT[0], INPUT[3], INPUT[5]]
+        );
+    }
+
     #[test]
     fn filtering_iter_contains() {
         let log_iter = to_log_iter(LOG_INPUT.iter().copied());
@@ -236,6 +971,7 @@ mod tests {
                 until: None,
                 contains: Some("Text2".into()),
                 min_level: None,
+                ..Default::default()
             },
             Direction::Forward,
         );
@@ -246,6 +982,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn filtering_iter_contains_large_input() {
+        let entries: Vec<Vec<u8>> = (0..50_000)
+            .map(|i| {
+                format!(
+                    "-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Request {} {}\n\n",
+                    i,
+                    if i % 7 == 0 { "needle" } else { "needl" }
+                )
+                .into_bytes()
+            })
+            .collect();
+        let log_iter = streaming_iterator::convert(
+            entries
+                .iter()
+                .map(|contents| LogEntry::from_contents(contents.clone())),
+        );
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                contains: Some("needle".into()),
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        let expected: Vec<&Vec<u8>> = entries
+            .iter()
+            .filter(|contents| contents.windows(6).any(|window| window == b"needle"))
+            .collect();
+        let filtered: Vec<Vec<u8>> = log_iter
+            .map(|entry| entry.contents().to_vec())
+            .cloned()
+            .collect();
+        assert_eq!(filtered.len(), 50_000 / 7 + 1);
+        assert!(filtered.iter().eq(expected));
+    }
+
     #[test]
     fn filtering_iter_contains_rev() {
         let log_iter = to_log_iter(LOG_INPUT.iter().copied().rev());
@@ -256,6 +1030,7 @@ mod tests {
                 until: None,
                 contains: Some("Text2".into()),
                 min_level: None,
+                ..Default::default()
             },
             Direction::Reverse,
         );
@@ -265,4 +1040,752 @@ mod tests {
             LOG_INPUT[1..2].iter().copied().rev().collect::<Vec<_>>()
         );
     }
+
+    const REGION_INPUT: &[&[u8]] = &[
+        b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: idle",
+        b"-info:<16866> 2020-01-01 20:01:00.000 UTC [A]: request 1 started",
+        b"-debug:<16866> 2020-01-01 20:02:00.000 UTC [A]: processing",
+        b"-info:<16866> 2020-01-01 20:03:00.000 UTC [A]: request 1 finished",
+        b"-info:<16866> 2020-01-01 20:04:00.000 UTC [A]: idle",
+        b"-info:<16866> 2020-01-01 20:05:00.000 UTC [A]: request 2 started",
+        b"-info:<16866> 2020-01-01 20:06:00.000 UTC [A]: request 2 finished",
+        b"-info:<16866> 2020-01-01 20:07:00.000 UTC [A]: idle",
+    ];
+
+    fn region_options() -> FilteringOptions {
+        FilteringOptions {
+            from_pattern: Pattern::new(r"request \d+ started"),
+            to_pattern: Pattern::new(r"request \d+ finished"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn filtering_iter_region() {
+        let log_iter = to_log_iter(REGION_INPUT.iter().copied());
+        let log_iter = filtering_iter(log_iter, region_options(), Direction::Forward);
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            [&REGION_INPUT[1..4], &REGION_INPUT[5..7]].concat()
+        );
+    }
+
+    #[test]
+    fn filtering_iter_region_rev() {
+        let log_iter = to_log_iter(REGION_INPUT.iter().copied().rev());
+        let log_iter = filtering_iter(log_iter, region_options(), Direction::Reverse);
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            [&REGION_INPUT[1..4], &REGION_INPUT[5..7]]
+                .concat()
+                .into_iter()
+                .rev()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn filtering_iter_region_rev_agrees_with_forward() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: a",
+            b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: END a",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: BEGIN b",
+            b"-info:<1> 2020-01-01 20:00:03.000 UTC [A]: BEGIN c",
+            b"-info:<1> 2020-01-01 20:00:04.000 UTC [A]: END BEGIN d",
+            b"-info:<1> 2020-01-01 20:00:05.000 UTC [A]: e",
+            b"-info:<1> 2020-01-01 20:00:06.000 UTC [A]: END BEGIN f",
+            b"-info:<1> 2020-01-01 20:00:07.000 UTC [A]: g",
+            b"-info:<1> 2020-01-01 20:00:08.000 UTC [A]: END g",
+            b"-info:<1> 2020-01-01 20:00:09.000 UTC [A]: BEGIN h",
+            b"-info:<1> 2020-01-01 20:00:10.000 UTC [A]: i",
+        ];
+        let options = || FilteringOptions {
+            from_pattern: Pattern::new("BEGIN"),
+            to_pattern: Pattern::new("END"),
+            ..Default::default()
+        };
+        let forward = filtering_iter(
+            to_log_iter(input.iter().copied()),
+            options(),
+            Direction::Forward,
+        )
+        .owned()
+        .collect::<Vec<_>>();
+        let mut reverse = filtering_iter(
+            to_log_iter(input.iter().copied().rev()),
+            options(),
+            Direction::Reverse,
+        )
+        .owned()
+        .collect::<Vec<_>>();
+        reverse.reverse();
+
+        assert_eq!(forward, [&input[2..5], &input[6..9], &input[9..]].concat());
+        assert_eq!(reverse, forward);
+    }
+
+    #[test]
+    fn filtering_iter_region_markers_dropped_by_level() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: before",
+            b"-debug:<1> 2020-01-01 20:00:01.000 UTC [A]: BEGIN",
+            b"-debug:<1> 2020-01-01 20:00:02.000 UTC [A]: detail",
+            b"-info:<1> 2020-01-01 20:00:03.000 UTC [A]: inside",
+            b"-debug:<1> 2020-01-01 20:00:04.000 UTC [A]: END",
+            b"-info:<1> 2020-01-01 20:00:05.000 UTC [A]: after",
+        ];
+        for &direction in &[Direction::Forward, Direction::Reverse] {
+            let entries: Vec<_> = match direction {
+                Direction::Forward => input.to_vec(),
+                Direction::Reverse => input.iter().rev().copied().collect(),
+            };
+            let log_iter = filtering_iter(
+                to_log_iter(entries.into_iter()),
+                FilteringOptions {
+                    min_level: Some(LogLevel::Info),
+                    from_pattern: Pattern::new("BEGIN"),
+                    to_pattern: Pattern::new("END"),
+                    ..Default::default()
+                },
+                direction,
+            );
+            assert_eq!(log_iter.owned().collect::<Vec<_>>(), vec![input[3]]);
+        }
+    }
+
+    #[test]
+    fn filtering_iter_stats() {
+        let options = FilteringOptions {
+            since: Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(21, 0, 0)),
+            until: Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(22, 30, 0)),
+            min_level: Some(LogLevel::Warning),
+            contains: Some("Text4".into()),
+            ..Default::default()
+        };
+        let stats = Rc::new(FilterStats::default());
+        let log_iter = to_log_iter(LOG_INPUT.iter().copied());
+        let log_iter =
+            filtering_iter_with_stats(log_iter, options.clone(), Direction::Forward, stats.clone());
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            LOG_INPUT[3..4].to_vec()
+        );
+        assert_eq!(
+            stats.report(&options),
+            "since: dropped 1, until: dropped 1, level: dropped 1, contains: dropped 1"
+        );
+    }
+
+    #[test]
+    fn filtering_iter_contains_unicode() {
+        let input: &[&[u8]] = &[
+            "-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Order at CAFÉ".as_bytes(),
+            "-info:<16866> 2020-01-01 21:00:00.000 UTC [A]: Order at cafe".as_bytes(),
+            b"-info:<16866> 2020-01-01 22:00:00.000 UTC [A]: Invalid \xff caf\xc3\xa9",
+        ];
+        let log_iter = to_log_iter(input.iter().copied());
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                contains: Some("café".into()),
+                contains_unicode: true,
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            vec![input[0], input[2]]
+        );
+    }
+
+    #[test]
+    fn filtering_iter_well_formed_only() {
+        let input: &[&[u8]] = &[
+            b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Complete",
+            b"-info:<16866> 2020-01-01 20:00:01.000 UTC: No category",
+            b"-info:<pid> 2020-01-01 20:00:02.000 UTC [A]: Invalid pid",
+            b"-unknown:<16866> 2020-01-01 20:00:03.000 UTC [A]: Unknown level",
+            b"-info:<16866> 2020-01-01 20:00 UTC [A]: Short timestamp",
+            b"MESSAGE Alphabet",
+            b"-warning:<1> 2020-01-01 20:00:04.000 UTC [B]: Complete too",
+        ];
+        let log_iter = to_log_iter(input.iter().copied());
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                well_formed_only: true,
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            vec![input[0], input[6]]
+        );
+    }
+
+    #[test]
+    fn filtering_iter_distinct_by_category() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: First A",
+            b"-info:<2> 2020-01-01 20:00:01.000 UTC [B]: First B",
+            b"-warning:<3> 2020-01-01 20:00:02.000 UTC [A]: Second A",
+            b"MESSAGE Alphabet",
+            b"-info:<4> 2020-01-01 20:00:03.000 UTC [C]: First C",
+            b"-info:<5> 2020-01-01 20:00:04.000 UTC [B]: Second B",
+        ];
+        let log_iter = to_log_iter(input.iter().copied());
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                distinct_by: Some(DistinctField::Category),
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            vec![input[0], input[1], input[3], input[4]]
+        );
+    }
+
+    #[test]
+    fn filtering_iter_max_age_rev() {
+        let log_iter = to_log_iter(LOG_INPUT.iter().copied().rev());
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                max_age: Some(Duration::minutes(60)),
+                ..Default::default()
+            },
+            Direction::Reverse,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            LOG_INPUT[2..].iter().copied().rev().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn filtering_iter_max_age_sliding() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: First",
+            b"-info:<1> 2020-01-01 22:00:00.000 UTC [A]: Second",
+            b"-info:<1> 2020-01-01 20:30:00.000 UTC [A]: Late",
+            b"-info:<1> 2020-01-01 21:30:00.000 UTC [A]: Third",
+        ];
+        let log_iter = to_log_iter(input.iter().copied());
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                max_age: Some(Duration::minutes(60)),
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            vec![input[0], input[1], input[3]]
+        );
+    }
+
+    #[test]
+    fn filtering_iter_category_pattern() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [DBRead]: Select",
+            b"-info:<1> 2020-01-01 20:00:01.000 UTC [Net]: Connect",
+            b"MESSAGE Alphabet",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [DBWrite]: Insert",
+        ];
+        let log_iter = to_log_iter(input.iter().copied());
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                category_pattern: Pattern::new("^DB"),
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            vec![input[0], input[3]]
+        );
+    }
+
+    #[test]
+    fn filtering_iter_context_time() {
+        let filter = |direction: Direction| {
+            let input: Vec<&[u8]> = match direction {
+                Direction::Forward => LOG_INPUT.to_vec(),
+                Direction::Reverse => LOG_INPUT.iter().rev().copied().collect(),
+            };
+            let log_iter = filtering_iter(
+                to_log_iter(input.into_iter()),
+                FilteringOptions {
+                    contains: Some("Text3".into()),
+                    context_time: Some(Duration::minutes(30)),
+                    ..Default::default()
+                },
+                direction,
+            );
+            log_iter.owned().collect::<Vec<_>>()
+        };
+
+        assert_eq!(filter(Direction::Forward), LOG_INPUT[1..4].to_vec());
+        assert_eq!(
+            filter(Direction::Reverse),
+            LOG_INPUT[1..4].iter().rev().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn filtering_iter_since_until_with_offsets() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01T21:59:00.000+02:00 [A]: Text1",
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Text2",
+            b"-info:<1> 2020-01-01T15:30:00.000-05:00 [A]: Text3",
+            b"-info:<1> 2020-01-01T21:00:00.000Z [A]: Text4",
+        ];
+        let log_iter = filtering_iter(
+            to_log_iter(input.iter().copied()),
+            FilteringOptions {
+                since: Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(20, 0, 0)),
+                until: Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(21, 0, 0)),
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(log_iter.owned().collect::<Vec<_>>(), input[1..3].to_vec());
+    }
+
+    #[test]
+    fn filtering_iter_time_resolution() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:41:59.999 UTC [A]: Text1",
+            b"-info:<1> 2020-01-01 20:42:00.000 UTC [A]: Text2",
+            b"-info:<1> 2020-01-01 20:42:00.476 UTC [A]: Text3",
+            b"-info:<1> 2020-01-01 20:42:00.999 UTC [A]: Text4",
+            b"-info:<1> 2020-01-01 20:42:01.000 UTC [A]: Text5",
+        ];
+        let filter = |time_resolution, direction| {
+            let mut ordered = input.to_vec();
+            if let Direction::Reverse = direction {
+                ordered.reverse();
+            }
+            let mut output = filtering_iter(
+                to_log_iter(ordered.into_iter()),
+                FilteringOptions {
+                    since: Some(NaiveDate::from_ymd(2020, 1, 1).and_hms_milli(20, 41, 59, 500)),
+                    until: Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(20, 42, 0)),
+                    time_resolution,
+                    ..Default::default()
+                },
+                direction,
+            )
+            .owned()
+            .collect::<Vec<_>>();
+            output.sort_by(|a, b| a.contents().cmp(b.contents()));
+            output
+        };
+
+        for &direction in &[Direction::Forward, Direction::Reverse] {
+            assert_eq!(
+                filter(TimeResolution::Millis, direction),
+                input[..1].to_vec()
+            );
+            assert_eq!(
+                filter(TimeResolution::Seconds, direction),
+                input[..4].to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn truncate_timestamp_resolutions() {
+        let timestamp = NaiveDate::from_ymd(2020, 1, 1).and_hms_micro(20, 42, 0, 476_123);
+        assert_eq!(
+            truncate_timestamp(timestamp, TimeResolution::Seconds),
+            NaiveDate::from_ymd(2020, 1, 1).and_hms(20, 42, 0)
+        );
+        assert_eq!(
+            truncate_timestamp(timestamp, TimeResolution::Millis),
+            NaiveDate::from_ymd(2020, 1, 1).and_hms_milli(20, 42, 0, 476)
+        );
+    }
+
+    #[test]
+    fn match_counter_counts_every_occurrence() {
+        let counter = MatchCounter::new("timeout", false);
+        assert_eq!(counter.count(b"timeout after timeout"), 2);
+        assert_eq!(counter.count(b"timeouttimeout Timeout"), 2);
+        assert_eq!(counter.count(b"none"), 0);
+
+        let counter = MatchCounter::new("Łódź", true);
+        assert_eq!(counter.count("ŁÓDŹ and łódź".as_bytes()), 2);
+        assert_eq!(counter.count(b"\xff\xfe"), 0);
+    }
+
+    #[test]
+    fn filtering_iter_kv_filters() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [Auth]: Login userID=42 ok",
+            b"-info:<1> 2020-01-01 20:00:01.000 UTC [Auth]: Login userID=420 ok",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [Auth]: Login sessionUserID=42 ok",
+            b"-warning:<1> 2020-01-01 20:00:03.000 UTC [Auth]: Logout userID=\"42\", reason=idle",
+            b"userID=42 reason=idle",
+        ];
+        let filter = |kv_filters: &[(&str, &str)]| {
+            let log_iter = filtering_iter(
+                to_log_iter(input.iter().copied()),
+                FilteringOptions {
+                    kv_filters: kv_filters
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect(),
+                    ..Default::default()
+                },
+                Direction::Forward,
+            );
+            log_iter.owned().collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            filter(&[("userID", "42")]),
+            vec![input[0], input[3], input[4]]
+        );
+        assert_eq!(
+            filter(&[("userID", "42"), ("reason", "idle")]),
+            vec![input[3], input[4]]
+        );
+        assert_eq!(filter(&[]), input.to_vec());
+    }
+
+    #[test]
+    fn filtering_iter_pairs_over_min_duration() {
+        let input: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [Job]: start fast",
+            b"-info:<1> 2020-01-01 20:00:00.100 UTC [Job]: start slow",
+            b"-info:<1> 2020-01-01 20:00:00.200 UTC [Job]: progress slow",
+            b"-info:<1> 2020-01-01 20:00:00.300 UTC [Job]: end fast",
+            b"-info:<1> 2020-01-01 20:00:00.400 UTC [Job]: end orphan",
+            b"-info:<1> 2020-01-01 20:00:01.000 UTC [Job]: end slow",
+            b"end undated",
+        ];
+        let pairs = |min_duration: Option<Duration>| {
+            let log_iter = filtering_iter(
+                to_log_iter(input.iter().copied()),
+                FilteringOptions {
+                    pair_pattern: Pattern::new(r"(start|(?P<end>end)) (?P<id>\w+)"),
+                    min_duration,
+                    ..Default::default()
+                },
+                Direction::Forward,
+            );
+            log_iter
+                .map(|entry| (entry.contents().to_vec(), entry.elapsed()))
+                .owned()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            pairs(Some(Duration::milliseconds(500))),
+            vec![(input[5].to_vec(), Some(Duration::milliseconds(900)))]
+        );
+        assert_eq!(
+            pairs(None),
+            vec![
+                (input[3].to_vec(), Some(Duration::milliseconds(300))),
+                (input[5].to_vec(), Some(Duration::milliseconds(900))),
+            ]
+        );
+    }
+
+    #[test]
+    fn pairing_forgets_oldest_pending_starts() {
+        let mut pairing = Pairing::new(Pattern::new(r"start (?P<id>\d+)").unwrap(), None);
+        for id in 0..=MAX_PENDING_PAIRS {
+            let contents = format!("-info:<1> 2020-01-01 20:00:00.000 UTC [Job]: start {}", id);
+            assert_eq!(
+                pairing.accept(&LogEntry::from_contents(contents.into_bytes())),
+                None
+            );
+        }
+        assert_eq!(pairing.pending.len(), MAX_PENDING_PAIRS);
+        assert!(!pairing.pending.contains_key(&b"0"[..]));
+        assert!(pairing.pending.contains_key(&b"1"[..]));
+    }
+
+    #[test]
+    fn pairing_ignores_orphan_ends() {
+        let mut pairing = Pairing::new(
+            Pattern::new(r"(start|(?P<end>end)) (?P<id>\w+)").unwrap(),
+            None,
+        );
+        let mut accept = |time: &str, text: &str| {
+            let contents = format!("-info:<1> 2020-01-01 {} UTC [Job]: {}", time, text);
+            pairing.accept(&LogEntry::from_contents(contents.into_bytes()))
+        };
+        assert_eq!(accept("20:00:00.000", "end job"), None);
+        assert_eq!(accept("20:00:01.000", "start job"), None);
+        assert_eq!(accept("20:00:02.000", "start job"), None);
+        assert_eq!(
+            accept("20:00:02.500", "end job"),
+            Some(Duration::milliseconds(500))
+        );
+        assert_eq!(accept("20:00:03.000", "end job"), None);
+    }
+
+    #[test]
+    fn filtering_iter_scan_window_stops_reading() {
+        let read = Rc::new(Cell::new(0));
+        let read_counter = read.clone();
+        let log_iter = to_log_iter(LOG_INPUT.iter().copied()).inspect(move |_| {
+            read_counter.set(read_counter.get() + 1);
+        });
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                scan_window: Some(Duration::minutes(60)),
+                ..Default::default()
+            },
+            Direction::Forward,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            LOG_INPUT[..2].to_vec()
+        );
+        assert_eq!(read.get(), 3);
+    }
+
+    #[test]
+    fn filtering_iter_scan_window_rev() {
+        let log_iter = to_log_iter(LOG_INPUT.iter().copied().rev());
+        let log_iter = filtering_iter(
+            log_iter,
+            FilteringOptions {
+                scan_window: Some(Duration::minutes(60)),
+                ..Default::default()
+            },
+            Direction::Reverse,
+        );
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            LOG_INPUT[2..].iter().copied().rev().collect::<Vec<_>>()
+        );
+    }
+
+    // writes to a buffer that can be inspected while the stats own the writer
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn explain(
+        input: &[&'static [u8]],
+        options: FilteringOptions,
+        direction: Direction,
+    ) -> Vec<String> {
+        let buffer = SharedBuffer::default();
+        let stats = Rc::new(FilterStats::explaining(buffer.clone()));
+        let mut input = input.to_vec();
+        if direction == Direction::Reverse {
+            input.reverse();
+        }
+        let log_iter = to_log_iter(input.into_iter());
+        filtering_iter_with_stats(log_iter, options, direction, stats).count();
+        let explanation = String::from_utf8(buffer.0.take()).expect("Invalid UTF-8 explanation");
+        explanation.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn filtering_iter_explains_each_filter() {
+        let at = |h, m| Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(h, m, 0));
+        let forward = |options| explain(LOG_INPUT, options, Direction::Forward);
+
+        assert_eq!(
+            forward(FilteringOptions {
+                since: at(21, 0),
+                ..Default::default()
+            }),
+            ["dropped: timestamp 2020-01-01 20:00:00 before since 2020-01-01 21:00:00: -debug:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1"]
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                until: at(22, 0),
+                ..Default::default()
+            }),
+            ["dropped: timestamp 2020-01-01 22:00:00 past until 2020-01-01 22:00:00: -critical:<16866> 2020-01-01 22:00:00.000 UTC [A]: Text4"]
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                scan_window: Some(Duration::hours(1)),
+                ..Default::default()
+            }),
+            ["dropped: beyond --scan-window from the first entry: -warning:<16866> 2020-01-01 21:30:00.000 UTC [A]: Text3"]
+        );
+        assert_eq!(
+            explain(
+                LOG_INPUT,
+                FilteringOptions {
+                    max_age: Some(Duration::hours(1)),
+                    ..Default::default()
+                },
+                Direction::Reverse
+            ),
+            [
+                "dropped: timestamp 2020-01-01 21:00:00 older than --max-age before the newest entry 2020-01-01 22:30:00: -info:<16866> 2020-01-01 21:00:00.000 UTC [A]: Text2",
+                "dropped: timestamp 2020-01-01 20:00:00 older than --max-age before the newest entry 2020-01-01 22:30:00: -debug:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1",
+            ]
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                min_level: Some(LogLevel::Warning),
+                ..Default::default()
+            }),
+            [
+                "dropped: level debug < min warning: -debug:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1",
+                "dropped: level info < min warning: -info:<16866> 2020-01-01 21:00:00.000 UTC [A]: Text2",
+            ]
+        );
+        assert_eq!(
+            explain(
+                &[LOG_INPUT[0], b"no header\nsecond line"],
+                FilteringOptions {
+                    well_formed_only: true,
+                    ..Default::default()
+                },
+                Direction::Forward
+            ),
+            ["dropped: incomplete header: no header"]
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                category_pattern: Pattern::new("^B$"),
+                ..Default::default()
+            })[0],
+            "dropped: category A does not match --category-regex: -debug:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1"
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                contains: Some("Text5".to_string()),
+                ..Default::default()
+            })[3],
+            "dropped: does not contain \"Text5\": -critical:<16866> 2020-01-01 22:00:00.000 UTC [A]: Text4"
+        );
+        assert_eq!(
+            explain(
+                &[b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: userID=42 reason=idle"],
+                FilteringOptions {
+                    kv_filters: vec![
+                        ("userID".to_string(), "42".to_string()),
+                        ("reason".to_string(), "error".to_string()),
+                    ],
+                    ..Default::default()
+                },
+                Direction::Forward
+            ),
+            ["dropped: reason is not error: -info:<1> 2020-01-01 20:00:00.000 UTC [A]: userID=42 reason=idle"]
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                from_pattern: Pattern::new("Text2"),
+                to_pattern: Pattern::new("Text3"),
+                ..Default::default()
+            })[0],
+            "dropped: outside the --from/--to region: -debug:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1"
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                distinct_by: Some(DistinctField::Category),
+                ..Default::default()
+            })[0],
+            "dropped: repeated category A: -info:<16866> 2020-01-01 21:00:00.000 UTC [A]: Text2"
+        );
+        assert_eq!(
+            forward(FilteringOptions {
+                pair_pattern: Pattern::new(r"(?P<id>Text)[1-4]|(?P<end>Text)5"),
+                ..Default::default()
+            })[0],
+            "dropped: not ending a --pair-regex operation long enough: -debug:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1"
+        );
+    }
+
+    // counts allocations of the current thread, so that tests running in parallel do not interfere
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn filtering_iter_contains_allocation_free() {
+        let entries: Vec<LogEntry> = (0..1000)
+            .map(|i| {
+                let result = if i % 10 == 0 { "timeout" } else { "ok" };
+                let contents = format!(
+                    "-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Request {} {}\n\n",
+                    i, result
+                );
+                LogEntry::from_contents(contents.into_bytes())
+            })
+            .collect();
+        let count = |contains: &str, contains_unicode: bool| {
+            let options = FilteringOptions {
+                contains: Some(contains.to_string()),
+                contains_unicode,
+                ..Default::default()
+            };
+            let mut log_iter = filtering_iter(
+                streaming_iterator::convert_ref(entries.iter()),
+                options,
+                Direction::Forward,
+            );
+            // the first entry may set up state shared by the following ones
+            assert!(log_iter.next().is_some());
+
+            let before = ALLOCATIONS.with(Cell::get);
+            let matching = log_iter.count();
+            (matching, ALLOCATIONS.with(Cell::get) - before)
+        };
+
+        assert_eq!(count("timeout", false), (99, 0));
+        // ASCII entries are matched regardless of case without lowercasing them
+        assert_eq!(count("TimeOut", true), (99, 0));
+    }
 }
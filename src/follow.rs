@@ -0,0 +1,264 @@
+use crate::error::Error;
+use crate::interrupt::Interrupt;
+use crate::result::Result;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// reads a file forever, waiting for new data at its end and reopening it
+// from the start once it gets rotated (like "tail -F")
+pub struct FollowReader {
+    path: PathBuf,
+    file: File,
+    pos: u64,
+    poll_interval: Duration,
+    idle_timer: Option<IdleTimer>,
+    interrupt: Interrupt,
+}
+
+impl FollowReader {
+    pub fn open(path: &Path, poll_interval: Duration) -> Result<Self> {
+        let file = File::open(path).map_err(|e| Error::CannotOpenFile(path.to_path_buf(), e))?;
+        Ok(FollowReader {
+            path: path.to_path_buf(),
+            file,
+            pos: 0,
+            poll_interval,
+            idle_timer: None,
+            interrupt: Interrupt::default(),
+        })
+    }
+
+    // ends the input once no more data is available after an interrupt is requested
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    pub fn with_idle_marker(mut self, interval: Option<Duration>) -> Self {
+        self.idle_timer = interval.map(|interval| IdleTimer::new(interval, Instant::now()));
+        self
+    }
+
+    fn is_rotated(&self) -> io::Result<bool> {
+        let current = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // the file has been moved away and not yet recreated
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        Ok(current.len() < self.pos || !is_same_file(&current, &self.file.metadata()?))
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let bytes_read = self.file.read(buf)?;
+            if bytes_read > 0 {
+                self.pos += bytes_read as u64;
+                if let Some(idle_timer) = &mut self.idle_timer {
+                    idle_timer.reset(Instant::now());
+                }
+                return Ok(bytes_read);
+            }
+
+            if let Some(idle) = self
+                .idle_timer
+                .as_mut()
+                .and_then(|t| t.poll(Instant::now()))
+            {
+                eprintln!("-- idle {} --", format_idle(idle));
+            }
+
+            if self.is_rotated()? {
+                self.file = File::open(&self.path)?;
+                self.pos = 0;
+            } else if self.interrupt.is_requested() {
+                return Ok(0);
+            } else {
+                thread::sleep(self.poll_interval);
+            }
+        }
+    }
+}
+
+// reports once per every full interval passed since the last reset
+pub struct IdleTimer {
+    interval: Duration,
+    idle_since: Instant,
+    reported_intervals: u128,
+}
+
+impl IdleTimer {
+    pub fn new(interval: Duration, now: Instant) -> Self {
+        IdleTimer {
+            interval: interval.max(Duration::from_millis(1)),
+            idle_since: now,
+            reported_intervals: 0,
+        }
+    }
+
+    pub fn reset(&mut self, now: Instant) {
+        self.idle_since = now;
+        self.reported_intervals = 0;
+    }
+
+    // idle time to report, if another full interval has passed since the last report
+    pub fn poll(&mut self, now: Instant) -> Option<Duration> {
+        let idle = now.saturating_duration_since(self.idle_since);
+        let intervals = idle.as_millis() / self.interval.as_millis();
+        if intervals > self.reported_intervals {
+            self.reported_intervals = intervals;
+            Some(self.interval * intervals as u32)
+        } else {
+            None
+        }
+    }
+}
+
+fn format_idle(idle: Duration) -> String {
+    if idle.subsec_millis() == 0 {
+        format!("{}s", idle.as_secs())
+    } else {
+        format!("{}ms", idle.as_millis())
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_a: &Metadata, _b: &Metadata) -> bool {
+    true
+}
+
+// flushes the inner writer after every write so that followed entries show up immediately
+pub struct FlushingWriter<W: Write>(pub W);
+
+impl<W: Write> Write for FlushingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes_written = self.0.write(buf)?;
+        self.0.flush()?;
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_entry_reader::LogEntryReader;
+    use std::io::BufReader;
+    use streaming_iterator::StreamingIterator;
+
+    const ENTRY_A: &[u8] = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Old file\n\n";
+    const ENTRY_B: &[u8] = b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Appended\n\n";
+    const ENTRY_C: &[u8] = b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: New file\n\n";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("riolog-{}-{}", std::process::id(), name))
+    }
+
+    fn next_contents(
+        reader: &mut impl StreamingIterator<Item = crate::log_entry::LogEntry>,
+    ) -> Vec<u8> {
+        reader.next().expect("No entry").contents().to_vec()
+    }
+
+    #[test]
+    fn follow_reader_recreated_file() -> Result<()> {
+        let path = temp_path("recreated.log");
+        let rotated_path = temp_path("recreated.log.1");
+        fs::write(&path, ENTRY_A)?;
+
+        let reader = FollowReader::open(&path, Duration::from_millis(1))?;
+        let mut reader = LogEntryReader::new(BufReader::new(reader), b"\n");
+        assert_eq!(next_contents(&mut reader), ENTRY_A);
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)?
+            .write_all(ENTRY_B)?;
+        assert_eq!(next_contents(&mut reader), ENTRY_B);
+
+        fs::rename(&path, &rotated_path)?;
+        fs::write(&path, [ENTRY_C, ENTRY_A].concat())?;
+        assert_eq!(next_contents(&mut reader), ENTRY_C);
+
+        fs::remove_file(&path)?;
+        fs::remove_file(&rotated_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn follow_reader_interrupted() -> Result<()> {
+        let path = temp_path("interrupted.log");
+        fs::write(&path, [ENTRY_A, ENTRY_B].concat())?;
+
+        let interrupt = Interrupt::default();
+        interrupt.request();
+        let reader = FollowReader::open(&path, Duration::from_millis(1))?.with_interrupt(interrupt);
+        let mut reader = LogEntryReader::new(BufReader::new(reader), b"\n");
+        assert_eq!(next_contents(&mut reader), ENTRY_A);
+        assert_eq!(next_contents(&mut reader), ENTRY_B);
+        assert!(reader.next().is_none());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn idle_timer_reports_each_interval() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut idle_timer = IdleTimer::new(Duration::from_secs(30), start);
+
+        assert_eq!(idle_timer.poll(at(10)), None);
+        assert_eq!(idle_timer.poll(at(30)), Some(Duration::from_secs(30)));
+        assert_eq!(idle_timer.poll(at(45)), None);
+        assert_eq!(idle_timer.poll(at(95)), Some(Duration::from_secs(90)));
+
+        idle_timer.reset(at(100));
+        assert_eq!(idle_timer.poll(at(120)), None);
+        assert_eq!(idle_timer.poll(at(130)), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn format_idle_units() {
+        assert_eq!(format_idle(Duration::from_secs(30)), "30s");
+        assert_eq!(format_idle(Duration::from_millis(1500)), "1500ms");
+    }
+
+    #[test]
+    fn follow_reader_truncated_file() -> Result<()> {
+        let path = temp_path("truncated.log");
+        fs::write(&path, [ENTRY_A, ENTRY_B].concat())?;
+
+        let reader = FollowReader::open(&path, Duration::from_millis(1))?;
+        let mut reader = LogEntryReader::new(BufReader::new(reader), b"\n");
+        assert_eq!(next_contents(&mut reader), ENTRY_A);
+        assert_eq!(next_contents(&mut reader), ENTRY_B);
+
+        fs::write(&path, ENTRY_C)?;
+        assert_eq!(next_contents(&mut reader), ENTRY_C);
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
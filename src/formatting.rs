@@ -1,39 +1,113 @@
+use crate::cli::EscapeMode;
 use crate::result::Result;
 use std::io::Write;
 
+const DEFAULT_ESCAPE_LEAD: u8 = b'\\';
+const DEFAULT_ESCAPES: &[(u8, &[u8])] = &[
+    (b'0', b"\0"),
+    (b'n', b"\n"),
+    (b'r', b""),
+    (b't', b"\t"),
+    (b'?', b"?"),
+    (b'\'', b"'"),
+    (b'"', b"\""),
+];
+
+// Escape sequences decoded in the standard mode: a lead byte followed by a key byte, replaced
+// with the bytes the key maps to. Line feeds in replacements are written as line terminators.
+// The lead byte doubled stands for itself and sequences with unknown keys are kept as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscapeTable {
+    lead: u8,
+    replacements: Vec<Option<Vec<u8>>>, // indexed by key
+}
+
+impl EscapeTable {
+    // replaces all escapes of the table
+    pub fn with_escapes(mut self, escapes: impl IntoIterator<Item = (u8, Vec<u8>)>) -> Self {
+        self.replacements = vec![None; 256];
+        for (key, replacement) in escapes {
+            self.replacements[key as usize] = Some(replacement);
+        }
+        self
+    }
+
+    pub fn with_lead(mut self, lead: u8) -> Self {
+        self.lead = lead;
+        self
+    }
+
+    fn replacement(&self, key: u8) -> Option<&[u8]> {
+        self.replacements[key as usize].as_deref()
+    }
+}
+
+impl Default for EscapeTable {
+    fn default() -> Self {
+        let table = EscapeTable {
+            lead: DEFAULT_ESCAPE_LEAD,
+            replacements: Vec::new(),
+        };
+        table.with_escapes(
+            DEFAULT_ESCAPES
+                .iter()
+                .map(|&(key, replacement)| (key, replacement.to_vec())),
+        )
+    }
+}
+
+// decodes escape sequences of a whole entry according to the mode
+pub fn decode_escapes(
+    buf: &[u8],
+    writer: &mut impl Write,
+    mode: EscapeMode,
+    table: &EscapeTable,
+    eol: &[u8],
+    after_eol: &[u8],
+) -> Result<()> {
+    match mode {
+        EscapeMode::None => writer.write_all(buf)?,
+        EscapeMode::Standard => {
+            format_special_chars(buf, writer, table, false, eol, after_eol)?;
+        }
+        EscapeMode::Aggressive => decode_escapes_aggressive(buf, writer, eol, after_eol)?,
+    }
+    Ok(())
+}
+
 pub fn format_special_chars(
     buf: &[u8],
     writer: &mut impl Write,
+    table: &EscapeTable,
     mut ctr_char_is_next: bool,
     eol: &[u8],
     after_eol: &[u8],
 ) -> Result<bool> {
     let mut last_slice_is_empty = false;
 
-    for s in buf.split(|&c| c == b'\\') {
+    for s in buf.split(|&c| c == table.lead) {
         if s.is_empty() {
             if ctr_char_is_next {
                 last_slice_is_empty = true;
                 ctr_char_is_next = false;
             } else {
-                writer.write_all(b"\\")?;
+                writer.write_all(&[table.lead])?;
                 last_slice_is_empty = false;
                 ctr_char_is_next = true;
             }
         } else if ctr_char_is_next {
-            match s[0] {
-                b'0' => writer.write_all(b"\0")?,
-                b'n' => {
-                    writer.write_all(eol)?;
-                    writer.write_all(after_eol)?;
+            match table.replacement(s[0]) {
+                Some(replacement) => {
+                    for (i, line) in replacement.split(|&c| c == b'\n').enumerate() {
+                        if i > 0 {
+                            writer.write_all(eol)?;
+                            writer.write_all(after_eol)?;
+                        }
+                        writer.write_all(line)?;
+                    }
                 }
-                b'r' => {}
-                b't' => writer.write_all(b"\t")?,
-                b'?' => writer.write_all(b"?")?,
-                b'\'' => writer.write_all(b"\'")?,
-                b'\"' => writer.write_all(b"\"")?,
-                _ => {
-                    writer.write_all(b"\\")?;
+                None => {
+                    writer.write_all(&[table.lead])?;
                     writer.write_all(&s[0..1])?;
                 }
             }
@@ -42,7 +116,7 @@ pub fn format_special_chars(
             ctr_char_is_next = true;
         } else {
             if last_slice_is_empty {
-                writer.write_all(b"\\")?;
+                writer.write_all(&[table.lead])?;
             }
             writer.write_all(s)?;
             last_slice_is_empty = false;
@@ -53,6 +127,96 @@ pub fn format_special_chars(
     Ok(last_slice_is_empty)
 }
 
+// Besides the escapes known to `format_special_chars` decodes `\xHH`, `\uHHHH` and octal `\NNN`
+// escapes, and shows control characters other than line breaks and tabs in caret notation.
+fn decode_escapes_aggressive(
+    buf: &[u8],
+    writer: &mut impl Write,
+    eol: &[u8],
+    after_eol: &[u8],
+) -> Result<()> {
+    let mut pos = 0;
+
+    while pos < buf.len() {
+        let c = buf[pos];
+        pos += 1;
+
+        if c != b'\\' || pos == buf.len() {
+            write_visible(&[c], writer)?;
+            continue;
+        }
+
+        let escaped = buf[pos];
+        pos += 1;
+        match escaped {
+            b'n' => {
+                writer.write_all(eol)?;
+                writer.write_all(after_eol)?;
+            }
+            b'r' => {}
+            b't' => writer.write_all(b"\t")?,
+            b'?' | b'\'' | b'\"' | b'\\' => writer.write_all(&[escaped])?,
+            b'x' => match parse_digits(&buf[pos..], 16, 2) {
+                Some((value, len)) => {
+                    pos += len;
+                    write_visible(&[value as u8], writer)?;
+                }
+                None => writer.write_all(b"\\x")?,
+            },
+            b'u' => match parse_digits(&buf[pos..], 16, 4)
+                .filter(|&(_, len)| len == 4)
+                .and_then(|(value, _)| std::char::from_u32(value))
+            {
+                Some(decoded) => {
+                    pos += 4;
+                    let mut utf8 = [0; 4];
+                    write_visible(decoded.encode_utf8(&mut utf8).as_bytes(), writer)?;
+                }
+                None => writer.write_all(b"\\u")?,
+            },
+            b'0'..=b'7' => {
+                let (value, len) = parse_digits(&buf[pos - 1..], 8, 3).expect("No octal digits");
+                let (value, len) = if value > 0xFF {
+                    parse_digits(&buf[pos - 1..], 8, 2).expect("No octal digits")
+                } else {
+                    (value, len)
+                };
+                pos += len - 1;
+                write_visible(&[value as u8], writer)?;
+            }
+            _ => writer.write_all(&[b'\\', escaped])?,
+        }
+    }
+
+    Ok(())
+}
+
+// value of up to `max_len` leading digits of given radix and their count
+fn parse_digits(buf: &[u8], radix: u32, max_len: usize) -> Option<(u32, usize)> {
+    let len = buf
+        .iter()
+        .take(max_len)
+        .take_while(|&&c| (c as char).is_digit(radix))
+        .count();
+    let digits = std::str::from_utf8(&buf[..len]).ok()?;
+    u32::from_str_radix(digits, radix)
+        .ok()
+        .map(|value| (value, len))
+}
+
+// writes the bytes replacing control characters (except line breaks and tabs) with caret notation
+fn write_visible(buf: &[u8], writer: &mut impl Write) -> Result<()> {
+    for &c in buf {
+        match c {
+            b'\n' | b'\r' | b'\t' => writer.write_all(&[c])?,
+            0..=0x1F => writer.write_all(&[b'^', c + 0x40])?,
+            0x7F => writer.write_all(b"^?")?,
+            _ => writer.write_all(&[c])?,
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,7 +225,14 @@ mod tests {
     fn format_special_chars_plain() -> Result<()> {
         let in_buf = b"abcdefg";
         let mut out_buf = Vec::<u8>::new();
-        let last_slice_is_empty = format_special_chars(in_buf, &mut out_buf, false, b"\n", b"")?;
+        let last_slice_is_empty = format_special_chars(
+            in_buf,
+            &mut out_buf,
+            &EscapeTable::default(),
+            false,
+            b"\n",
+            b"",
+        )?;
 
         assert_eq!(last_slice_is_empty, false);
         assert_eq!(out_buf, in_buf);
@@ -73,7 +244,14 @@ mod tests {
     fn format_special_chars_special_chars() -> Result<()> {
         let in_buf = b"a\\nb\\tc\\\'d\\\"e\\\\fg";
         let mut out_buf = Vec::<u8>::new();
-        let last_slice_is_empty = format_special_chars(in_buf, &mut out_buf, false, b"\n", b"")?;
+        let last_slice_is_empty = format_special_chars(
+            in_buf,
+            &mut out_buf,
+            &EscapeTable::default(),
+            false,
+            b"\n",
+            b"",
+        )?;
 
         assert_eq!(last_slice_is_empty, false);
         assert_eq!(out_buf, b"a\nb\tc\'d\"e\\fg");
@@ -85,7 +263,14 @@ mod tests {
     fn format_special_chars_unknown_special_chars() -> Result<()> {
         let in_buf = b"a\\ab\\bc\\cd";
         let mut out_buf = Vec::<u8>::new();
-        let last_slice_is_empty = format_special_chars(in_buf, &mut out_buf, false, b"\n", b"")?;
+        let last_slice_is_empty = format_special_chars(
+            in_buf,
+            &mut out_buf,
+            &EscapeTable::default(),
+            false,
+            b"\n",
+            b"",
+        )?;
 
         assert_eq!(last_slice_is_empty, false);
         assert_eq!(out_buf, in_buf);
@@ -97,7 +282,14 @@ mod tests {
     fn format_special_chars_slash_eol() -> Result<()> {
         let in_buf = b"abc\\";
         let mut out_buf = Vec::<u8>::new();
-        let last_slice_is_empty = format_special_chars(in_buf, &mut out_buf, false, b"\n", b"")?;
+        let last_slice_is_empty = format_special_chars(
+            in_buf,
+            &mut out_buf,
+            &EscapeTable::default(),
+            false,
+            b"\n",
+            b"",
+        )?;
 
         assert_eq!(last_slice_is_empty, true);
         assert_eq!(out_buf, b"abc");
@@ -110,8 +302,14 @@ mod tests {
         let in_buf = b"nabc";
         let mut out_buf = Vec::<u8>::new();
         let ctr_char_is_next = true;
-        let last_slice_is_empty =
-            format_special_chars(in_buf, &mut out_buf, ctr_char_is_next, b"\n", b"")?;
+        let last_slice_is_empty = format_special_chars(
+            in_buf,
+            &mut out_buf,
+            &EscapeTable::default(),
+            ctr_char_is_next,
+            b"\n",
+            b"",
+        )?;
 
         assert_eq!(last_slice_is_empty, false);
         assert_eq!(out_buf, b"\nabc");
@@ -124,12 +322,114 @@ mod tests {
         let in_buf = b"\\abc";
         let mut out_buf = Vec::<u8>::new();
         let ctr_char_is_next = true;
-        let last_slice_is_empty =
-            format_special_chars(in_buf, &mut out_buf, ctr_char_is_next, b"\n", b"")?;
+        let last_slice_is_empty = format_special_chars(
+            in_buf,
+            &mut out_buf,
+            &EscapeTable::default(),
+            ctr_char_is_next,
+            b"\n",
+            b"",
+        )?;
 
         assert_eq!(last_slice_is_empty, false);
         assert_eq!(out_buf, b"\\abc");
 
         Ok(())
     }
+
+    #[test]
+    fn format_special_chars_custom_lead() -> Result<()> {
+        let in_buf = b"a~nb~tc~~d~qe\\nf";
+        let mut out_buf = Vec::<u8>::new();
+        let table = EscapeTable::default().with_lead(b'~');
+        let last_slice_is_empty =
+            format_special_chars(in_buf, &mut out_buf, &table, false, b"\r\n", b"  ")?;
+
+        assert!(!last_slice_is_empty);
+        assert_eq!(out_buf, b"a\r\n  b\tc~d~qe\\nf");
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_special_chars_custom_table() -> Result<()> {
+        let in_buf = b"user\\aexample.com\\nx\\ty\\\\z\\\"";
+        let mut out_buf = Vec::<u8>::new();
+        let table = EscapeTable::default()
+            .with_escapes(vec![(b'a', b"@".to_vec()), (b'n', b"\n".to_vec())]);
+        let last_slice_is_empty =
+            format_special_chars(in_buf, &mut out_buf, &table, false, b"\n", b"")?;
+
+        assert!(!last_slice_is_empty);
+        assert_eq!(out_buf, b"user@example.com\nx\\ty\\z\\\"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_special_chars_custom_lead_split_buffers() -> Result<()> {
+        let table = EscapeTable::default()
+            .with_lead(b'~')
+            .with_escapes(vec![(b'n', b"\n".to_vec())]);
+        let mut out_buf = Vec::<u8>::new();
+        let ctr_char_is_next =
+            format_special_chars(b"abc~", &mut out_buf, &table, false, b"\n", b"")?;
+        format_special_chars(b"nd", &mut out_buf, &table, ctr_char_is_next, b"\n", b"")?;
+
+        assert_eq!(out_buf, b"abc\nd");
+
+        Ok(())
+    }
+
+    const MIXED_ESCAPES: &[u8] = b"a\\tb\\x41\\u00e9\\101\\x01\x02\\q";
+
+    fn decoded(mode: EscapeMode) -> Vec<u8> {
+        let mut out_buf = Vec::new();
+        decode_escapes(
+            MIXED_ESCAPES,
+            &mut out_buf,
+            mode,
+            &EscapeTable::default(),
+            b"\n",
+            b"",
+        )
+        .unwrap();
+        out_buf
+    }
+
+    #[test]
+    fn decode_escapes_none() {
+        assert_eq!(decoded(EscapeMode::None), MIXED_ESCAPES);
+    }
+
+    #[test]
+    fn decode_escapes_standard() {
+        assert_eq!(
+            decoded(EscapeMode::Standard),
+            b"a\tb\\x41\\u00e9\\101\\x01\x02\\q".to_vec()
+        );
+    }
+
+    #[test]
+    fn decode_escapes_aggressive() {
+        assert_eq!(
+            String::from_utf8_lossy(&decoded(EscapeMode::Aggressive)),
+            "a\tbAéA^A^B\\q"
+        );
+    }
+
+    #[test]
+    fn decode_escapes_aggressive_incomplete() {
+        let mut out_buf = Vec::new();
+        decode_escapes(
+            b"\\xZ \\u12 \\400 \\",
+            &mut out_buf,
+            EscapeMode::Aggressive,
+            &EscapeTable::default(),
+            b"\n",
+            b"",
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8_lossy(&out_buf), "\\xZ \\u12  0 \\");
+    }
 }
@@ -0,0 +1,158 @@
+use crate::eol;
+use crate::log_entry::LogEntry;
+use std::collections::VecDeque;
+use streaming_iterator::StreamingIterator;
+
+enum Phase {
+    Head,
+    Tail,
+    Done,
+}
+
+// Passes the first `head` entries, then holds the last `tail` of the remaining ones in a ring
+// buffer and passes them once the input ends, preceded by a separator with the number of entries
+// skipped in between. Without `tail` it stops reading after the head, like `take`. Separators,
+// e.g. of context, are passed along, but not counted as entries.
+pub struct HeadTailIter<I> {
+    input: I,
+    head: usize,
+    tail: Option<usize>,
+    passed: usize,
+    buffer: VecDeque<LogEntry>,
+    current: Option<LogEntry>,
+    phase: Phase,
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> HeadTailIter<I> {
+    pub fn new(input: I, head: Option<usize>, tail: Option<usize>) -> Self {
+        HeadTailIter {
+            input,
+            // only the tail is shown without a head, and everything without either
+            head: head.unwrap_or(if tail.is_some() { 0 } else { usize::MAX }),
+            tail,
+            passed: 0,
+            buffer: VecDeque::new(),
+            current: None,
+            phase: Phase::Head,
+        }
+    }
+
+    fn fill_buffer(&mut self, tail: usize) {
+        let mut buffered = 0;
+        let mut elided = 0;
+        while let Some(entry) = self.input.next() {
+            if entry.is_separator() {
+                self.buffer.push_back(entry.clone());
+                continue;
+            }
+            if tail == 0 {
+                elided += 1;
+                continue;
+            }
+            if buffered == tail {
+                while let Some(evicted) = self.buffer.pop_front() {
+                    if !evicted.is_separator() {
+                        elided += 1;
+                        break;
+                    }
+                }
+            } else {
+                buffered += 1;
+            }
+            self.buffer.push_back(entry.clone());
+        }
+
+        // separators left around the tail separate nothing from the marker
+        while self.buffer.front().is_some_and(LogEntry::is_separator) {
+            self.buffer.pop_front();
+        }
+        if tail == 0 {
+            self.buffer.clear();
+        }
+        if elided > 0 && self.head > 0 {
+            let marker = format!("... {} entries elided ...", elided);
+            self.buffer
+                .push_front(LogEntry::separator([marker.as_bytes(), eol::EOL].concat()));
+        }
+    }
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for HeadTailIter<I> {
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        if let Phase::Head = self.phase {
+            if self.passed < self.head {
+                self.input.advance();
+                match self.input.get() {
+                    Some(entry) if !entry.is_separator() => self.passed += 1,
+                    Some(_) => {}
+                    None => self.phase = Phase::Done,
+                }
+                return;
+            }
+            match self.tail {
+                Some(tail) => {
+                    self.fill_buffer(tail);
+                    self.phase = Phase::Tail;
+                }
+                None => self.phase = Phase::Done,
+            }
+        }
+        if let Phase::Tail = self.phase {
+            self.current = self.buffer.pop_front();
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        match self.phase {
+            Phase::Head => self.input.get(),
+            Phase::Tail => self.current.as_ref(),
+            Phase::Done => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &[&'static [u8]], head: Option<usize>, tail: Option<usize>) -> Vec<Vec<u8>> {
+        let entries = input.iter().map(|contents| {
+            if contents.starts_with(b"--") {
+                LogEntry::separator(contents.to_vec())
+            } else {
+                LogEntry::from_contents(contents.to_vec())
+            }
+        });
+        HeadTailIter::new(streaming_iterator::convert(entries), head, tail)
+            .cloned()
+            .map(|entry| entry.contents().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn head_tail_iter_elides_middle() {
+        let input: &[&[u8]] = &[b"1", b"2", b"3", b"--", b"4", b"5", b"6"];
+        assert_eq!(
+            run(input, Some(2), Some(2)),
+            vec![
+                b"1".to_vec(),
+                b"2".to_vec(),
+                [b"... 2 entries elided ...", eol::EOL].concat(),
+                b"5".to_vec(),
+                b"6".to_vec()
+            ]
+        );
+        assert_eq!(
+            run(input, Some(2), None),
+            vec![b"1".to_vec(), b"2".to_vec()]
+        );
+        assert_eq!(
+            run(input, None, Some(3)),
+            vec![b"4".to_vec(), b"5".to_vec(), b"6".to_vec()]
+        );
+        assert_eq!(run(input, Some(4), Some(3)).len(), 7);
+        assert_eq!(run(input, Some(1), Some(0)).len(), 2);
+    }
+}
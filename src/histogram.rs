@@ -0,0 +1,156 @@
+use crate::log_entry::LogEntry;
+use chrono::{Duration, NaiveDateTime};
+use std::collections::BTreeMap;
+use std::fmt;
+use streaming_iterator::StreamingIterator;
+
+const BAR_WIDTH: usize = 50;
+const TIMESTAMP_FORMAT: &str = "%F %T%.3f";
+
+// numbers of entries in consecutive time intervals aligned to multiples of the interval length
+pub struct Histogram {
+    interval_ms: i64,
+    buckets: BTreeMap<i64, usize>,
+}
+
+impl Histogram {
+    pub fn count(
+        mut log_entries: impl StreamingIterator<Item = LogEntry>,
+        interval: Duration,
+    ) -> Self {
+        let interval_ms = interval.num_milliseconds().max(1);
+        let mut buckets = BTreeMap::new();
+
+        while let Some(entry) = log_entries.next() {
+            if let Some(timestamp) = entry.timestamp() {
+                *buckets
+                    .entry(bucket_start(timestamp, interval_ms))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        Histogram {
+            interval_ms,
+            buckets,
+        }
+    }
+
+    pub fn buckets(&self) -> Vec<(NaiveDateTime, usize)> {
+        fill_buckets(&self.buckets, self.interval_ms)
+    }
+}
+
+// all buckets between the first and the last non-empty one, including empty ones
+pub fn fill_buckets<T: Copy + Default>(
+    buckets: &BTreeMap<i64, T>,
+    interval_ms: i64,
+) -> Vec<(NaiveDateTime, T)> {
+    let (first, last) = match (buckets.keys().next(), buckets.keys().last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return Vec::new(),
+    };
+
+    (0..=(last - first) / interval_ms)
+        .map(|i| first + i * interval_ms)
+        .map(|start| {
+            (
+                bucket_start_time(start),
+                buckets.get(&start).copied().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+// start of the interval containing the timestamp, in milliseconds since the epoch
+pub fn bucket_start(timestamp: NaiveDateTime, interval_ms: i64) -> i64 {
+    let millis = timestamp.timestamp() * 1000 + i64::from(timestamp.timestamp_subsec_millis());
+    millis.div_euclid(interval_ms) * interval_ms
+}
+
+fn bucket_start_time(start: i64) -> NaiveDateTime {
+    NaiveDateTime::from_timestamp(
+        start.div_euclid(1000),
+        (start.rem_euclid(1000) * 1_000_000) as u32,
+    )
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let buckets = self.buckets();
+        let max_count = buckets.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        let count_width = max_count.to_string().len();
+
+        for (start, count) in buckets {
+            let bar_len = count * BAR_WIDTH / max_count;
+            writeln!(
+                f,
+                "{}  {:>width$} {}",
+                start.format(TIMESTAMP_FORMAT),
+                count,
+                "#".repeat(bar_len),
+                width = count_width
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const LOG_INPUT: &[&[u8]] = &[
+        b"-info:<1> 2020-01-01 20:00:05.000 UTC [A]: Text1",
+        b"-info:<1> 2020-01-01 20:00:59.999 UTC [A]: Text2",
+        b"-info:<1> 2020-01-01 20:01:00.000 UTC [A]: Text3",
+        b"  continuation without timestamp",
+        b"-info:<1> 2020-01-01 20:03:30.000 UTC [A]: Text4",
+        b"-info:<1> 2020-01-01 20:03:31.000 UTC [A]: Text5",
+        b"-info:<1> 2020-01-01 20:03:32.000 UTC [A]: Text6",
+    ];
+
+    fn to_log_iter() -> impl StreamingIterator<Item = LogEntry> {
+        streaming_iterator::convert(
+            LOG_INPUT
+                .iter()
+                .map(|lines| LogEntry::from_contents(lines.to_vec())),
+        )
+    }
+
+    #[test]
+    fn histogram_bucket_counts() {
+        let histogram = Histogram::count(to_log_iter(), Duration::minutes(1));
+        let minute = |min| NaiveDate::from_ymd(2020, 1, 1).and_hms(20, min, 0);
+        assert_eq!(
+            histogram.buckets(),
+            vec![
+                (minute(0), 2),
+                (minute(1), 1),
+                (minute(2), 0),
+                (minute(3), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn histogram_display() {
+        let histogram = Histogram::count(to_log_iter(), Duration::minutes(2));
+        assert_eq!(
+            histogram.to_string(),
+            format!(
+                "2020-01-01 20:00:00.000  3 {}\n2020-01-01 20:02:00.000  3 {}\n",
+                "#".repeat(50),
+                "#".repeat(50)
+            )
+        );
+    }
+
+    #[test]
+    fn histogram_empty() {
+        let histogram = Histogram::count(streaming_iterator::empty(), Duration::minutes(1));
+        assert!(histogram.buckets().is_empty());
+        assert_eq!(histogram.to_string(), "");
+    }
+}
@@ -0,0 +1,301 @@
+use crate::eol;
+use crate::log_entry::LogLevel;
+use crate::log_entry_reader::LogEntryReader;
+use crate::result::Result;
+use chrono::NaiveDateTime;
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use streaming_iterator::StreamingIterator;
+
+const MAGIC: &[u8] = b"RIOLOGIX";
+const VERSION: u32 = 2;
+const SIDECAR_SUFFIX: &str = ".riolog-idx";
+const HEADER_LEN: usize = 32; // magic, version, log size, mtime seconds, mtime nanoseconds
+const RECORD_LEN: usize = 17; // offset, epoch milliseconds, level
+const NO_TIMESTAMP: i64 = i64::MIN;
+const NO_LEVEL: u8 = 0; // levels are stored shifted by one
+
+// version of a log file, which an index is valid for
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LogStamp {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+impl LogStamp {
+    fn of(log: &Path) -> Result<Self> {
+        let metadata = fs::metadata(log)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(LogStamp {
+            size: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        })
+    }
+}
+
+// Start offsets, timestamps and levels of all entries of a log file. Stored next to the log in
+// a sidecar file as a header identifying the log version by its size and modification time
+// followed by a packed table of little-endian (offset, epoch milliseconds, level) records.
+// Lookups by time assume the log is sorted, like --since does.
+#[derive(Debug, PartialEq)]
+pub struct EntryIndex {
+    stamp: LogStamp,
+    entries: Vec<(u64, Option<i64>, Option<LogLevel>)>,
+    // positions of entries with a timestamp, for searching them by it
+    timestamped: Vec<usize>,
+    // position of the first entry without a timestamp, which stops skipping by --since
+    first_untimestamped: usize,
+}
+
+impl EntryIndex {
+    // reads the sidecar index of the log, or builds and saves a new one if it is missing or
+    // the log has changed since. Failing to save the index does not prevent using it.
+    pub fn load_or_build(log: &Path) -> Result<Self> {
+        let stamp = LogStamp::of(log)?;
+        let sidecar = sidecar_path(log);
+
+        let saved = fs::read(&sidecar)
+            .ok()
+            .and_then(|bytes| EntryIndex::decode(&bytes))
+            .filter(|index| index.stamp == stamp);
+        if let Some(index) = saved {
+            return Ok(index);
+        }
+
+        let index = EntryIndex::build(File::open(log)?, stamp);
+        let _ = fs::write(&sidecar, index.encode());
+        Ok(index)
+    }
+
+    fn new(stamp: LogStamp, entries: Vec<(u64, Option<i64>, Option<LogLevel>)>) -> Self {
+        let timestamped: Vec<_> = (0..entries.len())
+            .filter(|&i| entries[i].1.is_some())
+            .collect();
+        let first_untimestamped = entries
+            .iter()
+            .position(|(_, timestamp, _)| timestamp.is_none())
+            .unwrap_or(entries.len());
+        EntryIndex {
+            stamp,
+            entries,
+            timestamped,
+            first_untimestamped,
+        }
+    }
+
+    fn build(log: impl Read, stamp: LogStamp) -> Self {
+        let mut entries = Vec::new();
+        let mut reader = LogEntryReader::new(BufReader::new(log), eol::EOL);
+        let mut start = 0;
+        while let Some(entry) = reader.next() {
+            let timestamp = entry
+                .timestamp()
+                .map(|timestamp| timestamp.timestamp_millis());
+            entries.push((start, timestamp, entry.level()));
+            start = entry.end_offset();
+        }
+        EntryIndex::new(stamp, entries)
+    }
+
+    // number of entries with a timestamp older than `since`
+    fn count_before(&self, since: NaiveDateTime) -> usize {
+        let since = since.timestamp_millis();
+        self.timestamped
+            .partition_point(|&i| self.entries[i].1.is_some_and(|timestamp| timestamp < since))
+    }
+
+    // offset of the first entry which `--since` does not skip, i.e. the first one without
+    // a timestamp or not older than `since`
+    pub fn offset_since(&self, since: NaiveDateTime) -> u64 {
+        let first = self
+            .timestamped
+            .get(self.count_before(since))
+            .map_or(self.entries.len(), |&i| i)
+            .min(self.first_untimestamped);
+        self.entries
+            .get(first)
+            .map_or(self.stamp.size, |&(offset, _, _)| offset)
+    }
+
+    // offset which reading newest first with `--since` can stop at, i.e. the start of the newest
+    // entry older than `since`, which ends the reading, or of the file if there is none
+    pub fn rev_offset_since(&self, since: NaiveDateTime) -> u64 {
+        self.count_before(since)
+            .checked_sub(1)
+            .map_or(0, |last| self.entries[self.timestamped[last]].0)
+    }
+
+    // numbers of entries of each level from the offset on
+    pub fn level_counts(&self, offset: u64) -> [usize; LogLevel::ALL.len()] {
+        let first = self
+            .entries
+            .partition_point(|&(start, _, _)| start < offset);
+        let mut counts = [0; LogLevel::ALL.len()];
+        for &(_, _, level) in &self.entries[first..] {
+            if let Some(level) = level {
+                counts[level as usize] += 1;
+            }
+        }
+        counts
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.entries.len() * RECORD_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.stamp.size.to_le_bytes());
+        bytes.extend_from_slice(&self.stamp.mtime_secs.to_le_bytes());
+        bytes.extend_from_slice(&self.stamp.mtime_nanos.to_le_bytes());
+        for &(offset, timestamp, level) in &self.entries {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&timestamp.unwrap_or(NO_TIMESTAMP).to_le_bytes());
+            bytes.push(level.map_or(NO_LEVEL, |level| level as u8 + 1));
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (header, records) = bytes.split_at(HEADER_LEN.min(bytes.len()));
+        if header.len() < HEADER_LEN
+            || !header.starts_with(MAGIC)
+            || u32::from_le_bytes(header[8..12].try_into().ok()?) != VERSION
+            || records.len() % RECORD_LEN != 0
+        {
+            return None;
+        }
+
+        let stamp = LogStamp {
+            size: u64::from_le_bytes(header[12..20].try_into().ok()?),
+            mtime_secs: u64::from_le_bytes(header[20..28].try_into().ok()?),
+            mtime_nanos: u32::from_le_bytes(header[28..32].try_into().ok()?),
+        };
+        let entries = records
+            .chunks_exact(RECORD_LEN)
+            .map(|record| {
+                let offset = u64::from_le_bytes(record[..8].try_into().ok()?);
+                let timestamp = i64::from_le_bytes(record[8..16].try_into().ok()?);
+                let level = match record[16] {
+                    NO_LEVEL => None,
+                    level => Some(*LogLevel::ALL.get(usize::from(level) - 1)?),
+                };
+                Some((
+                    offset,
+                    Some(timestamp).filter(|&t| t != NO_TIMESTAMP),
+                    level,
+                ))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(EntryIndex::new(stamp, entries))
+    }
+}
+
+pub fn sidecar_path(log: &Path) -> PathBuf {
+    let mut path = log.as_os_str().to_owned();
+    path.push(SIDECAR_SUFFIX);
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const LOG: &[u8] = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: First\n\n\
+                         -info:<1> 2020-01-01 20:01:00.000 UTC [A]: Second\n\
+                         continued\n\n\
+                         No header\n\n\
+                         -info:<1> 2020-01-01 20:03:00.000 UTC [A]: Fourth\n\n";
+
+    const STAMP: LogStamp = LogStamp {
+        size: LOG.len() as u64,
+        mtime_secs: 1_577_908_800,
+        mtime_nanos: 5,
+    };
+
+    fn minute(min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2020, 1, 1).and_hms(20, min, 0)
+    }
+
+    #[test]
+    fn entry_index_offsets() {
+        let index = EntryIndex::build(LOG, STAMP);
+        let second = LOG.iter().position(|&c| c == b'\n').unwrap() as u64 + 2;
+        let no_header = memchr::memmem::find(LOG, b"No header").unwrap() as u64;
+
+        assert_eq!(index.entries.len(), 4);
+        assert_eq!(index.offset_since(minute(0)), 0);
+        assert_eq!(index.offset_since(minute(1)), second);
+        assert_eq!(index.offset_since(minute(2)), no_header);
+        assert_eq!(index.offset_since(minute(30)), no_header);
+        assert_eq!(&LOG[second as usize..second as usize + 6], b"-info:");
+    }
+
+    #[test]
+    fn entry_index_rev_offsets_and_levels() {
+        let index = EntryIndex::build(LOG, STAMP);
+        let second = LOG.iter().position(|&c| c == b'\n').unwrap() as u64 + 2;
+        let fourth = memchr::memmem::rfind(LOG, b"-info:").unwrap() as u64;
+
+        assert_eq!(index.rev_offset_since(minute(0)), 0);
+        assert_eq!(index.rev_offset_since(minute(1)), 0);
+        assert_eq!(index.rev_offset_since(minute(2)), second);
+        assert_eq!(index.rev_offset_since(minute(3)), second);
+        assert_eq!(index.rev_offset_since(minute(30)), fourth);
+
+        assert_eq!(index.level_counts(0), [0, 3, 0, 0, 0]);
+        assert_eq!(index.level_counts(second + 1), [0, 1, 0, 0, 0]);
+        assert_eq!(index.level_counts(LOG.len() as u64), [0; 5]);
+    }
+
+    #[test]
+    fn entry_index_encoding() {
+        let index = EntryIndex::build(LOG, STAMP);
+        let bytes = index.encode();
+        assert_eq!(bytes.len(), HEADER_LEN + 4 * RECORD_LEN);
+        assert_eq!(EntryIndex::decode(&bytes), Some(index));
+        assert_eq!(EntryIndex::decode(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(EntryIndex::decode(b"RIOLOGIX"), None);
+        assert_eq!(EntryIndex::decode(b""), None);
+    }
+
+    #[test]
+    fn entry_index_sidecar_invalidation() -> Result<()> {
+        let log = std::env::temp_dir().join(format!("riolog-{}-index.log", std::process::id()));
+        let sidecar = sidecar_path(&log);
+        fs::write(&log, LOG)?;
+
+        let built = EntryIndex::load_or_build(&log)?;
+        let saved = fs::read(&sidecar)?;
+        let loaded = EntryIndex::load_or_build(&log)?;
+
+        let mut appended = LOG.to_vec();
+        appended.extend_from_slice(b"-info:<1> 2020-01-01 20:04:00.000 UTC [A]: Fifth\n\n");
+        fs::write(&log, &appended)?;
+        let rebuilt = EntryIndex::load_or_build(&log)?;
+
+        fs::remove_file(&log)?;
+        fs::remove_file(&sidecar)?;
+
+        assert_eq!(saved, built.encode());
+        assert_eq!(loaded, built);
+        assert_eq!(rebuilt.entries.len(), 5);
+        assert_eq!(
+            rebuilt.entries[4],
+            (
+                LOG.len() as u64,
+                Some(minute(4).timestamp_millis()),
+                Some(LogLevel::Info)
+            )
+        );
+        Ok(())
+    }
+}
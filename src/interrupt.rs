@@ -0,0 +1,109 @@
+use crate::result::Result;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub const EXIT_CODE: i32 = 130;
+
+// Ctrl-C handling: the first one stops writing further entries so that everything written so far
+// gets flushed on the way out, the second one terminates immediately
+#[derive(Clone, Default)]
+pub struct Interrupt {
+    requested: Arc<AtomicBool>,
+    checked_between_entries: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    pub fn install() -> Result<Self> {
+        let interrupt = Interrupt::default();
+        let handler_interrupt = interrupt.clone();
+        ctrlc::set_handler(move || {
+            if handler_interrupt.is_requested() {
+                std::process::exit(EXIT_CODE);
+            }
+            handler_interrupt.request();
+        })
+        .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(interrupt)
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    // called by a writer of styled entries that checks the interrupt before each entry itself,
+    // so that interruptible writers do not cut an entry short, leaving its color unreset
+    pub fn check_between_entries(&self) {
+        self.checked_between_entries.store(true, Ordering::SeqCst);
+    }
+
+    fn fails_writes(&self) -> bool {
+        self.is_requested() && !self.checked_between_entries.load(Ordering::SeqCst)
+    }
+}
+
+// fails every write once an interrupt is requested, which unwinds the processing and lets
+// the inner buffered writer flush its contents when dropped. Writes are let through when
+// the interrupt is checked between entries instead
+pub struct InterruptibleWriter<W: Write> {
+    inner: W,
+    interrupt: Interrupt,
+}
+
+impl<W: Write> InterruptibleWriter<W> {
+    pub fn new(inner: W, interrupt: Interrupt) -> Self {
+        InterruptibleWriter { inner, interrupt }
+    }
+}
+
+impl<W: Write> Write for InterruptibleWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.interrupt.fails_writes() {
+            return Err(io::Error::other("interrupted"));
+        }
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufWriter;
+
+    #[test]
+    fn interruptible_writer_flushes_written_output() {
+        let interrupt = Interrupt::default();
+        let mut output = Vec::new();
+        {
+            let writer = BufWriter::with_capacity(1024, &mut output);
+            let mut writer = InterruptibleWriter::new(writer, interrupt.clone());
+            writer.write_all(b"First\n\n").unwrap();
+            interrupt.request();
+            assert!(writer.write_all(b"Second\n\n").is_err());
+        }
+        assert!(interrupt.is_requested());
+        assert_eq!(output, b"First\n\n".to_vec());
+    }
+
+    #[test]
+    fn interruptible_writer_passes_writes_when_checked_between_entries() {
+        let interrupt = Interrupt::default();
+        interrupt.check_between_entries();
+        let mut output = Vec::new();
+        {
+            let mut writer = InterruptibleWriter::new(&mut output, interrupt.clone());
+            writer.write_all(b"First").unwrap();
+            interrupt.request();
+            writer.write_all(b"\n\n").unwrap();
+        }
+        assert_eq!(output, b"First\n\n".to_vec());
+    }
+}
@@ -0,0 +1,695 @@
+use crate::cli::{JsonShape, OutputFormat, OutputOptions};
+use crate::eol::trim_eol;
+use crate::formatting::decode_escapes;
+use crate::key_values::find_value;
+use crate::log_entry::LogEntry;
+use crate::result::Result;
+use std::io::Write;
+use std::path::Path;
+
+// RFC 3339 in UTC, with as many digits of the fraction of a second as needed for the precision
+// of the timestamp in the header
+const TIMESTAMP_FORMATS: [&str; 4] = ["%FT%TZ", "%FT%T%.3fZ", "%FT%T%.6fZ", "%FT%T%.9fZ"];
+const MAX_DEPTH: usize = 128;
+const INDENT: &[u8] = b"  ";
+
+const CODE_KEY: &[u8] = b"\x1B[34m";
+const CODE_STRING: &[u8] = b"\x1B[32m";
+const CODE_NUMBER: &[u8] = b"\x1B[36m";
+const CODE_KEYWORD: &[u8] = b"\x1B[35m";
+const CODE_NORMAL: &[u8] = b"\x1B[0m";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+pub fn write_entry(
+    entry: &LogEntry,
+    source: Option<&Path>,
+    writer: &mut impl Write,
+    opts: &OutputOptions,
+) -> Result<()> {
+    let value = entry_value(entry, source, opts)?;
+    if opts.format == OutputFormat::JsonPretty {
+        write_value_pretty(&value, writer, 0, opts.color_enabled)
+    } else {
+        write_value(&value, writer)
+    }
+}
+
+fn entry_value(entry: &LogEntry, source: Option<&Path>, opts: &OutputOptions) -> Result<Value> {
+    let string = |value: &[u8]| Value::String(String::from_utf8_lossy(value).into_owned());
+
+    let header = vec![
+        (
+            "timestamp".to_string(),
+            entry.timestamp().map_or(Value::Null, |timestamp| {
+                let format = TIMESTAMP_FORMATS[fraction_digits(entry.timestamp_text()).div_ceil(3)];
+                Value::String(timestamp.format(format).to_string())
+            }),
+        ),
+        (
+            "timestamp_raw".to_string(),
+            entry.timestamp_text().map_or(Value::Null, string),
+        ),
+        (
+            "level".to_string(),
+            entry
+                .level()
+                .map_or(Value::Null, |level| string(level.name().as_bytes())),
+        ),
+        (
+            "category".to_string(),
+            entry.category().map_or(Value::Null, string),
+        ),
+        (
+            "pid".to_string(),
+            entry
+                .pid()
+                .map_or(Value::Null, |pid| Value::Number(pid.to_string())),
+        ),
+    ];
+
+    let mut members = match opts.json_shape {
+        JsonShape::Flat => header,
+        JsonShape::Nested => vec![("header".to_string(), Value::Object(header))],
+    };
+
+    if let Some(source) = source {
+        members.push((
+            "source".to_string(),
+            Value::String(source.display().to_string()),
+        ));
+    }
+
+    if opts.show_size {
+        members.push((
+            "bytes".to_string(),
+            Value::Number(entry.contents().len().to_string()),
+        ));
+    }
+
+    if let Some(elapsed) = entry.elapsed() {
+        members.push((
+            "elapsed_ms".to_string(),
+            Value::Number(elapsed.num_milliseconds().to_string()),
+        ));
+    }
+
+    let message = entry.message().unwrap_or_else(|| entry.contents());
+
+    if !opts.extract_kv.is_empty() {
+        let values = opts
+            .extract_kv
+            .iter()
+            .map(|key| {
+                let value = find_value(message, key.as_bytes()).map_or(Value::Null, |value| {
+                    if is_integer(value) {
+                        Value::Number(String::from_utf8_lossy(value).into_owned())
+                    } else {
+                        string(value)
+                    }
+                });
+                (key.clone(), value)
+            })
+            .collect();
+        members.push(("kv".to_string(), Value::Object(values)));
+    }
+
+    let mut formatted = Vec::new();
+    decode_escapes(
+        message,
+        &mut formatted,
+        opts.escape_mode,
+        &opts.escape_table,
+        b"\n",
+        b"",
+    )?;
+    let message = trim_eol(&formatted);
+
+    match Some(message)
+        .filter(|_| opts.parse_embedded_json)
+        .and_then(parse)
+    {
+        Some(data) => members.push(("data".to_string(), data)),
+        None => members.push(("message".to_string(), string(message))),
+    }
+
+    Ok(Value::Object(members))
+}
+
+fn is_integer(value: &[u8]) -> bool {
+    let digits = value.strip_prefix(b"-").unwrap_or(value);
+    !digits.is_empty() && digits.iter().all(u8::is_ascii_digit)
+}
+
+pub fn write_string(value: &[u8], writer: &mut impl Write) -> Result<()> {
+    writer.write_all(b"\"")?;
+    for c in String::from_utf8_lossy(value).chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\t' => writer.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => {
+                let mut buf = [0; 4];
+                writer.write_all(c.encode_utf8(&mut buf).as_bytes())?;
+            }
+        }
+    }
+    writer.write_all(b"\"")?;
+    Ok(())
+}
+
+pub fn write_value(value: &Value, writer: &mut impl Write) -> Result<()> {
+    match value {
+        Value::Null => writer.write_all(b"null")?,
+        Value::Bool(value) => write!(writer, "{}", value)?,
+        Value::Number(value) => writer.write_all(value.as_bytes())?,
+        Value::String(value) => write_string(value.as_bytes(), writer)?,
+        Value::Array(values) => {
+            writer.write_all(b"[")?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_value(value, writer)?;
+            }
+            writer.write_all(b"]")?;
+        }
+        Value::Object(members) => {
+            writer.write_all(b"{")?;
+            for (i, (key, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_string(key.as_bytes(), writer)?;
+                writer.write_all(b":")?;
+                write_value(value, writer)?;
+            }
+            writer.write_all(b"}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_colored<W: Write>(
+    writer: &mut W,
+    color_code: &[u8],
+    color_enabled: bool,
+    write: impl FnOnce(&mut W) -> Result<()>,
+) -> Result<()> {
+    if color_enabled {
+        writer.write_all(color_code)?;
+    }
+    write(writer)?;
+    if color_enabled {
+        writer.write_all(CODE_NORMAL)?;
+    }
+    Ok(())
+}
+
+fn write_newline(writer: &mut impl Write, indent: usize) -> Result<()> {
+    writer.write_all(b"\n")?;
+    for _ in 0..indent {
+        writer.write_all(INDENT)?;
+    }
+    Ok(())
+}
+
+pub fn write_value_pretty(
+    value: &Value,
+    writer: &mut impl Write,
+    indent: usize,
+    color_enabled: bool,
+) -> Result<()> {
+    match value {
+        Value::Null | Value::Bool(_) => write_colored(writer, CODE_KEYWORD, color_enabled, |w| {
+            write_value(value, w)
+        })?,
+        Value::Number(_) => write_colored(writer, CODE_NUMBER, color_enabled, |w| {
+            write_value(value, w)
+        })?,
+        Value::String(string) => write_colored(writer, CODE_STRING, color_enabled, |w| {
+            write_string(string.as_bytes(), w)
+        })?,
+        Value::Array(values) if values.is_empty() => writer.write_all(b"[]")?,
+        Value::Array(values) => {
+            writer.write_all(b"[")?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_newline(writer, indent + 1)?;
+                write_value_pretty(value, writer, indent + 1, color_enabled)?;
+            }
+            write_newline(writer, indent)?;
+            writer.write_all(b"]")?;
+        }
+        Value::Object(members) if members.is_empty() => writer.write_all(b"{}")?,
+        Value::Object(members) => {
+            writer.write_all(b"{")?;
+            for (i, (key, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_newline(writer, indent + 1)?;
+                write_colored(writer, CODE_KEY, color_enabled, |w| {
+                    write_string(key.as_bytes(), w)
+                })?;
+                writer.write_all(b": ")?;
+                write_value_pretty(value, writer, indent + 1, color_enabled)?;
+            }
+            write_newline(writer, indent)?;
+            writer.write_all(b"}")?;
+        }
+    }
+    Ok(())
+}
+
+pub fn parse(input: &[u8]) -> Option<Value> {
+    let mut parser = Parser { input, pos: 0 };
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.pos == input.len() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &[u8]) -> bool {
+        if self.input[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Option<Value> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        self.skip_whitespace();
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.parse_string()?;
+                        if !self.eat(b':') {
+                            return None;
+                        }
+                        members.push((key, self.parse_value(depth + 1)?));
+                        if self.eat(b'}') {
+                            break;
+                        } else if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Value::Object(members))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        values.push(self.parse_value(depth + 1)?);
+                        if self.eat(b']') {
+                            break;
+                        } else if !self.eat(b',') {
+                            return None;
+                        }
+                    }
+                }
+                Some(Value::Array(values))
+            }
+            b'"' => self.parse_string().map(Value::String),
+            b't' if self.eat_keyword(b"true") => Some(Value::Bool(true)),
+            b'f' if self.eat_keyword(b"false") => Some(Value::Bool(false)),
+            b'n' if self.eat_keyword(b"null") => Some(Value::Null),
+            b'-' | b'0'..=b'9' => self.parse_number().map(Value::Number),
+            _ => None,
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<String> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let digits_start = parser.pos;
+            while let Some(b'0'..=b'9') = parser.peek() {
+                parser.pos += 1;
+            }
+            parser.pos > digits_start
+        };
+
+        self.eat_keyword(b"-");
+        if !self.eat_keyword(b"0") && !digits(self) {
+            return None;
+        }
+        if self.eat_keyword(b".") && !digits(self) {
+            return None;
+        }
+        if self.eat_keyword(b"e") || self.eat_keyword(b"E") {
+            if !self.eat_keyword(b"+") {
+                self.eat_keyword(b"-");
+            }
+            if !digits(self) {
+                return None;
+            }
+        }
+
+        String::from_utf8(self.input[start..self.pos].to_vec()).ok()
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        if !self.eat_keyword(b"\"") {
+            return None;
+        }
+
+        let mut output = Vec::new();
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'"' | b'\\' | b'/' => output.push(escaped),
+                        b'b' => output.push(0x08),
+                        b'f' => output.push(0x0c),
+                        b'n' => output.push(b'\n'),
+                        b'r' => output.push(b'\r'),
+                        b't' => output.push(b'\t'),
+                        b'u' => {
+                            let mut code = self.parse_hex4()?;
+                            if (0xd800..0xdc00).contains(&code) {
+                                if !self.eat_keyword(b"\\u") {
+                                    return None;
+                                }
+                                let low = self.parse_hex4()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return None;
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            let mut buf = [0; 4];
+                            let c = std::char::from_u32(code)?;
+                            output.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                        }
+                        _ => return None,
+                    }
+                }
+                c if c < 0x20 => return None,
+                c => output.push(c),
+            }
+        }
+
+        String::from_utf8(output).ok()
+    }
+
+    fn parse_hex4(&mut self) -> Option<u32> {
+        let hex = self.input.get(self.pos..self.pos + 4)?;
+        let code = u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+        self.pos += 4;
+        Some(code)
+    }
+}
+
+// number of digits of the fraction of a second, at most 9, and 3 for timestamps without text
+fn fraction_digits(timestamp_text: Option<&[u8]>) -> usize {
+    let text = match timestamp_text {
+        Some(text) => text,
+        None => return 3,
+    };
+    text.iter()
+        .position(|&c| c == b'.')
+        .map_or(0, |pos| {
+            text[pos + 1..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .count()
+        })
+        .min(9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::EscapeMode;
+
+    fn to_json(entry: &LogEntry) -> String {
+        to_json_with(
+            entry,
+            &OutputOptions {
+                escape_mode: EscapeMode::Standard,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn to_json_with(entry: &LogEntry, opts: &OutputOptions) -> String {
+        let mut out_buf = Vec::new();
+        write_entry(entry, None, &mut out_buf, opts).unwrap();
+        String::from_utf8(out_buf).unwrap()
+    }
+
+    #[test]
+    fn write_entry_full_header() {
+        let entry = LogEntry::from_contents(
+            b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: \"B\"\\tC\\nD\n\n".to_vec(),
+        );
+        assert_eq!(
+            to_json(&entry),
+            r#"{"timestamp":"2020-01-01T20:00:00.000Z","timestamp_raw":"2020-01-01 20:00:00.000","level":"info","category":"A","pid":16866,"message":"\"B\"\tC\nD"}"#
+        );
+    }
+
+    #[test]
+    fn write_entry_timestamp_round_trip() {
+        let timestamp = |contents: &[u8]| {
+            let entry = LogEntry::from_contents(contents.to_vec());
+            let value = entry_value(&entry, None, &OutputOptions::default()).unwrap();
+            match (member(&value, "timestamp"), member(&value, "timestamp_raw")) {
+                (Some(Value::String(timestamp)), Some(Value::String(raw))) => {
+                    let parsed = chrono::DateTime::parse_from_rfc3339(timestamp).unwrap();
+                    assert_eq!(Some(parsed.naive_utc()), entry.timestamp());
+                    (timestamp.clone(), raw.clone())
+                }
+                members => panic!("Unexpected timestamps: {:?}", members),
+            }
+        };
+
+        assert_eq!(
+            timestamp(b"-info:<1> 2020-01-13 20:42:00.476 UTC [A]: B\n\n"),
+            (
+                "2020-01-13T20:42:00.476Z".to_string(),
+                "2020-01-13 20:42:00.476".to_string()
+            )
+        );
+        assert_eq!(
+            timestamp(b"-info:<1> 2020-01-13T20:42:00.476123+02:00 [A]: B\n\n"),
+            (
+                "2020-01-13T18:42:00.476123Z".to_string(),
+                "2020-01-13T20:42:00.476123+02:00".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn write_entry_no_header() {
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        assert_eq!(
+            to_json(&entry),
+            r#"{"timestamp":null,"timestamp_raw":null,"level":null,"category":null,"pid":null,"message":"MESSAGE Alphabet"}"#
+        );
+    }
+
+    #[test]
+    fn write_entry_show_size() {
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        let opts = OutputOptions {
+            show_size: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_json_with(&entry, &opts),
+            r#"{"timestamp":null,"timestamp_raw":null,"level":null,"category":null,"pid":null,"bytes":18,"message":"MESSAGE Alphabet"}"#
+        );
+    }
+
+    #[test]
+    fn write_entry_extract_kv() {
+        let entry = LogEntry::from_contents(
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Login userID=42 name=\"J D\" delta=-7s\n\n"
+                .to_vec(),
+        );
+        let opts = OutputOptions {
+            extract_kv: vec![
+                "userID".to_string(),
+                "name".to_string(),
+                "delta".to_string(),
+                "missing".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            to_json_with(&entry, &opts),
+            r#"{"timestamp":"2020-01-01T20:00:00.000Z","timestamp_raw":"2020-01-01 20:00:00.000","level":"info","category":"A","pid":1,"kv":{"userID":42,"name":"J D","delta":"-7s","missing":null},"message":"Login userID=42 name=\"J D\" delta=-7s"}"#
+        );
+    }
+
+    #[test]
+    fn write_entry_embedded_json() {
+        let entry = LogEntry::from_contents(
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: {\n  \"id\": 7,\n  \"tags\": [\"a\", null, true],\n  \"ratio\": -1.5e3\n}\n\n".to_vec(),
+        );
+        let opts = OutputOptions {
+            parse_embedded_json: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_json_with(&entry, &opts),
+            r#"{"timestamp":"2020-01-01T20:00:00.000Z","timestamp_raw":"2020-01-01 20:00:00.000","level":"info","category":"A","pid":1,"data":{"id":7,"tags":["a",null,true],"ratio":-1.5e3}}"#
+        );
+    }
+
+    #[test]
+    fn write_entry_embedded_json_fallback() {
+        let entry = LogEntry::from_contents(
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: {broken\n\n".to_vec(),
+        );
+        let opts = OutputOptions {
+            parse_embedded_json: true,
+            ..Default::default()
+        };
+        assert!(to_json_with(&entry, &opts).ends_with(r#""message":"{broken"}"#));
+    }
+
+    #[test]
+    fn parse_strings() {
+        assert_eq!(
+            parse(br#""a\"b\u0041\ud83d\ude00""#),
+            Some(Value::String("a\"bA\u{1F600}".to_string()))
+        );
+        assert_eq!(parse(b"\"unterminated"), None);
+        assert_eq!(parse(b"01"), None);
+        assert_eq!(parse(b"[1,]"), None);
+    }
+
+    #[test]
+    fn write_entry_pretty_colored() {
+        let entry = LogEntry::from_contents(
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: {\"id\": 7, \"ok\": null}\n\n".to_vec(),
+        );
+        let opts = OutputOptions {
+            format: OutputFormat::JsonPretty,
+            color_enabled: true,
+            parse_embedded_json: true,
+            ..Default::default()
+        };
+        let json = to_json_with(&entry, &opts);
+        assert!(json.starts_with(
+            "{\n  \x1B[34m\"timestamp\"\x1B[0m: \x1B[32m\"2020-01-01T20:00:00.000Z\"\x1B[0m,\n"
+        ));
+        assert!(json.contains("\n  \x1B[34m\"pid\"\x1B[0m: \x1B[36m1\x1B[0m,\n"));
+        assert!(json.ends_with(
+            "\x1B[34m\"data\"\x1B[0m: {\n    \x1B[34m\"id\"\x1B[0m: \x1B[36m7\x1B[0m,\n    \x1B[34m\"ok\"\x1B[0m: \x1B[35mnull\x1B[0m\n  }\n}"
+        ));
+    }
+
+    #[test]
+    fn write_entry_pretty_uncolored() {
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        let opts = OutputOptions {
+            format: OutputFormat::JsonPretty,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_json_with(&entry, &opts),
+            "{\n  \"timestamp\": null,\n  \"timestamp_raw\": null,\n  \"level\": null,\n  \"category\": null,\n  \"pid\": null,\n  \"message\": \"MESSAGE Alphabet\"\n}"
+        );
+    }
+
+    fn member<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+        match value {
+            Value::Object(members) => members
+                .iter()
+                .find(|(member_key, _)| member_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn write_entry_shapes_round_trip() {
+        let entry = LogEntry::from_contents(
+            b"-warning:<7> 2020-01-01 20:00:00.000 UTC [A]: Text\n\n".to_vec(),
+        );
+        let level = Value::String("warning".to_string());
+        let message = Value::String("Text".to_string());
+
+        for (json_shape, nested) in [(JsonShape::Flat, false), (JsonShape::Nested, true)] {
+            let opts = OutputOptions {
+                json_shape,
+                ..Default::default()
+            };
+            let value = parse(to_json_with(&entry, &opts).as_bytes()).unwrap();
+            let header = if nested {
+                member(&value, "header").unwrap()
+            } else {
+                &value
+            };
+
+            assert_eq!(member(header, "level"), Some(&level));
+            assert_eq!(member(header, "pid"), Some(&Value::Number("7".to_string())));
+            assert_eq!(
+                member(header, "message"),
+                if nested { None } else { Some(&message) }
+            );
+            assert_eq!(member(&value, "message"), Some(&message));
+            assert_eq!(member(&value, "level").is_some(), !nested);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::eol;
+use crate::json::{self, Value};
+use crate::log_entry::{LogEntry, LogLevel};
+use chrono::{DateTime, NaiveDateTime};
+use std::io::BufRead;
+use streaming_iterator::StreamingIterator;
+
+const TIMESTAMP_FORMAT: &str = "%F %T%.3f";
+
+// numeric timestamps greater than this are in milliseconds rather than seconds since the epoch
+const MAX_EPOCH_SECONDS: f64 = 1e11;
+
+// names of the members of JSON log lines holding header fields
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonFields {
+    pub level: String,
+    pub timestamp: String,
+    pub message: String,
+}
+
+impl Default for JsonFields {
+    fn default() -> Self {
+        JsonFields {
+            level: "level".to_string(),
+            timestamp: "ts".to_string(),
+            message: "msg".to_string(),
+        }
+    }
+}
+
+// Reads a log with one JSON object per line, e.g. {"level":"info","ts":"...","msg":"..."}.
+// Entries with both a level and a timestamp get a header in the native format, so that
+// the output looks like that of other logs, and otherwise consist of the message alone. Lines
+// which are not JSON objects are passed through as entries without a header, unless strict.
+pub struct JsonLinesReader<R: BufRead> {
+    reader: R,
+    fields: JsonFields,
+    strict: bool,
+    line: Vec<u8>,
+    entry: LogEntry,
+    offset: u64,
+}
+
+impl<R: BufRead> JsonLinesReader<R> {
+    pub fn new(reader: R, fields: JsonFields) -> Self {
+        JsonLinesReader {
+            reader,
+            fields,
+            strict: false,
+            line: Vec::new(),
+            entry: LogEntry::from_contents(Vec::new()),
+            offset: 0,
+        }
+    }
+
+    // drops lines which are not JSON objects
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    // offset in the log source the reader is positioned at
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_source(mut self, source: usize) -> Self {
+        self.entry = self.entry.with_source(source);
+        self
+    }
+
+    // fills the entry from the line, returning false if it should be skipped
+    fn parse_line(&mut self) -> bool {
+        let line = eol::trim_eol(&self.line);
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return false;
+        }
+
+        let members = match json::parse(line) {
+            Some(Value::Object(members)) => members,
+            _ if self.strict => return false,
+            _ => {
+                let contents = self.entry.contents_mut();
+                contents.extend_from_slice(line);
+                contents.extend_from_slice(eol::EOL);
+                contents.extend_from_slice(eol::EOL);
+                self.entry.fill_header(None, None);
+                return true;
+            }
+        };
+
+        let member = |name: &str| {
+            members
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+        };
+        let level = member(&self.fields.level).and_then(|value| match value {
+            Value::String(name) => parse_level(name),
+            _ => None,
+        });
+        let timestamp = member(&self.fields.timestamp).and_then(parse_timestamp);
+        let message = match member(&self.fields.message) {
+            Some(Value::String(message)) => escape_line_breaks(message),
+            Some(value) => {
+                let mut message = Vec::new();
+                json::write_value(value, &mut message).ok();
+                message
+            }
+            None => Vec::new(),
+        };
+
+        let contents = self.entry.contents_mut();
+        if let (Some(level), Some(timestamp)) = (level, timestamp) {
+            contents.extend_from_slice(
+                format!(
+                    "-{}:<> {} UTC []: ",
+                    level.name(),
+                    timestamp.format(TIMESTAMP_FORMAT)
+                )
+                .as_bytes(),
+            );
+        }
+        contents.extend_from_slice(&message);
+        contents.extend_from_slice(eol::EOL);
+        contents.extend_from_slice(eol::EOL);
+        self.entry.fill_header(level, timestamp);
+        true
+    }
+}
+
+impl<R: BufRead> StreamingIterator for JsonLinesReader<R> {
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        self.entry.reset();
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(bytes_read) if bytes_read > 0 => {
+                    self.offset += bytes_read as u64;
+                    if self.parse_line() {
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+        self.entry.set_end_offset(self.offset);
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.entry.contents().is_empty() {
+            None
+        } else {
+            Some(&self.entry)
+        }
+    }
+}
+
+fn parse_level(name: &str) -> Option<LogLevel> {
+    match name.to_lowercase().as_str() {
+        "trace" | "debug" => Some(LogLevel::Debug),
+        "info" => Some(LogLevel::Info),
+        "warn" | "warning" => Some(LogLevel::Warning),
+        "error" | "critical" => Some(LogLevel::Critical),
+        "fatal" | "panic" => Some(LogLevel::Fatal),
+        _ => None,
+    }
+}
+
+// RFC 3339 or naive UTC strings, or seconds or milliseconds since the epoch
+fn parse_timestamp(value: &Value) -> Option<NaiveDateTime> {
+    match value {
+        Value::String(timestamp) => DateTime::parse_from_rfc3339(timestamp)
+            .map(|timestamp| timestamp.naive_utc())
+            .or_else(|_| NaiveDateTime::parse_from_str(timestamp, "%F %T%.f"))
+            .or_else(|_| NaiveDateTime::parse_from_str(timestamp, "%FT%T%.f"))
+            .ok(),
+        Value::Number(number) => {
+            let seconds: f64 = number.parse().ok()?;
+            let seconds = if seconds.abs() > MAX_EPOCH_SECONDS {
+                seconds / 1000.0
+            } else {
+                seconds
+            };
+            let millis = (seconds * 1000.0).round() as i64;
+            NaiveDateTime::from_timestamp_opt(
+                millis.div_euclid(1000),
+                (millis.rem_euclid(1000) * 1_000_000) as u32,
+            )
+        }
+        _ => None,
+    }
+}
+
+// keeps the entry on a single line, leaving line breaks to escape decoding like in native logs.
+// Backslashes are escaped too, so that decoding restores them instead of sequences they start.
+fn escape_line_breaks(message: &str) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(message.len());
+    for &c in message.as_bytes() {
+        match c {
+            b'\n' => escaped.extend_from_slice(b"\\n"),
+            b'\r' => escaped.extend_from_slice(b"\\r"),
+            b'\\' => escaped.extend_from_slice(b"\\\\"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const INPUT: &[u8] =
+        b"{\"level\":\"info\",\"ts\":\"2020-01-13T20:42:00.476+02:00\",\"msg\":\"Started\"}\n\
+        \n\
+        {\"level\":\"error\",\"ts\":1578948121.5,\"msg\":\"Failed:\\nretrying\"}\n\
+        not json\n\
+        {\"msg\":\"No header\"}\n";
+
+    fn read(
+        reader: JsonLinesReader<&[u8]>,
+    ) -> Vec<(String, Option<LogLevel>, Option<NaiveDateTime>)> {
+        reader
+            .map(|entry| {
+                (
+                    String::from_utf8_lossy(entry.contents()).into_owned(),
+                    entry.level(),
+                    entry.timestamp(),
+                )
+            })
+            .owned()
+            .collect()
+    }
+
+    #[test]
+    fn json_lines_reader_entries() {
+        assert_eq!(
+            read(JsonLinesReader::new(INPUT, JsonFields::default())),
+            vec![
+                (
+                    "-info:<> 2020-01-13 18:42:00.476 UTC []: Started\n\n".to_string(),
+                    Some(LogLevel::Info),
+                    Some(NaiveDate::from_ymd(2020, 1, 13).and_hms_milli(18, 42, 0, 476))
+                ),
+                (
+                    "-critical:<> 2020-01-13 20:42:01.500 UTC []: Failed:\\nretrying\n\n"
+                        .to_string(),
+                    Some(LogLevel::Critical),
+                    Some(NaiveDate::from_ymd(2020, 1, 13).and_hms_milli(20, 42, 1, 500))
+                ),
+                ("not json\n\n".to_string(), None, None),
+                ("No header\n\n".to_string(), None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_lines_reader_strict_and_custom_fields() {
+        let fields = JsonFields {
+            level: "severity".to_string(),
+            timestamp: "time".to_string(),
+            message: "message".to_string(),
+        };
+        let input: &[u8] = b"{\"severity\":\"WARN\",\"time\":\"2020-01-13 20:42:00.476\",\"message\":\"Low disk\"}\n\
+            {\"severity\":\"WARN\",\n";
+        assert_eq!(
+            read(JsonLinesReader::new(input, fields).with_strict(true)),
+            vec![(
+                "-warning:<> 2020-01-13 20:42:00.476 UTC []: Low disk\n\n".to_string(),
+                Some(LogLevel::Warning),
+                Some(NaiveDate::from_ymd(2020, 1, 13).and_hms_milli(20, 42, 0, 476))
+            )]
+        );
+    }
+
+    #[test]
+    fn json_lines_reader_escapes_backslashes() {
+        let input: &[u8] = b"{\"level\":\"info\",\"ts\":1578948121.5,\"msg\":\"Saved C:\\\\new\\\\table\\nDone\"}\n";
+        assert_eq!(
+            read(JsonLinesReader::new(input, JsonFields::default())),
+            vec![(
+                "-info:<> 2020-01-13 20:42:01.500 UTC []: Saved C:\\\\new\\\\table\\nDone\n\n"
+                    .to_string(),
+                Some(LogLevel::Info),
+                Some(NaiveDate::from_ymd(2020, 1, 13).and_hms_milli(20, 42, 1, 500))
+            )]
+        );
+    }
+
+    #[test]
+    fn json_lines_reader_end_offsets() {
+        let mut reader = JsonLinesReader::new(INPUT, JsonFields::default()).with_offset(10);
+        let first_line_len = INPUT.iter().position(|&c| c == b'\n').unwrap() as u64 + 1;
+        assert_eq!(
+            reader.next().map(LogEntry::end_offset),
+            Some(10 + first_line_len)
+        );
+    }
+}
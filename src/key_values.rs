@@ -0,0 +1,72 @@
+use memchr::memmem;
+
+// Lookup of `key=value` pairs in entry messages. A key matches only as a whole word, so that
+// "id" is not found in "userID=42". Values end at whitespace, ',' or ';' unless enclosed in
+// double quotes, which are not part of the value.
+pub fn find_value<'a>(message: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    if key.is_empty() {
+        return None;
+    }
+
+    let mut start = 0;
+    while let Some(pos) = memmem::find(&message[start..], key).map(|pos| start + pos) {
+        let value_start = pos + key.len() + 1;
+        let is_pair = (pos == 0 || !is_key_char(message[pos - 1]))
+            && message.get(pos + key.len()) == Some(&b'=');
+        if is_pair {
+            return Some(value_at(&message[value_start..]));
+        }
+        start = pos + 1;
+    }
+
+    None
+}
+
+fn is_key_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'.' || c == b'-'
+}
+
+fn value_at(input: &[u8]) -> &[u8] {
+    if let Some(quoted) = input.strip_prefix(b"\"") {
+        let len = quoted
+            .iter()
+            .position(|&c| c == b'"')
+            .unwrap_or(quoted.len());
+        &quoted[..len]
+    } else {
+        let len = input
+            .iter()
+            .position(|&c| c.is_ascii_whitespace() || c == b',' || c == b';')
+            .unwrap_or(input.len());
+        &input[..len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_value_pairs() {
+        let message = b"Login ok userID=42, session=\"a b\"; duration_ms=517\n";
+        assert_eq!(find_value(message, b"userID"), Some(&b"42"[..]));
+        assert_eq!(find_value(message, b"session"), Some(&b"a b"[..]));
+        assert_eq!(find_value(message, b"duration_ms"), Some(&b"517"[..]));
+        assert_eq!(find_value(message, b"ID"), None);
+        assert_eq!(find_value(message, b"Login"), None);
+        assert_eq!(find_value(message, b""), None);
+    }
+
+    #[test]
+    fn find_value_skips_partial_keys() {
+        assert_eq!(
+            find_value(b"id=1 request_id=2 id=3", b"request_id"),
+            Some(&b"2"[..])
+        );
+        assert_eq!(find_value(b"xid=1 id=", b"id"), Some(&b""[..]));
+        assert_eq!(
+            find_value(b"path=\"unterminated", b"path"),
+            Some(&b"unterminated"[..])
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::log_entry::{LogEntry, LogLevel};
+use std::collections::VecDeque;
+use streaming_iterator::StreamingIterator;
+
+const LEVEL_COUNT: usize = LogLevel::Fatal as usize + 1;
+
+// Collects up to `n` latest entries of each level from entries read oldest first, keeping
+// a ring buffer per level. Returns them in chronological order, skipping entries without level.
+pub fn last_per_level(
+    mut entries: impl StreamingIterator<Item = LogEntry>,
+    n: usize,
+) -> Vec<LogEntry> {
+    let mut buffers = vec![VecDeque::with_capacity(n); LEVEL_COUNT];
+    let mut position = 0;
+
+    while let Some(entry) = entries.next() {
+        if let Some(level) = entry.level() {
+            let buffer = &mut buffers[level as usize];
+            if buffer.len() == n {
+                buffer.pop_front();
+            }
+            buffer.push_back((position, entry.clone()));
+        }
+        position += 1;
+    }
+
+    let mut selected: Vec<_> = buffers.into_iter().flatten().collect();
+    selected.sort_by_key(|&(position, _)| position);
+    selected.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// Same as `last_per_level`, but for entries read newest first. Stops reading once every level
+// from `lowest_level` up, i.e. every level the filters let through, has `n` entries, or as many
+// as the input has if their numbers are known, e.g. from an index. Otherwise a level missing from
+// the input, commonly fatal, makes it read the whole input.
+pub fn last_per_level_rev(
+    mut entries: impl StreamingIterator<Item = LogEntry>,
+    n: usize,
+    lowest_level: LogLevel,
+    available: Option<[usize; LEVEL_COUNT]>,
+) -> Vec<LogEntry> {
+    let available = available.unwrap_or([usize::MAX; LEVEL_COUNT]);
+    let mut counts = [0; LEVEL_COUNT];
+    let mut selected = Vec::new();
+
+    while (lowest_level as usize..LEVEL_COUNT).any(|level| counts[level] < n.min(available[level]))
+    {
+        let entry = match entries.next() {
+            Some(entry) => entry,
+            None => break,
+        };
+        if let Some(level) = entry.level() {
+            let count = &mut counts[level as usize];
+            if *count < n {
+                *count += 1;
+                selected.push(entry.clone());
+            }
+        }
+    }
+
+    selected.reverse();
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG_INPUT: &[&[u8]] = &[
+        b"-debug:<1> 2020-01-01 20:00:00.000 UTC [A]: Debug1",
+        b"-critical:<1> 2020-01-01 20:00:01.000 UTC [A]: Critical1",
+        b"-debug:<1> 2020-01-01 20:00:02.000 UTC [A]: Debug2",
+        b"MESSAGE Alphabet",
+        b"-debug:<1> 2020-01-01 20:00:03.000 UTC [A]: Debug3",
+        b"-info:<1> 2020-01-01 20:00:04.000 UTC [A]: Info1",
+        b"-critical:<1> 2020-01-01 20:00:05.000 UTC [A]: Critical2",
+        b"-critical:<1> 2020-01-01 20:00:06.000 UTC [A]: Critical3",
+    ];
+
+    fn to_log_iter(
+        input_iter: impl Iterator<Item = &'static [u8]>,
+    ) -> impl StreamingIterator<Item = LogEntry> {
+        streaming_iterator::convert(input_iter.map(|lines| LogEntry::from_contents(lines.to_vec())))
+    }
+
+    fn expected() -> Vec<&'static [u8]> {
+        vec![
+            LOG_INPUT[2],
+            LOG_INPUT[4],
+            LOG_INPUT[5],
+            LOG_INPUT[6],
+            LOG_INPUT[7],
+        ]
+    }
+
+    #[test]
+    fn last_per_level_forward() {
+        let selected = last_per_level(to_log_iter(LOG_INPUT.iter().copied()), 2);
+        assert_eq!(selected, expected());
+    }
+
+    #[test]
+    fn last_per_level_reverse() {
+        let selected = last_per_level_rev(
+            to_log_iter(LOG_INPUT.iter().copied().rev()),
+            2,
+            LogLevel::Debug,
+            None,
+        );
+        assert_eq!(selected, expected());
+    }
+
+    #[test]
+    fn last_per_level_reverse_stops_at_lowest_level() {
+        let mut read = 0;
+        let input = LOG_INPUT
+            .iter()
+            .copied()
+            .rev()
+            .filter(|contents| {
+                contents.starts_with(b"-critical") || contents.starts_with(b"-fatal")
+            })
+            .chain(std::iter::once(
+                &b"-fatal:<1> 2020-01-01 20:00:07.000 UTC [A]: Fatal1"[..],
+            ))
+            .chain(LOG_INPUT.iter().copied());
+        let entries = to_log_iter(input).inspect(|_| read += 1);
+        let selected = last_per_level_rev(entries, 1, LogLevel::Critical, None);
+        assert_eq!(read, 4);
+        assert_eq!(selected.len(), 2);
+    }
+
+    #[test]
+    fn last_per_level_reverse_stops_at_available_counts() {
+        let mut read = 0;
+        let entries = to_log_iter(LOG_INPUT.iter().copied().rev()).inspect(|_| read += 1);
+        let selected = last_per_level_rev(entries, 2, LogLevel::Info, Some([3, 1, 0, 3, 0]));
+        assert_eq!(read, 3);
+        assert_eq!(selected, vec![LOG_INPUT[5], LOG_INPUT[6], LOG_INPUT[7]]);
+    }
+
+    #[test]
+    fn last_per_level_at_most_n() {
+        let count = |entries: &[LogEntry], level| {
+            entries
+                .iter()
+                .filter(|entry| entry.level() == Some(level))
+                .count()
+        };
+        let input: Vec<_> = to_log_iter(LOG_INPUT.iter().copied()).cloned().collect();
+
+        for n in 1..5 {
+            let selected = last_per_level(to_log_iter(LOG_INPUT.iter().copied()), n);
+            for &level in &[LogLevel::Debug, LogLevel::Info, LogLevel::Critical] {
+                assert_eq!(count(&selected, level), n.min(count(&input, level)));
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+// Caps the size of the output, ending it at an entry boundary: entries are written until
+// the given number of bytes is reached, so the last one may end past it.
+#[derive(Debug)]
+pub struct ByteLimit {
+    limit: u64,
+    written: Cell<u64>,
+    truncated: Cell<bool>,
+}
+
+impl ByteLimit {
+    pub fn new(limit: u64) -> Self {
+        ByteLimit {
+            limit,
+            written: Cell::new(0),
+            truncated: Cell::new(false),
+        }
+    }
+
+    // whether another entry may be written, remembering that the output is truncated otherwise
+    pub fn allows_entry(&self) -> bool {
+        let allowed = self.written.get() < self.limit;
+        if !allowed {
+            self.truncated.set(true);
+        }
+        allowed
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated.get()
+    }
+
+    pub fn note(&self) -> String {
+        format!(
+            "Output truncated after {} bytes (--limit-bytes {})",
+            self.written.get(),
+            self.limit
+        )
+    }
+}
+
+// counts bytes written to the inner writer towards the limit, if any
+pub struct CountingWriter<W: Write> {
+    inner: W,
+    limit: Option<Rc<ByteLimit>>,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W, limit: Option<Rc<ByteLimit>>) -> Self {
+        CountingWriter { inner, limit }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(limit) = &self.limit {
+            limit.written.set(limit.written.get() + written as u64);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_limit_allows_entries_until_reached() -> io::Result<()> {
+        let limit = Rc::new(ByteLimit::new(10));
+        let mut out_buf = Vec::new();
+        let mut writer = CountingWriter::new(&mut out_buf, Some(limit.clone()));
+
+        assert!(limit.allows_entry());
+        writer.write_all(b"First\n")?;
+        assert!(limit.allows_entry());
+        writer.write_all(b"Second\n")?;
+        assert!(!limit.is_truncated());
+        assert!(!limit.allows_entry());
+        assert!(limit.is_truncated());
+        assert_eq!(
+            limit.note(),
+            "Output truncated after 13 bytes (--limit-bytes 10)"
+        );
+        Ok(())
+    }
+}
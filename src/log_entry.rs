@@ -1,5 +1,9 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, Duration, NaiveDateTime};
 use std::cell::Cell;
+use std::rc::Rc;
+
+// how far into the header line a timestamp not found right after the pid is looked for
+const TIMESTAMP_SEARCH_LEN: usize = 64;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LogLevel {
@@ -10,6 +14,81 @@ pub enum LogLevel {
     Fatal = 4,
 }
 
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warning,
+        LogLevel::Critical,
+        LogLevel::Fatal,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warning",
+            LogLevel::Critical => "critical",
+            LogLevel::Fatal => "fatal",
+        }
+    }
+}
+
+// maps the first letter of a level name in the header to a level,
+// with custom letters taking precedence over the standard ones
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelMap {
+    custom: Vec<(u8, LogLevel)>,
+}
+
+impl LevelMap {
+    pub fn insert(&mut self, letter: u8, level: LogLevel) {
+        self.custom
+            .retain(|&(custom_letter, _)| custom_letter != letter);
+        self.custom.push((letter, level));
+    }
+
+    pub fn get(&self, letter: u8) -> Option<LogLevel> {
+        match self
+            .custom
+            .iter()
+            .find(|&&(custom_letter, _)| custom_letter == letter)
+        {
+            Some(&(_, level)) => Some(level),
+            None => match letter {
+                b'd' => Some(LogLevel::Debug),
+                b'i' => Some(LogLevel::Info),
+                b'w' => Some(LogLevel::Warning),
+                b'c' => Some(LogLevel::Critical),
+                b'f' => Some(LogLevel::Fatal),
+                _ => None,
+            },
+        }
+    }
+
+    // whether the line starts with a header, i.e. "-<level name>:<", the level being recognized
+    // like by `LogEntry::level`
+    pub fn is_header(&self, line: &[u8]) -> bool {
+        match line {
+            [b'-', letter, rest @ ..] if self.get(*letter).is_some() => {
+                let name_len = rest
+                    .iter()
+                    .position(|c| !c.is_ascii_alphabetic())
+                    .unwrap_or(rest.len());
+                rest[name_len..].starts_with(b":<")
+            }
+            _ => false,
+        }
+    }
+}
+
+// time until the entry following in the output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NextGap {
+    Gap(Duration),
+    Last,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Cache<T> {
     Empty,
@@ -22,32 +101,45 @@ pub struct LogEntry {
     level: Cell<Cache<Option<LogLevel>>>,
     timestamp: Cell<Cache<Option<NaiveDateTime>>>,
     source: usize, // index of log source the entry comes from
+    level_map: Rc<LevelMap>,
+    separator: bool, // divides non-adjacent entries instead of coming from a log source
+    next_gap: Option<NextGap>,
+    elapsed: Option<Duration>, // since the start of the operation the entry ends
+    end_offset: u64,           // offset just past the entry in its log source, when read forward
 }
 
 impl LogEntry {
-    pub fn new() -> LogEntry {
-        LogEntry {
-            contents: Vec::new(),
-            level: Cell::new(Cache::Empty),
-            timestamp: Cell::new(Cache::Empty),
-            source: 0,
-        }
-    }
-
-    #[cfg(test)]
+    // an entry of the first log source, with the header parsed from contents when first needed
+    // unless given with `with_level` and `with_timestamp`
     pub fn from_contents(contents: Vec<u8>) -> LogEntry {
         LogEntry {
             contents,
             level: Cell::new(Cache::Empty),
             timestamp: Cell::new(Cache::Empty),
             source: 0,
+            level_map: Rc::default(),
+            separator: false,
+            next_gap: None,
+            elapsed: None,
+            end_offset: 0,
         }
     }
 
+    pub fn separator(contents: Vec<u8>) -> LogEntry {
+        let entry = LogEntry {
+            separator: true,
+            ..LogEntry::from_contents(contents)
+        };
+        entry.fill_header(None, None);
+        entry
+    }
+
     pub fn reset(&mut self) {
         self.contents.clear();
         self.level.set(Cache::Empty);
         self.timestamp.set(Cache::Empty);
+        self.next_gap = None;
+        self.elapsed = None;
     }
 
     pub fn with_source(mut self, source: usize) -> Self {
@@ -55,6 +147,11 @@ impl LogEntry {
         self
     }
 
+    pub fn with_level_map(mut self, level_map: Rc<LevelMap>) -> Self {
+        self.level_map = level_map;
+        self
+    }
+
     pub fn contents(&self) -> &[u8] {
         self.contents.as_slice()
     }
@@ -72,14 +169,7 @@ impl LogEntry {
                 .iter()
                 .position(|&c| c == b'-')
                 .and_then(|pos| self.contents.get(pos + 1))
-                .and_then(|level| match level {
-                    b'd' => Some(LogLevel::Debug),
-                    b'i' => Some(LogLevel::Info),
-                    b'w' => Some(LogLevel::Warning),
-                    b'c' => Some(LogLevel::Critical),
-                    b'f' => Some(LogLevel::Fatal),
-                    _ => None,
-                });
+                .and_then(|&level| self.level_map.get(level));
 
             self.level.set(Cache::Filled(level));
             level
@@ -90,22 +180,132 @@ impl LogEntry {
         if let Cache::Filled(timestamp) = self.timestamp.get() {
             timestamp
         } else {
-            let timestamp = self
-                .contents
-                .iter()
-                .position(|&c| c == b'>')
-                .map(|pos| pos + 2)
-                .and_then(|pos| self.contents.get(pos..pos + 23))
-                .and_then(|input| parse_timestamp(input));
+            let timestamp = self.timestamp_text().and_then(|text| {
+                if text.get(10) == Some(&b'T') {
+                    parse_rfc3339_timestamp(text)
+                } else {
+                    parse_timestamp(text)
+                }
+            });
 
             self.timestamp.set(Cache::Filled(timestamp));
             timestamp
         }
     }
 
+    // the timestamp as written in the header, e.g. "2020-01-13 20:42:00.476" or
+    // "2020-01-13T20:42:00.476+02:00"
+    pub fn timestamp_text(&self) -> Option<&[u8]> {
+        let header = &self.contents[self.timestamp_start()?..];
+        if header.get(10) == Some(&b'T') {
+            let word_len = header
+                .iter()
+                .position(|c| c.is_ascii_whitespace())
+                .unwrap_or(header.len());
+            Some(&header[..word_len])
+        } else {
+            header.get(..23)
+        }
+    }
+
+    // the timestamp with the time zone written after it, up to the category, e.g.
+    // "2020-01-13 20:42:00.476 UTC"
+    pub fn timestamp_field(&self) -> Option<&[u8]> {
+        let start = self.timestamp_start()?;
+        let (category_start, _) = self.category_range()?;
+        self.contents
+            .get(start..category_start - 1)
+            .map(<[u8]>::trim_ascii_end)
+    }
+
+    // Where the timestamp starts: right after the pid, e.g. "<16866> ", or else at the first date
+    // near the beginning of the header line, so that a stray '>' before the pid or fields of
+    // unexpected width do not hide it.
+    fn timestamp_start(&self) -> Option<usize> {
+        let after_pid = self.contents.iter().position(|&c| c == b'>')? + 2;
+        if starts_with_date(self.contents.get(after_pid..).unwrap_or_default()) {
+            return Some(after_pid);
+        }
+        let line_len = memchr::memchr(b'\n', &self.contents).unwrap_or(self.contents.len());
+        (0..line_len.min(TIMESTAMP_SEARCH_LEN)).find(|&pos| starts_with_date(&self.contents[pos..]))
+    }
+
+    // Overrides the timestamp parsed from contents, which are left untouched, until the entry is
+    // reset. Like `fill_header`, it takes the entry by shared reference, so that it works on
+    // the entry reused by a reader and on entries passed along by `inspect`.
+    pub fn fill_timestamp(&self, timestamp: NaiveDateTime) {
+        self.timestamp.set(Cache::Filled(Some(timestamp)));
+    }
+
+    // sets the level and the timestamp of an entry whose header was parsed from another format,
+    // bypassing parsing of contents until the entry is reset
+    pub fn fill_header(&self, level: Option<LogLevel>, timestamp: Option<NaiveDateTime>) {
+        self.level.set(Cache::Filled(level));
+        self.timestamp.set(Cache::Filled(timestamp));
+    }
+
+    pub fn pid(&self) -> Option<u32> {
+        let start = self.contents.iter().position(|&c| c == b'<')? + 1;
+        let len = self.contents[start..].iter().position(|&c| c == b'>')?;
+        std::str::from_utf8(&self.contents[start..start + len])
+            .ok()
+            .and_then(|pid| pid.parse().ok())
+    }
+
+    pub fn category(&self) -> Option<&[u8]> {
+        self.category_range()
+            .map(|(start, end)| &self.contents[start..end])
+    }
+
+    pub fn message(&self) -> Option<&[u8]> {
+        self.category_range()
+            .and_then(|(_, end)| self.contents.get(end + 1..))
+            .map(|message| message.strip_prefix(b": ").unwrap_or(message))
+    }
+
     pub fn source(&self) -> usize {
         self.source
     }
+
+    pub fn is_separator(&self) -> bool {
+        self.separator
+    }
+
+    pub fn next_gap(&self) -> Option<NextGap> {
+        self.next_gap
+    }
+
+    pub fn set_next_gap(&mut self, next_gap: Option<NextGap>) {
+        self.next_gap = next_gap;
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Option<Duration>) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn end_offset(&self) -> u64 {
+        self.end_offset
+    }
+
+    pub fn set_end_offset(&mut self, end_offset: u64) {
+        self.end_offset = end_offset;
+    }
+
+    // byte range of the category name enclosed in brackets after the timestamp
+    fn category_range(&self) -> Option<(usize, usize)> {
+        let header_pos = self.contents.iter().position(|&c| c == b'>')?;
+        let start = header_pos
+            + self.contents[header_pos..]
+                .iter()
+                .position(|&c| c == b'[')?
+            + 1;
+        let len = self.contents[start..].iter().position(|&c| c == b']')?;
+        Some((start, start + len))
+    }
 }
 
 impl PartialEq<&[u8]> for LogEntry {
@@ -114,11 +314,32 @@ impl PartialEq<&[u8]> for LogEntry {
     }
 }
 
+// whether the text begins with a date followed by the time, e.g. "2020-01-13 " or "2020-01-13T"
+fn starts_with_date(text: &[u8]) -> bool {
+    match text.get(..11) {
+        Some([y1, y2, y3, y4, b'-', m1, m2, b'-', d1, d2, b' ' | b'T']) => {
+            [y1, y2, y3, y4, m1, m2, d1, d2]
+                .iter()
+                .all(|c| c.is_ascii_digit())
+        }
+        _ => false,
+    }
+}
+
 fn parse_timestamp(input: &[u8]) -> Option<NaiveDateTime> {
     let input = String::from_utf8_lossy(input);
     NaiveDateTime::parse_from_str(&input, "%F %T.%3f").ok()
 }
 
+// timestamp with an offset, e.g. "2020-01-13T20:42:00.476+02:00" or "2020-01-13T18:42:00Z",
+// converted to UTC like the rest of the timestamps
+fn parse_rfc3339_timestamp(input: &[u8]) -> Option<NaiveDateTime> {
+    let input = std::str::from_utf8(input).ok()?;
+    DateTime::parse_from_rfc3339(input)
+        .ok()
+        .map(|timestamp| timestamp.naive_utc())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,6 +353,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn log_entry_timestamp_with_offset() {
+        let timestamp = |contents: &[u8]| LogEntry::from_contents(contents.to_vec()).timestamp();
+        let utc = NaiveDate::from_ymd(2020, 1, 13).and_hms_milli(18, 42, 0, 476);
+
+        assert_eq!(
+            timestamp(b"-info:<123> 2020-01-13T20:42:00.476+02:00 [Cat]: msg"),
+            Some(utc)
+        );
+        assert_eq!(
+            timestamp(b"-info:<123> 2020-01-13T18:42:00.476Z [Cat]: msg"),
+            Some(utc)
+        );
+        assert_eq!(
+            timestamp(b"-info:<123> 2020-01-13T13:42:00.476-05:00 [Cat]: msg"),
+            Some(utc)
+        );
+        assert_eq!(
+            timestamp(b"-info:<123> 2020-01-13 18:42:00.476 UTC [Cat]: msg"),
+            Some(utc)
+        );
+        assert_eq!(
+            timestamp(b"-info:<123> 2020-01-13T18:42:00.476 [Cat]: msg"),
+            None
+        );
+    }
+
+    #[test]
+    fn log_entry_timestamp_with_shifted_header() {
+        let timestamp = |contents: &[u8]| LogEntry::from_contents(contents.to_vec()).timestamp();
+        let expected = Some(NaiveDate::from_ymd(2020, 1, 13).and_hms_milli(18, 42, 0, 476));
+
+        assert_eq!(
+            timestamp(b"-info:<123> 2020-01-13 18:42:00.476 UTC [Cat]: msg"),
+            expected
+        );
+        assert_eq!(
+            timestamp(b"-info:<1234567> 2020-01-13 18:42:00.476 UTC [Cat]: msg"),
+            expected
+        );
+        assert_eq!(
+            timestamp(b"-in>fo:<1234567> 2020-01-13 18:42:00.476 UTC [Cat]: msg"),
+            expected
+        );
+        assert_eq!(
+            timestamp(b"-info:<123>  2020-01-13T18:42:00.476Z [Cat]: msg"),
+            expected
+        );
+        assert_eq!(timestamp(b"-info:<123> 2020-01-13 18:42"), None);
+        assert_eq!(
+            timestamp(b"-info:<123> [Cat]: msg\n2020-01-13 18:42:00.476"),
+            None
+        );
+    }
+
     #[test]
     fn log_entry_level() {
         let entry =
@@ -139,6 +415,64 @@ mod tests {
         assert_eq!(entry.level(), Some(LogLevel::Info));
     }
 
+    #[test]
+    fn log_entry_preset_header() {
+        let timestamp = NaiveDate::from_ymd(2021, 2, 3).and_hms_milli(4, 5, 6, 7);
+        let entry =
+            LogEntry::from_contents(b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: B".to_vec())
+                .with_source(2);
+        entry.fill_header(Some(LogLevel::Fatal), Some(timestamp));
+        assert_eq!(entry.source(), 2);
+        assert_eq!(entry.level(), Some(LogLevel::Fatal));
+        assert_eq!(entry.timestamp(), Some(timestamp));
+        assert_eq!(entry.category(), Some(&b"A"[..]));
+
+        let entry = LogEntry::from_contents(b"No header".to_vec());
+        entry.fill_header(Some(LogLevel::Info), None);
+        assert_eq!(entry.level(), Some(LogLevel::Info));
+        assert_eq!(entry.timestamp(), None);
+        entry.fill_timestamp(timestamp);
+        assert_eq!(entry.timestamp(), Some(timestamp));
+    }
+
+    #[test]
+    fn log_entry_level_custom_map() {
+        let mut level_map = LevelMap::default();
+        level_map.insert(b'e', LogLevel::Critical);
+        level_map.insert(b'd', LogLevel::Info);
+        let level_map = Rc::new(level_map);
+
+        let entry = LogEntry::from_contents(b"-e:<1> 2020-01-01 20:00:00.000 UTC [A]: B".to_vec())
+            .with_level_map(level_map.clone());
+        assert_eq!(entry.level(), Some(LogLevel::Critical));
+
+        let entry = LogEntry::from_contents(b"-d:<1> 2020-01-01 20:00:00.000 UTC [A]: B".to_vec())
+            .with_level_map(level_map.clone());
+        assert_eq!(entry.level(), Some(LogLevel::Info));
+
+        let entry = LogEntry::from_contents(b"-w:<1> 2020-01-01 20:00:00.000 UTC [A]: B".to_vec())
+            .with_level_map(level_map);
+        assert_eq!(entry.level(), Some(LogLevel::Warning));
+    }
+
+    #[test]
+    fn log_entry_header_fields() {
+        let entry = LogEntry::from_contents(
+            b"-info:<16866> 2020-01-01 20:00:00.000 UTC [Cat]: Message\n\n".to_vec(),
+        );
+        assert_eq!(entry.pid(), Some(16866));
+        assert_eq!(entry.category(), Some(&b"Cat"[..]));
+        assert_eq!(entry.message(), Some(&b"Message\n\n"[..]));
+    }
+
+    #[test]
+    fn log_entry_header_fields_missing() {
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        assert_eq!(entry.pid(), None);
+        assert_eq!(entry.category(), None);
+        assert_eq!(entry.message(), None);
+    }
+
     #[test]
     fn log_entry_timestamp() {
         let entry =
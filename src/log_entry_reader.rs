@@ -1,30 +1,153 @@
-use crate::log_entry::LogEntry;
+use crate::eol;
+use crate::log_entry::{LevelMap, LogEntry};
+use crate::pattern::Pattern;
 use crate::result::Result;
 use crate::rev_reader::RevReader;
-use std::io::{BufRead, Read, Seek};
+use std::io::{self, BufRead, Read, Seek};
+use std::rc::Rc;
 use streaming_iterator::StreamingIterator;
 
+// ends an entry cut at the maximum size, which continues in the next one
+const CUT_MARKER: &[u8] = b"[entry cut at --max-entry-size]";
+
+// What ends an entry of a log
+#[derive(Clone, Debug, Default)]
+pub enum EntrySeparator {
+    #[default]
+    BlankLine,
+    Header, // a blank line or a line beginning with a header, which starts the next entry
+    Line(Pattern), // a line matching the pattern, e.g. a line of dashes; blank lines are contents
+}
+
 pub struct LogEntryReader<R: BufRead> {
     reader: R,
+    eol_seq: &'static [u8],
     eol_seq_last: u8,
     eol_seq_len: usize,
     entry: LogEntry,
+    offset: u64,
+    level_map: Rc<LevelMap>,
+    separator: EntrySeparator,
+    next_line: Vec<u8>, // header line read past the end of the entry with the header separator
+    max_entry_size: usize,
 }
 
 impl<R: BufRead> LogEntryReader<R> {
     pub fn new(reader: R, eol_seq: &'static [u8]) -> Self {
         LogEntryReader {
             reader,
+            eol_seq,
             eol_seq_last: *eol_seq.last().expect("EOL sequence is empty"),
             eol_seq_len: eol_seq.len(),
-            entry: LogEntry::new(),
+            entry: LogEntry::from_contents(Vec::new()),
+            offset: 0,
+            level_map: Rc::default(),
+            separator: EntrySeparator::BlankLine,
+            next_line: Vec::new(),
+            max_entry_size: usize::MAX,
         }
     }
 
+    // cuts entries longer than the size, e.g. binary data without line breaks, so that they
+    // are not read into memory as a whole
+    pub fn with_max_entry_size(mut self, max_entry_size: Option<usize>) -> Self {
+        self.max_entry_size = max_entry_size.unwrap_or(usize::MAX).max(1);
+        self
+    }
+
+    // Ends entries as chosen instead of at blank lines. Starting entries also at lines beginning
+    // with a header keeps e.g. continuation lines of an entry cut off at the top of the file
+    // from being merged with the next entry.
+    pub fn with_separator(mut self, separator: EntrySeparator) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    // offset in the log source the reader is positioned at
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
     pub fn with_source(mut self, source: usize) -> Self {
         self.entry = self.entry.with_source(source);
         self
     }
+
+    pub fn with_level_map(mut self, level_map: Rc<LevelMap>) -> Self {
+        self.level_map = level_map.clone();
+        self.entry = self.entry.with_level_map(level_map);
+        self
+    }
+
+    // Reads the entry line by line to end it before a header line, which is kept for the next
+    // one, or at a separator line, which is dropped. Such an entry gets a blank line appended,
+    // so that it is written like one ended by a blank line.
+    fn advance_by_lines(&mut self) {
+        let mut line = std::mem::take(&mut self.next_line);
+        let contents = self.entry.contents_mut();
+        contents.append(&mut line);
+
+        loop {
+            line.clear();
+            let room = self
+                .max_entry_size
+                .saturating_sub(self.entry.contents().len());
+            if room == 0 {
+                if has_more(&mut self.reader) {
+                    cut_entry(self.entry.contents_mut(), self.eol_seq);
+                }
+                break;
+            }
+            let bytes_read =
+                match read_until_limited(&mut self.reader, self.eol_seq_last, &mut line, room) {
+                    Ok(bytes_read) if bytes_read > 0 => bytes_read,
+                    _ => {
+                        let contents = self.entry.contents_mut();
+                        if let EntrySeparator::Line(_) = self.separator {
+                            trim_blank_lines(contents, self.eol_seq);
+                        }
+                        end_final_entry(contents, self.eol_seq);
+                        break;
+                    }
+                };
+            self.offset += bytes_read as u64;
+
+            let line_ended = line.last() == Some(&self.eol_seq_last);
+            let complete = line_ended || bytes_read < room;
+            let blank = line == self.eol_seq;
+            let contents = self.entry.contents_mut();
+            match &self.separator {
+                EntrySeparator::Line(pattern) if complete => {
+                    if pattern.is_match(eol::trim_eol(&line)) {
+                        if !contents.is_empty() {
+                            trim_blank_lines(contents, self.eol_seq);
+                            contents.extend_from_slice(self.eol_seq);
+                            break;
+                        }
+                    } else if !(blank && contents.is_empty()) {
+                        contents.append(&mut line);
+                    }
+                }
+                EntrySeparator::Header if blank => {
+                    if !contents.is_empty() {
+                        contents.append(&mut line);
+                        break;
+                    }
+                }
+                EntrySeparator::Header
+                    if self.level_map.is_header(&line) && !contents.is_empty() =>
+                {
+                    contents.extend_from_slice(self.eol_seq);
+                    self.next_line = line;
+                    break;
+                }
+                _ => contents.append(&mut line),
+            }
+        }
+        self.entry
+            .set_end_offset(self.offset - self.next_line.len() as u64);
+    }
 }
 
 impl<R: BufRead> StreamingIterator for LogEntryReader<R> {
@@ -32,18 +155,40 @@ impl<R: BufRead> StreamingIterator for LogEntryReader<R> {
 
     fn advance(&mut self) {
         self.entry.reset();
-        while let Ok(bytes_read) = self
-            .reader
-            .read_until(self.eol_seq_last, &mut self.entry.contents_mut())
-        {
-            if bytes_read <= self.eol_seq_len {
+        if !matches!(self.separator, EntrySeparator::BlankLine) {
+            self.advance_by_lines();
+            return;
+        }
+        while let Ok(bytes_read) = read_until_limited(
+            &mut self.reader,
+            self.eol_seq_last,
+            self.entry.contents_mut(),
+            self.max_entry_size,
+        ) {
+            self.offset += bytes_read as u64;
+            let contents = self.entry.contents();
+            let at_limit = contents.len() >= self.max_entry_size;
+            // only a line of just the EOL sequence is blank, not a short line such as "a\n"
+            // of a file with other line endings or at the end of the input without EOL
+            if bytes_read == 0
+                || (bytes_read == self.eol_seq_len && contents.ends_with(self.eol_seq))
+            {
                 if bytes_read == 0 || self.entry.contents().len() > self.eol_seq_len {
+                    if bytes_read == 0 {
+                        end_final_entry(self.entry.contents_mut(), self.eol_seq);
+                    }
                     break;
                 } else {
                     self.entry.contents_mut().clear();
                 }
+            } else if at_limit {
+                if has_more(&mut self.reader) {
+                    cut_entry(self.entry.contents_mut(), self.eol_seq);
+                }
+                break;
             }
         }
+        self.entry.set_end_offset(self.offset);
     }
 
     fn get(&self) -> Option<&Self::Item> {
@@ -55,11 +200,81 @@ impl<R: BufRead> StreamingIterator for LogEntryReader<R> {
     }
 }
 
+// like `BufRead::read_until`, but stops also when the buffer reaches the limit
+fn read_until_limited(
+    reader: &mut impl BufRead,
+    byte: u8,
+    buf: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<usize> {
+    let mut bytes_read = 0;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let room = limit.saturating_sub(buf.len());
+        let available = &available[..available.len().min(room)];
+        let (used, done) = match memchr::memchr(byte, available) {
+            Some(i) => (i + 1, true),
+            None => (available.len(), available.is_empty()),
+        };
+        buf.extend_from_slice(&available[..used]);
+        reader.consume(used);
+        bytes_read += used;
+        if done {
+            return Ok(bytes_read);
+        }
+    }
+}
+
+fn has_more(reader: &mut impl BufRead) -> bool {
+    reader
+        .fill_buf()
+        .is_ok_and(|available| !available.is_empty())
+}
+
+// drops blank lines at the end of the contents
+fn trim_blank_lines(contents: &mut Vec<u8>, eol_seq: &[u8]) {
+    while contents.ends_with(eol_seq)
+        && contents[..contents.len() - eol_seq.len()].ends_with(eol_seq)
+    {
+        contents.truncate(contents.len() - eol_seq.len());
+    }
+}
+
+// Ends the last entry of the input, whose last line ends without a blank line following it,
+// with a blank line, so that it is the same as when followed by other entries. An entry whose
+// last line has no EOL is left as it is.
+fn end_final_entry(contents: &mut Vec<u8>, eol_seq: &[u8]) {
+    if contents.ends_with(eol_seq) && contents.len() > eol_seq.len() {
+        let line = &contents[..contents.len() - eol_seq.len()];
+        if !line.ends_with(eol_seq) {
+            contents.extend_from_slice(eol_seq);
+        }
+    }
+}
+
+// ends the entry with the cut marker, written like an entry ended by a blank line
+fn cut_entry(contents: &mut Vec<u8>, eol_seq: &[u8]) {
+    if !contents.ends_with(eol_seq) {
+        contents.extend_from_slice(eol_seq);
+    }
+    contents.extend_from_slice(CUT_MARKER);
+    contents.extend_from_slice(eol_seq);
+    contents.extend_from_slice(eol_seq);
+}
+
 pub struct LogEntryRevReader<R: Read> {
     reader: RevReader<R>,
     eol_seq_first: u8,
     eol_seq: &'static [u8],
     entry: LogEntry,
+    level_map: Rc<LevelMap>,
+    separator: EntrySeparator,
+    max_entry_size: usize,
+    cut: bool, // whether the last entry was cut from the one read next
 }
 
 impl<R: Read + Seek> LogEntryRevReader<R> {
@@ -68,14 +283,42 @@ impl<R: Read + Seek> LogEntryRevReader<R> {
             reader: RevReader::with_capacity(reader, capacity)?,
             eol_seq_first: *eol_seq.first().expect("EOL sequence is empty"),
             eol_seq,
-            entry: LogEntry::new(),
+            entry: LogEntry::from_contents(Vec::new()),
+            level_map: Rc::default(),
+            separator: EntrySeparator::BlankLine,
+            max_entry_size: usize::MAX,
+            cut: false,
         })
     }
 
+    // like `LogEntryReader::with_max_entry_size`, with the end of a cut entry read first
+    pub fn with_max_entry_size(mut self, max_entry_size: Option<usize>) -> Self {
+        self.max_entry_size = max_entry_size.unwrap_or(usize::MAX).max(1);
+        self
+    }
+
     pub fn with_source(mut self, source: usize) -> Self {
         self.entry = self.entry.with_source(source);
         self
     }
+
+    pub fn with_level_map(mut self, level_map: Rc<LevelMap>) -> Self {
+        self.level_map = level_map.clone();
+        self.entry = self.entry.with_level_map(level_map);
+        self
+    }
+
+    // reads only entries from the offset on, which has to be the start of an entry
+    pub fn with_start(mut self, offset: u64) -> Self {
+        self.reader = self.reader.with_start(offset);
+        self
+    }
+
+    // like `LogEntryReader::with_separator`, with an entry started by a header ending at it
+    pub fn with_separator(mut self, separator: EntrySeparator) -> Self {
+        self.separator = separator;
+        self
+    }
 }
 
 impl<R: Read + Seek> StreamingIterator for LogEntryRevReader<R> {
@@ -83,21 +326,70 @@ impl<R: Read + Seek> StreamingIterator for LogEntryRevReader<R> {
 
     fn advance(&mut self) {
         self.entry.reset();
-
-        while let Some(buf) = self
-            .reader
-            .read_until(self.eol_seq_first, self.eol_seq.len())
-        {
-            if !buf.is_empty() && self.entry.contents_mut().is_empty() {
+        let continued = std::mem::take(&mut self.cut);
+
+        loop {
+            let room = self
+                .max_entry_size
+                .saturating_sub(self.entry.contents().len());
+            if room == 0 {
+                self.cut = true;
+                break;
+            }
+            let (buf, line_complete) =
+                match self
+                    .reader
+                    .read_until_limited(self.eol_seq_first, self.eol_seq.len(), room)
+                {
+                    Some(line) => line,
+                    None => break,
+                };
+
+            let (blank_ends, separator_line) = match &self.separator {
+                EntrySeparator::Line(pattern) => (false, line_complete && pattern.is_match(&buf)),
+                _ => (true, false),
+            };
+            let empty = self.entry.contents().is_empty();
+            if separator_line {
+                if empty {
+                    continue;
+                }
+                break;
+            }
+            if !buf.is_empty() && empty {
                 *self.entry.contents_mut() = buf;
-            } else if !buf.is_empty() && !self.entry.contents_mut().is_empty() {
+            } else if !empty && (!buf.is_empty() || !blank_ends) {
                 *self.entry.contents_mut() =
                     [buf.as_slice(), self.eol_seq, self.entry.contents()].concat();
-            } else if buf.is_empty() && !self.entry.contents_mut().is_empty() {
+            } else if !empty {
+                break;
+            }
+
+            if !line_complete {
+                self.cut = true;
+                break;
+            }
+            if matches!(self.separator, EntrySeparator::Header)
+                && self.level_map.is_header(self.entry.contents())
+            {
                 break;
             }
         }
 
+        // blank lines following a separator line are not contents, like when read forward
+        if let EntrySeparator::Line(_) = self.separator {
+            let contents = self.entry.contents_mut();
+            while contents.starts_with(self.eol_seq) {
+                contents.drain(..self.eol_seq.len());
+            }
+        }
+
+        if continued && !self.entry.contents().is_empty() {
+            let contents = self.entry.contents_mut();
+            contents.extend_from_slice(self.eol_seq);
+            contents.extend_from_slice(CUT_MARKER);
+        }
+
         if !self.entry.contents_mut().is_empty() {
             self.entry.contents_mut().reserve(self.eol_seq.len() * 2);
             for c in self.eol_seq.iter().cycle().take(self.eol_seq.len() * 2) {
@@ -136,7 +428,7 @@ mod tests {
         let entry = reader.next().unwrap();
         assert_eq!(
             String::from_utf8_lossy(entry.contents()),
-            String::from_utf8_lossy(one_line.as_slice())
+            String::from_utf8_lossy(&[LOG_ENTRIES[0], EOL_LF, EOL_LF].concat())
         );
         assert_eq!(reader.next(), None);
     }
@@ -149,7 +441,7 @@ mod tests {
         let entry = reader.next().unwrap();
         assert_eq!(
             String::from_utf8_lossy(entry.contents()),
-            String::from_utf8_lossy(one_line.as_slice())
+            String::from_utf8_lossy(&[LOG_ENTRIES[0], EOL_CRLF, EOL_CRLF].concat())
         );
         assert_eq!(reader.next(), None);
     }
@@ -311,6 +603,49 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn log_entry_reader_two_lines_crlf_no_trailing() {
+        let line_one = [LOG_ENTRIES[0], EOL_CRLF, EOL_CRLF].concat();
+        let line_two = [LOG_ENTRIES[1], EOL_CRLF].concat();
+
+        let reader = Cursor::new([line_one.as_slice(), line_two.as_slice()].concat());
+        let mut reader = LogEntryReader::new(reader, EOL_CRLF);
+
+        let entry = reader.next().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(entry.contents()),
+            String::from_utf8_lossy(line_one.as_slice())
+        );
+
+        // the same as when followed by a blank line, like the entry read in reverse
+        let entry = reader.next().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(entry.contents()),
+            String::from_utf8_lossy(&[LOG_ENTRIES[1], EOL_CRLF, EOL_CRLF].concat())
+        );
+        assert_eq!(entry.end_offset(), (line_one.len() + line_two.len()) as u64);
+
+        assert_eq!(reader.next(), None);
+    }
+
+    #[test]
+    fn log_entry_reader_two_lines_crlf_no_trailing_split_on_header() {
+        let input = [LOG_ENTRIES[0], EOL_CRLF, LOG_ENTRIES[1], EOL_CRLF].concat();
+        let entries: Vec<Vec<u8>> = LogEntryReader::new(Cursor::new(&input), EOL_CRLF)
+            .with_separator(EntrySeparator::Header)
+            .map(|entry| entry.contents().to_vec())
+            .owned()
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                [LOG_ENTRIES[0], EOL_CRLF, EOL_CRLF].concat(),
+                [LOG_ENTRIES[1], EOL_CRLF, EOL_CRLF].concat()
+            ]
+        );
+    }
+
     #[test]
     fn log_entry_reader_two_lines_crlf_no_trailing_rev() -> Result<()> {
         let line_one = [LOG_ENTRIES[0], EOL_CRLF, EOL_CRLF].concat();
@@ -406,4 +741,316 @@ mod tests {
         assert_eq!(reader.next(), None);
         Ok(())
     }
+
+    fn read_split_on_header(input: &[u8]) -> Vec<(String, u64)> {
+        LogEntryReader::new(Cursor::new(input), EOL_LF)
+            .with_separator(EntrySeparator::Header)
+            .map(|entry| {
+                (
+                    String::from_utf8_lossy(entry.contents()).into_owned(),
+                    entry.end_offset(),
+                )
+            })
+            .owned()
+            .collect()
+    }
+
+    #[test]
+    fn log_entry_reader_split_on_header_leading_orphan_block() {
+        let input = [
+            b"orphaned continuation\nanother one\n" as &[u8],
+            LOG_ENTRIES[0],
+            EOL_LF,
+            EXTRA_LOG_ENTRY_LINE,
+            EOL_LF,
+            EOL_LF,
+        ]
+        .concat();
+        let orphan_len = 34;
+
+        assert_eq!(
+            read_split_on_header(&input),
+            vec![
+                (
+                    "orphaned continuation\nanother one\n\n".to_string(),
+                    orphan_len
+                ),
+                (
+                    String::from_utf8_lossy(&input[orphan_len as usize..]).into_owned(),
+                    input.len() as u64
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn log_entry_reader_split_on_header_back_to_back() {
+        let input = [
+            LOG_ENTRIES[0],
+            EOL_LF,
+            LOG_ENTRIES[1],
+            EOL_LF,
+            EXTRA_LOG_ENTRY_LINE,
+            EOL_LF,
+            b"-not a header\n\n",
+            LOG_ENTRIES[0],
+        ]
+        .concat();
+        let first_len = LOG_ENTRIES[0].len() as u64 + 1;
+        let second_len = input.len() as u64 - first_len - LOG_ENTRIES[0].len() as u64;
+
+        assert_eq!(
+            read_split_on_header(&input),
+            vec![
+                (
+                    String::from_utf8_lossy(&[LOG_ENTRIES[0], EOL_LF, EOL_LF].concat())
+                        .into_owned(),
+                    first_len
+                ),
+                (
+                    String::from_utf8_lossy(
+                        &[
+                            LOG_ENTRIES[1],
+                            EOL_LF,
+                            EXTRA_LOG_ENTRY_LINE,
+                            EOL_LF,
+                            b"-not a header\n\n"
+                        ]
+                        .concat()
+                    )
+                    .into_owned(),
+                    first_len + second_len
+                ),
+                (
+                    String::from_utf8_lossy(LOG_ENTRIES[0]).into_owned(),
+                    input.len() as u64
+                ),
+            ]
+        );
+    }
+
+    const DASHED_INPUT: &[u8] = b"-info:<1> 2020-01-13 20:08:18.476 UTC [A]: First\n\
+                                  \n\
+                                  second paragraph\n\
+                                  \n\
+                                  -----\n\
+                                  \n\
+                                  -info:<1> 2020-01-13 20:09:18.476 UTC [A]: Second\n\
+                                  -----\n\
+                                  -info:<1> 2020-01-13 20:10:18.476 UTC [A]: Last\n";
+
+    fn dashed_separator() -> EntrySeparator {
+        EntrySeparator::Line(Pattern::new("^-{5,}$").unwrap())
+    }
+
+    #[test]
+    fn log_entry_reader_dashed_line_separator() {
+        let entries: Vec<(String, u64)> = LogEntryReader::new(Cursor::new(DASHED_INPUT), EOL_LF)
+            .with_separator(dashed_separator())
+            .map(|entry| {
+                (
+                    String::from_utf8_lossy(entry.contents()).into_owned(),
+                    entry.end_offset(),
+                )
+            })
+            .owned()
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "-info:<1> 2020-01-13 20:08:18.476 UTC [A]: First\n\nsecond paragraph\n\n"
+                        .to_string(),
+                    74
+                ),
+                (
+                    "-info:<1> 2020-01-13 20:09:18.476 UTC [A]: Second\n\n".to_string(),
+                    131
+                ),
+                (
+                    "-info:<1> 2020-01-13 20:10:18.476 UTC [A]: Last\n\n".to_string(),
+                    DASHED_INPUT.len() as u64
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn log_entry_rev_reader_dashed_line_separator() -> Result<()> {
+        let entries: Vec<String> =
+            LogEntryRevReader::with_capacity(Cursor::new(DASHED_INPUT), EOL_LF, 16)?
+                .with_separator(dashed_separator())
+                .map(|entry| String::from_utf8_lossy(entry.contents()).into_owned())
+                .owned()
+                .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                "-info:<1> 2020-01-13 20:10:18.476 UTC [A]: Last\n\n",
+                "-info:<1> 2020-01-13 20:09:18.476 UTC [A]: Second\n\n",
+                "-info:<1> 2020-01-13 20:08:18.476 UTC [A]: First\n\nsecond paragraph\n\n",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn log_entry_rev_reader_header_separator() -> Result<()> {
+        let input = [
+            b"orphaned continuation\n" as &[u8],
+            LOG_ENTRIES[0],
+            EOL_LF,
+            LOG_ENTRIES[1],
+            EOL_LF,
+            EXTRA_LOG_ENTRY_LINE,
+            EOL_LF,
+        ]
+        .concat();
+        let entries: Vec<Vec<u8>> =
+            LogEntryRevReader::with_capacity(Cursor::new(input), EOL_LF, 16)?
+                .with_separator(EntrySeparator::Header)
+                .map(|entry| entry.contents().to_vec())
+                .owned()
+                .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                [LOG_ENTRIES[1], EOL_LF, EXTRA_LOG_ENTRY_LINE, EOL_LF, EOL_LF].concat(),
+                [LOG_ENTRIES[0], EOL_LF, EOL_LF].concat(),
+                b"orphaned continuation\n\n".to_vec(),
+            ]
+        );
+        Ok(())
+    }
+
+    // contents of the entries with cut markers and line breaks removed
+    fn strip_cuts(entries: &[Vec<u8>]) -> Vec<u8> {
+        entries
+            .iter()
+            .flat_map(|entry| {
+                let entry = entry.strip_suffix(b"\n\n").unwrap_or(entry);
+                entry.strip_suffix(CUT_MARKER).unwrap_or(entry).to_vec()
+            })
+            .filter(|&c| c != b'\n')
+            .collect()
+    }
+
+    #[test]
+    fn log_entry_reader_max_entry_size_blob() {
+        let blob = vec![b'x'; 10_000];
+        let entries: Vec<Vec<u8>> = LogEntryReader::new(Cursor::new(&blob), EOL_LF)
+            .with_max_entry_size(Some(1000))
+            .map(|entry| entry.contents().to_vec())
+            .owned()
+            .collect();
+
+        assert_eq!(entries.len(), 10);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.len() <= 1000 + CUT_MARKER.len() + 3));
+        assert!(entries[..9]
+            .iter()
+            .all(|entry| entry.ends_with(CUT_MARKER)
+                || entry.ends_with(&[CUT_MARKER, b"\n\n"].concat())));
+        assert_eq!(entries[9], vec![b'x'; 1000]);
+        assert_eq!(strip_cuts(&entries), blob);
+    }
+
+    #[test]
+    fn log_entry_reader_max_entry_size_resync() {
+        let input = [
+            LOG_ENTRIES[0],
+            EOL_LF,
+            &[b'x'; 30],
+            EOL_LF,
+            EOL_LF,
+            LOG_ENTRIES[1],
+            EOL_LF,
+            EOL_LF,
+        ]
+        .concat();
+        let mut reader = LogEntryReader::new(Cursor::new(&input), EOL_LF)
+            .with_max_entry_size(Some(LOG_ENTRIES[0].len() + 10));
+
+        let first = reader.next().unwrap().contents().to_vec();
+        assert!(first.starts_with(LOG_ENTRIES[0]));
+        assert!(first.ends_with(&[CUT_MARKER, EOL_LF, EOL_LF].concat()));
+        assert_eq!(
+            reader.next().unwrap().contents(),
+            [&[b'x'; 21] as &[u8], EOL_LF, EOL_LF].concat().as_slice()
+        );
+        assert_eq!(
+            reader.next().unwrap().contents(),
+            [LOG_ENTRIES[1], EOL_LF, EOL_LF].concat().as_slice()
+        );
+        assert_eq!(reader.next(), None);
+    }
+
+    #[test]
+    fn log_entry_rev_reader_max_entry_size_blob() -> Result<()> {
+        let blob = vec![b'x'; 10_000];
+        let mut entries: Vec<Vec<u8>> =
+            LogEntryRevReader::with_capacity(Cursor::new(&blob), EOL_LF, 64)?
+                .with_max_entry_size(Some(1000))
+                .map(|entry| entry.contents().to_vec())
+                .owned()
+                .collect();
+
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[0], [&[b'x'; 1000] as &[u8], b"\n\n"].concat());
+        assert!(entries[1..]
+            .iter()
+            .all(|entry| entry.ends_with(&[CUT_MARKER, b"\n\n"].concat())));
+        entries.reverse();
+        assert_eq!(strip_cuts(&entries), blob);
+        Ok(())
+    }
+
+    // contents of all entries read in given direction, in the order of the file
+    fn entry_set(input: &[u8], eol_seq: &'static [u8], reverse: bool) -> Result<Vec<Vec<u8>>> {
+        // trailing blank lines, including the one appended by the reader, are not compared
+        let normalize = |entry: &LogEntry| eol::trim_eol(entry.contents()).to_vec();
+        let mut entries: Vec<Vec<u8>> = if reverse {
+            LogEntryRevReader::with_capacity(Cursor::new(input), eol_seq, 7)?
+                .map(normalize)
+                .owned()
+                .collect()
+        } else {
+            LogEntryReader::new(Cursor::new(input), eol_seq)
+                .map(normalize)
+                .owned()
+                .collect()
+        };
+        if reverse {
+            entries.reverse();
+        }
+        Ok(entries)
+    }
+
+    #[test]
+    fn log_entry_readers_agree_on_entries() -> Result<()> {
+        let fixtures: [(&[u8], &'static [u8]); 8] = [
+            (b"a\n\nb\nc\n\n", EOL_LF),
+            (b"\n\n\na\n\n\n\nb\n", EOL_LF),
+            (b"a\nb\n\n\n\nc\nd\ne", EOL_LF),
+            (b"a\n \nb\n\n\tc\n\n", EOL_LF),
+            (b"a\r\n\r\nb\r\nc\r\n\r\n\r\n", EOL_CRLF),
+            (b"\r\na\r\nb\r\n\r\nc", EOL_CRLF),
+            (b"a\nb\n\n", EOL_CRLF),
+            (b"a\r\n\nb\r\n\r\nc\r\n", EOL_CRLF),
+        ];
+
+        for (input, eol_seq) in fixtures {
+            assert_eq!(
+                entry_set(input, eol_seq, true)?,
+                entry_set(input, eol_seq, false)?,
+                "{:?}",
+                String::from_utf8_lossy(input)
+            );
+        }
+        Ok(())
+    }
 }
@@ -1,25 +1,45 @@
 use crate::direction::Direction;
 use crate::log_entry::LogEntry;
-use std::cmp::Ordering;
+use chrono::{Duration, NaiveDateTime};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use streaming_iterator::StreamingIterator;
 
 pub struct LogEntryReaderMux<I: StreamingIterator<Item = LogEntry>> {
     input_iters: Vec<I>,
     curr: Option<usize>,
-    cmp_fn: fn(&(usize, &I), &(usize, &I)) -> Ordering,
+    direction: Direction,
+    precision_ms: Option<i64>,
+    dedupe: Option<Dedupe>,
 }
 
-impl<I: StreamingIterator<Item = LogEntry>> LogEntryReaderMux<I> {
-    fn min_iter((_, iter1): &(usize, &I), (_, iter2): &(usize, &I)) -> Ordering {
-        iter1
-            .get()
-            .expect("Finished iter1 called")
-            .timestamp()
-            .cmp(&iter2.get().expect("Finished iter2 called").timestamp())
-    }
+type DedupeKey = (Option<NaiveDateTime>, Vec<u8>);
+
+#[derive(Default)]
+struct Dedupe {
+    last: Option<DedupeKey>,  // of the last entry shown
+    last_sources: Vec<usize>, // sources of the entries equal to the last one
+}
 
-    fn max_iter(t1: &(usize, &I), t2: &(usize, &I)) -> Ordering {
-        Self::min_iter(t1, t2).reverse()
+impl Dedupe {
+    // whether the entry duplicates the last one shown, remembering it otherwise
+    fn is_duplicate(&mut self, entry: Option<&LogEntry>) -> bool {
+        let (source, key) = match entry {
+            Some(entry) => (
+                entry.source(),
+                (entry.timestamp(), trim_eols(entry.contents()).to_vec()),
+            ),
+            None => return false,
+        };
+
+        if self.last.as_ref() == Some(&key) && !self.last_sources.contains(&source) {
+            self.last_sources.push(source);
+            return true;
+        }
+        self.last = Some(key);
+        self.last_sources.clear();
+        self.last_sources.push(source);
+        false
     }
 }
 
@@ -28,18 +48,29 @@ impl<I: StreamingIterator<Item = LogEntry>> LogEntryReaderMux<I> {
         LogEntryReaderMux {
             input_iters,
             curr: None,
-            cmp_fn: match direction {
-                Direction::Forward => Self::min_iter,
-                Direction::Reverse => Self::max_iter,
-            },
+            direction,
+            precision_ms: None,
+            dedupe: None,
         }
     }
-}
 
-impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for LogEntryReaderMux<I> {
-    type Item = LogEntry;
+    // orders entries with timestamps rounded down to the precision, and by source within
+    // the same rounded timestamp
+    pub fn with_precision(mut self, precision: Option<Duration>) -> Self {
+        self.precision_ms = precision
+            .map(|precision| precision.num_milliseconds())
+            .filter(|&precision_ms| precision_ms > 1);
+        self
+    }
 
-    fn advance(&mut self) {
+    // skips entries with the same timestamp and contents as the entry shown just before, if it
+    // comes from another source. Repeated entries of a single source are all shown.
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = Some(Dedupe::default()).filter(|_| dedupe);
+        self
+    }
+
+    fn advance_merged(&mut self) {
         if let Some(curr) = self.curr {
             let curr_iter = &mut self.input_iters[curr];
             curr_iter.advance();
@@ -52,12 +83,50 @@ impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for LogEntryReader
             self.input_iters.retain(|i| i.get().is_some());
         }
 
-        self.curr = self
+        let precision_ms = self.precision_ms;
+        let keys = self
             .input_iters
             .iter()
-            .enumerate()
-            .min_by(self.cmp_fn)
-            .map(|(index, _)| index);
+            .map(|iter| merge_key(iter.get().expect("Finished iter called"), precision_ms));
+        self.curr = match self.direction {
+            Direction::Forward => keys.enumerate().min_by_key(|&(_, key)| key),
+            Direction::Reverse => keys.enumerate().max_by_key(|&(_, key)| key),
+        }
+        .map(|(index, _)| index);
+    }
+}
+
+// the last entry of a file may lack the blank line ending the others
+fn trim_eols(contents: &[u8]) -> &[u8] {
+    let len = contents
+        .iter()
+        .rposition(|&c| c != b'\n' && c != b'\r')
+        .map_or(0, |pos| pos + 1);
+    &contents[..len]
+}
+
+// position of the entry in the merged output when read forward
+fn merge_key(entry: &LogEntry, precision_ms: Option<i64>) -> (Option<i64>, usize) {
+    let timestamp = entry.timestamp().map(|timestamp| {
+        let millis = timestamp.timestamp_millis();
+        precision_ms.map_or(millis, |precision_ms| millis.div_euclid(precision_ms))
+    });
+    (timestamp, entry.source())
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for LogEntryReaderMux<I> {
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        self.advance_merged();
+        while let Some(dedupe) = &mut self.dedupe {
+            let input_iters = &self.input_iters;
+            let curr = self.curr.and_then(|idx| input_iters.get(idx));
+            if !dedupe.is_duplicate(curr.and_then(|iter| iter.get())) {
+                break;
+            }
+            self.advance_merged();
+        }
     }
 
     fn get(&self) -> Option<&Self::Item> {
@@ -67,10 +136,113 @@ impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for LogEntryReader
     }
 }
 
+// merge key of the current entry of an input and the index of the input, which breaks ties
+// the same way as scanning the inputs in order does
+type Rank = ((Option<i64>, usize), usize);
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum HeapRank {
+    Forward(Reverse<Rank>),
+    Reverse(Rank),
+}
+
+impl HeapRank {
+    fn index(&self) -> usize {
+        match self {
+            HeapRank::Forward(Reverse((_, index))) | HeapRank::Reverse((_, index)) => *index,
+        }
+    }
+}
+
+// Merges entries in the same order as LogEntryReaderMux, but keeps the current entries of
+// the inputs in a binary heap instead of scanning all of them for every entry.
+pub struct LogEntryReaderHeapMux<I: StreamingIterator<Item = LogEntry>> {
+    input_iters: Vec<I>,
+    heap: BinaryHeap<HeapRank>,
+    curr: Option<usize>,
+    started: bool,
+    direction: Direction,
+    precision_ms: Option<i64>,
+    dedupe: Option<Dedupe>,
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> LogEntryReaderHeapMux<I> {
+    pub fn new(input_iters: Vec<I>, direction: Direction) -> Self {
+        LogEntryReaderHeapMux {
+            heap: BinaryHeap::with_capacity(input_iters.len()),
+            input_iters,
+            curr: None,
+            started: false,
+            direction,
+            precision_ms: None,
+            dedupe: None,
+        }
+    }
+
+    pub fn with_precision(mut self, precision: Option<Duration>) -> Self {
+        self.precision_ms = precision
+            .map(|precision| precision.num_milliseconds())
+            .filter(|&precision_ms| precision_ms > 1);
+        self
+    }
+
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = Some(Dedupe::default()).filter(|_| dedupe);
+        self
+    }
+
+    fn advance_merged(&mut self) {
+        if let Some(curr) = self.curr.take() {
+            self.input_iters[curr].advance();
+            self.push(curr);
+        } else if !self.started {
+            self.started = true;
+            for index in 0..self.input_iters.len() {
+                self.input_iters[index].advance();
+                self.push(index);
+            }
+        }
+
+        self.curr = self.heap.pop().map(|rank| rank.index());
+    }
+
+    fn push(&mut self, index: usize) {
+        if let Some(entry) = self.input_iters[index].get() {
+            let rank = (merge_key(entry, self.precision_ms), index);
+            self.heap.push(match self.direction {
+                Direction::Forward => HeapRank::Forward(Reverse(rank)),
+                Direction::Reverse => HeapRank::Reverse(rank),
+            });
+        }
+    }
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for LogEntryReaderHeapMux<I> {
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        self.advance_merged();
+        while let Some(dedupe) = &mut self.dedupe {
+            let input_iters = &self.input_iters;
+            let curr = self.curr.and_then(|idx| input_iters[idx].get());
+            if !dedupe.is_duplicate(curr) {
+                break;
+            }
+            self.advance_merged();
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        self.curr.and_then(|idx| self.input_iters[idx].get())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::log_entry_reader::LogEntryRevReader;
     use chrono::{Datelike, Timelike};
+    use std::io::{self, Read, Seek, SeekFrom};
 
     const LOG_INPUTS: &[&[&[u8]]] = &[
         &[
@@ -111,6 +283,61 @@ mod tests {
         GoldenResult(1, Some((2020, 01, 01, 21, 30, 0))),
     ];
 
+    #[test]
+    fn log_entry_reader_mux_dedupe() {
+        let logs: &[&[&[u8]]] = &[
+            &[
+                b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Started\n\n",
+                b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Replicated\n\n",
+                b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Replicated\n\n",
+            ],
+            &[
+                b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Replicated",
+                b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: Stopped",
+            ],
+        ];
+        let iterators = |direction: Direction| {
+            logs.iter()
+                .enumerate()
+                .map(|(source, log)| {
+                    let mut entries: Vec<_> = log
+                        .iter()
+                        .map(|lines| LogEntry::from_contents(lines.to_vec()).with_source(source))
+                        .collect();
+                    if direction == Direction::Reverse {
+                        entries.reverse();
+                    }
+                    entries
+                })
+                .map(streaming_iterator::convert)
+                .collect()
+        };
+        let sources = |reader: LogEntryReaderMux<_>| -> Vec<usize> {
+            reader.map(LogEntry::source).cloned().collect()
+        };
+
+        // the entry repeated within the first file is kept, its copy in the second is not
+        let reader = LogEntryReaderMux::new(iterators(Direction::Forward), Direction::Forward)
+            .with_dedupe(true);
+        assert_eq!(sources(reader), vec![0, 0, 0, 1]);
+        let reader = LogEntryReaderMux::new(iterators(Direction::Reverse), Direction::Reverse)
+            .with_dedupe(true);
+        assert_eq!(sources(reader), vec![1, 1, 0, 0]);
+        let reader = LogEntryReaderMux::new(iterators(Direction::Forward), Direction::Forward);
+        assert_eq!(sources(reader), vec![0, 0, 0, 1, 1]);
+
+        // the heap-based mux deduplicates the same way
+        for &(direction, expected) in &[
+            (Direction::Forward, &[0, 0, 0, 1]),
+            (Direction::Reverse, &[1, 1, 0, 0]),
+        ] {
+            let reader =
+                LogEntryReaderHeapMux::new(iterators(direction), direction).with_dedupe(true);
+            let sources: Vec<_> = reader.map(LogEntry::source).cloned().collect();
+            assert_eq!(sources, expected.to_vec());
+        }
+    }
+
     #[test]
     fn log_entry_reader_mux() {
         let iterators = LOG_INPUTS
@@ -152,4 +379,172 @@ mod tests {
             GOLDEN_RESULTS.iter().copied().rev().collect::<Vec<_>>()
         );
     }
+
+    const JITTERED_INPUTS: &[&[&[u8]]] = &[
+        &[
+            b"-info:<1> 2020-01-01 20:00:00.010 UTC [A]: Request",
+            b"-info:<1> 2020-01-01 20:00:00.030 UTC [A]: Response",
+            b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Next",
+        ],
+        &[
+            b"-info:<2> 2020-01-01 20:00:00.005 UTC [A]: Received",
+            b"-info:<2> 2020-01-01 20:00:00.020 UTC [A]: Replied",
+        ],
+    ];
+
+    fn merge_jittered(direction: Direction, precision: Option<Duration>) -> Vec<Vec<u8>> {
+        let iterators = JITTERED_INPUTS
+            .iter()
+            .enumerate()
+            .map(|(source, log)| {
+                let entries = log
+                    .iter()
+                    .map(|lines| LogEntry::from_contents(lines.to_vec()).with_source(source));
+                match direction {
+                    Direction::Forward => entries.collect::<Vec<_>>(),
+                    Direction::Reverse => entries.rev().collect::<Vec<_>>(),
+                }
+            })
+            .map(streaming_iterator::convert)
+            .collect();
+
+        LogEntryReaderMux::new(iterators, direction)
+            .with_precision(precision)
+            .map(|entry| entry.message().unwrap_or_default().to_vec())
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn log_entry_reader_mux_precision() {
+        assert_eq!(
+            merge_jittered(Direction::Forward, None),
+            vec![
+                b"Received".to_vec(),
+                b"Request".to_vec(),
+                b"Replied".to_vec(),
+                b"Response".to_vec(),
+                b"Next".to_vec(),
+            ]
+        );
+        assert_eq!(
+            merge_jittered(Direction::Forward, Some(Duration::seconds(1))),
+            vec![
+                b"Request".to_vec(),
+                b"Response".to_vec(),
+                b"Received".to_vec(),
+                b"Replied".to_vec(),
+                b"Next".to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn log_entry_reader_heap_mux() {
+        for direction in [Direction::Forward, Direction::Reverse] {
+            let to_iterators = || -> Vec<_> {
+                LOG_INPUTS
+                    .iter()
+                    .chain(JITTERED_INPUTS)
+                    .enumerate()
+                    .map(|(source, log)| {
+                        let entries = log
+                            .iter()
+                            .map(|lines| LogEntry::from_contents(lines.to_vec()))
+                            .map(|entry| entry.with_source(source));
+                        match direction {
+                            Direction::Forward => entries.collect::<Vec<_>>(),
+                            Direction::Reverse => entries.rev().collect::<Vec<_>>(),
+                        }
+                    })
+                    .map(streaming_iterator::convert)
+                    .collect()
+            };
+
+            for precision in [None, Some(Duration::seconds(1))] {
+                let linear: Vec<LogEntry> = LogEntryReaderMux::new(to_iterators(), direction)
+                    .with_precision(precision)
+                    .owned()
+                    .collect();
+                let heap: Vec<LogEntry> = LogEntryReaderHeapMux::new(to_iterators(), direction)
+                    .with_precision(precision)
+                    .owned()
+                    .collect();
+                assert_eq!(heap.len(), 9);
+                assert_eq!(heap, linear);
+            }
+        }
+    }
+
+    #[test]
+    fn log_entry_reader_mux_precision_reverse() {
+        let mut forward = merge_jittered(Direction::Forward, Some(Duration::seconds(1)));
+        forward.reverse();
+        assert_eq!(
+            merge_jittered(Direction::Reverse, Some(Duration::seconds(1))),
+            forward
+        );
+    }
+
+    // panics when reading anything before the given offset
+    struct GuardedReader {
+        inner: io::Cursor<Vec<u8>>,
+        guarded_len: u64,
+    }
+
+    impl Read for GuardedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            assert!(
+                self.inner.position() >= self.guarded_len,
+                "Read at {} past the newest entries",
+                self.inner.position()
+            );
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for GuardedReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn log_entry_reader_mux_reverse_stops_reading() {
+        let iterators = (0..2)
+            .map(|source| {
+                let log: Vec<u8> = (0..100)
+                    .flat_map(|minute| {
+                        format!(
+                            "-info:<1> 2020-01-01 {:02}:{:02}:{:02}.000 UTC [A]: B\n\n",
+                            minute / 60,
+                            minute % 60,
+                            source
+                        )
+                        .into_bytes()
+                    })
+                    .collect();
+                let reader = GuardedReader {
+                    guarded_len: log.len() as u64 - 1024,
+                    inner: io::Cursor::new(log),
+                };
+                LogEntryRevReader::with_capacity(reader, b"\n", 64)
+                    .unwrap()
+                    .with_source(source)
+            })
+            .collect();
+
+        let reader = LogEntryReaderMux::new(iterators, Direction::Reverse);
+        let results: Vec<GoldenResult> = reader.take(4).owned().map(Into::into).collect();
+
+        assert_eq!(
+            results,
+            vec![
+                GoldenResult(1, Some((2020, 1, 1, 1, 39, 1))),
+                GoldenResult(0, Some((2020, 1, 1, 1, 39, 0))),
+                GoldenResult(1, Some((2020, 1, 1, 1, 38, 1))),
+                GoldenResult(0, Some((2020, 1, 1, 1, 38, 0))),
+            ]
+        );
+    }
 }
@@ -1,423 +1,3436 @@
+mod benchmark;
+mod buffering;
+mod checkpoint;
 mod cli;
+mod context;
 mod direction;
+mod encoding;
 mod eol;
 mod error;
+mod explore;
 mod filtering;
+mod follow;
 mod formatting;
+mod head_tail;
+mod histogram;
+mod index;
+mod interrupt;
+mod json;
+mod json_lines;
+mod key_values;
+mod last_per_level;
+mod limit;
 mod log_entry;
 mod log_entry_reader;
 mod log_entry_reader_mux;
+mod notify;
+mod order;
+mod pattern;
+mod plan;
+mod progress;
+mod rendering;
 mod result;
+mod resume;
 mod rev_reader;
-
-use crate::cli::Options;
+mod selfcheck;
+mod sorting;
+mod summary;
+mod tee;
+mod template;
+mod transforming;
+mod tsv;
+#[cfg(unix)]
+mod tty;
+mod window_stats;
+mod wrap;
+
+use crate::benchmark::{CountingReader, Throughput};
+use crate::buffering::{buffer_entries, spill_separator, Buffered, DEFAULT_MEMORY_LIMIT};
+use crate::checkpoint::SkipWritten;
+use crate::cli::{
+    ColorBy, DistinctField, Encoding, EntrySelector, EscapeMode, FilteringOptions, FoldMarkers,
+    InputFormat, OnlyField, Options, OutputFormat, OutputOptions,
+};
 use crate::direction::Direction;
+use crate::encoding::TranscodingWriter;
 use crate::error::Error;
-use crate::filtering::filtering_iter;
-use crate::formatting::format_special_chars;
-use crate::log_entry::{LogEntry, LogLevel};
-use crate::log_entry_reader::{LogEntryReader, LogEntryRevReader};
-use crate::log_entry_reader_mux::LogEntryReaderMux;
+use crate::explore::{explore, load_entries};
+use crate::filtering::{
+    field_value, filtering_iter_with_stats, truncate_timestamp, FilterStats, MatchCounter,
+};
+use crate::follow::{FlushingWriter, FollowReader};
+use crate::formatting::{decode_escapes, format_special_chars, EscapeTable};
+use crate::head_tail::HeadTailIter;
+use crate::histogram::Histogram;
+use crate::index::EntryIndex;
+use crate::interrupt::{Interrupt, InterruptibleWriter};
+use crate::json_lines::JsonLinesReader;
+use crate::last_per_level::{last_per_level, last_per_level_rev};
+use crate::limit::CountingWriter;
+use crate::log_entry::{LevelMap, LogEntry, LogLevel};
+use crate::log_entry_reader::{EntrySeparator, LogEntryReader, LogEntryRevReader};
+use crate::log_entry_reader_mux::LogEntryReaderHeapMux;
+use crate::order::{OrderCheck, OrderCheckingIter};
+use crate::plan::{is_stdin, write_plan, ReadPlan};
+use crate::progress::{Progress, ProgressReader};
+use crate::rendering::{
+    add_fold_markers, align_header, collapse_trace, compact_timestamp, cut_at_pattern,
+    elapsed_prefix, flatten_lines, host_label, is_multiline, is_same_hour, mask_header,
+    next_gap_prefix, palette_code, parse_flags, replace_all, restore_style_after_resets,
+    source_code, summary_line, truncate_lines,
+};
 use crate::result::Result;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use crate::resume::{ResumeToken, ResumeTracker};
+use crate::rev_reader::RevReader;
+use crate::sorting::{sort_entries, sort_spilled};
+use crate::tee::TeeWriter;
+use crate::transforming::{joining_iter, transforming_iter};
+use crate::window_stats::WindowStats;
+use crate::wrap::WrappingWriter;
+use chrono::NaiveDateTime;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::rc::Rc;
 use streaming_iterator::StreamingIterator;
 
 const IO_BUF_SIZE: usize = 1024 * 1024;
 
+// counted by --list-categories in place of the category of entries which have none
+const NO_CATEGORY: &[u8] = b"(none)";
+
 fn main() {
-    if let Err(error) = run() {
-        eprintln!("{}", error);
-        std::process::exit(1);
+    match run() {
+        Err(Error::Interrupted) => std::process::exit(interrupt::EXIT_CODE),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        Ok(()) => {}
     }
 }
 
 fn run() -> Result<()> {
-    let opts = Options::read()?;
+    let mut opts = Options::read()?;
 
-    if let Some(output_file) = &opts.output_file {
-        let writer = File::create(output_file)
-            .map(|w| BufWriter::with_capacity(IO_BUF_SIZE, w))
-            .map_err(|e| Error::CannotCreateFile(output_file.clone(), e))?;
-        read_log(writer, opts)
+    let interrupt = if opts.benchmark {
+        Interrupt::default()
+    } else {
+        Interrupt::install()?
+    };
+    opts.interrupt = interrupt.clone();
+    opts.output_options.interrupt = interrupt.clone();
+
+    if opts.dry_run {
+        write_plan(io::stdout().lock(), &opts)
+    } else if opts.benchmark || opts.interactive {
+        read_log(io::sink(), opts)
+    } else if let Some(command) = opts.exec.clone() {
+        run_through_command(&command, opts, &interrupt)
+    } else if let Some(output_file) = &opts.output_file {
+        let writer = create_output(output_file, opts.output_append)
+            .map(|w| BufWriter::with_capacity(IO_BUF_SIZE, w))?;
+        let writer = InterruptibleWriter::new(writer, interrupt.clone());
+        stop_on_interrupt(read_log(writer, opts), &interrupt)
     } else if opts.pager {
-        let mut less_command = Command::new("less");
-        less_command.arg("--quit-if-one-screen");
-
-        if opts.color_enabled {
-            less_command.arg("--RAW-CONTROL-CHARS");
-        }
-
-        if !opts.wrap {
-            less_command.arg("--chop-long-lines");
-        }
-
-        let mut less_process = less_command
-            .stdin(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let mut less_process = less_command(&opts).stdin(Stdio::piped()).spawn()?;
 
         let writer = less_process
             .stdin
             .as_mut()
             .map(|w| BufWriter::with_capacity(IO_BUF_SIZE, w))
-            .ok_or(Error::CannotUseLessStdin)?;
+            .ok_or_else(|| Error::CannotUseStdin("less".to_string()))?;
 
-        let res = read_log(writer, opts);
+        let res = read_log_teed(writer, opts, &interrupt);
 
-        ignore_broken_pipe(res)?;
+        // the pager keeps showing the output written before the interrupt until it is closed
+        let res = stop_on_interrupt(ignore_broken_pipe(res), &interrupt);
 
         less_process.wait()?;
 
-        Ok(())
+        res
     } else {
         let stdout = std::io::stdout();
         let writer = BufWriter::with_capacity(IO_BUF_SIZE, stdout.lock());
-        ignore_broken_pipe(read_log(writer, opts))
+        stop_on_interrupt(
+            ignore_broken_pipe(read_log_teed(writer, opts, &interrupt)),
+            &interrupt,
+        )
+    }
+}
+
+fn less_command(opts: &Options) -> Command {
+    let mut less_command = Command::new("less");
+    less_command.arg("--quit-if-one-screen");
+
+    if opts.output_options.color_enabled {
+        less_command.arg("--RAW-CONTROL-CHARS");
+    }
+
+    if !opts.wrap {
+        less_command.arg("--chop-long-lines");
+    }
+
+    less_command.stderr(Stdio::piped());
+    less_command
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut shell_command = Command::new("sh");
+    shell_command.arg("-c").arg(command);
+    shell_command
+}
+
+#[cfg(not(unix))]
+fn shell_command(command: &str) -> Command {
+    let mut shell_command = Command::new("cmd");
+    shell_command.arg("/C").arg(command);
+    shell_command
+}
+
+// Pipes the output through the --exec command, whose output goes to the output file, the pager
+// or stdout. The command exiting early ends reading, like closing the pager does.
+fn run_through_command(command: &str, opts: Options, interrupt: &Interrupt) -> Result<()> {
+    let command_output = match &opts.output_file {
+        Some(output_file) => Stdio::from(create_output(output_file, opts.output_append)?),
+        None if opts.pager => Stdio::piped(),
+        None => Stdio::inherit(),
+    };
+    let mut process = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(command_output)
+        .spawn()
+        .map_err(|e| Error::CannotRunCommand(command.to_string(), e))?;
+    let mut less_process = match process.stdout.take() {
+        Some(pipe) => match less_command(&opts).stdin(pipe).spawn() {
+            Ok(less_process) => Some(less_process),
+            Err(e) => {
+                // the command would be left running, waiting for input which never comes
+                let _ = process.kill();
+                let _ = process.wait();
+                return Err(e.into());
+            }
+        },
+        None => None,
+    };
+
+    let writer = process
+        .stdin
+        .take()
+        .map(|w| BufWriter::with_capacity(IO_BUF_SIZE, w))
+        .ok_or_else(|| Error::CannotUseStdin(command.to_string()))?;
+    let res = read_log_teed(writer, opts, interrupt);
+    let res = stop_on_interrupt(ignore_broken_pipe(res), interrupt);
+
+    let status = process.wait()?;
+    if let Some(less_process) = &mut less_process {
+        less_process.wait()?;
+    }
+    // the shell exits with these when the command cannot be found or executed
+    match status.code() {
+        Some(126) | Some(127) if res.is_ok() => Err(Error::CannotRunCommand(
+            command.to_string(),
+            io::Error::other(status.to_string()),
+        )),
+        _ => res,
+    }
+}
+
+// opens the output file, truncating it unless appending to it
+fn create_output(path: &Path, append: bool) -> Result<File> {
+    let file = if append {
+        OpenOptions::new().create(true).append(true).open(path)
+    } else {
+        File::create(path)
+    };
+    file.map_err(|e| Error::CannotCreateFile(path.to_path_buf(), e))
+}
+
+// also writes the output to the --tee file, which is complete even if the pager is closed early
+fn read_log_teed(writer: impl Write, opts: Options, interrupt: &Interrupt) -> Result<()> {
+    let tee_file = match &opts.tee_file {
+        Some(tee_file) => File::create(tee_file)
+            .map(|w| BufWriter::with_capacity(IO_BUF_SIZE, w))
+            .map_err(|e| Error::CannotCreateFile(tee_file.clone(), e))?,
+        None => return read_log(InterruptibleWriter::new(writer, interrupt.clone()), opts),
+    };
+
+    let mut tee = TeeWriter::new(writer, tee_file);
+    let res = read_log(InterruptibleWriter::new(&mut tee, interrupt.clone()), opts)
+        .and_then(|_| Ok(tee.flush()?));
+    match tee.take_primary_error() {
+        Some(error) if res.is_ok() => Err(error.into()),
+        _ => res,
     }
 }
 
 fn read_log(writer: impl Write, opts: Options) -> Result<()> {
-    if opts.input_files.is_empty()
-        || (opts.input_files.len() == 1 && opts.input_files[0] == Path::new("-"))
-    {
-        let stdin = io::stdin();
-        if opts.is_filtering_or_coloring() {
-            let reader = LogEntryReader::new(stdin.lock(), eol::EOL);
-            let reader = filtering_iter(reader, opts.filtering_options.clone(), Direction::Forward);
-            write_log(
+    read_log_reporting(writer, io::stderr(), opts)
+}
+
+// writes reports about the run, unless quiet, to the report writer
+fn read_log_reporting(
+    writer: impl Write,
+    mut report_writer: impl Write,
+    mut opts: Options,
+) -> Result<()> {
+    if opts.resume != ResumeToken::default() || opts.resume_token_file.is_some() {
+        opts.resume = start_offsets(&opts.input_files, &opts.resume)?;
+    }
+    apply_indexes(&mut opts)?;
+    if opts.resume_token_file.is_some() {
+        let tracker = ResumeTracker::new(opts.resume.clone(), opts.input_files.len());
+        opts.resume_tracker = Some(Rc::new(tracker));
+    }
+    if opts.verify_order {
+        let check = OrderCheck::new(opts.input_files.clone(), opts.strict);
+        let check = if opts.quiet {
+            check
+        } else {
+            check.with_report(io::stderr())
+        };
+        opts.order_check = Some(Rc::new(check));
+    }
+
+    let stats = Rc::new(if opts.explain {
+        FilterStats::explaining(io::stderr())
+    } else {
+        FilterStats::default()
+    });
+    let progress = if shows_progress(&opts) {
+        let total = opts
+            .input_files
+            .iter()
+            .filter_map(|file| std::fs::metadata(file).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        Some(Rc::new(Progress::new(total)))
+    } else {
+        None
+    };
+
+    let result = read_log_with_stats(writer, &opts, &stats, &progress);
+
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+
+    if let (Some(rate_summary), false) = (&opts.rate_summary, opts.quiet) {
+        writeln!(
+            report_writer,
+            "{}",
+            rate_summary.render(rate_summary.elapsed())
+        )?;
+    }
+
+    if let (Some(tracker), Some(token_file)) = (&opts.resume_tracker, &opts.resume_token_file) {
+        // entries written before an error are kept in the output, so their offsets are saved too
+        if result.is_ok() {
+            tracker.finish();
+        }
+        std::fs::write(token_file, tracker.token().to_string())
+            .map_err(|e| Error::CannotCreateFile(token_file.clone(), e))?;
+    }
+
+    if let Some(checkpoint) = &opts.output_options.checkpoint {
+        checkpoint.save()?;
+    }
+
+    result?;
+
+    if let (Some(check), true) = (&opts.order_check, opts.strict) {
+        if let Some(violation) = check.violation() {
+            return Err(Error::OutOfOrder(violation));
+        }
+    }
+
+    if let (Some(limit), false) = (&opts.output_options.byte_limit, opts.quiet) {
+        if limit.is_truncated() {
+            writeln!(report_writer, "{}", limit.note())?;
+        }
+    }
+
+    if opts.explain_filters && !opts.quiet {
+        writeln!(report_writer, "{}", stats.report(&opts.filtering_options))?;
+    }
+
+    Ok(())
+}
+
+// progress is shown only for inputs of known size and never over the pager screen
+fn shows_progress(opts: &Options) -> bool {
+    opts.progress_bar
+        && !opts.quiet
+        && (opts.benchmark || opts.output_file.is_some() || !opts.pager)
+        && !opts.follow
+        && opts.entry_selector.is_none()
+        && !opts.input_files.is_empty()
+        && opts.input_files.iter().all(|file| !is_stdin(file))
+        && atty::is(atty::Stream::Stderr)
+}
+
+fn read_log_with_stats(
+    writer: impl Write,
+    opts: &Options,
+    stats: &Rc<FilterStats>,
+    progress: &Option<Rc<Progress>>,
+) -> Result<()> {
+    match ReadPlan::of(opts) {
+        ReadPlan::SelfCheck => check_merge(writer, opts),
+        ReadPlan::SelectedEntry => {
+            let selector = opts.entry_selector.expect("No entry selector");
+            read_selected_entry(writer, opts, selector)
+        }
+        ReadPlan::Follow => {
+            let file = opts.input_files.first().expect("No elements");
+            let idle_marker = opts
+                .idle_marker
+                .filter(|_| !opts.quiet)
+                .and_then(|interval| interval.to_std().ok());
+            let reader = FollowReader::open(file, follow::POLL_INTERVAL)?
+                .with_idle_marker(idle_marker)
+                .with_interrupt(opts.interrupt.clone());
+            let reader = BufReader::with_capacity(IO_BUF_SIZE, reader);
+            read_stream(reader, FlushingWriter(writer), opts, stats)
+        }
+        ReadPlan::Stream => {
+            let stdin = io::stdin();
+            let reader = CountingReader::new(stdin.lock(), opts.input_bytes.clone());
+            read_stream(reader, writer, opts, stats)
+        }
+        ReadPlan::FastReverse => {
+            let file = &opts.input_files[0];
+            let file = File::open(file).map_err(|e| Error::CannotOpenFile(file.clone(), e))?;
+            write_log_fast_rev(
+                file,
+                writer,
+                fast_escape_table(opts),
+                opts.output_options.encoding,
+            )
+        }
+        ReadPlan::Merge(direction) => read_merged(writer, opts, direction, stats, progress),
+        ReadPlan::Fast => {
+            let file = opts.input_files.first().expect("No elements");
+            let file = File::open(&file).map_err(|e| Error::CannotOpenFile(file.clone(), e))?;
+            let reader =
+                BufReader::with_capacity(IO_BUF_SIZE, ProgressReader::new(file, progress.clone()));
+            write_log_fast(
                 reader,
                 writer,
-                opts.color_enabled,
-                opts.formatting_enabled,
-                &opts.input_files,
+                fast_escape_table(opts),
+                opts.output_options.encoding,
             )
-        } else {
-            write_log_fast(stdin.lock(), writer, opts.formatting_enabled)
         }
-    } else if opts.is_filtering_or_coloring() || opts.input_files.len() > 1 || opts.reverse {
-        let readers: Result<Vec<_>> = opts
+    }
+}
+
+// reads entries of all input files, interleaving them if there are many
+fn read_merged(
+    writer: impl Write,
+    opts: &Options,
+    direction: Direction,
+    stats: &Rc<FilterStats>,
+    progress: &Option<Rc<Progress>>,
+) -> Result<()> {
+    let has_stdin = opts.input_files.iter().any(|file| is_stdin(file));
+
+    let max_age_opts;
+    let opts = if opts.filtering_options.max_age.is_some() && !has_stdin {
+        max_age_opts = with_max_age_cutoff(
+            opts,
+            newest_timestamp_in_files(&opts.input_files, &opts.separator)?,
+        );
+        &max_age_opts
+    } else {
+        opts
+    };
+
+    if has_stdin {
+        let stdin = io::stdin();
+        let readers: Result<Vec<Box<dyn BufRead>>> = opts
             .input_files
             .iter()
-            .map(|f| File::open(&f).map_err(|e| Error::CannotOpenFile(f.clone(), e)))
-            .map(|f| f.map(|f| BufReader::with_capacity(IO_BUF_SIZE, f)))
+            .map(|f| {
+                if is_stdin(f) {
+                    let reader = CountingReader::new(stdin.lock(), opts.input_bytes.clone());
+                    Ok(Box::new(reader) as Box<dyn BufRead>)
+                } else {
+                    let file = File::open(f).map_err(|e| Error::CannotOpenFile(f.clone(), e))?;
+                    let file = CountingReader::new(file, opts.input_bytes.clone());
+                    Ok(Box::new(BufReader::with_capacity(IO_BUF_SIZE, file)) as Box<dyn BufRead>)
+                }
+            })
             .collect();
+        return merge_forward(readers?, writer, opts, stats);
+    }
 
-        let readers = readers?;
+    let readers: Result<Vec<_>> = opts
+        .input_files
+        .iter()
+        .map(|f| File::open(&f).map_err(|e| Error::CannotOpenFile(f.clone(), e)))
+        .enumerate()
+        .map(|(i, f)| {
+            let mut f = f?;
+            let offset = opts.resume.offset(i);
+            if offset > 0 {
+                f.seek(SeekFrom::Start(offset))?;
+            }
+            Ok(f)
+        })
+        .map(|f| f.map(|f| CountingReader::new(f, opts.input_bytes.clone())))
+        .map(|f| f.map(|f| ProgressReader::new(f, progress.clone())))
+        .map(|f| f.map(|f| BufReader::with_capacity(IO_BUF_SIZE, f)))
+        .collect();
+
+    let readers = readers?;
 
-        if opts.reverse {
+    match direction {
+        Direction::Reverse => {
             let mut entry_iters: Vec<_> = readers
                 .into_iter()
                 .enumerate()
                 .map(|(i, r)| {
-                    let reader = LogEntryRevReader::with_capacity(r, eol::EOL, IO_BUF_SIZE)?;
-                    Ok(filtering_iter(
-                        reader.with_source(i),
+                    let reader = rev_entry_reader(r, i, opts)?;
+                    Ok(filtering_iter_with_stats(
+                        joining_iter(
+                            reader,
+                            opts.transforming_options.join_continuations,
+                            Direction::Reverse,
+                        ),
                         opts.filtering_options.clone(),
                         Direction::Reverse,
+                        stats.clone(),
                     ))
                 })
                 .collect::<Result<_>>()?;
 
             if entry_iters.len() == 1 {
                 let entry_iter = entry_iters.pop().expect("No elements");
-                write_log(
-                    entry_iter,
-                    writer,
-                    opts.color_enabled,
-                    opts.formatting_enabled,
-                    &opts.input_files,
-                )
-            } else {
-                let reader = LogEntryReaderMux::new(entry_iters, Direction::Reverse);
-                write_log(
-                    reader,
-                    writer,
-                    opts.color_enabled,
-                    opts.formatting_enabled,
-                    &opts.input_files,
-                )
-            }
-        } else {
-            let mut entry_iters: Vec<_> = readers
-                .into_iter()
-                .enumerate()
-                .map(|(i, r)| LogEntryReader::new(r, eol::EOL).with_source(i))
-                .map(|reader| {
-                    filtering_iter(reader, opts.filtering_options.clone(), Direction::Forward)
-                })
-                .collect();
-
-            if entry_iters.len() == 1 {
-                let entry_iter = entry_iters.pop().expect("No elements");
-                write_log(
-                    entry_iter,
-                    writer,
-                    opts.color_enabled,
-                    opts.formatting_enabled,
-                    &opts.input_files,
-                )
+                write_rev_entries(entry_iter, writer, opts)
             } else {
-                let reader = LogEntryReaderMux::new(entry_iters, Direction::Forward);
-                write_log(
-                    reader,
-                    writer,
-                    opts.color_enabled,
-                    opts.formatting_enabled,
-                    &opts.input_files,
-                )
+                let reader = LogEntryReaderHeapMux::new(entry_iters, Direction::Reverse)
+                    .with_precision(opts.merge_precision)
+                    .with_dedupe(opts.merge_dedupe);
+                write_rev_entries(reader, writer, opts)
             }
         }
-    } else {
-        let file = opts.input_files.first().expect("No elements");
-        let file = File::open(&file).map_err(|e| Error::CannotOpenFile(file.clone(), e))?;
-        let reader = BufReader::with_capacity(IO_BUF_SIZE, file);
-        write_log_fast(reader, writer, opts.formatting_enabled)
+        Direction::Forward => merge_forward(readers, writer, opts, stats),
     }
 }
 
-fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
-    match result {
-        Err(Error::Io(error)) if error.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
-        result => result,
-    }
+// compares merging implementations, reading every input file once for each of them
+fn check_merge(mut writer: impl Write, opts: &Options) -> Result<()> {
+    let direction = if opts.reverse {
+        Direction::Reverse
+    } else {
+        Direction::Forward
+    };
+    let entry_iters = || -> Result<Vec<_>> {
+        opts.input_files
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let file = File::open(f).map_err(|e| Error::CannotOpenFile(f.clone(), e))?;
+                let reader = BufReader::with_capacity(IO_BUF_SIZE, file);
+                match direction {
+                    Direction::Forward => Ok(entry_reader(reader, i, 0, opts)),
+                    Direction::Reverse => rev_entry_reader(reader, i, opts),
+                }
+            })
+            .collect()
+    };
+
+    let count = selfcheck::check_mux(
+        entry_iters()?,
+        entry_iters()?,
+        direction,
+        opts.merge_precision,
+        opts.merge_dedupe,
+    )?;
+
+    writeln!(
+        writer,
+        "Self-check passed: {} entries merged identically",
+        count
+    )?;
+    Ok(())
 }
 
-fn write_log_fast(
-    mut reader: impl BufRead,
-    mut writer: impl Write,
-    formatting: bool,
-) -> Result<()> {
-    let mut ctr_char_is_next = false;
+fn read_selected_entry(writer: impl Write, opts: &Options, selector: EntrySelector) -> Result<()> {
+    let file = opts.input_files.first().filter(|file| !is_stdin(file));
 
-    loop {
-        let buf = reader.fill_buf()?;
-        if buf.is_empty() {
-            break;
+    if let Some(file) = file {
+        let mut file = File::open(file).map_err(|e| Error::CannotOpenFile(file.clone(), e))?;
+        if let EntrySelector::Offset(offset) = selector {
+            file.seek(SeekFrom::Start(offset))?;
         }
-
-        if formatting {
-            ctr_char_is_next =
-                format_special_chars(buf, &mut writer, ctr_char_is_next, eol::EOL, b"")?;
-        } else {
-            writer.write_all(buf)?;
+        let reader = BufReader::with_capacity(IO_BUF_SIZE, file);
+        write_selected_entry(reader, writer, opts, selector)
+    } else {
+        let stdin = io::stdin();
+        let mut reader = stdin.lock();
+        if let EntrySelector::Offset(offset) = selector {
+            io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
         }
-
-        let consumed_bytes = buf.len();
-        reader.consume(consumed_bytes);
+        write_selected_entry(reader, writer, opts, selector)
     }
-
-    Ok(())
 }
 
-const CODE_CYAN: &[u8; 5] = b"\x1B[36m";
-const CODE_GRAY: &[u8; 5] = b"\x1B[37m";
-const CODE_RED: &[u8; 5] = b"\x1B[31m";
-const CODE_RED_BRIGHT: &[u8; 5] = b"\x1B[91m";
-const CODE_WHITE: &[u8; 5] = b"\x1B[97m";
-const CODE_YELLOW: &[u8; 5] = b"\x1B[33m";
-const CODE_NORMAL: &[u8; 4] = b"\x1B[0m";
+// expects the reader to be already positioned at the offset of an offset selector
+fn write_selected_entry(
+    reader: impl BufRead,
+    writer: impl Write,
+    opts: &Options,
+    selector: EntrySelector,
+) -> Result<()> {
+    let reader = CountingReader::new(reader, opts.input_bytes.clone());
+    let entries = LogEntryReader::new(reader, eol::EOL)
+        .with_separator(opts.separator.clone())
+        .with_max_entry_size(opts.max_entry_size)
+        .with_level_map(opts.level_map.clone());
+    match selector {
+        EntrySelector::Offset(_) => write_entries(entries.take(1), writer, opts),
+        EntrySelector::Number(number) => {
+            write_entries(entries.skip(number - 1).take(1), writer, opts)
+        }
+    }
+}
 
-fn write_log(
-    mut log_entries: impl StreamingIterator<Item = LogEntry>,
-    mut writer: impl Write,
-    color_enabled: bool,
-    formatting: bool,
-    input_files: &[PathBuf],
+fn read_stream(
+    reader: impl BufRead,
+    writer: impl Write,
+    opts: &Options,
+    stats: &Rc<FilterStats>,
 ) -> Result<()> {
-    let code_normal_eol = [CODE_NORMAL, eol::EOL].concat();
+    if !(opts.is_filtering_or_coloring() || opts.reverse_buffer) {
+        return write_log_fast(
+            reader,
+            writer,
+            fast_escape_table(opts),
+            opts.output_options.encoding,
+        );
+    }
 
-    while let Some(entry) = log_entries.next() {
-        if input_files.len() > 1 {
-            if color_enabled {
-                writer.write_all(CODE_CYAN)?;
+    let reader = entry_reader(reader, 0, 0, opts);
+
+    if opts.filtering_options.max_age.is_some() && !opts.follow {
+        // the newest entry is known only after reading the whole input
+        let memory_limit = opts.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT);
+        match buffer_entries(reader, memory_limit)? {
+            Buffered::Memory(entries) => {
+                let newest = entries.iter().rev().find_map(LogEntry::timestamp);
+                let opts = with_max_age_cutoff(opts, newest);
+                read_entry_stream(streaming_iterator::convert(entries), writer, &opts, stats)
             }
-            write!(writer, "{}: ", input_files[entry.source()].display())?;
-            if color_enabled {
-                writer.write_all(CODE_NORMAL)?;
+            Buffered::Spilled(mut spill_file) => {
+                let newest = newest_timestamp(&mut spill_file, spill_separator())?;
+                spill_file.seek(SeekFrom::Start(0))?;
+                let opts = with_max_age_cutoff(opts, newest);
+                let reader = BufReader::with_capacity(IO_BUF_SIZE, spill_file);
+                let reader = LogEntryReader::new(reader, eol::EOL)
+                    .with_separator(spill_separator())
+                    .with_level_map(opts.level_map.clone());
+                read_entry_stream(reader, writer, &opts, stats)
             }
         }
+    } else {
+        read_entry_stream(reader, writer, opts, stats)
+    }
+}
 
-        let level = if color_enabled { entry.level() } else { None };
+fn read_entry_stream(
+    reader: impl StreamingIterator<Item = LogEntry>,
+    writer: impl Write,
+    opts: &Options,
+    stats: &Rc<FilterStats>,
+) -> Result<()> {
+    let reader = filtering_iter_with_stats(
+        joining_iter(
+            reader,
+            opts.transforming_options.join_continuations,
+            Direction::Forward,
+        ),
+        opts.filtering_options.clone(),
+        Direction::Forward,
+        stats.clone(),
+    );
+    if opts.reverse_buffer && opts.last_per_level.is_none() {
+        let memory_limit = opts.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT);
+        match buffer_entries(reader, memory_limit)? {
+            Buffered::Memory(entries) => write_entries(
+                streaming_iterator::convert(entries.into_iter().rev()),
+                writer,
+                opts,
+            ),
+            Buffered::Spilled(spill_file) => {
+                let reader = LogEntryRevReader::with_capacity(spill_file, eol::EOL, IO_BUF_SIZE)?
+                    .with_separator(spill_separator())
+                    .with_level_map(opts.level_map.clone());
+                write_entries(reader, writer, opts)
+            }
+        }
+    } else {
+        write_fwd_entries(reader, writer, opts)
+    }
+}
 
-        let eol: &[u8] = if color_enabled {
-            &code_normal_eol
-        } else {
-            eol::EOL
-        };
+// reads entries of an input in its format, positioned at given offset
+fn entry_reader<'a>(
+    reader: impl BufRead + 'a,
+    source: usize,
+    offset: u64,
+    opts: &Options,
+) -> Box<dyn StreamingIterator<Item = LogEntry> + 'a> {
+    let reader: Box<dyn StreamingIterator<Item = LogEntry> + 'a> = match opts.input_format {
+        InputFormat::Native => Box::new(
+            LogEntryReader::new(reader, eol::EOL)
+                .with_separator(opts.separator.clone())
+                .with_max_entry_size(opts.max_entry_size)
+                .with_source(source)
+                .with_offset(offset)
+                .with_level_map(opts.level_map.clone()),
+        ),
+        InputFormat::JsonLines => Box::new(
+            JsonLinesReader::new(reader, opts.jsonl_fields.clone())
+                .with_strict(opts.jsonl_strict)
+                .with_source(source)
+                .with_offset(offset),
+        ),
+    };
+    match &opts.order_check {
+        Some(check) => Box::new(OrderCheckingIter::new(
+            reader,
+            check.clone(),
+            Direction::Forward,
+        )),
+        None => reader,
+    }
+}
 
-        let color_code: &[u8] = match level {
-            Some(LogLevel::Debug) => CODE_GRAY,
-            Some(LogLevel::Info) => CODE_WHITE,
-            Some(LogLevel::Warning) => CODE_YELLOW,
-            Some(LogLevel::Critical) => CODE_RED,
-            Some(LogLevel::Fatal) => CODE_RED_BRIGHT,
-            None => b"",
-        };
+// reads entries of an input newest first, starting from its end
+fn rev_entry_reader<'a>(
+    reader: impl Read + Seek + 'a,
+    source: usize,
+    opts: &Options,
+) -> Result<Box<dyn StreamingIterator<Item = LogEntry> + 'a>> {
+    let reader = LogEntryRevReader::with_capacity(reader, eol::EOL, IO_BUF_SIZE)?
+        .with_max_entry_size(opts.max_entry_size)
+        .with_separator(opts.separator.clone())
+        .with_source(source)
+        .with_level_map(opts.level_map.clone())
+        .with_start(opts.resume.offset(source));
+    Ok(match &opts.order_check {
+        Some(check) => Box::new(OrderCheckingIter::new(
+            reader,
+            check.clone(),
+            Direction::Reverse,
+        )),
+        None => Box::new(reader),
+    })
+}
 
-        writer.write_all(color_code)?;
+// Interleaves entries of all inputs read forward by their timestamps. An entry is written only
+// once the next entry of every input is known, so a blocking input such as stdin holds back the
+// others until it provides more data or ends.
+fn merge_forward(
+    readers: Vec<impl BufRead>,
+    writer: impl Write,
+    opts: &Options,
+    stats: &Rc<FilterStats>,
+) -> Result<()> {
+    let mut entry_iters: Vec<_> = readers
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| entry_reader(r, i, opts.resume.offset(i), opts))
+        .map(|reader| {
+            filtering_iter_with_stats(
+                joining_iter(
+                    reader,
+                    opts.transforming_options.join_continuations,
+                    Direction::Forward,
+                ),
+                opts.filtering_options.clone(),
+                Direction::Forward,
+                stats.clone(),
+            )
+        })
+        .collect();
 
-        if formatting {
-            format_special_chars(entry.contents(), &mut writer, false, eol, color_code)?;
-        } else {
-            writer.write_all(entry.contents())?;
-        }
+    if entry_iters.len() == 1 {
+        let entry_iter = entry_iters.pop().expect("No elements");
+        write_fwd_entries(entry_iter, writer, opts)
+    } else {
+        let reader = LogEntryReaderHeapMux::new(entry_iters, Direction::Forward)
+            .with_precision(opts.merge_precision)
+            .with_dedupe(opts.merge_dedupe);
+        write_fwd_entries(reader, writer, opts)
+    }
+}
 
-        if color_enabled {
-            writer.write_all(CODE_NORMAL)?;
+// timestamp of the last entry which has one
+fn newest_timestamp(
+    reader: impl Read + Seek,
+    separator: EntrySeparator,
+) -> Result<Option<NaiveDateTime>> {
+    let mut entries =
+        LogEntryRevReader::with_capacity(reader, eol::EOL, IO_BUF_SIZE)?.with_separator(separator);
+    while let Some(entry) = entries.next() {
+        if let Some(timestamp) = entry.timestamp() {
+            return Ok(Some(timestamp));
         }
     }
-
-    Ok(())
+    Ok(None)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::NaiveDate;
-    use eol;
+// offsets of the token, except for files which got shorter (e.g. rotated) and are read from
+// the start
+fn start_offsets(input_files: &[PathBuf], token: &ResumeToken) -> Result<ResumeToken> {
+    let mut offsets = Vec::with_capacity(input_files.len());
+    for (source, path) in input_files.iter().enumerate() {
+        let len = std::fs::metadata(path)
+            .map_err(|e| Error::CannotOpenFile(path.clone(), e))?
+            .len();
+        offsets.push(
+            Some(token.offset(source))
+                .filter(|&offset| offset <= len)
+                .unwrap_or(0),
+        );
+    }
+    Ok(ResumeToken::new(offsets))
+}
 
-    const LOREM_IPSUM: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed \
-do eiusmod tempor incididunt ut labore et dolore magna aliqua. In eu mi bibendum neque egestas \
-congue quisque egestas diam. Amet tellus cras adipiscing enim. Feugiat vivamus at augue eget \
-arcu dictum varius duis at. Integer enim neque volutpat ac tincidunt. Lobortis elementum nibh \
-tellus molestie. A iaculis at erat pellentesque. Porttitor rhoncus dolor purus non enim praesent \
-elementum facilisis. Sed blandit libero volutpat sed cras. Consectetur lorem donec massa sapien \
-faucibus et molestie.";
+// Narrows reading of input files using their indexes. With --since, reading forward starts at
+// the first entry of each file not skipped by it, unless an offset to resume at is further, and
+// reading newest first stops at the newest entry older than it. Reading newest first for
+// --last-per-level also gets the number of entries of each level in the part read, so it does not
+// read the rest looking for levels there are no more entries of.
+fn apply_indexes(opts: &mut Options) -> Result<()> {
+    if !opts.index
+        || opts.entry_selector.is_some()
+        || opts.input_files.is_empty()
+        || opts.input_files.iter().any(|file| is_stdin(file))
+    {
+        return Ok(());
+    }
+    let reverse = opts.reverse || opts.last_per_level.is_some();
+    let since = opts
+        .filtering_options
+        .since
+        .map(|since| truncate_timestamp(since, opts.filtering_options.time_resolution));
+    // levels in the index are those of standard headers
+    let count_levels =
+        reverse && opts.last_per_level.is_some() && *opts.level_map == LevelMap::default();
+    if since.is_none() && !count_levels {
+        return Ok(());
+    }
 
-    const ELEMENTUM_EU: &[u8] = b"Elementum eu facilisis sed odio morbi quis commodo odio \
-aenean. Quis eleifend quam adipiscing vitae. A iaculis at erat pellentesque adipiscing. Pulvinar \
-pellentesque habitant morbi tristique senectus et netus et malesuada. Commodo elit at imperdiet \
-dui. Volutpat est velit egestas dui id. Dictum sit amet justo donec enim diam vulputate ut. \
-Blandit libero volutpat sed cras ornare arcu dui vivamus arcu. At urna condimentum mattis \
-pellentesque id nibh tortor id aliquet. Quam pellentesque nec nam aliquam sem et tortor.";
+    let mut offsets = Vec::with_capacity(opts.input_files.len());
+    let mut level_counts = [0; LogLevel::ALL.len()];
+    for (source, path) in opts.input_files.iter().enumerate() {
+        let index = EntryIndex::load_or_build(path).map_err(|e| match e {
+            Error::Io(e) => Error::CannotOpenFile(path.clone(), e),
+            e => e,
+        })?;
+        let offset = match since {
+            Some(since) if reverse => index.rev_offset_since(since),
+            Some(since) => index.offset_since(since),
+            None => 0,
+        };
+        let offset = offset.max(opts.resume.offset(source));
+        for (total, count) in level_counts.iter_mut().zip(index.level_counts(offset)) {
+            *total += count;
+        }
+        offsets.push(offset);
+    }
+    opts.resume = ResumeToken::new(offsets);
+    if count_levels {
+        opts.level_counts = Some(level_counts);
+    }
+    Ok(())
+}
 
-    const DT_FORMAT: &str = "%F %T.%3f";
+fn newest_timestamp_in_files(
+    input_files: &[PathBuf],
+    separator: &EntrySeparator,
+) -> Result<Option<NaiveDateTime>> {
+    let mut newest = None;
+    for path in input_files {
+        let file = File::open(path).map_err(|e| Error::CannotOpenFile(path.clone(), e))?;
+        newest = newest.max(newest_timestamp(file, separator.clone())?);
+    }
+    Ok(newest)
+}
 
-    fn header(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> Vec<u8> {
-        let buf = format!(
-            "-info:<22954> {} UTC [Category]: ",
-            NaiveDate::from_ymd(year, month, day)
-                .and_hms(hour, min, sec)
-                .format(DT_FORMAT)
+// turns --max-age into --since relative to the newest entry of the whole input
+fn with_max_age_cutoff(opts: &Options, newest: Option<NaiveDateTime>) -> Options {
+    let mut opts = opts.clone();
+    let filtering_options = &mut opts.filtering_options;
+    if let (Some(max_age), Some(newest)) = (filtering_options.max_age, newest) {
+        let cutoff = newest - max_age;
+        filtering_options.since = Some(
+            filtering_options
+                .since
+                .map_or(cutoff, |since| since.max(cutoff)),
         );
-        buf.into_bytes()
     }
+    opts
+}
 
-    #[test]
-    fn write_log_fast_simple_test() -> Result<()> {
-        let in_buf = b"abc\\ndef\\\\ghi".to_vec();
-        let mut out_buf = Vec::<u8>::new();
+// writes entries read oldest first, or only the latest ones of each level if requested
+fn write_fwd_entries(
+    entries: impl StreamingIterator<Item = LogEntry>,
+    writer: impl Write,
+    opts: &Options,
+) -> Result<()> {
+    match opts.last_per_level {
+        Some(n) => {
+            let mut selected = last_per_level(entries, n);
+            if opts.reverse {
+                selected.reverse();
+            }
+            write_entries(streaming_iterator::convert(selected), writer, opts)
+        }
+        None => write_entries(entries, writer, opts),
+    }
+}
 
-        write_log_fast(&mut in_buf.as_slice(), &mut out_buf, true)?;
+// the lowest level of entries let through by the level filters, including those per category
+fn lowest_level(filtering_options: &FilteringOptions) -> LogLevel {
+    let min_level = filtering_options.min_level.unwrap_or(LogLevel::Debug);
+    filtering_options
+        .category_levels
+        .values()
+        .fold(min_level, |lowest, &level| {
+            if (level as i32) < (lowest as i32) {
+                level
+            } else {
+                lowest
+            }
+        })
+}
 
-        let mut pattern = b"abc".to_vec();
+// writes entries read newest first, or only the latest ones of each level if requested
+fn write_rev_entries(
+    entries: impl StreamingIterator<Item = LogEntry>,
+    writer: impl Write,
+    opts: &Options,
+) -> Result<()> {
+    match opts.last_per_level {
+        Some(n) => {
+            let mut selected = last_per_level_rev(
+                entries,
+                n,
+                lowest_level(&opts.filtering_options),
+                opts.level_counts,
+            );
+            if opts.reverse {
+                selected.reverse();
+            }
+            write_entries(streaming_iterator::convert(selected), writer, opts)
+        }
+        None => write_entries(entries, writer, opts),
+    }
+}
+
+fn write_entries(
+    log_entries: impl StreamingIterator<Item = LogEntry>,
+    writer: impl Write,
+    opts: &Options,
+) -> Result<()> {
+    match opts.sort_by {
+        Some(key) => {
+            let memory_limit = opts.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT);
+            match buffer_entries(log_entries, memory_limit)? {
+                Buffered::Memory(mut entries) => {
+                    sort_entries(&mut entries, key);
+                    if opts.reverse {
+                        entries.reverse();
+                    }
+                    write_ordered_entries(streaming_iterator::convert(entries), writer, opts)
+                }
+                Buffered::Spilled(spill_file) => {
+                    let entries = sort_spilled(
+                        spill_file,
+                        key,
+                        memory_limit,
+                        opts.reverse,
+                        opts.level_map.clone(),
+                    )?;
+                    write_ordered_entries(entries, writer, opts)
+                }
+            }
+        }
+        None => write_ordered_entries(log_entries, writer, opts),
+    }
+}
+
+fn write_ordered_entries(
+    log_entries: impl StreamingIterator<Item = LogEntry>,
+    mut writer: impl Write,
+    opts: &Options,
+) -> Result<()> {
+    let resume_tracker = opts.resume_tracker.clone();
+    let rate_summary = opts.rate_summary.clone();
+    let notifier = opts.notifier.clone().filter(|_| !opts.quiet);
+    let log_entries = transforming_iter(log_entries, opts.transforming_options.clone());
+    let direction = if opts.reverse {
+        Direction::Reverse
+    } else {
+        Direction::Forward
+    };
+    let log_entries = SkipWritten::new(log_entries, opts.since_position, direction);
+    let log_entries = HeadTailIter::new(log_entries, opts.head, opts.tail).inspect(move |entry| {
+        if let Some(tracker) = &resume_tracker {
+            tracker.record(entry);
+        }
+        if let Some(rate_summary) = &rate_summary {
+            rate_summary.record(entry);
+        }
+        if let Some(notifier) = &notifier {
+            notifier.record(entry);
+        }
+    });
+
+    if opts.benchmark {
+        let input_bytes = opts.input_bytes.clone().unwrap_or_default();
+        eprintln!("{}", Throughput::measure(log_entries, &input_bytes));
+        return Ok(());
+    }
+
+    if let Some(interval) = opts.histogram {
+        write!(writer, "{}", Histogram::count(log_entries, interval))?;
+        return Ok(());
+    }
+
+    if let Some(interval) = opts.window_stats {
+        write!(writer, "{}", WindowStats::count(log_entries, interval))?;
+        return Ok(());
+    }
+
+    if let Some(field) = opts.cardinality {
+        return write_cardinality(log_entries, writer, field, opts.cardinality_list);
+    }
+
+    if let (true, Some(contains)) = (opts.count_matches, &opts.filtering_options.contains) {
+        let counter = MatchCounter::new(contains, opts.filtering_options.contains_unicode);
+        return write_match_counts(log_entries, writer, &counter, &opts.input_files);
+    }
+
+    if opts.list_categories {
+        return write_category_counts(log_entries, writer);
+    }
+
+    if let Some(field) = opts.only {
+        return write_only_field(log_entries, writer, field, opts.only_blank_missing);
+    }
+
+    if opts.interactive {
+        let entries = load_entries(
+            log_entries,
+            opts.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT),
+        )?;
+        return explore(entries, |entries, width| {
+            let output_options = OutputOptions {
+                format: OutputFormat::Text,
+                wrap_columns: Some(width),
+                byte_limit: None,
+                checkpoint: None,
+                ..opts.output_options.clone()
+            };
+            let mut rendered = Vec::new();
+            write_log(
+                streaming_iterator::convert(entries),
+                &mut rendered,
+                &output_options,
+                &opts.input_files,
+            )?;
+            Ok(rendered)
+        });
+    }
+
+    write_log(log_entries, writer, &opts.output_options, &opts.input_files)
+}
+
+fn write_cardinality(
+    mut log_entries: impl StreamingIterator<Item = LogEntry>,
+    mut writer: impl Write,
+    field: DistinctField,
+    list: bool,
+) -> Result<()> {
+    let mut values = HashSet::new();
+    while let Some(entry) = log_entries.next() {
+        if let Some(value) = field_value(entry, field) {
+            values.insert(value);
+        }
+    }
+
+    write!(writer, "{}", values.len())?;
+    writer.write_all(eol::EOL)?;
+
+    if list {
+        let mut values: Vec<_> = values.into_iter().collect();
+        values.sort();
+        for value in values {
+            writer.write_all(&value)?;
+            writer.write_all(eol::EOL)?;
+        }
+    }
+
+    Ok(())
+}
+
+// categories with numbers of entries, most frequent first, then by name
+fn write_category_counts(
+    mut log_entries: impl StreamingIterator<Item = LogEntry>,
+    mut writer: impl Write,
+) -> Result<()> {
+    let mut counts = HashMap::<Vec<u8>, usize>::new();
+    while let Some(entry) = log_entries.next() {
+        if !entry.is_separator() {
+            let category = entry.category().unwrap_or(NO_CATEGORY);
+            *counts.entry(category.to_vec()).or_default() += 1;
+        }
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    let count_width = counts
+        .first()
+        .map_or(0, |(_, count)| count.to_string().len());
+    for (category, count) in counts {
+        write!(writer, "{:>width$} ", count, width = count_width)?;
+        writer.write_all(&category)?;
+        writer.write_all(eol::EOL)?;
+    }
+
+    Ok(())
+}
+
+// the field of each entry on its own line, with timestamps in UTC like "2020-01-01 20:00:00.000"
+// and line breaks in messages collapsed into spaces like in summary lines
+fn write_only_field(
+    mut log_entries: impl StreamingIterator<Item = LogEntry>,
+    mut writer: impl Write,
+    field: OnlyField,
+    blank_missing: bool,
+) -> Result<()> {
+    while let Some(entry) = log_entries.next() {
+        if entry.is_separator() {
+            continue;
+        }
+        let value = match field {
+            OnlyField::Timestamps => entry.timestamp().map(|timestamp| {
+                Cow::Owned(
+                    timestamp
+                        .format(ONLY_TIMESTAMP_FORMAT)
+                        .to_string()
+                        .into_bytes(),
+                )
+            }),
+            OnlyField::Levels => entry
+                .level()
+                .map(|level| Cow::Borrowed(level.name().as_bytes())),
+            OnlyField::Categories => entry.category().map(Cow::Borrowed),
+            OnlyField::Messages => entry.message().map(|message| {
+                let message = String::from_utf8_lossy(message);
+                Cow::Owned(
+                    message
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        .into_bytes(),
+                )
+            }),
+        };
+        match value {
+            Some(value) => writer.write_all(&value)?,
+            None if blank_missing => {}
+            None => continue,
+        }
+        writer.write_all(eol::EOL)?;
+    }
+    Ok(())
+}
+
+// total number of occurrences followed by the numbers for each input file, if there are more
+fn write_match_counts(
+    mut log_entries: impl StreamingIterator<Item = LogEntry>,
+    mut writer: impl Write,
+    counter: &MatchCounter,
+    input_files: &[PathBuf],
+) -> Result<()> {
+    let mut counts = vec![0; input_files.len().max(1)];
+    while let Some(entry) = log_entries.next() {
+        if !entry.is_separator() {
+            if let Some(count) = counts.get_mut(entry.source()) {
+                *count += counter.count(entry.contents());
+            }
+        }
+    }
+
+    write!(writer, "{}", counts.iter().sum::<usize>())?;
+    writer.write_all(eol::EOL)?;
+
+    if input_files.len() > 1 {
+        for (path, count) in input_files.iter().zip(counts) {
+            write!(writer, "{}: {}", path.display(), count)?;
+            writer.write_all(eol::EOL)?;
+        }
+    }
+
+    Ok(())
+}
+
+// the output written before the interrupt has already been flushed once the result is known
+fn stop_on_interrupt(result: Result<()>, interrupt: &Interrupt) -> Result<()> {
+    if interrupt.is_requested() {
+        Err(Error::Interrupted)
+    } else {
+        result
+    }
+}
+
+fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+    match result {
+        Err(Error::Io(error)) if error.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+fn fast_escape_table(opts: &Options) -> Option<&EscapeTable> {
+    Some(&opts.output_options.escape_table)
+        .filter(|_| opts.output_options.escape_mode != EscapeMode::None)
+}
+
+// decodes escapes by the table, if any
+fn write_log_fast(
+    mut reader: impl BufRead,
+    writer: impl Write,
+    escape_table: Option<&EscapeTable>,
+    encoding: Encoding,
+) -> Result<()> {
+    let mut writer = TranscodingWriter::new(writer, encoding);
+    let mut ctr_char_is_next = false;
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+
+        if let Some(table) = escape_table {
+            ctr_char_is_next =
+                format_special_chars(buf, &mut writer, table, ctr_char_is_next, eol::EOL, b"")?;
+        } else {
+            writer.write_all(buf)?;
+        }
+
+        let consumed_bytes = buf.len();
+        reader.consume(consumed_bytes);
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+// writes entries starting from the last one without parsing them, like `write_log_fast`
+fn write_log_fast_rev(
+    reader: impl Read + Seek,
+    writer: impl Write,
+    escape_table: Option<&EscapeTable>,
+    encoding: Encoding,
+) -> Result<()> {
+    let mut writer = TranscodingWriter::new(writer, encoding);
+    let mut reader = RevReader::with_capacity(reader, IO_BUF_SIZE)?;
+    let eol_seq_first = *eol::EOL.first().expect("EOL sequence is empty");
+    let mut lines = Vec::new(); // of the current entry, the last one first
+    let mut entry = Vec::new();
+
+    loop {
+        let line = reader.read_until(eol_seq_first, eol::EOL.len());
+        match line {
+            Some(line) if !line.is_empty() => lines.push(line),
+            _ => {
+                if !lines.is_empty() {
+                    entry.clear();
+                    for line in lines.drain(..).rev() {
+                        entry.extend_from_slice(&line);
+                        entry.extend_from_slice(eol::EOL);
+                    }
+                    entry.extend_from_slice(eol::EOL);
+
+                    if let Some(table) = escape_table {
+                        format_special_chars(&entry, &mut writer, table, false, eol::EOL, b"")?;
+                    } else {
+                        writer.write_all(&entry)?;
+                    }
+                }
+                if line.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+const CODE_CYAN: &[u8; 5] = b"\x1B[36m";
+const CODE_GRAY: &[u8; 5] = b"\x1B[37m";
+const CODE_RED: &[u8; 5] = b"\x1B[31m";
+const CODE_RED_BRIGHT: &[u8; 5] = b"\x1B[91m";
+const CODE_WHITE: &[u8; 5] = b"\x1B[97m";
+const CODE_YELLOW: &[u8; 5] = b"\x1B[33m";
+const RESTART_MARKER: &[u8] = b"=== RESTART ===";
+const ESCALATION_MARKER: &[u8] = b"=== ESCALATION ===";
+const DATE_MARKER_FORMAT: &str = "%F";
+const ONLY_TIMESTAMP_FORMAT: &str = "%F %T%.3f";
+const VIM_FOLD_OPEN: &[u8] = b" {{{";
+const VIM_FOLD_CLOSE: &[u8] = b" }}}";
+
+const CODE_EMPHASIS: &[u8; 6] = b"\x1B[1;4m";
+const CODE_NORMAL: &[u8; 4] = b"\x1B[0m";
+
+fn write_log(
+    mut log_entries: impl StreamingIterator<Item = LogEntry>,
+    writer: impl Write,
+    opts: &OutputOptions,
+    input_files: &[PathBuf],
+) -> Result<()> {
+    // JSON output is valid UTF-8 already
+    let encoding = match opts.format {
+        OutputFormat::Text => opts.encoding,
+        _ => Encoding::Passthrough,
+    };
+    // only text is wrapped, before transcoding to count the characters of the input encoding
+    let wrap_columns = opts
+        .wrap_columns
+        .filter(|_| opts.format == OutputFormat::Text);
+    let writer = CountingWriter::new(writer, opts.byte_limit.clone());
+    let mut writer = WrappingWriter::new(TranscodingWriter::new(writer, encoding), wrap_columns)
+        .with_single_byte_chars(encoding == Encoding::Latin1);
+    let color_enabled = opts.color_enabled;
+    let code_normal_eol = [CODE_NORMAL, eol::EOL].concat();
+    let mut body = Vec::new();
+    let mut style = Vec::new();
+    let mut last_timestamp = None;
+    let mut escalated = false;
+    let host_labels: Vec<_> = input_files
+        .iter()
+        .map(|file| {
+            opts.host_pattern
+                .as_ref()
+                .and_then(|pattern| host_label(pattern, file))
+        })
+        .collect();
+
+    opts.interrupt.check_between_entries();
+
+    while let Some(entry) = log_entries.next() {
+        if opts.interrupt.is_requested()
+            || opts
+                .byte_limit
+                .as_ref()
+                .is_some_and(|limit| !limit.allows_entry())
+        {
+            break;
+        }
+
+        if entry.is_separator() {
+            if opts.format == OutputFormat::Text {
+                writer.write_all(entry.contents())?;
+            }
+            continue;
+        }
+
+        if let Some(checkpoint) = &opts.checkpoint {
+            checkpoint.record(entry.timestamp());
+        }
+
+        if opts.format != OutputFormat::Text {
+            let source = input_files
+                .get(entry.source())
+                .filter(|_| input_files.len() > 1)
+                .map(PathBuf::as_path);
+            if opts.format == OutputFormat::Tsv {
+                tsv::write_entry(entry, source, &mut writer, opts)?;
+            } else {
+                json::write_entry(entry, source, &mut writer, opts)?;
+            }
+            writer.write_all(eol::EOL)?;
+            continue;
+        }
+
+        let timestamp = entry.timestamp();
+        let after_gap = match (opts.emphasize_after_gap, timestamp, last_timestamp) {
+            (Some(gap), Some(timestamp), Some(last_timestamp)) => {
+                let elapsed = if timestamp > last_timestamp {
+                    timestamp - last_timestamp
+                } else {
+                    last_timestamp - timestamp
+                };
+                elapsed > gap
+            }
+            _ => false,
+        };
+        let (compact, date_changed) = match (timestamp, last_timestamp) {
+            (Some(timestamp), Some(last_timestamp)) if opts.compact_time => (
+                is_same_hour(timestamp, last_timestamp),
+                timestamp.date() != last_timestamp.date(),
+            ),
+            _ => (false, false),
+        };
+        let restarted = match (timestamp, last_timestamp) {
+            (Some(timestamp), Some(last_timestamp)) if opts.mark_restarts => {
+                timestamp < last_timestamp
+            }
+            _ => false,
+        } || opts
+            .restart_pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(entry.contents()));
+        last_timestamp = timestamp.or(last_timestamp);
+
+        if let Some(timestamp) = timestamp.filter(|_| date_changed) {
+            if color_enabled {
+                writer.write_all(CODE_GRAY)?;
+            }
+            write!(writer, "=== {} ===", timestamp.format(DATE_MARKER_FORMAT))?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+            writer.write_all(eol::EOL)?;
+            writer.write_all(eol::EOL)?;
+        }
+
+        if restarted {
+            if color_enabled {
+                writer.write_all(CODE_CYAN)?;
+            }
+            writer.write_all(RESTART_MARKER)?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+            writer.write_all(eol::EOL)?;
+            writer.write_all(eol::EOL)?;
+        }
+
+        // entries without a level neither escalate nor recover
+        let escalation = match entry.level() {
+            Some(LogLevel::Critical) | Some(LogLevel::Fatal) => {
+                !std::mem::replace(&mut escalated, true)
+            }
+            Some(_) => {
+                escalated = false;
+                false
+            }
+            None => false,
+        };
+
+        if escalation && opts.flag_escalation {
+            if color_enabled {
+                writer.write_all(CODE_RED_BRIGHT)?;
+            }
+            writer.write_all(ESCALATION_MARKER)?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+            writer.write_all(eol::EOL)?;
+            writer.write_all(eol::EOL)?;
+        }
+
+        if let Some(next_gap) = entry.next_gap() {
+            if color_enabled {
+                writer.write_all(CODE_GRAY)?;
+            }
+            writer.write_all(next_gap_prefix(next_gap).as_bytes())?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+        }
+
+        if let Some(elapsed) = entry.elapsed() {
+            if color_enabled {
+                writer.write_all(CODE_GRAY)?;
+            }
+            writer.write_all(elapsed_prefix(elapsed).as_bytes())?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+        }
+
+        if opts.debug_parse {
+            if color_enabled {
+                writer.write_all(CODE_GRAY)?;
+            }
+            write!(writer, "{} ", parse_flags(entry))?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+        }
+
+        if opts.show_size {
+            if color_enabled {
+                writer.write_all(CODE_GRAY)?;
+            }
+            write!(writer, "[{} B] ", entry.contents().len())?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+        }
+
+        let label_code: &[u8] = if opts.color_by_source && input_files.len() > 1 {
+            source_code(entry.source())
+        } else {
+            CODE_CYAN
+        };
+        if let Some(Some(host_label)) = host_labels.get(entry.source()) {
+            if color_enabled {
+                writer.write_all(label_code)?;
+            }
+            write!(writer, "{}: ", host_label)?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+        } else if input_files.len() > 1 {
+            if color_enabled {
+                writer.write_all(label_code)?;
+            }
+            write!(writer, "{}: ", input_files[entry.source()].display())?;
+            if color_enabled {
+                writer.write_all(CODE_NORMAL)?;
+            }
+        }
+
+        let color_code: &[u8] = match opts.color_by {
+            _ if !color_enabled => b"",
+            ColorBy::Level => match entry.level() {
+                Some(LogLevel::Debug) => CODE_GRAY,
+                Some(LogLevel::Info) => CODE_WHITE,
+                Some(LogLevel::Warning) => CODE_YELLOW,
+                Some(LogLevel::Critical) => CODE_RED,
+                Some(LogLevel::Fatal) => CODE_RED_BRIGHT,
+                None => b"",
+            },
+            ColorBy::Category => entry.category().map_or(&b""[..], palette_code),
+            ColorBy::Pid => entry
+                .pid()
+                .map_or(&b""[..], |pid| palette_code(pid.to_string().as_bytes())),
+        };
+
+        style.clear();
+        if after_gap && color_enabled {
+            style.extend_from_slice(CODE_EMPHASIS);
+        }
+        style.extend_from_slice(color_code);
+
+        let rendered = match &opts.template {
+            Some(template) => {
+                let source = input_files.get(entry.source()).map(PathBuf::as_path);
+                let mut rendered =
+                    template.render(entry, source, opts.template_fallback.as_bytes());
+                rendered.extend_from_slice(eol::EOL);
+                Some(rendered)
+            }
+            None if opts.summary_line => {
+                let mut rendered = summary_line(entry);
+                rendered.extend_from_slice(eol::EOL);
+                Some(rendered)
+            }
+            None if opts.align => align_header(entry),
+            None => None,
+        };
+        let contents = match rendered.as_deref() {
+            Some(rendered) => rendered,
+            None if opts.message_only_output => entry.message().unwrap_or_else(|| entry.contents()),
+            None => entry.contents(),
+        };
+
+        let eol: &[u8] = if color_enabled {
+            &code_normal_eol
+        } else {
+            eol::EOL
+        };
+
+        let styled;
+        let decoded = if opts.keep_ansi && !style.is_empty() {
+            styled = restore_style_after_resets(contents, &style);
+            &styled
+        } else {
+            contents
+        };
+
+        body.clear();
+        decode_escapes(
+            decoded,
+            &mut body,
+            opts.escape_mode,
+            &opts.escape_table,
+            eol,
+            &style,
+        )?;
+
+        // the message is the tail of the contents unless rendered by a template
+        let mut header_len = entry
+            .message()
+            .filter(|message| contents.ends_with(message))
+            .map_or(0, |message| contents.len() - message.len());
+
+        if opts.diffable {
+            header_len = mask_header(&mut body, header_len);
+        }
+
+        if compact {
+            header_len = compact_timestamp(&mut body, header_len);
+        }
+
+        for (pattern, replacement) in &opts.replacements {
+            replace_all(&mut body, pattern, replacement.as_bytes(), header_len);
+        }
+
+        if let Some(pattern) = &opts.cut_pattern {
+            cut_at_pattern(&mut body, pattern, header_len);
+        }
+
+        if opts.collapse_traces {
+            collapse_trace(&mut body);
+        }
+
+        if let Some(max_lines) = opts.max_lines_per_entry {
+            truncate_lines(&mut body, max_lines);
+        }
+
+        if let Some(marker) = &opts.flatten {
+            flatten_lines(&mut body, marker.as_bytes());
+        }
+
+        if opts.fold_markers == FoldMarkers::Vim && is_multiline(&body) {
+            add_fold_markers(&mut body, VIM_FOLD_OPEN, VIM_FOLD_CLOSE);
+        }
+
+        writer.write_all(&style)?;
+        writer.write_all(&body)?;
+
+        if color_enabled {
+            writer.write_all(CODE_NORMAL)?;
+        }
+    }
+
+    writer.get_mut().finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::FilteringOptions;
+    use chrono::NaiveDate;
+    use eol;
+
+    const LOREM_IPSUM: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed \
+do eiusmod tempor incididunt ut labore et dolore magna aliqua. In eu mi bibendum neque egestas \
+congue quisque egestas diam. Amet tellus cras adipiscing enim. Feugiat vivamus at augue eget \
+arcu dictum varius duis at. Integer enim neque volutpat ac tincidunt. Lobortis elementum nibh \
+tellus molestie. A iaculis at erat pellentesque. Porttitor rhoncus dolor purus non enim praesent \
+elementum facilisis. Sed blandit libero volutpat sed cras. Consectetur lorem donec massa sapien \
+faucibus et molestie.";
+
+    const ELEMENTUM_EU: &[u8] = b"Elementum eu facilisis sed odio morbi quis commodo odio \
+aenean. Quis eleifend quam adipiscing vitae. A iaculis at erat pellentesque adipiscing. Pulvinar \
+pellentesque habitant morbi tristique senectus et netus et malesuada. Commodo elit at imperdiet \
+dui. Volutpat est velit egestas dui id. Dictum sit amet justo donec enim diam vulputate ut. \
+Blandit libero volutpat sed cras ornare arcu dui vivamus arcu. At urna condimentum mattis \
+pellentesque id nibh tortor id aliquet. Quam pellentesque nec nam aliquam sem et tortor.";
+
+    const DT_FORMAT: &str = "%F %T.%3f";
+
+    fn header(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> Vec<u8> {
+        let buf = format!(
+            "-info:<22954> {} UTC [Category]: ",
+            NaiveDate::from_ymd(year, month, day)
+                .and_hms(hour, min, sec)
+                .format(DT_FORMAT)
+        );
+        buf.into_bytes()
+    }
+
+    #[test]
+    fn write_log_fast_simple_test() -> Result<()> {
+        let in_buf = b"abc\\ndef\\\\ghi".to_vec();
+        let mut out_buf = Vec::<u8>::new();
+
+        write_log_fast(
+            &mut in_buf.as_slice(),
+            &mut out_buf,
+            Some(&EscapeTable::default()),
+            Encoding::Passthrough,
+        )?;
+
+        let mut pattern = b"abc".to_vec();
         pattern.append(&mut eol::EOL.to_vec());
         pattern.append(&mut b"def\\ghi".to_vec());
 
-        assert_eq!(out_buf, pattern);
+        assert_eq!(out_buf, pattern);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_fast_single_entry() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 01, 13, 20, 42, 00));
+        in_buf.append(&mut LOREM_IPSUM.to_vec());
+        in_buf.append(&mut b"\n\n".to_vec());
+
+        let mut out_buf = Vec::<u8>::new();
+
+        write_log_fast(
+            &mut in_buf.as_slice(),
+            &mut out_buf,
+            Some(&EscapeTable::default()),
+            Encoding::Passthrough,
+        )?;
+
+        assert_eq!(out_buf, in_buf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_fast_two_entries() -> Result<()> {
+        let mut in_buf = Vec::new();
+
+        in_buf.append(&mut header(2020, 01, 13, 20, 42, 00));
+        in_buf.append(&mut LOREM_IPSUM.to_vec());
+        in_buf.append(&mut b"\n\n".to_vec());
+
+        in_buf.append(&mut header(2020, 01, 13, 20, 43, 00));
+        in_buf.append(&mut ELEMENTUM_EU.to_vec());
+        in_buf.append(&mut b"\n\n".to_vec());
+
+        let mut out_buf = Vec::<u8>::new();
+
+        write_log_fast(
+            &mut in_buf.as_slice(),
+            &mut out_buf,
+            Some(&EscapeTable::default()),
+            Encoding::Passthrough,
+        )?;
+
+        assert_eq!(out_buf, in_buf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_fast_rev_matches_entry_reverse() -> Result<()> {
+        let mut in_buf = header(2020, 1, 13, 20, 42, 0);
+        in_buf.append(&mut b"First\\nescaped\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        in_buf.append(&mut LOREM_IPSUM.to_vec());
+        in_buf.append(&mut b"\ncontinued\n\n\n".to_vec());
+        in_buf.append(&mut b"No header\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 44, 0));
+        in_buf.append(&mut ELEMENTUM_EU.to_vec());
+        in_buf.append(&mut b"\n\n".to_vec());
+
+        for escape_mode in &[EscapeMode::None, EscapeMode::Standard] {
+            let opts = OutputOptions {
+                escape_mode: *escape_mode,
+                ..Default::default()
+            };
+            let mut expected = Vec::<u8>::new();
+            let reader =
+                LogEntryRevReader::with_capacity(std::io::Cursor::new(&in_buf), eol::EOL, 16)?;
+            write_log(reader, &mut expected, &opts, &[])?;
+
+            let table = EscapeTable::default();
+            let escape_table = Some(&table).filter(|_| *escape_mode != EscapeMode::None);
+            let mut out_buf = Vec::<u8>::new();
+            write_log_fast_rev(
+                std::io::Cursor::new(&in_buf),
+                &mut out_buf,
+                escape_table,
+                Encoding::Passthrough,
+            )?;
+
+            assert_eq!(
+                String::from_utf8_lossy(&out_buf),
+                String::from_utf8_lossy(&expected)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_encoding() -> Result<()> {
+        let mut in_buf = header(2020, 1, 13, 20, 42, 0);
+        in_buf.append(&mut b"Caf\xE9 \xC3\xA9\n\n".to_vec());
+
+        let write = |encoding: Encoding| -> Result<Vec<u8>> {
+            let opts = OutputOptions {
+                encoding,
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+            write_log(reader, &mut out_buf, &opts, &[])?;
+            Ok(out_buf)
+        };
+        let message = |output: Vec<u8>| output[header(2020, 1, 13, 20, 42, 0).len()..].to_vec();
+
+        assert_eq!(
+            message(write(Encoding::Utf8Lossy)?),
+            "Caf\u{FFFD} é\n\n".as_bytes()
+        );
+        assert_eq!(message(write(Encoding::Latin1)?), "Café Ã©\n\n".as_bytes());
+        assert_eq!(write(Encoding::Passthrough)?, in_buf);
+
+        let mut out_buf = Vec::<u8>::new();
+        write_log_fast(in_buf.as_slice(), &mut out_buf, None, Encoding::Utf8Lossy)?;
+        assert_eq!(message(out_buf), "Caf\u{FFFD} é\n\n".as_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_single_entry_uncolored() -> Result<()> {
+        let mut contents = Vec::new();
+        contents.append(&mut header(2020, 01, 13, 20, 42, 00));
+        contents.append(&mut LOREM_IPSUM.to_vec());
+        contents.append(&mut b"\n\n".to_vec());
+
+        let entry = LogEntry::from_contents(contents.clone());
+        let entries = vec![entry];
+        let input_files = [PathBuf::from("logname")];
+
+        let mut out_buf = Vec::<u8>::new();
+
+        write_log(
+            streaming_iterator::convert(entries),
+            &mut out_buf,
+            &OutputOptions {
+                escape_mode: EscapeMode::Standard,
+                ..Default::default()
+            },
+            &input_files,
+        )?;
+
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut contents);
+        assert_eq!(out_buf, in_buf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_two_entries_uncolored() -> Result<()> {
+        let mut contents1 = Vec::new();
+        contents1.append(&mut header(2020, 01, 13, 20, 42, 00));
+        contents1.append(&mut LOREM_IPSUM.to_vec());
+        contents1.append(&mut b"\n\n".to_vec());
+        let entry1 = LogEntry::from_contents(contents1.clone());
+
+        let mut contents2 = Vec::new();
+        contents2.append(&mut header(2020, 01, 13, 20, 43, 00));
+        contents2.append(&mut ELEMENTUM_EU.to_vec());
+        contents2.append(&mut b"\n\n".to_vec());
+        let entry2 = LogEntry::from_contents(contents2.clone());
+
+        let entries = vec![entry1, entry2];
+        let input_files = [PathBuf::from("logname")];
+
+        let mut out_buf = Vec::<u8>::new();
+
+        write_log(
+            streaming_iterator::convert(entries),
+            &mut out_buf,
+            &OutputOptions {
+                escape_mode: EscapeMode::Standard,
+                ..Default::default()
+            },
+            &input_files,
+        )?;
+
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut contents1);
+        in_buf.append(&mut contents2);
+        assert_eq!(out_buf, in_buf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_template() -> Result<()> {
+        let mut contents = Vec::new();
+        contents.append(&mut header(2020, 1, 13, 20, 42, 0));
+        contents.append(&mut b"Line\\nNext\n\n".to_vec());
+        let entries = vec![LogEntry::from_contents(contents)];
+        let input_files = [PathBuf::from("logname")];
+
+        let mut out_buf = Vec::<u8>::new();
+
+        write_log(
+            streaming_iterator::convert(entries),
+            &mut out_buf,
+            &OutputOptions {
+                escape_mode: EscapeMode::Standard,
+                template: template::Template::parse("{level}@{source}: {message}"),
+                ..Default::default()
+            },
+            &input_files,
+        )?;
+
+        let pattern = [&b"info@logname: Line"[..], eol::EOL, b"Next", eol::EOL].concat();
+        assert_eq!(out_buf, pattern);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_entries_json_fill_timestamps() -> Result<()> {
+        let mut contents = Vec::new();
+        contents.append(&mut header(2020, 1, 13, 20, 42, 0));
+        contents.append(&mut b"Exception\n\n".to_vec());
+        let entries = vec![
+            LogEntry::from_contents(contents),
+            LogEntry::from_contents(b"  at frame\n\n".to_vec()),
+        ];
+
+        let opts = Options {
+            transforming_options: cli::TransformingOptions {
+                fill_timestamps: true,
+                ..Default::default()
+            },
+            output_options: OutputOptions {
+                format: OutputFormat::Json,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        write_entries(streaming_iterator::convert(entries), &mut out_buf, &opts)?;
+
+        let out_buf = String::from_utf8(out_buf).unwrap();
+        let lines: Vec<_> = out_buf.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"timestamp":"2020-01-13T20:42:00.000Z","timestamp_raw":"2020-01-13 20:42:00.000","level":"info""#));
+        assert!(lines[1].starts_with(
+            r#"{"timestamp":"2020-01-13T20:42:00.000Z","timestamp_raw":null,"level":null"#
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_entries_head() -> Result<()> {
+        let entries = vec![
+            LogEntry::from_contents(b"First\n\n".to_vec()),
+            LogEntry::from_contents(b"Second\n\n".to_vec()),
+            LogEntry::from_contents(b"Third\n\n".to_vec()),
+        ];
+
+        let opts = Options {
+            head: Some(2),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        write_entries(streaming_iterator::convert(entries), &mut out_buf, &opts)?;
+
+        assert_eq!(out_buf, b"First\n\nSecond\n\n".to_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_log_join_continuations_with_level_filter() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("riolog-{}-join.log", std::process::id()));
+        let critical: &[u8] = b"-critical:<1> 2020-01-13 20:42:00.000 UTC [A]: Failed\n\n";
+        let mut file_buf = critical.to_vec();
+        file_buf.append(&mut b"  at frame1\n\n".to_vec());
+        file_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        file_buf.append(&mut b"Retrying\n\n".to_vec());
+        file_buf.append(&mut b"  at frame2\n\n".to_vec());
+        std::fs::write(&path, &file_buf)?;
+
+        let run = |reverse: bool| -> Result<Vec<u8>> {
+            let opts = Options {
+                input_files: vec![path.clone()],
+                reverse,
+                filtering_options: FilteringOptions {
+                    min_level: Some(LogLevel::Critical),
+                    ..Default::default()
+                },
+                transforming_options: cli::TransformingOptions {
+                    join_continuations: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            read_log(&mut out_buf, opts)?;
+            Ok(out_buf)
+        };
+        let forward = run(false);
+        let reverse = run(true);
+        std::fs::remove_file(&path)?;
+
+        let pattern = [eol::trim_eol(critical), b"\n  at frame1\n\n"].concat();
+        assert_eq!(
+            String::from_utf8_lossy(&forward?),
+            String::from_utf8_lossy(&pattern)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&reverse?),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_log_head_and_tail_with_filter() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("riolog-{}-head-tail.log", std::process::id()));
+        let mut file_buf = Vec::new();
+        for min in 0..10 {
+            file_buf.append(&mut header(2020, 1, 13, 20, min, 0));
+            let parity = if min % 2 == 0 { "Even" } else { "Odd" };
+            file_buf.append(&mut format!("{} {}\n\n", parity, min).into_bytes());
+        }
+        std::fs::write(&path, &file_buf)?;
+
+        let opts = Options {
+            input_files: vec![path.clone()],
+            filtering_options: FilteringOptions {
+                contains: Some("Even".to_string()),
+                ..Default::default()
+            },
+            head: Some(2),
+            tail: Some(1),
+            ..Default::default()
+        };
+        let mut out_buf = Vec::<u8>::new();
+        let result = read_log(&mut out_buf, opts);
+        std::fs::remove_file(&path)?;
+        result?;
+
+        let mut pattern = Vec::new();
+        for min in [0, 2] {
+            pattern.append(&mut header(2020, 1, 13, 20, min, 0));
+            pattern.append(&mut format!("Even {}\n\n", min).into_bytes());
+        }
+        pattern.append(&mut b"... 2 entries elided ...\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 8, 0));
+        pattern.append(&mut b"Even 8\n\n".to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_log_resume_token() -> Result<()> {
+        let temp_path = |name: &str| {
+            std::env::temp_dir().join(format!("riolog-{}-resume-{}", std::process::id(), name))
+        };
+        let paths = [temp_path("a.log"), temp_path("b.log")];
+        let token_path = temp_path("token");
+        for (source, path) in paths.iter().enumerate() {
+            let mut file_buf = Vec::new();
+            for min in 0..3 {
+                file_buf.append(&mut header(2020, 1, 13, 20, min * 2 + source as u32, 0));
+                file_buf.append(&mut format!("Entry {}\n\n", min * 2 + source as u32).into_bytes());
+            }
+            std::fs::write(path, &file_buf)?;
+        }
+
+        let run = |head: Option<usize>| -> Result<String> {
+            let resume = match std::fs::read_to_string(&token_path) {
+                Ok(token) => ResumeToken::parse(&token).expect("Invalid resume token"),
+                Err(_) => ResumeToken::default(),
+            };
+            let opts = Options {
+                input_files: paths.to_vec(),
+                head,
+                resume,
+                resume_token_file: Some(token_path.clone()),
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            read_log(&mut out_buf, opts)?;
+            Ok(String::from_utf8_lossy(&out_buf).into_owned())
+        };
+        let entries = |output: &str| -> Vec<String> {
+            output
+                .lines()
+                .filter_map(|line| line.split(": ").last())
+                .filter(|message| message.starts_with("Entry"))
+                .map(String::from)
+                .collect()
+        };
+
+        let first = run(Some(3))?;
+        let second = run(None)?;
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&paths[1])?
+            .write_all(&[header(2020, 1, 13, 20, 10, 0), b"Entry 10\n\n".to_vec()].concat())?;
+        let third = run(None)?;
+        let token = std::fs::read_to_string(&token_path)?;
+
+        for path in paths.iter().chain(std::iter::once(&token_path)) {
+            std::fs::remove_file(path)?;
+        }
+
+        assert_eq!(entries(&first), ["Entry 0", "Entry 1", "Entry 2"]);
+        assert_eq!(entries(&second), ["Entry 3", "Entry 4", "Entry 5"]);
+        assert_eq!(entries(&third), ["Entry 10"]);
+        let entry_len = (header(2020, 1, 13, 20, 0, 0).len() + b"Entry 0\n\n".len()) as u64;
+        assert_eq!(
+            ResumeToken::parse(&token),
+            Some(ResumeToken::new(vec![3 * entry_len, 4 * entry_len + 1]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_notifies_without_filters() -> Result<()> {
+        let notifier = Rc::new(notify::LevelNotifier::new(LogLevel::Fatal));
+        let opts = Options {
+            notifier: Some(notifier.clone()),
+            ..Default::default()
+        };
+        let in_buf: &[u8] = b"-info:<1> 2020-01-13 20:42:00.000 UTC [A]: B\n\n\
+                              -fatal:<1> 2020-01-13 20:42:01.000 UTC [A]: C\n\n";
+
+        let mut out_buf = Vec::<u8>::new();
+        read_stream(in_buf, &mut out_buf, &opts, &Rc::default())?;
+
+        assert_eq!(out_buf, in_buf);
+        assert!(notifier.alerted());
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_follow_rate_summary() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("riolog-{}-summary.log", std::process::id()));
+        let mut file_buf = Vec::new();
+        for (level, sec) in [("info", 0), ("warning", 1), ("critical", 2), ("info", 3)] {
+            file_buf.append(
+                &mut format!(
+                    "-{}:<1> 2020-01-13 20:42:0{}.000 UTC [A]: B\n\n",
+                    level, sec
+                )
+                .into_bytes(),
+            );
+        }
+        std::fs::write(&path, &file_buf)?;
+
+        // the interrupt ends following once the data written so far is read
+        let interrupt = Interrupt::default();
+        interrupt.request();
+        let reader = FollowReader::open(&path, std::time::Duration::from_millis(1))?
+            .with_interrupt(interrupt);
+        let opts = Options {
+            follow: true,
+            input_files: vec![path.clone()],
+            rate_summary: Some(Rc::new(summary::RateSummary::new())),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        read_stream(BufReader::new(reader), &mut out_buf, &opts, &Rc::default())?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(out_buf, file_buf);
+        let rate_summary = opts.rate_summary.expect("No summary");
+        assert_eq!(
+            rate_summary.render(std::time::Duration::from_secs(2)),
+            "4 entries in 2.0s (2.0/s): debug=0 info=2 warning=1 critical=1 fatal=0"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_log_selfcheck() -> Result<()> {
+        let temp_path = |name: &str| {
+            std::env::temp_dir().join(format!("riolog-{}-selfcheck-{}", std::process::id(), name))
+        };
+        let paths = [temp_path("a.log"), temp_path("b.log"), temp_path("c.log")];
+        for (source, path) in paths.iter().enumerate() {
+            let mut file_buf = Vec::new();
+            for min in 0..4 {
+                file_buf.append(&mut header(2020, 1, 13, 20, min * 3 + source as u32 % 2, 0));
+                file_buf.append(&mut format!("Entry {}\n\n", min).into_bytes());
+            }
+            std::fs::write(path, &file_buf)?;
+        }
+
+        let run = |reverse: bool| -> Result<String> {
+            let opts = Options {
+                input_files: paths.to_vec(),
+                selfcheck: true,
+                reverse,
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            read_log(&mut out_buf, opts)?;
+            Ok(String::from_utf8_lossy(&out_buf).into_owned())
+        };
+        let forward = run(false);
+        let reverse = run(true);
+
+        for path in &paths {
+            std::fs::remove_file(path)?;
+        }
+
+        assert_eq!(
+            forward?,
+            "Self-check passed: 12 entries merged identically\n"
+        );
+        assert_eq!(
+            reverse?,
+            "Self-check passed: 12 entries merged identically\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_log_selfcheck_with_separator() -> Result<()> {
+        let temp_path = |name: &str| {
+            std::env::temp_dir().join(format!(
+                "riolog-{}-selfcheck-sep-{}",
+                std::process::id(),
+                name
+            ))
+        };
+        let paths = [temp_path("a.log"), temp_path("b.log")];
+        for (source, path) in paths.iter().enumerate() {
+            let mut file_buf = Vec::new();
+            for min in 0..3 {
+                file_buf.append(&mut header(2020, 1, 13, 20, min * 2 + source as u32, 0));
+                file_buf.append(&mut format!("Entry {}\n", min).into_bytes());
+            }
+            std::fs::write(path, &file_buf)?;
+        }
+
+        // entries are not separated by blank lines, but start with headers
+        let opts = Options {
+            input_files: paths.to_vec(),
+            selfcheck: true,
+            separator: EntrySeparator::Header,
+            ..Default::default()
+        };
+        let mut out_buf = Vec::<u8>::new();
+        let result = read_log(&mut out_buf, opts);
+        for path in &paths {
+            std::fs::remove_file(path)?;
+        }
+        result?;
+
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            "Self-check passed: 6 entries merged identically\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_log_index_since() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("riolog-{}-index-since.log", std::process::id()));
+        let mut file_buf = Vec::new();
+        for min in 0..4 {
+            file_buf.append(&mut header(2020, 1, 13, 20, min, 0));
+            file_buf.append(&mut format!("Entry {}\n\n", min).into_bytes());
+        }
+        std::fs::write(&path, &file_buf)?;
+
+        let run = |index: bool, reverse: bool, last_per_level: Option<usize>| -> Result<String> {
+            let opts = Options {
+                input_files: vec![path.clone()],
+                index,
+                reverse,
+                last_per_level,
+                filtering_options: FilteringOptions {
+                    since: Some(NaiveDate::from_ymd(2020, 1, 13).and_hms(20, 2, 0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            read_log(&mut out_buf, opts)?;
+            Ok(String::from_utf8_lossy(&out_buf).into_owned())
+        };
+        let indexed = run(true, false, None);
+        let sidecar = crate::index::sidecar_path(&path);
+        let sidecar_exists = sidecar.exists();
+        let plain = run(false, false, None);
+        let modes = [(true, None), (false, Some(1)), (true, Some(1))];
+        let reversed: Vec<_> = modes
+            .iter()
+            .map(|&(reverse, n)| Ok((run(true, reverse, n)?, run(false, reverse, n)?)))
+            .collect();
+
+        std::fs::remove_file(&path)?;
+        std::fs::remove_file(&sidecar)?;
+
+        let indexed = indexed?;
+        assert!(sidecar_exists);
+        assert_eq!(indexed, plain?);
+        assert!(indexed.contains("Entry 2") && !indexed.contains("Entry 1"));
+        let reversed = reversed.into_iter().collect::<Result<Vec<_>>>()?;
+        assert!(reversed[0]
+            .0
+            .as_bytes()
+            .starts_with(&header(2020, 1, 13, 20, 3, 0)));
+        assert!(reversed[0].0.contains("Entry 2") && !reversed[0].0.contains("Entry 1"));
+        assert!(reversed[1].0.contains("Entry 3") && !reversed[1].0.contains("Entry 2"));
+        for (indexed, plain) in &reversed {
+            assert_eq!(indexed, plain);
+        }
+        Ok(())
+    }
+
+    // output and standard error of a run reading the file
+    fn run_capturing_stderr(opts: Options) -> Result<(String, String)> {
+        let mut out_buf = Vec::<u8>::new();
+        let mut err_buf = Vec::<u8>::new();
+        read_log_reporting(&mut out_buf, &mut err_buf, opts)?;
+        Ok((
+            String::from_utf8_lossy(&out_buf).into_owned(),
+            String::from_utf8_lossy(&err_buf).into_owned(),
+        ))
+    }
+
+    #[test]
+    fn read_log_quiet() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("riolog-{}-quiet.log", std::process::id()));
+        let mut file_buf = header(2020, 1, 13, 20, 42, 0);
+        file_buf.append(&mut b"First\n\n".to_vec());
+        file_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        file_buf.append(&mut b"Second\n\n".to_vec());
+        std::fs::write(&path, &file_buf)?;
+
+        let opts = |quiet: bool| Options {
+            input_files: vec![path.clone()],
+            explain_filters: true,
+            rate_summary: Some(Rc::new(crate::summary::RateSummary::new())),
+            quiet,
+            filtering_options: FilteringOptions {
+                since: Some(NaiveDate::from_ymd(2020, 1, 13).and_hms(20, 43, 0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let quiet = run_capturing_stderr(opts(true));
+        let verbose = run_capturing_stderr(opts(false));
+        std::fs::remove_file(&path)?;
+
+        let (quiet_out, quiet_err) = quiet?;
+        let (verbose_out, verbose_err) = verbose?;
+        assert_eq!(quiet_err, "");
+        assert!(!verbose_err.is_empty());
+        assert_eq!(quiet_out, verbose_out);
+        assert!(quiet_out.contains("Second") && !quiet_out.contains("First"));
+        Ok(())
+    }
+
+    #[test]
+    fn read_log_jsonl_input() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("riolog-{}-input.jsonl", std::process::id()));
+        std::fs::write(
+            &path,
+            "{\"level\":\"info\",\"ts\":\"2020-01-13T20:42:00.000Z\",\"msg\":\"Started\"}\n\
+             {\"level\":\"error\",\"ts\":\"2020-01-13T20:43:00.000Z\",\"msg\":\"Failed C:\\\\new\\\\table\"}\n\
+             {\"level\":\"fatal\",\"ts\":\"2020-01-13T20:44:00.000Z\",\"msg\":\"Crashed\"}\n",
+        )?;
+
+        let opts = Options {
+            input_files: vec![path.clone()],
+            input_format: InputFormat::JsonLines,
+            output_options: OutputOptions {
+                escape_mode: EscapeMode::Standard,
+                ..Default::default()
+            },
+            filtering_options: FilteringOptions {
+                min_level: Some(LogLevel::Critical),
+                until: Some(NaiveDate::from_ymd(2020, 1, 13).and_hms(20, 44, 0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut out_buf = Vec::<u8>::new();
+        let res = read_log(&mut out_buf, opts);
+        std::fs::remove_file(&path)?;
+        res?;
+
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            "-critical:<> 2020-01-13 20:43:00.000 UTC []: Failed C:\\new\\table\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merge_forward_stdin_with_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("riolog-{}-merge.log", std::process::id()));
+        let mut file_buf = header(2020, 1, 13, 20, 42, 0);
+        file_buf.append(&mut b"File 1\n\n".to_vec());
+        file_buf.append(&mut header(2020, 1, 13, 20, 44, 0));
+        file_buf.append(&mut b"File 2\n\n".to_vec());
+        std::fs::write(&path, &file_buf)?;
+
+        let mut stdin_buf = header(2020, 1, 13, 20, 43, 0);
+        stdin_buf.append(&mut b"Stdin 1\n\n".to_vec());
+        stdin_buf.append(&mut header(2020, 1, 13, 20, 45, 0));
+        stdin_buf.append(&mut b"Stdin 2\n\n".to_vec());
+
+        let readers: Vec<Box<dyn BufRead>> = vec![
+            Box::new(io::Cursor::new(stdin_buf)),
+            Box::new(BufReader::new(File::open(&path)?)),
+        ];
+        let opts = Options {
+            input_files: vec![PathBuf::from("-"), path.clone()],
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        merge_forward(readers, &mut out_buf, &opts, &Rc::default())?;
+        std::fs::remove_file(&path)?;
+
+        let file_prefix = format!("{}: ", path.display()).into_bytes();
+        let mut pattern = file_prefix.clone();
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 0));
+        pattern.append(&mut b"File 1\n\n-: ".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 43, 0));
+        pattern.append(&mut b"Stdin 1\n\n".to_vec());
+        pattern.append(&mut file_prefix.clone());
+        pattern.append(&mut header(2020, 1, 13, 20, 44, 0));
+        pattern.append(&mut b"File 2\n\n-: ".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 45, 0));
+        pattern.append(&mut b"Stdin 2\n\n".to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_reverse_buffer() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"First\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        in_buf.append(&mut b"Second\n\n".to_vec());
+
+        let opts = Options {
+            reverse: true,
+            reverse_buffer: true,
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        read_stream(io::Cursor::new(in_buf), &mut out_buf, &opts, &Rc::default())?;
+
+        let mut pattern = header(2020, 1, 13, 20, 43, 0);
+        pattern.append(&mut b"Second\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 0));
+        pattern.append(&mut b"First\n\n".to_vec());
+        assert_eq!(out_buf, pattern);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_reverse_buffer_spilled() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"First\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        in_buf.append(&mut b"Second\nline\n\n".to_vec());
+
+        let opts = Options {
+            reverse: true,
+            reverse_buffer: true,
+            memory_limit: Some(16),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        read_stream(io::Cursor::new(in_buf), &mut out_buf, &opts, &Rc::default())?;
+
+        let mut pattern = header(2020, 1, 13, 20, 43, 0);
+        pattern.append(&mut b"Second\nline\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 0));
+        pattern.append(&mut b"First\n\n".to_vec());
+        assert_eq!(out_buf, pattern);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_sort_by_spilled() -> Result<()> {
+        let mut in_buf = Vec::new();
+        for (min, text) in &[(44, "Third"), (42, "First"), (43, "Second\nline")] {
+            in_buf.append(&mut header(2020, 1, 13, 20, *min, 0));
+            in_buf.append(&mut format!("{}\n\n", text).into_bytes());
+        }
+
+        let opts = Options {
+            sort_by: Some(cli::SortKey::Timestamp),
+            memory_limit: Some(16),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        read_stream(io::Cursor::new(in_buf), &mut out_buf, &opts, &Rc::default())?;
+
+        let mut pattern = header(2020, 1, 13, 20, 42, 0);
+        pattern.append(&mut b"First\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 43, 0));
+        pattern.append(&mut b"Second\nline\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 44, 0));
+        pattern.append(&mut b"Third\n\n".to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_stream_max_age_equals_since() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"First\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 21, 30, 0));
+        in_buf.append(&mut b"Second\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 21, 50, 0));
+        in_buf.append(&mut b"Third\n\n".to_vec());
+
+        let since_opts = Options {
+            filtering_options: FilteringOptions {
+                since: Some(NaiveDate::from_ymd(2020, 1, 13).and_hms(20, 50, 0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut since_out_buf = Vec::<u8>::new();
+        read_stream(
+            io::Cursor::new(in_buf.clone()),
+            &mut since_out_buf,
+            &since_opts,
+            &Rc::default(),
+        )?;
+
+        for memory_limit in &[None, Some(16)] {
+            let opts = Options {
+                memory_limit: *memory_limit,
+                filtering_options: FilteringOptions {
+                    max_age: Some(chrono::Duration::hours(1)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            read_stream(
+                io::Cursor::new(in_buf.clone()),
+                &mut out_buf,
+                &opts,
+                &Rc::default(),
+            )?;
+            assert_eq!(out_buf, since_out_buf);
+        }
+
+        let mut pattern = header(2020, 1, 13, 21, 30, 0);
+        pattern.append(&mut b"Second\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 21, 50, 0));
+        pattern.append(&mut b"Third\n\n".to_vec());
+        assert_eq!(since_out_buf, pattern);
+
+        Ok(())
+    }
+
+    fn selector_fixture() -> Vec<u8> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"First\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        in_buf.append(&mut b"Second\nline\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 44, 0));
+        in_buf.append(&mut b"Third\n\n".to_vec());
+        in_buf
+    }
+
+    #[test]
+    fn write_selected_entry_at_offset() -> Result<()> {
+        let in_buf = selector_fixture();
+        let offset = header(2020, 1, 13, 20, 42, 0).len() + b"First\n\n".len();
+
+        let mut reader = io::Cursor::new(in_buf);
+        reader.seek(SeekFrom::Start(offset as u64))?;
+
+        let selector = EntrySelector::Offset(offset as u64);
+        let mut out_buf = Vec::<u8>::new();
+        write_selected_entry(reader, &mut out_buf, &Options::default(), selector)?;
+
+        let mut pattern = header(2020, 1, 13, 20, 43, 0);
+        pattern.append(&mut b"Second\nline\n\n".to_vec());
+        assert_eq!(out_buf, pattern);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_selected_entry_at_number() -> Result<()> {
+        let reader = io::Cursor::new(selector_fixture());
+
+        let mut out_buf = Vec::<u8>::new();
+        write_selected_entry(
+            reader,
+            &mut out_buf,
+            &Options::default(),
+            EntrySelector::Number(3),
+        )?;
+
+        let mut pattern = header(2020, 1, 13, 20, 44, 0);
+        pattern.append(&mut b"Third\n\n".to_vec());
+        assert_eq!(out_buf, pattern);
+
+        let reader = io::Cursor::new(selector_fixture());
+        let mut out_buf = Vec::<u8>::new();
+        write_selected_entry(
+            reader,
+            &mut out_buf,
+            &Options::default(),
+            EntrySelector::Number(4),
+        )?;
+        assert!(out_buf.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_message_only_output() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"First\\nline\n\n".to_vec());
+        in_buf.append(&mut b"No header\n\n".to_vec());
+
+        let opts = OutputOptions {
+            escape_mode: EscapeMode::Standard,
+            message_only_output: true,
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            "First\nline\n\nNo header\n\n"
+        );
 
         Ok(())
     }
 
     #[test]
-    fn write_log_fast_single_entry() -> Result<()> {
+    fn write_log_show_size() -> Result<()> {
         let mut in_buf = Vec::new();
-        in_buf.append(&mut header(2020, 01, 13, 20, 42, 00));
-        in_buf.append(&mut LOREM_IPSUM.to_vec());
-        in_buf.append(&mut b"\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"First\n\n".to_vec());
+        in_buf.append(&mut b"No header\n\n".to_vec());
 
-        let mut out_buf = Vec::<u8>::new();
+        let opts = OutputOptions {
+            show_size: true,
+            ..Default::default()
+        };
 
-        write_log_fast(&mut in_buf.as_slice(), &mut out_buf, true)?;
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
 
-        assert_eq!(out_buf, in_buf);
+        let first_len = header(2020, 1, 13, 20, 42, 0).len() + b"First\n\n".len();
+        let mut pattern = format!("[{} B] ", first_len).into_bytes();
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 0));
+        pattern.append(&mut b"First\n\n[11 B] No header\n\n".to_vec());
+        assert_eq!(out_buf, pattern);
 
         Ok(())
     }
 
     #[test]
-    fn write_log_fast_two_entries() -> Result<()> {
-        let mut in_buf = Vec::new();
-
-        in_buf.append(&mut header(2020, 01, 13, 20, 42, 00));
-        in_buf.append(&mut LOREM_IPSUM.to_vec());
-        in_buf.append(&mut b"\n\n".to_vec());
+    fn write_cardinality_categories() -> Result<()> {
+        let in_buf: &[u8] = b"-info:<1> 2020-01-01 20:00:00.000 UTC [Net]: A\n\n\
+-debug:<2> 2020-01-01 20:00:01.000 UTC [Db]: B\n\n\
+-info:<1> 2020-01-01 20:00:02.000 UTC [Net]: C\n\n\
+No header\n\n\
+-warning:<3> 2020-01-01 20:00:03.000 UTC [Auth]: D\n\n";
 
-        in_buf.append(&mut header(2020, 01, 13, 20, 43, 00));
-        in_buf.append(&mut ELEMENTUM_EU.to_vec());
-        in_buf.append(&mut b"\n\n".to_vec());
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf, eol::EOL);
+        write_cardinality(reader, &mut out_buf, DistinctField::Category, false)?;
+        assert_eq!(String::from_utf8_lossy(&out_buf), "3\n");
 
         let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf, eol::EOL);
+        write_cardinality(reader, &mut out_buf, DistinctField::Category, true)?;
+        assert_eq!(String::from_utf8_lossy(&out_buf), "3\nAuth\nDb\nNet\n");
 
-        write_log_fast(&mut in_buf.as_slice(), &mut out_buf, true)?;
+        Ok(())
+    }
 
-        assert_eq!(out_buf, in_buf);
+    #[test]
+    fn write_category_counts_with_uncategorized() -> Result<()> {
+        let in_buf: &[u8] = b"-info:<1> 2020-01-01 20:00:00.000 UTC [net.http]: A\n\n\
+-debug:<2> 2020-01-01 20:00:01.000 UTC [db]: B\n\n\
+-info:<1> 2020-01-01 20:00:02.000 UTC [net.http]: C\n\n\
+No header\n\n\
+-warning:<3> 2020-01-01 20:00:03.000 UTC [net.dns]: D\n\n\
+-info:<1> 2020-01-01 20:00:04.000 UTC [net.http]: E\n\n";
 
+        let mut out_buf = Vec::<u8>::new();
+        write_category_counts(LogEntryReader::new(in_buf, eol::EOL), &mut out_buf)?;
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            "3 net.http\n1 (none)\n1 db\n1 net.dns\n"
+        );
         Ok(())
     }
 
     #[test]
-    fn write_log_single_entry_uncolored() -> Result<()> {
-        let mut contents = Vec::new();
-        contents.append(&mut header(2020, 01, 13, 20, 42, 00));
-        contents.append(&mut LOREM_IPSUM.to_vec());
-        contents.append(&mut b"\n\n".to_vec());
+    fn write_entries_only_timestamps_and_levels() -> Result<()> {
+        let in_buf: &[u8] = b"-info:<1> 2020-01-01 20:00:00.000 UTC [net.http]: A\n\n\
+No header\n\n\
+-warning:<3> 2020-01-01 20:00:03.250 UTC [net.dns]: D\n  at\tresolve\r\n\n\
+-info:<1> 2020-01-01 20:00:04.000 UTC [net.http]: E\n\n";
+        let run = |only: OnlyField, only_blank_missing: bool| -> Result<String> {
+            let opts = Options {
+                only: Some(only),
+                only_blank_missing,
+                head: Some(3),
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            write_entries(LogEntryReader::new(in_buf, eol::EOL), &mut out_buf, &opts)?;
+            Ok(String::from_utf8_lossy(&out_buf).into_owned())
+        };
 
-        let entry = LogEntry::from_contents(contents.clone());
-        let entries = vec![entry];
-        let input_files = [PathBuf::from("logname")];
+        assert_eq!(
+            run(OnlyField::Timestamps, false)?,
+            "2020-01-01 20:00:00.000\n2020-01-01 20:00:03.250\n"
+        );
+        assert_eq!(run(OnlyField::Levels, true)?, "info\n\nwarning\n");
+        assert_eq!(run(OnlyField::Messages, false)?, "A\nD at resolve\n");
+        Ok(())
+    }
+
+    #[test]
+    fn write_match_counts_per_file() -> Result<()> {
+        let entries = vec![
+            LogEntry::from_contents(
+                b"-info:<1> 2020-01-01 20:00:00.000 UTC [Net]: timeout, retrying after timeout\n\n"
+                    .to_vec(),
+            ),
+            LogEntry::from_contents(
+                b"-info:<1> 2020-01-01 20:00:01.000 UTC [Net]: ok\n\n".to_vec(),
+            ),
+            LogEntry::from_contents(
+                b"-info:<1> 2020-01-01 20:00:02.000 UTC [Net]: timeout\n\n".to_vec(),
+            )
+            .with_source(1),
+        ];
+        let counter = MatchCounter::new("timeout", false);
 
         let mut out_buf = Vec::<u8>::new();
+        let log_entries = streaming_iterator::convert(entries.clone().into_iter().take(2));
+        write_match_counts(log_entries, &mut out_buf, &counter, &[])?;
+        assert_eq!(String::from_utf8_lossy(&out_buf), "2\n");
 
-        write_log(
+        let mut out_buf = Vec::<u8>::new();
+        let files = [PathBuf::from("a.log"), PathBuf::from("b.log")];
+        write_match_counts(
             streaming_iterator::convert(entries),
             &mut out_buf,
-            false,
-            true,
-            &input_files,
+            &counter,
+            &files,
         )?;
+        assert_eq!(String::from_utf8_lossy(&out_buf), "3\na.log: 2\nb.log: 1\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_diffable() -> Result<()> {
+        let opts = OutputOptions {
+            diffable: true,
+            ..Default::default()
+        };
+
+        let mut outputs = Vec::new();
+        for in_buf in [
+            &b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Started\n\n"[..],
+            b"-info:<923> 2020-03-05 07:12:45.789 UTC [A]: Started\n\n",
+        ] {
+            let mut out_buf = Vec::<u8>::new();
+            let reader = LogEntryReader::new(in_buf, eol::EOL);
+            write_log(reader, &mut out_buf, &opts, &[])?;
+            outputs.push(out_buf);
+        }
+
+        assert_eq!(outputs[0], outputs[1]);
+        assert_eq!(
+            String::from_utf8_lossy(&outputs[0]),
+            "-info:<0> YYYY-MM-DD hh:mm:ss.sss UTC [A]: Started\n\n"
+        );
+
+        Ok(())
+    }
 
+    #[test]
+    fn write_log_compact_time() -> Result<()> {
         let mut in_buf = Vec::new();
-        in_buf.append(&mut contents);
-        assert_eq!(out_buf, in_buf);
+        for (day, hour, min, sec) in [
+            (13, 20, 42, 0),
+            (13, 20, 42, 5),
+            (13, 21, 0, 0),
+            (14, 21, 0, 1),
+        ] {
+            in_buf.append(&mut header(2020, 1, day, hour, min, sec));
+            in_buf.append(&mut b"Message\n\n".to_vec());
+        }
+
+        let opts = OutputOptions {
+            compact_time: true,
+            ..Default::default()
+        };
 
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = header(2020, 1, 13, 20, 42, 0);
+        pattern.append(&mut b"Message\n\n".to_vec());
+        pattern.append(&mut b"-info:<22954> :42:05.000 UTC [Category]: Message\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 21, 0, 0));
+        pattern.append(&mut b"Message\n\n".to_vec());
+        pattern.append(&mut b"=== 2020-01-14 ===\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 14, 21, 0, 1));
+        pattern.append(&mut b"Message\n\n".to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
         Ok(())
     }
 
     #[test]
-    fn write_log_two_entries_uncolored() -> Result<()> {
-        let mut contents1 = Vec::new();
-        contents1.append(&mut header(2020, 01, 13, 20, 42, 00));
-        contents1.append(&mut LOREM_IPSUM.to_vec());
-        contents1.append(&mut b"\n\n".to_vec());
-        let entry1 = LogEntry::from_contents(contents1.clone());
+    fn write_log_summary_line() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"Exception\n  at a\n  at b\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 1));
+        in_buf.append(&mut b"Single\n\n".to_vec());
+
+        let opts = OutputOptions {
+            summary_line: true,
+            ..Default::default()
+        };
 
-        let mut contents2 = Vec::new();
-        contents2.append(&mut header(2020, 01, 13, 20, 43, 00));
-        contents2.append(&mut ELEMENTUM_EU.to_vec());
-        contents2.append(&mut b"\n\n".to_vec());
-        let entry2 = LogEntry::from_contents(contents2.clone());
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
 
-        let entries = vec![entry1, entry2];
-        let input_files = [PathBuf::from("logname")];
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            "2020-01-13 20:42:00.000 info     Exception at a at b\n\
+             2020-01-13 20:42:01.000 info     Single\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_flatten_escaped_line_breaks() -> Result<()> {
+        let mut in_buf = header(2020, 1, 13, 20, 42, 0);
+        in_buf.append(&mut b"Request failed:\\n  timeout\n  retrying\n\n".to_vec());
+
+        let opts = OutputOptions {
+            flatten: Some("⏎".to_string()),
+            escape_mode: EscapeMode::Standard,
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = header(2020, 1, 13, 20, 42, 0);
+        pattern.append(
+            &mut "Request failed:⏎  timeout⏎  retrying\n\n"
+                .as_bytes()
+                .to_vec(),
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_flatten() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"Exception\n  at a\n  at b\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 1));
+        in_buf.append(&mut b"Single\n\n".to_vec());
+
+        let opts = OutputOptions {
+            flatten: Some(" | ".to_string()),
+            ..Default::default()
+        };
 
         let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
 
-        write_log(
-            streaming_iterator::convert(entries),
-            &mut out_buf,
-            false,
-            true,
-            &input_files,
-        )?;
+        let mut pattern = header(2020, 1, 13, 20, 42, 0);
+        pattern.append(&mut b"Exception |   at a |   at b\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 1));
+        pattern.append(&mut b"Single\n\n".to_vec());
+        assert_eq!(out_buf, pattern);
+        assert_eq!(
+            out_buf
+                .split(|&c| c == b'\n')
+                .filter(|line| !line.is_empty())
+                .count(),
+            2
+        );
+        Ok(())
+    }
 
+    #[test]
+    fn write_log_fold_markers() -> Result<()> {
         let mut in_buf = Vec::new();
-        in_buf.append(&mut contents1);
-        in_buf.append(&mut contents2);
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"Exception\n  at a\n  at b\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 1));
+        in_buf.append(&mut b"Single\n\n".to_vec());
+
+        let opts = OutputOptions {
+            fold_markers: FoldMarkers::Vim,
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = header(2020, 1, 13, 20, 42, 0);
+        pattern.append(&mut b"Exception {{{\n  at a\n  at b }}}\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 1));
+        pattern.append(&mut b"Single\n\n".to_vec());
+        assert_eq!(out_buf, pattern);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_limit_bytes() -> Result<()> {
+        let entries: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: First\n\n",
+            b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Second\n\n",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: Third\n\n",
+        ];
+        let in_buf = entries.concat();
+        let limit = Rc::new(limit::ByteLimit::new(entries[0].len() as u64 + 1));
+        let opts = OutputOptions {
+            byte_limit: Some(limit.clone()),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&entries[..2].concat())
+        );
+        assert!(limit.is_truncated());
+
+        // reaching the limit with the last entry does not truncate the output
+        let limit = Rc::new(limit::ByteLimit::new(in_buf.len() as u64));
+        let opts = OutputOptions {
+            byte_limit: Some(limit.clone()),
+            ..Default::default()
+        };
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
         assert_eq!(out_buf, in_buf);
+        assert!(!limit.is_truncated());
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_output_appends_runs() -> Result<()> {
+        let runs: &[&[u8]] = &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: First run\n\n",
+            b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Second run\n\n",
+        ];
+        let path = std::env::temp_dir().join(format!("riolog-{}-append.log", std::process::id()));
+        let run = |input: &[u8], append: bool| -> Result<Vec<u8>> {
+            let writer = create_output(&path, append)?;
+            write_log(
+                LogEntryReader::new(input, eol::EOL),
+                writer,
+                &OutputOptions::default(),
+                &[],
+            )?;
+            Ok(std::fs::read(&path)?)
+        };
+
+        let _ = std::fs::remove_file(&path);
+        let appended = run(runs[0], true).and_then(|_| run(runs[1], true));
+        let replaced = run(runs[1], false);
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(appended?, runs.concat());
+        assert_eq!(replaced?, runs[1]);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_through_command_to_output_file() -> Result<()> {
+        let temp_path = |name: &str| {
+            std::env::temp_dir().join(format!("riolog-{}-exec-{}", std::process::id(), name))
+        };
+        let (input, output) = (temp_path("input.log"), temp_path("output.log"));
+        std::fs::write(
+            &input,
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Kept\n\n\
+              -info:<1> 2020-01-01 20:00:01.000 UTC [A]: Noise\n\n",
+        )?;
+        let run = |command: &str| {
+            let opts = Options {
+                input_files: vec![input.clone()],
+                output_file: Some(output.clone()),
+                ..Default::default()
+            };
+            run_through_command(command, opts, &Interrupt::default())
+        };
+
+        let filtered = run("grep Kept").and_then(|_| Ok(std::fs::read(&output)?));
+        let missing = run("riolog-no-such-command 2>/dev/null");
+        std::fs::remove_file(&input)?;
+        std::fs::remove_file(&output)?;
+
+        assert_eq!(
+            filtered?,
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Kept\n".to_vec()
+        );
+        assert!(matches!(missing, Err(Error::CannotRunCommand(..))));
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_records_checkpoint() -> Result<()> {
+        let in_buf = b"-info:<1> 2020-01-01 20:00:01.500 UTC [A]: Newest\n\n\
+                       -info:<1> 2020-01-01 20:00:00.000 UTC [A]: Older\n\n\
+                       Without header\n\n";
+        let path =
+            std::env::temp_dir().join(format!("riolog-{}-write-until.txt", std::process::id()));
+        let checkpoint = Rc::new(checkpoint::Checkpoint::new(path.clone()));
+        let opts = OutputOptions {
+            checkpoint: Some(checkpoint.clone()),
+            ..Default::default()
+        };
+
+        let reader = LogEntryReader::new(&in_buf[..], eol::EOL);
+        write_log(reader, io::sink(), &opts, &[])?;
+        checkpoint.save()?;
+        let saved = std::fs::read_to_string(&path);
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(saved?, "2020-01-01 20:00:01.500\n1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn write_entries_resumes_within_same_millisecond() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("riolog-{}-resume-ms.txt", std::process::id()));
+        let run = |in_buf: &[u8]| -> Result<Vec<u8>> {
+            let position = checkpoint::read_position(&path, "since-file")?;
+            let checkpoint =
+                Rc::new(checkpoint::Checkpoint::new(path.clone()).with_start(position));
+            let opts = Options {
+                since_position: position,
+                output_options: OutputOptions {
+                    checkpoint: Some(checkpoint.clone()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut out_buf = Vec::new();
+            write_entries(LogEntryReader::new(in_buf, eol::EOL), &mut out_buf, &opts)?;
+            checkpoint.save()?;
+            Ok(out_buf)
+        };
+
+        let first: &[u8] = b"-info:<1> 2020-01-01 20:00:01.500 UTC [A]: First\n\n";
+        let second: &[u8] = b"-info:<1> 2020-01-01 20:00:01.500 UTC [A]: Second\n\n";
+        let outputs = [
+            run(first),
+            run(&[first, second].concat()),
+            run(&[first, second].concat()),
+        ];
+        let saved = std::fs::read_to_string(&path);
+        std::fs::remove_file(&path)?;
+
+        let [first_run, second_run, third_run] = outputs;
+        assert_eq!(first_run?, first.to_vec());
+        assert_eq!(second_run?, second.to_vec());
+        assert_eq!(third_run?, Vec::<u8>::new());
+        assert_eq!(saved?, "2020-01-01 20:00:01.500\n2\n");
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_colored_escaped_eol() -> Result<()> {
+        let mut in_buf = header(2020, 1, 13, 20, 42, 0);
+        in_buf.append(&mut b"First\\nSecond\n\n".to_vec());
+
+        let opts = OutputOptions {
+            color_enabled: true,
+            escape_mode: EscapeMode::Standard,
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = CODE_WHITE.to_vec();
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 0));
+        pattern.extend_from_slice(b"First");
+        pattern.extend_from_slice(CODE_NORMAL);
+        pattern.extend_from_slice(eol::EOL);
+        pattern.extend_from_slice(CODE_WHITE);
+        pattern.extend_from_slice(b"Second\n\n");
+        pattern.extend_from_slice(CODE_NORMAL);
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_keep_ansi() -> Result<()> {
+        let mut in_buf = header(2020, 1, 13, 20, 42, 0);
+        in_buf.append(&mut b"Status \x1B[32mOK\x1B[0m done\n\n".to_vec());
+
+        let opts = OutputOptions {
+            color_enabled: true,
+            keep_ansi: true,
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = CODE_WHITE.to_vec();
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 0));
+        pattern.extend_from_slice(b"Status \x1B[32mOK\x1B[0m");
+        pattern.extend_from_slice(CODE_WHITE);
+        pattern.extend_from_slice(b" done\n\n");
+        pattern.extend_from_slice(CODE_NORMAL);
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_interrupted_finishes_entry() -> Result<()> {
+        // requests the interrupt as soon as the first entry starts being written
+        struct RequestingWriter<'a> {
+            output: &'a mut Vec<u8>,
+            interrupt: Interrupt,
+        }
+
+        impl Write for RequestingWriter<'_> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.interrupt.request();
+                self.output.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut in_buf = header(2020, 1, 13, 20, 42, 0);
+        in_buf.append(&mut b"First\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        in_buf.append(&mut b"Second\n\n".to_vec());
+
+        let interrupt = Interrupt::default();
+        let opts = OutputOptions {
+            color_enabled: true,
+            interrupt: interrupt.clone(),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let writer = RequestingWriter {
+            output: &mut out_buf,
+            interrupt: interrupt.clone(),
+        };
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        let result = write_log(
+            reader,
+            InterruptibleWriter::new(writer, interrupt.clone()),
+            &opts,
+            &[],
+        );
+        assert!(matches!(
+            stop_on_interrupt(result, &interrupt),
+            Err(Error::Interrupted)
+        ));
+
+        let mut pattern = CODE_WHITE.to_vec();
+        pattern.append(&mut header(2020, 1, 13, 20, 42, 0));
+        pattern.extend_from_slice(b"First\n\n");
+        pattern.extend_from_slice(CODE_NORMAL);
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_color_by_source() -> Result<()> {
+        let input_files = [PathBuf::from("a.log"), PathBuf::from("b.log")];
+        let run = |color_enabled: bool, color_by_source: bool| -> Result<String> {
+            let entries = (0..2)
+                .map(|source| LogEntry::from_contents(b"Message\n\n".to_vec()).with_source(source));
+            let opts = OutputOptions {
+                color_enabled,
+                color_by_source,
+                ..Default::default()
+            };
+            let mut out_buf = Vec::<u8>::new();
+            write_log(
+                streaming_iterator::convert(entries),
+                &mut out_buf,
+                &opts,
+                &input_files,
+            )?;
+            Ok(String::from_utf8_lossy(&out_buf).into_owned())
+        };
+
+        let colored = run(true, true)?;
+        assert!(colored.contains("\x1B[96ma.log: \x1B[0m"));
+        assert!(colored.contains("\x1B[95mb.log: \x1B[0m"));
+        assert_eq!(run(true, false)?.matches("\x1B[36m").count(), 2);
+        assert_eq!(run(false, true)?, "a.log: Message\n\nb.log: Message\n\n");
+        assert_ne!(source_code(0), source_code(1));
+        assert_eq!(source_code(6), source_code(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_wrap_columns() -> Result<()> {
+        let line: &[u8] = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Lorem ipsum dolor sit amet";
+        let mut in_buf = line.to_vec();
+        in_buf.extend_from_slice(b"\n\n");
+
+        let opts = OutputOptions {
+            color_enabled: true,
+            wrap_columns: Some(20),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = Vec::new();
+        let parts: Vec<_> = line.chunks(20).collect();
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                pattern.extend_from_slice(eol::EOL);
+            }
+            pattern.extend_from_slice(CODE_WHITE);
+            pattern.extend_from_slice(part);
+            // the wrapping writer resets the style at the line breaks it inserts
+            if i + 1 < parts.len() {
+                pattern.extend_from_slice(CODE_NORMAL);
+            }
+        }
+        pattern.extend_from_slice(b"\n\n");
+        pattern.extend_from_slice(CODE_NORMAL);
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_emphasize_after_gap() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"First\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 10));
+        in_buf.append(&mut b"Second\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 43, 0));
+        in_buf.append(&mut b"Third\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 43, 5));
+        in_buf.append(&mut b"Fourth\n\n".to_vec());
+
+        let opts = OutputOptions {
+            color_enabled: true,
+            emphasize_after_gap: Some(chrono::Duration::seconds(30)),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = Vec::new();
+        for (sec, text, emphasized) in [
+            (0, "First", false),
+            (10, "Second", false),
+            (60, "Third", true),
+            (65, "Fourth", false),
+        ] {
+            if emphasized {
+                pattern.extend_from_slice(CODE_EMPHASIS);
+            }
+            pattern.extend_from_slice(CODE_WHITE);
+            pattern.append(&mut header(2020, 1, 13, 20, 42 + sec / 60, sec % 60));
+            pattern.extend_from_slice(text.as_bytes());
+            pattern.extend_from_slice(b"\n\n");
+            pattern.extend_from_slice(CODE_NORMAL);
+        }
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_restart_markers() -> Result<()> {
+        let mut in_buf = Vec::new();
+        in_buf.append(&mut header(2020, 1, 13, 20, 42, 0));
+        in_buf.append(&mut b"Before\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 30, 0));
+        in_buf.append(&mut b"Clock went back\n\n".to_vec());
+        in_buf.append(&mut header(2020, 1, 13, 20, 31, 0));
+        in_buf.append(&mut b"Service v1.2 starting\n\n".to_vec());
+
+        let opts = OutputOptions {
+            mark_restarts: true,
+            restart_pattern: pattern::Pattern::new("Service .* starting"),
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_slice(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let mut pattern = header(2020, 1, 13, 20, 42, 0);
+        pattern.append(&mut b"Before\n\n=== RESTART ===\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 30, 0));
+        pattern.append(&mut b"Clock went back\n\n=== RESTART ===\n\n".to_vec());
+        pattern.append(&mut header(2020, 1, 13, 20, 31, 0));
+        pattern.append(&mut b"Service v1.2 starting\n\n".to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            String::from_utf8_lossy(&pattern)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_log_flag_escalation() -> Result<()> {
+        let entries: [(&str, u32); 7] = [
+            ("info", 0),
+            ("warning", 1),
+            ("critical", 2),
+            ("fatal", 3),
+            ("info", 4),
+            ("critical", 5),
+            ("debug", 6),
+        ];
+        let entry = |level: &str, sec: u32| {
+            format!(
+                "-{}:<1> 2020-01-13 20:42:{:02}.000 UTC [A]: Message\n\n",
+                level, sec
+            )
+        };
+        let in_buf: String = entries
+            .iter()
+            .map(|&(level, sec)| entry(level, sec))
+            .collect();
+
+        let opts = OutputOptions {
+            flag_escalation: true,
+            ..Default::default()
+        };
+
+        let mut out_buf = Vec::<u8>::new();
+        let reader = LogEntryReader::new(in_buf.as_bytes(), eol::EOL);
+        write_log(reader, &mut out_buf, &opts, &[])?;
+
+        let marker = "=== ESCALATION ===\n\n";
+        let pattern = [
+            entry("info", 0),
+            entry("warning", 1),
+            marker.to_string(),
+            entry("critical", 2),
+            entry("fatal", 3),
+            entry("info", 4),
+            marker.to_string(),
+            entry("critical", 5),
+            entry("debug", 6),
+        ]
+        .concat();
+        assert_eq!(String::from_utf8_lossy(&out_buf), pattern);
 
         Ok(())
     }
@@ -0,0 +1,76 @@
+use crate::log_entry::{LogEntry, LogLevel};
+use std::cell::Cell;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const BELL: &[u8] = b"\x07";
+
+// bursts of entries within this time of the last alert do not ring again
+const DEBOUNCE: Duration = Duration::from_secs(5);
+
+// Alerts with a terminal bell on standard error about entries of at least given level, e.g. while
+// following a file in another window.
+#[derive(Debug)]
+pub struct LevelNotifier {
+    min_level: LogLevel,
+    last_alert: Cell<Option<Instant>>,
+}
+
+impl LevelNotifier {
+    pub fn new(min_level: LogLevel) -> Self {
+        LevelNotifier {
+            min_level,
+            last_alert: Cell::new(None),
+        }
+    }
+
+    pub fn record(&self, entry: &LogEntry) {
+        if self.should_alert(entry, Instant::now()) {
+            let stderr = std::io::stderr();
+            let mut stderr = stderr.lock();
+            let _ = stderr.write_all(BELL).and_then(|_| stderr.flush());
+        }
+    }
+
+    fn should_alert(&self, entry: &LogEntry, now: Instant) -> bool {
+        let matches = entry
+            .level()
+            .is_some_and(|level| (level as i32) >= (self.min_level as i32));
+        let debounced = self
+            .last_alert
+            .get()
+            .is_some_and(|last_alert| now.duration_since(last_alert) < DEBOUNCE);
+        if !matches || debounced {
+            return false;
+        }
+        self.last_alert.set(Some(now));
+        true
+    }
+
+    #[cfg(test)]
+    pub fn alerted(&self) -> bool {
+        self.last_alert.get().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_notifier_debounce() {
+        let notifier = LevelNotifier::new(LogLevel::Critical);
+        let entry = |contents: &[u8]| LogEntry::from_contents(contents.to_vec());
+        let fatal = entry(b"-fatal:<1> 2020-01-01 20:00:00.000 UTC [A]: B\n\n");
+        let critical = entry(b"-critical:<1> 2020-01-01 20:00:00.000 UTC [A]: B\n\n");
+        let warning = entry(b"-warning:<1> 2020-01-01 20:00:00.000 UTC [A]: B\n\n");
+        let start = Instant::now();
+
+        assert!(!notifier.should_alert(&warning, start));
+        assert!(notifier.should_alert(&fatal, start));
+        assert!(!notifier.should_alert(&fatal, start + Duration::from_secs(1)));
+        assert!(!notifier.should_alert(&critical, start + Duration::from_secs(4)));
+        assert!(notifier.should_alert(&critical, start + DEBOUNCE));
+        assert!(!notifier.should_alert(&entry(b"No header\n\n"), start + DEBOUNCE * 3));
+    }
+}
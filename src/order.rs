@@ -0,0 +1,185 @@
+use crate::direction::Direction;
+use crate::log_entry::LogEntry;
+use chrono::NaiveDateTime;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+use streaming_iterator::StreamingIterator;
+
+// Verifies that entries of every input are sorted by their timestamps, which merging relies on.
+// Each entry going back in time is reported, or, when strict, ends reading to fail with the
+// first one. Entries without a timestamp are not checked.
+pub struct OrderCheck {
+    files: Vec<PathBuf>,
+    strict: bool,
+    report: Option<RefCell<Box<dyn Write>>>,
+    previous: RefCell<HashMap<usize, (Option<NaiveDateTime>, u64)>>, // timestamp and end offset
+    violation: RefCell<Option<String>>,
+}
+
+impl OrderCheck {
+    pub fn new(files: Vec<PathBuf>, strict: bool) -> Self {
+        OrderCheck {
+            files,
+            strict,
+            report: None,
+            previous: RefCell::new(HashMap::new()),
+            violation: RefCell::new(None),
+        }
+    }
+
+    // writes violations as they are found, unless strict
+    pub fn with_report(mut self, writer: impl Write + 'static) -> Self {
+        self.report = Some(RefCell::new(Box::new(writer)));
+        self
+    }
+
+    // the first violation found, if any
+    pub fn violation(&self) -> Option<String> {
+        self.violation.borrow().clone()
+    }
+
+    // records the entry, returning false if reading should stop
+    fn accept(&self, entry: &LogEntry, direction: Direction) -> bool {
+        let mut previous = self.previous.borrow_mut();
+        let (last_timestamp, start_offset) =
+            previous.get(&entry.source()).copied().unwrap_or_default();
+        let timestamp = entry.timestamp();
+        previous.insert(
+            entry.source(),
+            (timestamp.or(last_timestamp), entry.end_offset()),
+        );
+
+        let (earlier, later) = match (last_timestamp, timestamp, direction) {
+            (Some(last), Some(timestamp), Direction::Forward) if timestamp < last => {
+                (last, timestamp)
+            }
+            (Some(last), Some(timestamp), Direction::Reverse) if timestamp > last => {
+                (timestamp, last)
+            }
+            _ => return true,
+        };
+
+        let file = self
+            .files
+            .get(entry.source())
+            .map_or_else(|| "-".into(), |file| file.display().to_string());
+        // offsets are known only when reading forward
+        let location = match direction {
+            Direction::Forward => format!("{} at offset {}", file, start_offset),
+            Direction::Reverse => file,
+        };
+        let violation = format!(
+            "Entries out of order in {}: {} followed by {}",
+            location, earlier, later
+        );
+
+        if let (Some(writer), false) = (&self.report, self.strict) {
+            // reporting is best effort, like other reports on standard error
+            let _ = writeln!(writer.borrow_mut(), "{}", violation);
+        }
+        self.violation.borrow_mut().get_or_insert(violation);
+        !self.strict
+    }
+}
+
+// checks the order of entries of a single input read in given direction
+pub struct OrderCheckingIter<I> {
+    input: I,
+    check: Rc<OrderCheck>,
+    direction: Direction,
+    stopped: bool,
+}
+
+impl<I> OrderCheckingIter<I> {
+    pub fn new(input: I, check: Rc<OrderCheck>, direction: Direction) -> Self {
+        OrderCheckingIter {
+            input,
+            check,
+            direction,
+            stopped: false,
+        }
+    }
+}
+
+impl<I: StreamingIterator<Item = LogEntry>> StreamingIterator for OrderCheckingIter<I> {
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        if self.stopped {
+            return;
+        }
+        self.input.advance();
+        if let Some(entry) = self.input.get() {
+            self.stopped = !self.check.accept(entry, self.direction);
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.stopped {
+            None
+        } else {
+            self.input.get()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eol;
+    use crate::log_entry_reader::LogEntryReader;
+
+    const OUT_OF_ORDER: &[u8] = b"-info:<1> 2020-01-01 20:00:01.000 UTC [A]: Later\n\n\
+                                  -info:<1> 2020-01-01 20:00:00.000 UTC [A]: Earlier\n\n";
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn read(check: &Rc<OrderCheck>) -> usize {
+        let reader = LogEntryReader::new(OUT_OF_ORDER, eol::EOL);
+        OrderCheckingIter::new(reader, check.clone(), Direction::Forward).count()
+    }
+
+    #[test]
+    fn order_check_reports_regression() {
+        let buffer = SharedBuffer::default();
+        let check = Rc::new(
+            OrderCheck::new(vec![PathBuf::from("a.log")], false).with_report(buffer.clone()),
+        );
+
+        assert_eq!(read(&check), 2);
+        let expected = "Entries out of order in a.log at offset 50: \
+                        2020-01-01 20:00:01 followed by 2020-01-01 20:00:00";
+        assert_eq!(check.violation().as_deref(), Some(expected));
+        assert_eq!(
+            String::from_utf8_lossy(&buffer.0.borrow()),
+            format!("{}\n", expected)
+        );
+    }
+
+    #[test]
+    fn order_check_strict_stops_reading() {
+        let buffer = SharedBuffer::default();
+        let check = Rc::new(
+            OrderCheck::new(vec![PathBuf::from("a.log")], true).with_report(buffer.clone()),
+        );
+
+        assert_eq!(read(&check), 1);
+        assert!(check.violation().is_some());
+        assert!(buffer.0.borrow().is_empty());
+    }
+}
@@ -0,0 +1,843 @@
+// Minimal backtracking regular expression engine operating on bytes.
+//
+// Supported syntax: literals, `.`, character classes (`[a-z]`, `[^0-9]`), escapes
+// (`\d`, `\w`, `\s`, their negations, `\b`, `\B`, `\n`, `\t`, `\r`), anchors (`^` and `$`,
+// matching at line boundaries), groups (`(...)`, `(?:...)`, `(?P<name>...)`), alternation
+// and quantifiers (`*`, `+`, `?`, `{n}`, `{n,}`, `{n,m}`, optionally lazy with `?`).
+// A leading `(?i)` makes the whole pattern ASCII case-insensitive.
+
+use std::cell::RefCell;
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Empty,
+    Byte(u8),
+    Any,
+    Class(Vec<(u8, u8)>, bool),
+    LineStart,
+    LineEnd,
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Inst {
+    Byte(u8),
+    Any,
+    Class(Vec<(u8, u8)>, bool),
+    LineStart,
+    LineEnd,
+    WordBoundary(bool),
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    Match,
+}
+
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    insts: Vec<Inst>,
+    slot_count: usize,
+    names: Vec<(String, usize)>,
+    case_insensitive: bool,
+    visited: RefCell<Visited>,
+}
+
+// Above this number of (instruction, position) states, i.e. 8 MiB of marks of visited ones, texts
+// are searched by `Pattern::pike` instead of backtracking, so that long entries do not take memory
+// proportional to their length times the length of the pattern.
+const MAX_BACKTRACK_STATES: usize = 1 << 26;
+
+// (instruction, position) states already explored by backtracking, one bit each, kept between
+// matches to avoid allocating the bitmap each time
+#[derive(Clone, Debug, Default)]
+struct Visited {
+    bits: Vec<u64>,
+}
+
+// threads of the Pike VM at one position in order of preference, at most one per instruction
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    marks: Vec<usize>,
+    generation: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Captures {
+    slots: Vec<Option<usize>>,
+    names: Vec<(String, usize)>,
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    group_count: usize,
+    names: Vec<(String, usize)>,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Option<Pattern> {
+        let (case_insensitive, body) = match source.strip_prefix("(?i)") {
+            Some(body) => (true, body),
+            None => (false, source),
+        };
+
+        let mut parser = Parser {
+            input: body.as_bytes(),
+            pos: 0,
+            group_count: 1,
+            names: Vec::new(),
+        };
+        let node = parser.parse_alt()?;
+        if parser.pos != parser.input.len() {
+            return None;
+        }
+
+        let mut insts = vec![Inst::Save(0)];
+        compile(&node, &mut insts);
+        insts.push(Inst::Save(1));
+        insts.push(Inst::Match);
+
+        Some(Pattern {
+            insts,
+            slot_count: parser.group_count * 2,
+            names: parser.names,
+            case_insensitive,
+            visited: RefCell::default(),
+        })
+    }
+
+    pub fn has_group(&self, name: &str) -> bool {
+        self.names.iter().any(|(n, _)| n == name)
+    }
+
+    pub fn is_match(&self, text: &[u8]) -> bool {
+        self.find(text).is_some()
+    }
+
+    pub fn find(&self, text: &[u8]) -> Option<Range<usize>> {
+        self.find_at(text, 0)
+    }
+
+    pub fn find_at(&self, text: &[u8], start: usize) -> Option<Range<usize>> {
+        self.captures_at(text, start)
+            .and_then(|captures| captures.get(0))
+    }
+
+    pub fn captures(&self, text: &[u8]) -> Option<Captures> {
+        self.captures_at(text, 0)
+    }
+
+    fn captures_at(&self, text: &[u8], start: usize) -> Option<Captures> {
+        let positions = text.len().checked_sub(start)? + 1;
+        let slots = if self.insts.len().saturating_mul(positions) <= MAX_BACKTRACK_STATES {
+            self.backtrack(text, start, positions)
+        } else {
+            self.pike(text, start)
+        }?;
+        Some(Captures {
+            slots,
+            names: self.names.clone(),
+        })
+    }
+
+    fn backtrack(&self, text: &[u8], start: usize, positions: usize) -> Option<Vec<Option<usize>>> {
+        let mut slots = vec![None; self.slot_count];
+        let mut visited = self.visited.borrow_mut();
+        visited.reset(self.insts.len() * positions);
+
+        for pos in start..=text.len() {
+            if self.exec(text, start, pos, &mut slots, &mut visited) {
+                return Some(slots);
+            }
+        }
+
+        None
+    }
+
+    // states are marked visited relative to `origin`, where the search started
+    fn exec(
+        &self,
+        text: &[u8],
+        origin: usize,
+        start: usize,
+        slots: &mut [Option<usize>],
+        visited: &mut Visited,
+    ) -> bool {
+        enum Job {
+            Step(usize, usize),
+            Restore(usize, Option<usize>),
+        }
+
+        let mut stack = vec![Job::Step(0, start)];
+
+        while let Some(job) = stack.pop() {
+            let (mut pc, mut pos) = match job {
+                Job::Step(pc, pos) => (pc, pos),
+                Job::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+
+            loop {
+                if !visited.insert(pc * (text.len() + 1 - origin) + pos - origin) {
+                    break;
+                }
+
+                match &self.insts[pc] {
+                    Inst::Byte(byte) => match text.get(pos) {
+                        Some(c) if self.byte_eq(*c, *byte) => {
+                            pc += 1;
+                            pos += 1;
+                        }
+                        _ => break,
+                    },
+                    Inst::Any => match text.get(pos) {
+                        Some(c) if *c != b'\n' => {
+                            pc += 1;
+                            pos += 1;
+                        }
+                        _ => break,
+                    },
+                    Inst::Class(ranges, negated) => match text.get(pos) {
+                        Some(c) if self.class_matches(ranges, *negated, *c) => {
+                            pc += 1;
+                            pos += 1;
+                        }
+                        _ => break,
+                    },
+                    inst @ (Inst::LineStart | Inst::LineEnd | Inst::WordBoundary(_)) => {
+                        if assertion_holds(inst, text, pos) {
+                            pc += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    Inst::Split(first, second) => {
+                        stack.push(Job::Step(*second, pos));
+                        pc = *first;
+                    }
+                    Inst::Jmp(target) => pc = *target,
+                    Inst::Save(slot) => {
+                        stack.push(Job::Restore(*slot, slots[*slot]));
+                        slots[*slot] = Some(pos);
+                        pc += 1;
+                    }
+                    Inst::Match => return true,
+                }
+            }
+        }
+
+        false
+    }
+
+    // Searches following all alternatives at once, a position at a time, like a Pike VM. Takes
+    // memory depending only on the pattern, but is slower than backtracking.
+    fn pike(&self, text: &[u8], start: usize) -> Option<Vec<Option<usize>>> {
+        let mut current = Threads::new(self.insts.len());
+        let mut next = Threads::new(self.insts.len());
+        let mut matched = None;
+
+        for pos in start..=text.len() {
+            // a match found already is preferred to any starting further
+            if matched.is_none() {
+                self.add_thread(&mut current, 0, text, pos, vec![None; self.slot_count]);
+            } else if current.list.is_empty() {
+                break;
+            }
+
+            for (pc, slots) in current.list.drain(..) {
+                let advances = match &self.insts[pc] {
+                    Inst::Byte(byte) => text.get(pos).is_some_and(|&c| self.byte_eq(c, *byte)),
+                    Inst::Any => text.get(pos).is_some_and(|&c| c != b'\n'),
+                    Inst::Class(ranges, negated) => text
+                        .get(pos)
+                        .is_some_and(|&c| self.class_matches(ranges, *negated, c)),
+                    // threads after it are less preferred
+                    Inst::Match => {
+                        matched = Some(slots);
+                        break;
+                    }
+                    _ => false,
+                };
+                if advances {
+                    self.add_thread(&mut next, pc + 1, text, pos + 1, slots);
+                }
+            }
+
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+
+        matched
+    }
+
+    // adds threads reached from the instruction without consuming input, in order of preference
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        text: &[u8],
+        pos: usize,
+        slots: Vec<Option<usize>>,
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((mut pc, mut slots)) = stack.pop() {
+            while threads.insert(pc) {
+                match &self.insts[pc] {
+                    Inst::Split(first, second) => {
+                        stack.push((*second, slots.clone()));
+                        pc = *first;
+                    }
+                    Inst::Jmp(target) => pc = *target,
+                    Inst::Save(slot) => {
+                        slots[*slot] = Some(pos);
+                        pc += 1;
+                    }
+                    inst @ (Inst::LineStart | Inst::LineEnd | Inst::WordBoundary(_)) => {
+                        if !assertion_holds(inst, text, pos) {
+                            break;
+                        }
+                        pc += 1;
+                    }
+                    _ => {
+                        threads.list.push((pc, slots));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn byte_eq(&self, c: u8, expected: u8) -> bool {
+        if self.case_insensitive {
+            c.eq_ignore_ascii_case(&expected)
+        } else {
+            c == expected
+        }
+    }
+
+    fn class_matches(&self, ranges: &[(u8, u8)], negated: bool, c: u8) -> bool {
+        let in_ranges = |c: u8| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+        let found = if self.case_insensitive {
+            in_ranges(c.to_ascii_lowercase()) || in_ranges(c.to_ascii_uppercase())
+        } else {
+            in_ranges(c)
+        };
+        found != negated
+    }
+}
+
+// whether a zero-width instruction matches at the position
+fn assertion_holds(inst: &Inst, text: &[u8], pos: usize) -> bool {
+    match inst {
+        Inst::LineStart => pos == 0 || text[pos - 1] == b'\n',
+        Inst::LineEnd => pos == text.len() || text[pos] == b'\n' || text[pos] == b'\r',
+        Inst::WordBoundary(expected) => {
+            let before = pos > 0 && is_word_byte(text[pos - 1]);
+            let after = pos < text.len() && is_word_byte(text[pos]);
+            (before != after) == *expected
+        }
+        _ => true,
+    }
+}
+
+impl Visited {
+    fn reset(&mut self, len: usize) {
+        self.bits.clear();
+        self.bits.resize(len.div_ceil(64), 0);
+    }
+
+    // returns false if the state has been visited already
+    fn insert(&mut self, index: usize) -> bool {
+        let (word, bit) = (&mut self.bits[index / 64], 1 << (index % 64));
+        let inserted = *word & bit == 0;
+        *word |= bit;
+        inserted
+    }
+}
+
+impl Threads {
+    fn new(inst_count: usize) -> Self {
+        Threads {
+            list: Vec::new(),
+            marks: vec![0; inst_count],
+            generation: 1,
+        }
+    }
+
+    // returns false if the instruction has a thread already
+    fn insert(&mut self, pc: usize) -> bool {
+        let inserted = self.marks[pc] != self.generation;
+        self.marks[pc] = self.generation;
+        inserted
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.generation += 1;
+    }
+}
+
+impl Captures {
+    pub fn get(&self, index: usize) -> Option<Range<usize>> {
+        match (self.slots.get(index * 2)?, self.slots.get(index * 2 + 1)?) {
+            (Some(start), Some(end)) => Some(*start..*end),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self, name: &str) -> Option<Range<usize>> {
+        let &(_, index) = self.names.iter().find(|(n, _)| n == name)?;
+        self.get(index)
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_alt(&mut self) -> Option<Node> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat(b'|') {
+            branches.push(self.parse_concat()?);
+        }
+
+        if branches.len() == 1 {
+            branches.pop()
+        } else {
+            Some(Node::Alt(branches))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Option<Node> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == b'|' || c == b')' {
+                break;
+            }
+            nodes.push(self.parse_repeat()?);
+        }
+
+        match nodes.len() {
+            0 => Some(Node::Empty),
+            1 => nodes.pop(),
+            _ => Some(Node::Concat(nodes)),
+        }
+    }
+
+    fn parse_repeat(&mut self) -> Option<Node> {
+        let mut node = self.parse_atom()?;
+
+        loop {
+            let (min, max) = match self.peek() {
+                Some(b'*') => self.quantifier(0, None),
+                Some(b'+') => self.quantifier(1, None),
+                Some(b'?') => self.quantifier(0, Some(1)),
+                Some(b'{') => match self.parse_counts() {
+                    Some(counts) => counts,
+                    None => break,
+                },
+                _ => break,
+            };
+
+            if let Node::LineStart | Node::LineEnd | Node::WordBoundary(_) = node {
+                return None;
+            }
+
+            let greedy = !self.eat(b'?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+
+        Some(node)
+    }
+
+    fn quantifier(&mut self, min: usize, max: Option<usize>) -> (usize, Option<usize>) {
+        self.pos += 1;
+        (min, max)
+    }
+
+    // parses `{n}`, `{n,}` or `{n,m}` leaving the position after the closing brace
+    fn parse_counts(&mut self) -> Option<(usize, Option<usize>)> {
+        let end = self.pos + self.input[self.pos..].iter().position(|&c| c == b'}')?;
+        let counts = std::str::from_utf8(&self.input[self.pos + 1..end]).ok()?;
+        let (min, max) = match counts.find(',') {
+            Some(comma) => {
+                let max = &counts[comma + 1..];
+                let max = if max.is_empty() {
+                    None
+                } else {
+                    Some(max.parse().ok()?)
+                };
+                (counts[..comma].parse().ok()?, max)
+            }
+            None => {
+                let count = counts.parse().ok()?;
+                (count, Some(count))
+            }
+        };
+
+        if max.is_some_and(|max| max < min) {
+            return None;
+        }
+
+        self.pos = end + 1;
+        Some((min, max))
+    }
+
+    fn parse_atom(&mut self) -> Option<Node> {
+        let c = self.peek()?;
+        self.pos += 1;
+
+        match c {
+            b'(' => {
+                let index = if self.eat(b'?') {
+                    if self.eat(b':') {
+                        None
+                    } else {
+                        self.eat(b'P');
+                        if !self.eat(b'<') {
+                            return None;
+                        }
+                        let len = self.input[self.pos..].iter().position(|&c| c == b'>')?;
+                        let name =
+                            std::str::from_utf8(&self.input[self.pos..self.pos + len]).ok()?;
+                        self.names.push((name.to_string(), self.group_count));
+                        self.pos += len + 1;
+                        Some(self.next_group())
+                    }
+                } else {
+                    Some(self.next_group())
+                };
+
+                let node = self.parse_alt()?;
+                if !self.eat(b')') {
+                    return None;
+                }
+                Some(Node::Group(Box::new(node), index))
+            }
+            b'[' => self.parse_class(),
+            b'.' => Some(Node::Any),
+            b'^' => Some(Node::LineStart),
+            b'$' => Some(Node::LineEnd),
+            b'\\' => {
+                let escaped = self.peek()?;
+                self.pos += 1;
+                match escaped {
+                    b'b' => Some(Node::WordBoundary(true)),
+                    b'B' => Some(Node::WordBoundary(false)),
+                    _ => match escape_class(escaped) {
+                        Some((ranges, negated)) => Some(Node::Class(ranges, negated)),
+                        None => Some(Node::Byte(escape_byte(escaped))),
+                    },
+                }
+            }
+            b'*' | b'+' | b'?' | b')' => None,
+            c => Some(Node::Byte(c)),
+        }
+    }
+
+    fn parse_class(&mut self) -> Option<Node> {
+        let negated = self.eat(b'^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+
+            let lo = match c {
+                b']' if !first => break,
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escape_class(escaped) {
+                        Some((class_ranges, false)) => {
+                            ranges.extend(class_ranges);
+                            first = false;
+                            continue;
+                        }
+                        Some((_, true)) => return None,
+                        None => escape_byte(escaped),
+                    }
+                }
+                c => c,
+            };
+            first = false;
+
+            if self.peek() == Some(b'-') && self.input.get(self.pos + 1) != Some(&b']') {
+                self.pos += 1;
+                let hi = match self.peek()? {
+                    b'\\' => {
+                        self.pos += 1;
+                        escape_byte(self.peek()?)
+                    }
+                    c => c,
+                };
+                self.pos += 1;
+                if hi < lo {
+                    return None;
+                }
+                ranges.push((lo, hi));
+            } else {
+                ranges.push((lo, lo));
+            }
+        }
+
+        Some(Node::Class(ranges, negated))
+    }
+
+    fn next_group(&mut self) -> usize {
+        self.group_count += 1;
+        self.group_count - 1
+    }
+}
+
+fn escape_class(c: u8) -> Option<(Vec<(u8, u8)>, bool)> {
+    let digit = vec![(b'0', b'9')];
+    let word = vec![(b'0', b'9'), (b'A', b'Z'), (b'a', b'z'), (b'_', b'_')];
+    let space = vec![(b' ', b' '), (b'\t', b'\r')];
+    match c {
+        b'd' => Some((digit, false)),
+        b'D' => Some((digit, true)),
+        b'w' => Some((word, false)),
+        b'W' => Some((word, true)),
+        b's' => Some((space, false)),
+        b'S' => Some((space, true)),
+        _ => None,
+    }
+}
+
+fn escape_byte(c: u8) -> u8 {
+    match c {
+        b'n' => b'\n',
+        b't' => b'\t',
+        b'r' => b'\r',
+        c => c,
+    }
+}
+
+fn is_word_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+fn compile(node: &Node, insts: &mut Vec<Inst>) {
+    match node {
+        Node::Empty => {}
+        Node::Byte(c) => insts.push(Inst::Byte(*c)),
+        Node::Any => insts.push(Inst::Any),
+        Node::Class(ranges, negated) => insts.push(Inst::Class(ranges.clone(), *negated)),
+        Node::LineStart => insts.push(Inst::LineStart),
+        Node::LineEnd => insts.push(Inst::LineEnd),
+        Node::WordBoundary(expected) => insts.push(Inst::WordBoundary(*expected)),
+        Node::Group(node, index) => {
+            if let Some(index) = index {
+                insts.push(Inst::Save(index * 2));
+                compile(node, insts);
+                insts.push(Inst::Save(index * 2 + 1));
+            } else {
+                compile(node, insts);
+            }
+        }
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, insts)),
+        Node::Alt(branches) => {
+            let mut jumps = Vec::new();
+            for (i, branch) in branches.iter().enumerate() {
+                if i + 1 < branches.len() {
+                    let split = insts.len();
+                    insts.push(Inst::Split(split + 1, 0));
+                    compile(branch, insts);
+                    jumps.push(insts.len());
+                    insts.push(Inst::Jmp(0));
+                    let next = insts.len();
+                    insts[split] = Inst::Split(split + 1, next);
+                } else {
+                    compile(branch, insts);
+                }
+            }
+            let end = insts.len();
+            for jump in jumps {
+                insts[jump] = Inst::Jmp(end);
+            }
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => {
+            for _ in 0..*min {
+                compile(node, insts);
+            }
+
+            let split = |body: usize, skip: usize| {
+                if *greedy {
+                    Inst::Split(body, skip)
+                } else {
+                    Inst::Split(skip, body)
+                }
+            };
+
+            match max {
+                None => {
+                    let start = insts.len();
+                    insts.push(Inst::Jmp(0));
+                    compile(node, insts);
+                    insts.push(Inst::Jmp(start));
+                    let end = insts.len();
+                    insts[start] = split(start + 1, end);
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(insts.len());
+                        insts.push(Inst::Jmp(0));
+                        compile(node, insts);
+                    }
+                    let end = insts.len();
+                    for start in splits {
+                        insts[start] = split(start + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<Range<usize>> {
+        Pattern::new(pattern).unwrap().find(text.as_bytes())
+    }
+
+    #[test]
+    fn pattern_literal() {
+        assert_eq!(find("abc", "xxabcxx"), Some(2..5));
+        assert_eq!(find("abd", "xxabcxx"), None);
+    }
+
+    #[test]
+    fn pattern_classes_and_quantifiers() {
+        assert_eq!(find(r"\d+", "id=12345;"), Some(3..8));
+        assert_eq!(find("[a-c]{2,3}", "xxabcd"), Some(2..5));
+        assert_eq!(find("[^x]+", "xxabx"), Some(2..4));
+        assert_eq!(find("a.*?c", "abcbc"), Some(0..3));
+        assert_eq!(find("a.*c", "abcbc"), Some(0..5));
+        assert_eq!(find("colou?r", "color"), Some(0..5));
+    }
+
+    #[test]
+    fn pattern_anchors() {
+        assert_eq!(find("^DB", "DBRead"), Some(0..2));
+        assert_eq!(find("^DB", "NetDB"), None);
+        assert_eq!(find("^second$", "first\nsecond\n"), Some(6..12));
+        assert_eq!(find(r"\bcat\b", "concat cat"), Some(7..10));
+    }
+
+    #[test]
+    fn pattern_alternation_and_groups() {
+        assert_eq!(find("start|end", "the end"), Some(4..7));
+        assert_eq!(find("(ab)+c", "ababc"), Some(0..5));
+
+        assert_eq!(
+            find(r"(start|end) (?P<id>\w+)", "request end job42"),
+            Some(8..17)
+        );
+    }
+
+    #[test]
+    fn pattern_case_insensitive() {
+        assert_eq!(find("(?i)error", "An ERROR occurred"), Some(3..8));
+        assert_eq!(find("(?i)[a-z]+", "ABC1"), Some(0..3));
+    }
+
+    #[test]
+    fn pattern_invalid() {
+        assert!(Pattern::new("(abc").is_none());
+        assert!(Pattern::new("abc)").is_none());
+        assert!(Pattern::new("[abc").is_none());
+        assert!(Pattern::new("*abc").is_none());
+    }
+
+    #[test]
+    fn pattern_nested_empty_loops_terminate() {
+        assert_eq!(find("(a*)*b", "aaaaaaaaaaaaaaaaaaaaaaaac"), None);
+    }
+
+    #[test]
+    fn pattern_reused_across_texts() {
+        let pattern = Pattern::new("b+c").unwrap();
+        assert_eq!(pattern.find(b"aaaabbbbc"), Some(4..9));
+        assert_eq!(pattern.find(b"bd"), None);
+        assert_eq!(pattern.find(b"bc"), Some(0..2));
+        assert_eq!(pattern.find_at(b"bc bbc", 1), Some(3..6));
+        assert_eq!(pattern.find(b"abc"), Some(1..3));
+    }
+
+    #[test]
+    fn pattern_pike_agrees_with_backtracking() {
+        let cases = [
+            ("abc", "xxabcxx"),
+            (r"\d+", "id=12345;"),
+            ("[a-c]{2,3}", "xxabcd"),
+            ("a.*?c", "abcbc"),
+            ("a.*c", "abcbc"),
+            ("^second$", "first\nsecond\n"),
+            (r"\bcat\b", "concat cat"),
+            ("start|end", "the end"),
+            (r"(start|(?P<end>end)) (?P<id>\w+)", "request end job42"),
+            ("(?i)[a-z]+", "ABC1"),
+            ("(a*)*b", "aaaaaaac"),
+            ("(a|ab)(c|bcd)", "abcd"),
+        ];
+        for (source, text) in cases.iter() {
+            let pattern = Pattern::new(source).unwrap();
+            let text = text.as_bytes();
+            assert_eq!(
+                pattern.pike(text, 0),
+                pattern.backtrack(text, 0, text.len() + 1),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn pattern_long_text_bounded_memory() {
+        let pattern = Pattern::new(r"zz[a-z]+\d{3}end").unwrap();
+        let mut text = vec![b'a'; 8 << 20];
+        text.extend_from_slice(b" zzabc123end");
+        assert!(pattern.insts.len() * text.len() > MAX_BACKTRACK_STATES);
+
+        assert_eq!(pattern.find(&text), Some(text.len() - 11..text.len()));
+        assert_eq!(pattern.find(&text[..text.len() - 1]), None);
+        assert!(pattern.visited.borrow().bits.capacity() * 64 <= MAX_BACKTRACK_STATES);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::cli::Options;
+use crate::direction::Direction;
+use crate::filtering::active_filters;
+use crate::result::Result;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// How the log is read, decided from the options alone, so that it can be shown by --dry-run
+// without reading anything.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReadPlan {
+    SelfCheck,
+    SelectedEntry,
+    Follow,
+    Stream,           // standard input read forward
+    FastReverse,      // a single file copied entry by entry from the end, without parsing
+    Merge(Direction), // entries of all inputs filtered, interleaved by timestamps if many
+    Fast,             // a single file copied as is
+}
+
+impl ReadPlan {
+    pub fn of(opts: &Options) -> Self {
+        let single_file = opts.input_files.len() == 1;
+        if opts.selfcheck {
+            ReadPlan::SelfCheck
+        } else if opts.entry_selector.is_some() {
+            ReadPlan::SelectedEntry
+        } else if opts.follow {
+            ReadPlan::Follow
+        } else if opts.input_files.is_empty() || (single_file && is_stdin(&opts.input_files[0])) {
+            ReadPlan::Stream
+        } else if opts.reverse && !opts.is_filtering_or_coloring() && single_file {
+            ReadPlan::FastReverse
+        } else if opts.is_filtering_or_coloring() || !single_file || opts.reverse {
+            // standard input cannot be read backward, so it is merged with other inputs forward
+            let has_stdin = opts.input_files.iter().any(|file| is_stdin(file));
+            if (opts.reverse || opts.last_per_level.is_some()) && !has_stdin {
+                ReadPlan::Merge(Direction::Reverse)
+            } else {
+                ReadPlan::Merge(Direction::Forward)
+            }
+        } else {
+            ReadPlan::Fast
+        }
+    }
+
+    fn describe(self, opts: &Options) -> &'static str {
+        match self {
+            ReadPlan::SelfCheck => "compare merging implementations",
+            ReadPlan::SelectedEntry => "read a single selected entry",
+            ReadPlan::Follow => "follow the file as it grows, parsing entries",
+            ReadPlan::Stream if opts.reverse_buffer => {
+                "read entries from standard input, buffering them to reverse"
+            }
+            ReadPlan::Stream => "read entries forward from standard input",
+            ReadPlan::FastReverse => "copy entries from the end without parsing them (fast path)",
+            ReadPlan::Merge(Direction::Forward) => "read entries forward, parsing them",
+            ReadPlan::Merge(Direction::Reverse) => {
+                "read entries backward from the end, parsing them"
+            }
+            ReadPlan::Fast => "copy the file as is (fast path)",
+        }
+    }
+}
+
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+// whether reading the input can seek, e.g. to read it backward
+fn describe_input(path: &Path) -> String {
+    if is_stdin(path) {
+        return "standard input, not seekable".to_string();
+    }
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => format!("{} bytes, seekable", metadata.len()),
+        Ok(_) => "not seekable".to_string(),
+        Err(e) => format!("cannot be read: {}", e),
+    }
+}
+
+pub fn write_plan(mut writer: impl Write, opts: &Options) -> Result<()> {
+    let plan = ReadPlan::of(opts);
+    writeln!(writer, "plan: {}", plan.describe(opts))?;
+
+    if let ReadPlan::Merge(direction) = plan {
+        if opts.input_files.len() > 1 {
+            let order = match direction {
+                Direction::Forward => "oldest first",
+                Direction::Reverse => "newest first",
+            };
+            writeln!(
+                writer,
+                "merge: {} inputs interleaved by timestamps, {}",
+                opts.input_files.len(),
+                order
+            )?;
+        }
+    }
+
+    if opts.input_files.is_empty() {
+        writeln!(writer, "input: - ({})", describe_input(Path::new("-")))?;
+    }
+    for path in &opts.input_files {
+        writeln!(
+            writer,
+            "input: {} ({})",
+            path.display(),
+            describe_input(path)
+        )?;
+    }
+
+    let filters = active_filters(&opts.filtering_options);
+    if filters.is_empty() {
+        writeln!(writer, "filters: none")?;
+    } else {
+        writeln!(writer, "filters: {}", filters.join(", "))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::FilteringOptions;
+    use crate::log_entry::LogLevel;
+    use std::path::PathBuf;
+
+    #[test]
+    fn read_plan_two_files_reverse() -> Result<()> {
+        let dir = std::env::temp_dir();
+        let paths: Vec<PathBuf> = ["a", "b"]
+            .iter()
+            .map(|name| dir.join(format!("riolog-{}-plan-{}.log", std::process::id(), name)))
+            .collect();
+        for path in &paths {
+            fs::write(path, b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: B\n\n")?;
+        }
+
+        let opts = Options {
+            input_files: paths.clone(),
+            reverse: true,
+            filtering_options: FilteringOptions {
+                min_level: Some(LogLevel::Warning),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut out_buf = Vec::new();
+        let written = write_plan(&mut out_buf, &opts);
+        for path in &paths {
+            fs::remove_file(path)?;
+        }
+        written?;
+
+        assert_eq!(ReadPlan::of(&opts), ReadPlan::Merge(Direction::Reverse));
+        assert_eq!(
+            String::from_utf8_lossy(&out_buf),
+            format!(
+                "plan: read entries backward from the end, parsing them\n\
+                 merge: 2 inputs interleaved by timestamps, newest first\n\
+                 input: {} (46 bytes, seekable)\n\
+                 input: {} (46 bytes, seekable)\n\
+                 filters: level\n",
+                paths[0].display(),
+                paths[1].display()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_plan_fast_paths_and_stdin() {
+        let file = |reverse: bool, input_files: &[&str]| Options {
+            input_files: input_files.iter().map(PathBuf::from).collect(),
+            reverse,
+            ..Default::default()
+        };
+        assert_eq!(ReadPlan::of(&file(false, &["a.log"])), ReadPlan::Fast);
+        assert_eq!(ReadPlan::of(&file(true, &["a.log"])), ReadPlan::FastReverse);
+        assert_eq!(ReadPlan::of(&file(true, &["-"])), ReadPlan::Stream);
+        assert_eq!(
+            ReadPlan::of(&file(true, &["a.log", "-"])),
+            ReadPlan::Merge(Direction::Forward)
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::cell::Cell;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+const RENDER_INTERVAL: Duration = Duration::from_millis(100);
+const CODE_CLEAR_LINE: &[u8] = b"\r\x1B[K";
+
+// progress of reading inputs of known total size, rendered to stderr
+pub struct Progress {
+    total: u64,
+    done: Cell<u64>,
+    start: Instant,
+    last_render: Cell<Option<Instant>>,
+}
+
+impl Progress {
+    pub fn new(total: u64) -> Self {
+        Progress {
+            total,
+            done: Cell::new(0),
+            start: Instant::now(),
+            last_render: Cell::new(None),
+        }
+    }
+
+    pub fn advance(&self, bytes: u64) {
+        self.done.set(self.done.get() + bytes);
+
+        let now = Instant::now();
+        let due = self
+            .last_render
+            .get()
+            .is_none_or(|last_render| now.duration_since(last_render) >= RENDER_INTERVAL);
+        if due {
+            self.last_render.set(Some(now));
+            let line = render(self.done.get(), self.total, now.duration_since(self.start));
+            let _ = io::stderr().write_all(line.as_bytes());
+        }
+    }
+
+    pub fn finish(&self) {
+        if self.last_render.get().is_some() {
+            let _ = io::stderr().write_all(CODE_CLEAR_LINE);
+        }
+    }
+}
+
+// passes all reads through, reporting the number of bytes read to the progress (if any)
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: Option<Rc<Progress>>,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, progress: Option<Rc<Progress>>) -> Self {
+        ProgressReader { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        if let Some(progress) = &self.progress {
+            progress.advance(bytes_read as u64);
+        }
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Seek> Seek for ProgressReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+pub fn eta(done: u64, total: u64, elapsed: Duration) -> Option<Duration> {
+    if done == 0 {
+        None
+    } else {
+        let remaining = total.saturating_sub(done);
+        let nanos = elapsed.as_nanos() * u128::from(remaining) / u128::from(done);
+        Some(Duration::from_nanos(nanos as u64))
+    }
+}
+
+fn render(done: u64, total: u64, elapsed: Duration) -> String {
+    let ratio = if total == 0 {
+        1.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    };
+    let filled = (ratio * BAR_WIDTH as f64) as usize;
+
+    let eta = match eta(done, total, elapsed) {
+        Some(eta) => {
+            let secs = eta.as_secs();
+            format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        }
+        None => "--:--:--".to_string(),
+    };
+
+    format!(
+        "\r[{}{}] {:3.0}% ETA {}",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        ratio * 100.0,
+        eta
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eta_known_rate() {
+        assert_eq!(
+            eta(250, 1000, Duration::from_secs(10)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            eta(1000, 1000, Duration::from_secs(10)),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(eta(0, 1000, Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn render_bar() {
+        assert_eq!(
+            render(500, 1000, Duration::from_secs(3723)),
+            format!("\r[{}{}]  50% ETA 01:02:03", "#".repeat(15), "-".repeat(15))
+        );
+    }
+}
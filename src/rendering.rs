@@ -0,0 +1,785 @@
+use crate::eol;
+use crate::log_entry::{LogEntry, NextGap};
+use crate::pattern::Pattern;
+use chrono::{Duration, NaiveDateTime, Timelike};
+use std::ops::Range;
+use std::path::Path;
+
+const ALIGN_LEVEL_WIDTH: usize = 8;
+const ALIGN_PID_WIDTH: usize = 7;
+const ALIGN_CATEGORY_WIDTH: usize = 20;
+const TIMESTAMP_LEN: usize = 23;
+const SUMMARY_MESSAGE_WIDTH: usize = 80;
+const TIMESTAMP_MASK: &[u8] = b"YYYY-MM-DD hh:mm:ss.sss";
+const TIMESTAMP_HOUR_LEN: usize = 13; // "YYYY-MM-DD hh"
+
+// splits the body into its contents and the run of line terminators ending the entry
+fn split_trailing_eol(body: &[u8]) -> (&[u8], &[u8]) {
+    body.split_at(eol::trim_eol(body).len())
+}
+
+pub fn truncate_lines(body: &mut Vec<u8>, max_lines: usize) {
+    let (contents, trailing_eol) = split_trailing_eol(body);
+    let line_count = contents.split(|&c| c == b'\n').count();
+
+    if line_count <= max_lines {
+        return;
+    }
+
+    let kept_len = contents
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| c == b'\n')
+        .nth(max_lines - 1)
+        .map_or(contents.len(), |(pos, _)| pos + 1);
+
+    let marker = format!("… ({} more lines)", line_count - max_lines);
+    let trailing_eol = trailing_eol.to_vec();
+
+    body.truncate(kept_len);
+    body.extend_from_slice(marker.as_bytes());
+    body.extend_from_slice(if trailing_eol.is_empty() {
+        eol::EOL
+    } else {
+        &trailing_eol
+    });
+}
+
+// whether the body spans more than one line, not counting the line terminators ending the entry
+pub fn is_multiline(body: &[u8]) -> bool {
+    split_trailing_eol(body).0.contains(&b'\n')
+}
+
+// joins the lines of the body into one with the marker in place of each interior line break
+pub fn flatten_lines(body: &mut Vec<u8>, marker: &[u8]) {
+    let (contents, trailing_eol) = split_trailing_eol(body);
+    if !contents.contains(&b'\n') {
+        return;
+    }
+
+    let mut flattened = Vec::with_capacity(body.len());
+    for (i, line) in contents.split(|&c| c == b'\n').enumerate() {
+        if i > 0 {
+            flattened.extend_from_slice(marker);
+        }
+        flattened.extend_from_slice(line.strip_suffix(b"\r").unwrap_or(line));
+    }
+    flattened.extend_from_slice(trailing_eol);
+    *body = flattened;
+}
+
+// appends the open marker to the first line and the close marker to the last line of the body
+pub fn add_fold_markers(body: &mut Vec<u8>, open: &[u8], close: &[u8]) {
+    let contents_len = split_trailing_eol(body).0.len();
+    body.splice(contents_len..contents_len, close.iter().copied());
+
+    let first_line_len = eol::trim_eol(match body.iter().position(|&c| c == b'\n') {
+        Some(pos) => &body[..=pos],
+        None => body,
+    })
+    .len();
+    body.splice(first_line_len..first_line_len, open.iter().copied());
+}
+
+// replaces the pid with 0 and the timestamp with a placeholder of the same width in the first
+// `header_len` bytes of the body, keeping the padding of aligned headers. Returns the length
+// of the masked header
+pub fn mask_header(body: &mut Vec<u8>, header_len: usize) -> usize {
+    let header_len = header_len.min(body.len());
+    let pid_start = match body[..header_len].iter().position(|&c| c == b'<') {
+        Some(pos) => pos + 1,
+        None => return header_len,
+    };
+    let pid_end = match body[pid_start..header_len].iter().position(|&c| c == b'>') {
+        Some(len) => pid_start + len,
+        None => return header_len,
+    };
+
+    let padding = body[pid_start..pid_end]
+        .iter()
+        .take_while(|&&c| c == b' ')
+        .count();
+    let mut header_len = header_len;
+    if pid_start + padding < pid_end
+        && body[pid_start + padding..pid_end]
+            .iter()
+            .all(u8::is_ascii_digit)
+    {
+        let masked_pid = if padding > 0 {
+            format!("{:>width$}", 0, width = pid_end - pid_start)
+        } else {
+            "0".to_string()
+        };
+        header_len = header_len + masked_pid.len() - (pid_end - pid_start);
+        body.splice(pid_start..pid_end, masked_pid.bytes());
+    }
+
+    if let Some(ts_range) = timestamp_range(body, header_len) {
+        body[ts_range].copy_from_slice(TIMESTAMP_MASK);
+    }
+
+    header_len
+}
+
+// position of the timestamp following the pid in the first `header_len` bytes of the body
+fn timestamp_range(body: &[u8], header_len: usize) -> Option<Range<usize>> {
+    let ts_start = body[..header_len]
+        .iter()
+        .position(|&c| c == b'>')
+        .map(|pos| pos + 2)
+        .filter(|&pos| pos + TIMESTAMP_LEN <= header_len)?;
+    let ts_range = ts_start..ts_start + TIMESTAMP_LEN;
+    std::str::from_utf8(&body[ts_range.clone()])
+        .ok()
+        .and_then(|ts| NaiveDateTime::parse_from_str(ts, "%F %T%.3f").ok())
+        .map(|_| ts_range)
+}
+
+pub fn is_same_hour(timestamp: NaiveDateTime, other: NaiveDateTime) -> bool {
+    timestamp.date() == other.date() && timestamp.hour() == other.hour()
+}
+
+// drops the date and the hour from the timestamp in the first `header_len` bytes of the body,
+// leaving e.g. ":00:00.000". Returns the length of the shortened header
+pub fn compact_timestamp(body: &mut Vec<u8>, header_len: usize) -> usize {
+    let header_len = header_len.min(body.len());
+    match timestamp_range(body, header_len) {
+        Some(ts_range) => {
+            body.drain(ts_range.start..ts_range.start + TIMESTAMP_HOUR_LEN);
+            header_len - TIMESTAMP_HOUR_LEN
+        }
+        None => header_len,
+    }
+}
+
+// re-emits the style after every SGR sequence of the text which only resets the attributes or
+// the foreground color, so that colors embedded in the input do not end the style of the entry
+pub fn restore_style_after_resets(text: &[u8], style: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(text.len());
+    let mut pos = 0;
+
+    while let Some(start) = text[pos..]
+        .windows(2)
+        .position(|window| window == b"\x1B[")
+        .map(|offset| pos + offset)
+    {
+        let params_start = start + 2;
+        let params_len = text[params_start..]
+            .iter()
+            .position(|&c| !(c.is_ascii_digit() || c == b';'));
+        let end = match params_len {
+            Some(len) if text[params_start + len] == b'm' => params_start + len + 1,
+            _ => {
+                output.extend_from_slice(&text[pos..params_start]);
+                pos = params_start;
+                continue;
+            }
+        };
+
+        output.extend_from_slice(&text[pos..end]);
+        let is_reset = text[params_start..end - 1]
+            .split(|&c| c == b';')
+            .all(|param| matches!(param, b"" | b"0" | b"39"));
+        if is_reset {
+            output.extend_from_slice(style);
+        }
+        pos = end;
+    }
+
+    output.extend_from_slice(&text[pos..]);
+    output
+}
+
+// cuts the body at the first match of the pattern found from `start` on, marking the cut
+// with an ellipsis
+pub fn cut_at_pattern(body: &mut Vec<u8>, pattern: &Pattern, start: usize) {
+    let (contents, trailing_eol) = split_trailing_eol(body);
+    let cut = match pattern.find_at(contents, start.min(contents.len())) {
+        Some(range) => range.start,
+        None => return,
+    };
+    let trailing_eol = trailing_eol.to_vec();
+
+    body.truncate(cut);
+    body.extend_from_slice("…".as_bytes());
+    body.extend_from_slice(if trailing_eol.is_empty() {
+        eol::EOL
+    } else {
+        &trailing_eol
+    });
+}
+
+// replaces all matches of the pattern found from `start` on
+pub fn replace_all(body: &mut Vec<u8>, pattern: &Pattern, replacement: &[u8], start: usize) {
+    let mut output = Vec::with_capacity(body.len());
+    let mut pos = start.min(body.len());
+    output.extend_from_slice(&body[..pos]);
+
+    while let Some(range) = pattern.find_at(body, pos) {
+        output.extend_from_slice(&body[pos..range.start]);
+        output.extend_from_slice(replacement);
+        if range.is_empty() {
+            // step over a character to avoid matching the same empty string again
+            match body.get(range.end) {
+                Some(&c) => output.push(c),
+                None => {
+                    pos = range.end;
+                    break;
+                }
+            }
+            pos = range.end + 1;
+        } else {
+            pos = range.end;
+        }
+    }
+
+    output.extend_from_slice(&body[pos..]);
+    *body = output;
+}
+
+// label of an input file extracted from its path, e.g. "web01" from "/var/log/web01.log"
+pub fn host_label(pattern: &Pattern, path: &Path) -> Option<String> {
+    let path = path.to_string_lossy();
+    let captures = pattern.captures(path.as_bytes())?;
+    let range = captures.get(1).or_else(|| captures.get(0))?;
+    // the match may end within a multi-byte character, e.g. of a `.` pattern
+    Some(String::from_utf8_lossy(&path.as_bytes()[range]).into_owned())
+}
+
+// flags of header fields found in the entry, e.g. "[TLcp]" with only timestamp and level present
+pub fn parse_flags(entry: &LogEntry) -> String {
+    let flag = |present: bool, letter: char| {
+        if present {
+            letter
+        } else {
+            letter.to_ascii_lowercase()
+        }
+    };
+    format!(
+        "[{}{}{}{}]",
+        flag(entry.timestamp().is_some(), 'T'),
+        flag(entry.level().is_some(), 'L'),
+        flag(entry.category().is_some(), 'C'),
+        flag(entry.pid().is_some(), 'P')
+    )
+}
+
+const PALETTE: [&[u8]; 6] = [
+    b"\x1B[31m",
+    b"\x1B[32m",
+    b"\x1B[33m",
+    b"\x1B[34m",
+    b"\x1B[35m",
+    b"\x1B[36m",
+];
+
+// color code assigned to a field value, stable across runs (FNV-1a hash)
+pub fn palette_code(key: &[u8]) -> &'static [u8] {
+    let hash = key.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &c| {
+        (hash ^ u64::from(c)).wrapping_mul(0x0100_0000_01b3)
+    });
+    PALETTE[(hash % PALETTE.len() as u64) as usize]
+}
+
+const SOURCE_PALETTE: [&[u8]; 6] = [
+    b"\x1B[96m",
+    b"\x1B[95m",
+    b"\x1B[92m",
+    b"\x1B[93m",
+    b"\x1B[94m",
+    b"\x1B[91m",
+];
+
+// color code of the label of an input file, by its position among the inputs
+pub fn source_code(source: usize) -> &'static [u8] {
+    SOURCE_PALETTE[source % SOURCE_PALETTE.len()]
+}
+
+// annotation of the time until the next entry, e.g. "(+1.250s) "
+pub fn next_gap_prefix(next_gap: NextGap) -> String {
+    match next_gap {
+        NextGap::Gap(gap) => {
+            let millis = gap.num_milliseconds();
+            let sign = if millis < 0 { '-' } else { '+' };
+            let millis = millis.abs();
+            format!("({}{}.{:03}s) ", sign, millis / 1000, millis % 1000)
+        }
+        NextGap::Last => "(last) ".to_string(),
+    }
+}
+
+// annotation of the duration of the operation ended by the entry, e.g. "(took 1.250s) "
+pub fn elapsed_prefix(elapsed: Duration) -> String {
+    let millis = elapsed.num_milliseconds();
+    let sign = if millis < 0 { "-" } else { "" };
+    let millis = millis.abs();
+    format!("(took {}{}.{:03}s) ", sign, millis / 1000, millis % 1000)
+}
+
+// collapses an entry looking like a stack trace (a first line followed by indented
+// continuation lines) to its first line and the number of omitted frames
+pub fn collapse_trace(body: &mut Vec<u8>) {
+    let (contents, trailing_eol) = split_trailing_eol(body);
+    let mut lines = contents.split(|&c| c == b'\n');
+    let first_line_len = lines.next().map_or(0, <[u8]>::len);
+
+    let mut frame_count = 0;
+    for line in lines {
+        match line.first() {
+            Some(b' ') | Some(b'\t') => frame_count += 1,
+            Some(b'\r') | None => {}
+            Some(_) => return,
+        }
+    }
+
+    if frame_count == 0 {
+        return;
+    }
+
+    let marker = format!(" [+{} frames]", frame_count);
+    let trailing_eol = trailing_eol.to_vec();
+
+    let first_line_len = if body[..first_line_len].ends_with(b"\r") {
+        first_line_len - 1
+    } else {
+        first_line_len
+    };
+    body.truncate(first_line_len);
+    body.extend_from_slice(marker.as_bytes());
+    body.extend_from_slice(if trailing_eol.is_empty() {
+        eol::EOL
+    } else {
+        &trailing_eol
+    });
+}
+
+// shortens the field to at most `width` characters, marking the cut with an ellipsis
+fn fit(field: &str, width: usize) -> String {
+    if field.chars().count() <= width {
+        field.to_string()
+    } else {
+        let mut fitted: String = field.chars().take(width - 1).collect();
+        fitted.push('…');
+        fitted
+    }
+}
+
+pub fn align_header(entry: &LogEntry) -> Option<Vec<u8>> {
+    let level = entry.level()?;
+    let pid = entry.pid()?;
+    let timestamp = String::from_utf8_lossy(entry.timestamp_field()?);
+    let category = String::from_utf8_lossy(entry.category()?);
+    let message = entry.message()?;
+
+    let header = format!(
+        "-{:<level_width$}:<{:>pid_width$}> {} [{:<category_width$}]: ",
+        fit(level.name(), ALIGN_LEVEL_WIDTH),
+        pid,
+        timestamp,
+        fit(&category, ALIGN_CATEGORY_WIDTH),
+        level_width = ALIGN_LEVEL_WIDTH,
+        pid_width = ALIGN_PID_WIDTH,
+        category_width = ALIGN_CATEGORY_WIDTH,
+    );
+
+    let mut aligned = header.into_bytes();
+    aligned.extend_from_slice(message);
+    Some(aligned)
+}
+
+// timestamp, level and the beginning of the message with runs of whitespace including line
+// breaks collapsed into single spaces, e.g. "2020-01-01 20:00:00.000 info     Request handled"
+pub fn summary_line(entry: &LogEntry) -> Vec<u8> {
+    let timestamp = entry.timestamp().map_or_else(
+        || "-".to_string(),
+        |timestamp| timestamp.format("%F %T%.3f").to_string(),
+    );
+    let level = entry.level().map_or("-", |level| level.name());
+    let message = String::from_utf8_lossy(entry.message().unwrap_or_else(|| entry.contents()));
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    format!(
+        "{:<timestamp_width$} {:<level_width$} {}",
+        timestamp,
+        level,
+        fit(&message, SUMMARY_MESSAGE_WIDTH),
+        timestamp_width = TIMESTAMP_LEN,
+        level_width = ALIGN_LEVEL_WIDTH,
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTILINE_ENTRY: &[u8] =
+        b"-critical:<1> 2020-01-01 20:00:00.000 UTC [A]: Exception\n  at a\n  at b\n  at c\n\n";
+
+    #[test]
+    fn truncate_lines_long_entry() {
+        let mut body = MULTILINE_ENTRY.to_vec();
+        truncate_lines(&mut body, 2);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-critical:<1> 2020-01-01 20:00:00.000 UTC [A]: Exception\n  at a\n… (2 more lines)\n\n"
+        );
+    }
+
+    #[test]
+    fn truncate_lines_header_only() {
+        let mut body = MULTILINE_ENTRY.to_vec();
+        truncate_lines(&mut body, 1);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-critical:<1> 2020-01-01 20:00:00.000 UTC [A]: Exception\n… (3 more lines)\n\n"
+        );
+    }
+
+    #[test]
+    fn truncate_lines_short_entry() {
+        let mut body = MULTILINE_ENTRY.to_vec();
+        truncate_lines(&mut body, 4);
+        assert_eq!(body, MULTILINE_ENTRY);
+    }
+
+    #[test]
+    fn is_multiline_trailing_eol() {
+        assert!(is_multiline(MULTILINE_ENTRY));
+        assert!(!is_multiline(
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Message\n\n"
+        ));
+    }
+
+    #[test]
+    fn add_fold_markers_multiline_entry() {
+        let mut body = MULTILINE_ENTRY.to_vec();
+        add_fold_markers(&mut body, b" {{{", b" }}}");
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-critical:<1> 2020-01-01 20:00:00.000 UTC [A]: Exception {{{\n  at a\n  at b\n  at c }}}\n\n"
+        );
+    }
+
+    #[test]
+    fn summary_line_multiline_entry() {
+        let entry = LogEntry::from_contents(MULTILINE_ENTRY.to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&summary_line(&entry)),
+            "2020-01-01 20:00:00.000 critical Exception at a at b at c"
+        );
+
+        let entry = LogEntry::from_contents(b"No header\n\n".to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&summary_line(&entry)),
+            "-                       -        No header"
+        );
+    }
+
+    #[test]
+    fn summary_line_truncates_multibyte_message() {
+        let message = "žluťoučký kůň ".repeat(10);
+        let entry = LogEntry::from_contents(
+            format!("-info:<1> 2020-01-01 20:00:00.000 UTC [A]: {}\n\n", message).into_bytes(),
+        );
+        let summary = String::from_utf8(summary_line(&entry)).unwrap();
+        let shown = summary
+            .strip_prefix("2020-01-01 20:00:00.000 info     ")
+            .unwrap();
+        assert_eq!(shown.chars().count(), 80);
+        assert!(shown.ends_with('…'));
+        assert!(message.starts_with(shown.trim_end_matches('…')));
+    }
+
+    #[test]
+    fn flatten_lines_multiline_entry() {
+        let mut body = MULTILINE_ENTRY.to_vec();
+        flatten_lines(&mut body, "⏎".as_bytes());
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-critical:<1> 2020-01-01 20:00:00.000 UTC [A]: Exception⏎  at a⏎  at b⏎  at c\n\n"
+        );
+
+        let mut body = b"First\r\n\r\nThird\r\n\r\n".to_vec();
+        flatten_lines(&mut body, b" ");
+        assert_eq!(body, b"First  Third\r\n\r\n");
+
+        let mut body = b"Single\n\n".to_vec();
+        flatten_lines(&mut body, b" ");
+        assert_eq!(body, b"Single\n\n");
+    }
+
+    #[test]
+    fn compact_timestamp_header() {
+        let entry = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: 2020-01-01 20:00:00.000\n\n";
+        let header_len = entry.len() - b"2020-01-01 20:00:00.000\n\n".len();
+        let mut body = entry.to_vec();
+        let header_len = compact_timestamp(&mut body, header_len);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-info:<1> :00:00.000 UTC [A]: 2020-01-01 20:00:00.000\n\n"
+        );
+        assert_eq!(&body[header_len..], b"2020-01-01 20:00:00.000\n\n");
+
+        let mut body = b"No header 2020-01-01 20:00:00.000\n\n".to_vec();
+        assert_eq!(compact_timestamp(&mut body, 0), 0);
+        assert_eq!(body, b"No header 2020-01-01 20:00:00.000\n\n");
+    }
+
+    #[test]
+    fn mask_header_plain() {
+        let entry = b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Message <1>\n\n";
+        let header_len = entry.len() - b"Message <1>\n\n".len();
+        let mut body = entry.to_vec();
+        let header_len = mask_header(&mut body, header_len);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-info:<0> YYYY-MM-DD hh:mm:ss.sss UTC [A]: Message <1>\n\n"
+        );
+        assert_eq!(&body[header_len..], b"Message <1>\n\n");
+    }
+
+    #[test]
+    fn mask_header_aligned() {
+        let entry = LogEntry::from_contents(
+            b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Message\n\n".to_vec(),
+        );
+        let mut body = align_header(&entry).unwrap();
+        let header_len = body.len() - b"Message\n\n".len();
+        mask_header(&mut body, header_len);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-info    :<      0> YYYY-MM-DD hh:mm:ss.sss UTC [A                   ]: Message\n\n"
+        );
+    }
+
+    #[test]
+    fn mask_header_without_header() {
+        let mut body = b"MESSAGE <Alphabet> 2020-01-01 20:00:00.000\n\n".to_vec();
+        assert_eq!(mask_header(&mut body, 0), 0);
+        assert_eq!(
+            body,
+            b"MESSAGE <Alphabet> 2020-01-01 20:00:00.000\n\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn restore_style_after_resets_embedded_color() {
+        let text = b"Status: \x1B[32mOK\x1B[0m, took \x1B[1;33m5 ms\x1B[m done";
+        assert_eq!(
+            String::from_utf8_lossy(&restore_style_after_resets(text, b"\x1B[97m")),
+            "Status: \x1B[32mOK\x1B[0m\x1B[97m, took \x1B[1;33m5 ms\x1B[m\x1B[97m done"
+        );
+    }
+
+    #[test]
+    fn restore_style_after_resets_other_sequences() {
+        let text = b"\x1B[2J cleared \x1B[ unfinished \x1B[0;31mred\x1B[";
+        assert_eq!(restore_style_after_resets(text, b"\x1B[97m"), text.to_vec());
+    }
+
+    #[test]
+    fn cut_at_pattern_marker() {
+        let entry = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Failed stacktrace=a.b.c\n\n";
+        let header_len = entry.len() - b"Failed stacktrace=a.b.c\n\n".len();
+        let mut body = entry.to_vec();
+        cut_at_pattern(&mut body, &Pattern::new("stacktrace=").unwrap(), header_len);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Failed …\n\n"
+        );
+    }
+
+    #[test]
+    fn cut_at_pattern_keeps_header() {
+        let entry = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Message\n\n";
+        let header_len = entry.len() - b"Message\n\n".len();
+        let mut body = entry.to_vec();
+        cut_at_pattern(&mut body, &Pattern::new("info|Absent").unwrap(), header_len);
+        assert_eq!(body, entry.to_vec());
+    }
+
+    #[test]
+    fn replace_all_two_substitutions() {
+        let entry =
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Request 0a1b2c3d-77 done in 5 ms\n\n";
+        let header_len = entry.len() - b"Request 0a1b2c3d-77 done in 5 ms\n\n".len();
+        let mut body = entry.to_vec();
+        replace_all(
+            &mut body,
+            &Pattern::new("[0-9a-f]{8}-").unwrap(),
+            b"REQ-",
+            header_len,
+        );
+        replace_all(&mut body, &Pattern::new(" ms").unwrap(), b"ms", header_len);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Request REQ-77 done in 5ms\n\n"
+        );
+    }
+
+    #[test]
+    fn replace_all_keeps_header() {
+        let entry = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: info\n\n";
+        let header_len = entry.len() - b"info\n\n".len();
+        let mut body = entry.to_vec();
+        replace_all(
+            &mut body,
+            &Pattern::new("info").unwrap(),
+            b"INFO",
+            header_len,
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-info:<1> 2020-01-01 20:00:00.000 UTC [A]: INFO\n\n"
+        );
+    }
+
+    #[test]
+    fn host_label_from_file_name() {
+        let pattern = Pattern::new(r"([^/]+)\.log$").unwrap();
+        assert_eq!(
+            host_label(&pattern, Path::new("/var/log/web01.log")),
+            Some("web01".to_string())
+        );
+        assert_eq!(
+            host_label(&pattern, Path::new("web01.log")),
+            Some("web01".to_string())
+        );
+        assert_eq!(host_label(&pattern, Path::new("-")), None);
+
+        let pattern = Pattern::new("db[0-9]+").unwrap();
+        assert_eq!(
+            host_label(&pattern, Path::new("logs/db02-app.log")),
+            Some("db02".to_string())
+        );
+    }
+
+    #[test]
+    fn host_label_non_ascii_path() {
+        let pattern = Pattern::new("^.").unwrap();
+        assert_eq!(
+            host_label(&pattern, Path::new("żółw.log")),
+            Some("\u{FFFD}".to_string())
+        );
+
+        let pattern = Pattern::new(r"([^/]+)\.log$").unwrap();
+        assert_eq!(
+            host_label(&pattern, Path::new("/var/log/serwer-łódź.log")),
+            Some("serwer-łódź".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_flags_full_and_missing_header() {
+        let entry = LogEntry::from_contents(
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Message\n\n".to_vec(),
+        );
+        assert_eq!(parse_flags(&entry), "[TLCP]");
+
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        assert_eq!(parse_flags(&entry), "[tlcp]");
+    }
+
+    #[test]
+    fn palette_code_stable_and_distinct() {
+        let pid_1 = palette_code(b"16866");
+        let pid_2 = palette_code(b"16867");
+        assert_ne!(pid_1, pid_2);
+        assert_eq!(palette_code(b"16866"), pid_1);
+        assert_eq!(palette_code(b"16867"), pid_2);
+    }
+
+    #[test]
+    fn next_gap_prefix_forward_and_backward() {
+        assert_eq!(
+            next_gap_prefix(NextGap::Gap(Duration::milliseconds(61250))),
+            "(+61.250s) "
+        );
+        assert_eq!(
+            next_gap_prefix(NextGap::Gap(Duration::milliseconds(-500))),
+            "(-0.500s) "
+        );
+        assert_eq!(next_gap_prefix(NextGap::Last), "(last) ");
+    }
+
+    #[test]
+    fn elapsed_prefix_milliseconds() {
+        assert_eq!(
+            elapsed_prefix(Duration::milliseconds(1250)),
+            "(took 1.250s) "
+        );
+        assert_eq!(elapsed_prefix(Duration::zero()), "(took 0.000s) ");
+    }
+
+    #[test]
+    fn collapse_trace_indented_frames() {
+        let mut body = MULTILINE_ENTRY.to_vec();
+        collapse_trace(&mut body);
+        assert_eq!(
+            String::from_utf8_lossy(&body),
+            "-critical:<1> 2020-01-01 20:00:00.000 UTC [A]: Exception [+3 frames]\n\n"
+        );
+    }
+
+    #[test]
+    fn collapse_trace_not_a_trace() {
+        let entry = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Table\nrow 1\n  row 2\n\n";
+        let mut body = entry.to_vec();
+        collapse_trace(&mut body);
+        assert_eq!(body, entry.to_vec());
+
+        let entry = b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: Single line\n\n";
+        let mut body = entry.to_vec();
+        collapse_trace(&mut body);
+        assert_eq!(body, entry.to_vec());
+    }
+
+    #[test]
+    fn align_header_message_column() {
+        let entries = [
+            &b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: First\n\n"[..],
+            b"-critical:<16866> 2020-01-01 20:00:01.000 UTC [Category]: Second\n\n",
+            b"-debug:<123456789> 2020-01-01 20:00:02.000 UTC [AVeryLongCategoryNameIndeed]: Third\n\n",
+        ];
+
+        let aligned: Vec<String> = entries
+            .iter()
+            .map(|contents| LogEntry::from_contents(contents.to_vec()))
+            .map(|entry| String::from_utf8(align_header(&entry).unwrap()).unwrap())
+            .collect();
+
+        // pids wider than the column are kept whole and shift the rest of the header
+        let columns: Vec<usize> = aligned[..2]
+            .iter()
+            .map(|line| line[..line.find("]: ").unwrap()].chars().count())
+            .collect();
+        assert!(columns.iter().all(|&column| column == columns[0]));
+
+        assert_eq!(
+            aligned[1],
+            "-critical:<  16866> 2020-01-01 20:00:01.000 UTC [Category            ]: Second\n\n"
+        );
+        assert_eq!(
+            aligned[2],
+            "-debug   :<123456789> 2020-01-01 20:00:02.000 UTC [AVeryLongCategoryNa…]: Third\n\n"
+        );
+    }
+
+    #[test]
+    fn align_header_rfc3339_timestamp() {
+        let entry = LogEntry::from_contents(
+            b"-info:<16866> 2020-01-13T20:42:00.476+02:00 [Category]: Message\n\n".to_vec(),
+        );
+        assert_eq!(
+            String::from_utf8(align_header(&entry).unwrap()).unwrap(),
+            "-info    :<  16866> 2020-01-13T20:42:00.476+02:00 [Category            ]: Message\n\n"
+        );
+    }
+
+    #[test]
+    fn align_header_without_header() {
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        assert_eq!(align_header(&entry), None);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::log_entry::LogEntry;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+
+// offsets just past the last processed entry of each input file, written as comma separated
+// "source:offset" pairs with sources being indices of the input files, e.g. "0:1024,1:512"
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResumeToken {
+    offsets: Vec<u64>,
+}
+
+impl ResumeToken {
+    pub fn new(offsets: Vec<u64>) -> Self {
+        ResumeToken { offsets }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut offsets = Vec::new();
+        for pair in input.trim().split(',').filter(|pair| !pair.is_empty()) {
+            let (source, offset) = pair.split_once(':')?;
+            let source: usize = source.trim().parse().ok()?;
+            let offset: u64 = offset.trim().parse().ok()?;
+            if offsets.len() <= source {
+                offsets.resize(source + 1, 0);
+            }
+            offsets[source] = offset;
+        }
+        Some(ResumeToken { offsets })
+    }
+
+    pub fn offset(&self, source: usize) -> u64 {
+        self.offsets.get(source).copied().unwrap_or(0)
+    }
+
+    pub fn sources(&self) -> usize {
+        self.offsets.len()
+    }
+}
+
+impl fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (source, offset) in self.offsets.iter().enumerate() {
+            if source > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}:{}", source, offset)?;
+        }
+        Ok(())
+    }
+}
+
+// Records how far each input has been processed by the entries passing to the output, which come
+// in the order of their inputs. An entry counts as processed only once the next one is requested
+// (or the output is finished), so that an entry interrupted while being written is processed
+// again on resume.
+#[derive(Debug, Default)]
+pub struct ResumeTracker {
+    token: RefCell<ResumeToken>,
+    pending: Cell<Option<(usize, u64)>>,
+}
+
+impl ResumeTracker {
+    pub fn new(token: ResumeToken, sources: usize) -> Self {
+        let mut token = token;
+        token.offsets.resize(token.offsets.len().max(sources), 0);
+        ResumeTracker {
+            token: RefCell::new(token),
+            pending: Cell::new(None),
+        }
+    }
+
+    pub fn record(&self, entry: &LogEntry) {
+        self.commit();
+        if !entry.is_separator() {
+            self.pending.set(Some((entry.source(), entry.end_offset())));
+        }
+    }
+
+    pub fn finish(&self) {
+        self.commit();
+    }
+
+    pub fn token(&self) -> ResumeToken {
+        self.token.borrow().clone()
+    }
+
+    fn commit(&self) {
+        if let Some((source, end_offset)) = self.pending.take() {
+            let mut token = self.token.borrow_mut();
+            if token.offsets.len() <= source {
+                token.offsets.resize(source + 1, 0);
+            }
+            token.offsets[source] = end_offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_token_parse_and_display() {
+        let token = ResumeToken::parse("0:1024, 2:512").unwrap();
+        assert_eq!(token.offset(0), 1024);
+        assert_eq!(token.offset(1), 0);
+        assert_eq!(token.offset(2), 512);
+        assert_eq!(token.offset(3), 0);
+        assert_eq!(token.to_string(), "0:1024,1:0,2:512");
+        assert_eq!(ResumeToken::parse(""), Some(ResumeToken::default()));
+        assert_eq!(ResumeToken::parse("0=1024"), None);
+        assert_eq!(ResumeToken::parse("a:1"), None);
+    }
+}
@@ -4,6 +4,7 @@ use std::io::{Read, Seek, SeekFrom};
 pub struct RevReader<R: Read> {
     reader: R,
     pos: u64,
+    start: u64,
     buf: Vec<u8>,
     buf_pos: usize,
 }
@@ -18,22 +19,42 @@ impl<R: Read + Seek> RevReader<R> {
         Ok(RevReader {
             reader: inner,
             pos,
+            start: 0,
             buf,
             buf_pos: 0,
         })
     }
 
+    // reads only the part of the input after `start`, as if it began there
+    pub fn with_start(mut self, start: u64) -> Self {
+        self.start = start.min(self.pos);
+        self
+    }
+
     pub fn read_until(&mut self, byte: u8, skip_len: usize) -> Option<Vec<u8>> {
+        self.read_until_limited(byte, skip_len, usize::MAX)
+            .map(|(output, _)| output)
+    }
+
+    // Reads at most `limit` bytes of the line, leaving the rest of it for the next read. Returns
+    // also whether the line was read completely.
+    pub fn read_until_limited(
+        &mut self,
+        byte: u8,
+        skip_len: usize,
+        limit: usize,
+    ) -> Option<(Vec<u8>, bool)> {
         let mut output = Vec::new();
 
         loop {
             if self.buf_pos == 0 {
-                let bytes_to_read = if self.pos >= self.buf.len() as u64 {
+                let left = self.pos - self.start;
+                let bytes_to_read = if left >= self.buf.len() as u64 {
                     self.buf.len()
-                } else if self.pos > 0 {
-                    self.pos as usize
+                } else if left > 0 {
+                    left as usize
                 } else if !output.is_empty() {
-                    return Some(output);
+                    return Some((output, true));
                 } else {
                     return None;
                 };
@@ -49,12 +70,24 @@ impl<R: Read + Seek> RevReader<R> {
                 self.buf_pos = bytes_to_read;
             }
 
-            let mut find_it = self.buf[0..self.buf_pos].iter().enumerate().rev();
+            let start = memchr::memrchr(byte, &self.buf[0..self.buf_pos]);
+            let room = limit - output.len();
+            let line_start = start.map_or(0, |i| i + skip_len);
+            if self.buf_pos - line_start.min(self.buf_pos) > room {
+                Self::push_front(&mut output, &self.buf[(self.buf_pos - room)..self.buf_pos]);
+                self.buf_pos -= room;
+                return Some((output, false));
+            }
 
-            if let Some(i) = find_it.find(|(_, value)| **value == byte).map(|(i, _)| i) {
-                Self::push_front(&mut output, &self.buf[(i + skip_len)..self.buf_pos]);
+            if let Some(i) = start {
+                if line_start > self.buf_pos {
+                    // the rest of the separator was read before, at the end of the next block
+                    output.drain(..(line_start - self.buf_pos).min(output.len()));
+                } else {
+                    Self::push_front(&mut output, &self.buf[line_start..self.buf_pos]);
+                }
                 self.buf_pos = i;
-                return Some(output);
+                return Some((output, true));
             }
 
             Self::push_front(&mut output, &self.buf[0..self.buf_pos]);
@@ -158,4 +191,14 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn rev_reader_start() -> Result<()> {
+        let input: &[u8] = b"first line\nsecond line\nthird line";
+        let mut reader = RevReader::with_capacity(Cursor::new(input), 4)?.with_start(11);
+        assert_eq!(reader.read_until(b'\n', 1), Some(b"third line".to_vec()));
+        assert_eq!(reader.read_until(b'\n', 1), Some(b"second line".to_vec()));
+        assert_eq!(reader.read_until(b'\n', 1), None);
+        Ok(())
+    }
 }
@@ -0,0 +1,135 @@
+use crate::direction::Direction;
+use crate::error::Error;
+use crate::log_entry::LogEntry;
+use crate::log_entry_reader_mux::{LogEntryReaderHeapMux, LogEntryReaderMux};
+use crate::result::Result;
+use chrono::Duration;
+use streaming_iterator::StreamingIterator;
+
+// Merges the same inputs by the heap-based mux used for output and by the linear scan it
+// replaced, and fails at the first entry they disagree on. Returns the number of merged entries.
+pub fn check_mux<I: StreamingIterator<Item = LogEntry>>(
+    linear_iters: Vec<I>,
+    heap_iters: Vec<I>,
+    direction: Direction,
+    precision: Option<Duration>,
+    dedupe: bool,
+) -> Result<usize> {
+    compare_entries(
+        LogEntryReaderMux::new(linear_iters, direction)
+            .with_precision(precision)
+            .with_dedupe(dedupe),
+        LogEntryReaderHeapMux::new(heap_iters, direction)
+            .with_precision(precision)
+            .with_dedupe(dedupe),
+    )
+}
+
+pub fn compare_entries(
+    mut expected: impl StreamingIterator<Item = LogEntry>,
+    mut actual: impl StreamingIterator<Item = LogEntry>,
+) -> Result<usize> {
+    let mut count = 0;
+    loop {
+        match (expected.next(), actual.next()) {
+            (None, None) => return Ok(count),
+            (Some(expected), Some(actual)) if is_same_entry(expected, actual) => count += 1,
+            _ => return Err(Error::SelfCheckFailed(count + 1)),
+        }
+    }
+}
+
+fn is_same_entry(expected: &LogEntry, actual: &LogEntry) -> bool {
+    expected.source() == actual.source()
+        && expected.end_offset() == actual.end_offset()
+        && expected.contents() == actual.contents()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG_INPUTS: &[&[&[u8]]] = &[
+        &[
+            b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: A1",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: A2",
+            b"-info:<1> 2020-01-01 20:00:04.000 UTC [A]: A3",
+        ],
+        &[
+            b"No header",
+            b"-info:<2> 2020-01-01 20:00:01.000 UTC [A]: B1",
+            b"-info:<2> 2020-01-01 20:00:02.000 UTC [A]: B2",
+        ],
+        &[b"-info:<3> 2020-01-01 20:00:03.000 UTC [A]: C1"],
+    ];
+
+    fn to_iterators(
+        skipped: Option<&[u8]>,
+    ) -> Vec<impl StreamingIterator<Item = LogEntry> + 'static> {
+        LOG_INPUTS
+            .iter()
+            .enumerate()
+            .map(|(source, log)| {
+                log.iter()
+                    .filter(|lines| Some(**lines) != skipped)
+                    .map(|lines| LogEntry::from_contents(lines.to_vec()).with_source(source))
+                    .collect::<Vec<_>>()
+            })
+            .map(streaming_iterator::convert)
+            .collect()
+    }
+
+    #[test]
+    fn check_mux_identical() {
+        assert_eq!(
+            check_mux(
+                to_iterators(None),
+                to_iterators(None),
+                Direction::Forward,
+                None,
+                false
+            )
+            .unwrap(),
+            7
+        );
+        assert_eq!(
+            check_mux(
+                to_iterators(None),
+                to_iterators(None),
+                Direction::Forward,
+                Some(Duration::seconds(2)),
+                false
+            )
+            .unwrap(),
+            7
+        );
+    }
+
+    #[test]
+    fn check_mux_divergence() {
+        // the heap path misses an entry
+        let result = check_mux(
+            to_iterators(None),
+            to_iterators(Some(LOG_INPUTS[1][1])),
+            Direction::Forward,
+            None,
+            false,
+        );
+        assert!(matches!(result, Err(Error::SelfCheckFailed(3))));
+
+        // the heap path orders entries differently
+        let result = compare_entries(
+            LogEntryReaderMux::new(to_iterators(None), Direction::Forward),
+            LogEntryReaderHeapMux::new(to_iterators(None), Direction::Forward)
+                .with_precision(Some(Duration::seconds(5))),
+        );
+        assert!(matches!(result, Err(Error::SelfCheckFailed(3))));
+
+        // the heap path ends early
+        let result = compare_entries(
+            LogEntryReaderMux::new(to_iterators(None), Direction::Forward),
+            LogEntryReaderHeapMux::new(to_iterators(None), Direction::Forward).take(6),
+        );
+        assert!(matches!(result, Err(Error::SelfCheckFailed(7))));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::buffering::{spill_entries, spill_separator, SpillFile};
+use crate::cli::SortKey;
+use crate::eol;
+use crate::log_entry::{LevelMap, LogEntry};
+use crate::log_entry_reader::{LogEntryReader, LogEntryRevReader};
+use crate::result::Result;
+use std::cmp::Ordering;
+use std::io::BufReader;
+use std::rc::Rc;
+use streaming_iterator::StreamingIterator;
+
+const RUN_BUF_SIZE: usize = 64 * 1024;
+
+// Stable sort by the given key, placing entries missing the key (e.g. without header) last.
+pub fn sort_entries(entries: &mut [LogEntry], key: SortKey) {
+    entries.sort_by(|a, b| compare(a, b, key));
+}
+
+// Sorts entries which did not fit in memory: the spilled entries are read back in chunks within
+// the limit, each chunk is sorted into a file of its own and the sorted files are merged.
+pub fn sort_spilled(
+    spill_file: SpillFile,
+    key: SortKey,
+    limit: usize,
+    reverse: bool,
+    level_map: Rc<LevelMap>,
+) -> Result<SortedRuns> {
+    let mut reader =
+        LogEntryReader::new(BufReader::with_capacity(RUN_BUF_SIZE, spill_file), eol::EOL)
+            .with_separator(spill_separator())
+            .with_level_map(level_map.clone());
+    let mut runs = Vec::new();
+    let mut chunk = Vec::new();
+    let mut size = 0;
+
+    while let Some(entry) = reader.next() {
+        size += entry.contents().len();
+        chunk.push(entry.clone());
+        if size >= limit {
+            runs.push(sorted_run(&mut chunk, key)?);
+            size = 0;
+        }
+    }
+    if !chunk.is_empty() {
+        runs.push(sorted_run(&mut chunk, key)?);
+    }
+
+    let runs = runs
+        .into_iter()
+        .map(
+            |run| -> Result<Box<dyn StreamingIterator<Item = LogEntry>>> {
+                if reverse {
+                    Ok(Box::new(
+                        LogEntryRevReader::with_capacity(run, eol::EOL, RUN_BUF_SIZE)?
+                            .with_separator(spill_separator())
+                            .with_level_map(level_map.clone()),
+                    ))
+                } else {
+                    Ok(Box::new(
+                        LogEntryReader::new(BufReader::with_capacity(RUN_BUF_SIZE, run), eol::EOL)
+                            .with_separator(spill_separator())
+                            .with_level_map(level_map.clone()),
+                    ))
+                }
+            },
+        )
+        .collect::<Result<_>>()?;
+
+    Ok(SortedRuns {
+        runs,
+        key,
+        reverse,
+        current: None,
+        started: false,
+    })
+}
+
+fn sorted_run(chunk: &mut Vec<LogEntry>, key: SortKey) -> Result<SpillFile> {
+    sort_entries(chunk, key);
+    let run = spill_entries(chunk)?;
+    chunk.clear();
+    Ok(run)
+}
+
+// Merges sorted runs into one sequence, the same as sorting all their entries at once. Equal
+// entries come from the earliest run first, or from the latest one when read in reverse.
+pub struct SortedRuns {
+    runs: Vec<Box<dyn StreamingIterator<Item = LogEntry>>>,
+    key: SortKey,
+    reverse: bool,
+    current: Option<usize>,
+    started: bool,
+}
+
+impl StreamingIterator for SortedRuns {
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        match self.current {
+            Some(current) => self.runs[current].advance(),
+            None if !self.started => self.runs.iter_mut().for_each(|run| run.advance()),
+            None => return,
+        }
+        self.started = true;
+
+        self.current = None;
+        for (i, run) in self.runs.iter().enumerate() {
+            let entry = match run.get() {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let precedes = match self.get() {
+                Some(best) if self.reverse => compare(entry, best, self.key) != Ordering::Less,
+                Some(best) => compare(entry, best, self.key) == Ordering::Less,
+                None => true,
+            };
+            if precedes {
+                self.current = Some(i);
+            }
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        self.current.and_then(|current| self.runs[current].get())
+    }
+}
+
+fn compare(a: &LogEntry, b: &LogEntry, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Timestamp => missing_last(a.timestamp()).cmp(&missing_last(b.timestamp())),
+        SortKey::Level => missing_last(a.level().map(|level| level as i32))
+            .cmp(&missing_last(b.level().map(|level| level as i32))),
+        SortKey::Category => missing_last(a.category()).cmp(&missing_last(b.category())),
+        SortKey::Size => a.contents().len().cmp(&b.contents().len()),
+    }
+}
+
+fn missing_last<T>(value: Option<T>) -> (bool, Option<T>) {
+    (value.is_none(), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG_INPUT: &[&[u8]] = &[
+        b"-warning:<1> 2020-01-01 20:00:02.000 UTC [B]: Third",
+        b"-debug:<1> 2020-01-01 20:00:00.000 UTC [C]: First, longest",
+        b"MESSAGE Alphabet",
+        b"-warning:<1> 2020-01-01 20:00:01.000 UTC [A]: Second",
+    ];
+
+    fn sorted(key: SortKey) -> Vec<LogEntry> {
+        let mut entries: Vec<_> = LOG_INPUT
+            .iter()
+            .map(|contents| LogEntry::from_contents(contents.to_vec()))
+            .collect();
+        sort_entries(&mut entries, key);
+        entries
+    }
+
+    #[test]
+    fn sort_entries_by_timestamp() {
+        assert_eq!(
+            sorted(SortKey::Timestamp),
+            vec![LOG_INPUT[1], LOG_INPUT[3], LOG_INPUT[0], LOG_INPUT[2]]
+        );
+    }
+
+    #[test]
+    fn sort_entries_by_level() {
+        assert_eq!(
+            sorted(SortKey::Level),
+            vec![LOG_INPUT[1], LOG_INPUT[0], LOG_INPUT[3], LOG_INPUT[2]]
+        );
+    }
+
+    #[test]
+    fn sort_entries_by_category() {
+        assert_eq!(
+            sorted(SortKey::Category),
+            vec![LOG_INPUT[3], LOG_INPUT[0], LOG_INPUT[1], LOG_INPUT[2]]
+        );
+    }
+
+    #[test]
+    fn sort_entries_by_size() {
+        assert_eq!(
+            sorted(SortKey::Size),
+            vec![LOG_INPUT[2], LOG_INPUT[0], LOG_INPUT[3], LOG_INPUT[1]]
+        );
+    }
+
+    #[test]
+    fn sort_spilled_merges_runs() -> Result<()> {
+        for &key in &[
+            SortKey::Timestamp,
+            SortKey::Level,
+            SortKey::Category,
+            SortKey::Size,
+        ] {
+            for &reverse in &[false, true] {
+                let mut expected = sorted(key);
+                if reverse {
+                    expected.reverse();
+                }
+
+                let entries: Vec<_> = LOG_INPUT
+                    .iter()
+                    .map(|contents| LogEntry::from_contents([contents, &b"\n\n"[..]].concat()))
+                    .collect();
+                // runs of one or two entries
+                let runs = sort_spilled(spill_entries(&entries)?, key, 60, reverse, Rc::default())?;
+                let merged: Vec<_> = runs
+                    .map(|entry| eol::trim_eol(entry.contents()).to_vec())
+                    .cloned()
+                    .collect();
+                let expected: Vec<_> = expected
+                    .iter()
+                    .map(|entry| entry.contents().to_vec())
+                    .collect();
+                assert_eq!(merged, expected);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::log_entry::{LogEntry, LogLevel};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+// volume of entries shown during a session, e.g. while following a file
+#[derive(Debug)]
+pub struct RateSummary {
+    start: Instant,
+    total: Cell<usize>,
+    levels: Cell<[usize; LogLevel::ALL.len()]>, // indexed by level
+}
+
+impl RateSummary {
+    pub fn new() -> Self {
+        RateSummary {
+            start: Instant::now(),
+            total: Cell::new(0),
+            levels: Cell::new([0; LogLevel::ALL.len()]),
+        }
+    }
+
+    pub fn record(&self, entry: &LogEntry) {
+        if entry.is_separator() {
+            return;
+        }
+        self.total.set(self.total.get() + 1);
+        if let Some(level) = entry.level() {
+            let mut levels = self.levels.get();
+            levels[level as usize] += 1;
+            self.levels.set(levels);
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn render(&self, elapsed: Duration) -> String {
+        let total = self.total.get();
+        let secs = elapsed.as_secs_f64();
+        let rate = if secs > 0.0 { total as f64 / secs } else { 0.0 };
+        let levels = self.levels.get();
+
+        let mut summary = format!("{} entries in {:.1}s ({:.1}/s):", total, secs, rate);
+        for &level in &LogLevel::ALL {
+            summary.push_str(&format!(" {}={}", level.name(), levels[level as usize]));
+        }
+        summary
+    }
+}
+
+impl Default for RateSummary {
+    fn default() -> Self {
+        RateSummary::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_summary_render() {
+        let summary = RateSummary::new();
+        for contents in [
+            &b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: B\n\n"[..],
+            b"-critical:<1> 2020-01-01 20:00:01.000 UTC [A]: B\n\n",
+            b"-info:<1> 2020-01-01 20:00:02.000 UTC [A]: B\n\n",
+            b"No header\n\n",
+        ] {
+            summary.record(&LogEntry::from_contents(contents.to_vec()));
+        }
+        summary.record(&LogEntry::separator(b"--\n".to_vec()));
+
+        assert_eq!(
+            summary.render(Duration::from_secs(2)),
+            "4 entries in 2.0s (2.0/s): debug=0 info=2 warning=0 critical=1 fatal=0"
+        );
+        assert_eq!(
+            summary.render(Duration::from_secs(0)),
+            "4 entries in 0.0s (0.0/s): debug=0 info=2 warning=0 critical=1 fatal=0"
+        );
+    }
+}
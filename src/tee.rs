@@ -0,0 +1,91 @@
+use std::io::{self, Write};
+
+// Writes everything to both writers. The primary one (e.g. the pager) may fail, e.g. when it is
+// closed before the end of the log, without interrupting the output to the secondary one (e.g.
+// a file). Its error is kept for the caller to check at the end.
+pub struct TeeWriter<P: Write, S: Write> {
+    primary: P,
+    secondary: S,
+    primary_error: Option<io::Error>,
+}
+
+impl<P: Write, S: Write> TeeWriter<P, S> {
+    pub fn new(primary: P, secondary: S) -> Self {
+        TeeWriter {
+            primary,
+            secondary,
+            primary_error: None,
+        }
+    }
+
+    pub fn take_primary_error(&mut self) -> Option<io::Error> {
+        self.primary_error.take()
+    }
+
+    fn on_primary(&mut self, op: impl FnOnce(&mut P) -> io::Result<()>) {
+        if self.primary_error.is_none() {
+            if let Err(error) = op(&mut self.primary) {
+                self.primary_error = Some(error);
+            }
+        }
+    }
+}
+
+impl<P: Write, S: Write> Write for TeeWriter<P, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.on_primary(|primary| primary.write_all(buf));
+        self.secondary.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.on_primary(|primary| primary.flush());
+        self.secondary.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tee_writer_identical_output() -> io::Result<()> {
+        let mut primary = Vec::new();
+        let mut secondary = Vec::new();
+        let mut writer = TeeWriter::new(&mut primary, &mut secondary);
+        writer.write_all(b"-info:<1> 2020-01-01 20:00:00.000 UTC [A]: B\n\n")?;
+        writer.write_all(b"No header\n\n")?;
+        writer.flush()?;
+        assert!(writer.take_primary_error().is_none());
+
+        assert_eq!(primary, secondary);
+        assert!(primary.ends_with(b"B\n\nNo header\n\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn tee_writer_primary_error() -> io::Result<()> {
+        let mut secondary = Vec::new();
+        let mut writer = TeeWriter::new(ClosedPipe, &mut secondary);
+        writer.write_all(b"First\n\n")?;
+        writer.write_all(b"Second\n\n")?;
+        writer.flush()?;
+
+        let error = writer.take_primary_error().map(|error| error.kind());
+        assert_eq!(error, Some(io::ErrorKind::BrokenPipe));
+        assert_eq!(secondary, b"First\n\nSecond\n\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::eol::trim_eol;
+use crate::log_entry::LogEntry;
+use std::path::Path;
+
+const TIMESTAMP_FORMAT: &str = "%F %T.%3f";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Timestamp,
+    Level,
+    Category,
+    Pid,
+    Source,
+    Message,
+    Raw,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(Vec<u8>),
+    Field(Field),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(input: &str) -> Option<Template> {
+        let mut segments = Vec::new();
+        let mut literal = Vec::new();
+        let mut chars = input.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push(b'{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push(b'}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}')?;
+                    let field = match &rest[..end] {
+                        "ts" => Field::Timestamp,
+                        "level" => Field::Level,
+                        "category" => Field::Category,
+                        "pid" => Field::Pid,
+                        "source" => Field::Source,
+                        "message" => Field::Message,
+                        "raw" => Field::Raw,
+                        _ => return None,
+                    };
+                    chars = rest[end + 1..].chars();
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                '}' => return None,
+                c => {
+                    let mut buf = [0; 4];
+                    literal.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Some(Template { segments })
+    }
+
+    pub fn render(&self, entry: &LogEntry, source: Option<&Path>, fallback: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        for segment in &self.segments {
+            let value = match segment {
+                Segment::Literal(literal) => Some(literal.clone()),
+                Segment::Field(Field::Timestamp) => entry
+                    .timestamp()
+                    .map(|ts| ts.format(TIMESTAMP_FORMAT).to_string().into_bytes()),
+                Segment::Field(Field::Level) => {
+                    entry.level().map(|level| level.name().as_bytes().to_vec())
+                }
+                Segment::Field(Field::Category) => entry.category().map(<[u8]>::to_vec),
+                Segment::Field(Field::Pid) => entry.pid().map(|pid| pid.to_string().into_bytes()),
+                Segment::Field(Field::Source) => {
+                    source.map(|path| path.display().to_string().into_bytes())
+                }
+                Segment::Field(Field::Message) => {
+                    entry.message().map(|message| trim_eol(message).to_vec())
+                }
+                Segment::Field(Field::Raw) => Some(trim_eol(entry.contents()).to_vec()),
+            };
+
+            output.extend_from_slice(value.as_deref().unwrap_or(fallback));
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY: &[u8] = b"-warning:<16866> 2020-01-13 20:09:18.476 UTC [Category]: Text\n\n";
+
+    #[test]
+    fn template_all_fields() {
+        let template =
+            Template::parse("{ts} [{level}] {category} <{pid}> {source} :: {message}").unwrap();
+        let entry = LogEntry::from_contents(ENTRY.to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&template.render(&entry, Some(Path::new("a.log")), b"")),
+            "2020-01-13 20:09:18.476 [warning] Category <16866> a.log :: Text"
+        );
+    }
+
+    #[test]
+    fn template_escaped_braces() {
+        let template = Template::parse("{{{level}}} {{raw}}").unwrap();
+        let entry = LogEntry::from_contents(ENTRY.to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&template.render(&entry, None, b"")),
+            "{warning} {raw}"
+        );
+    }
+
+    #[test]
+    fn template_missing_fields_fallback() {
+        let template = Template::parse("{ts}|{category}|{raw}").unwrap();
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        assert_eq!(
+            String::from_utf8_lossy(&template.render(&entry, None, b"-")),
+            "-|-|MESSAGE Alphabet"
+        );
+    }
+
+    #[test]
+    fn template_invalid() {
+        assert_eq!(Template::parse("{unknown}"), None);
+        assert_eq!(Template::parse("{ts"), None);
+        assert_eq!(Template::parse("ts}"), None);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::cli::TransformingOptions;
+use crate::direction::Direction;
+use crate::eol;
+use crate::log_entry::{LogEntry, NextGap};
+use streaming_iterator::StreamingIterator;
+
+pub fn transforming_iter(
+    input: impl StreamingIterator<Item = LogEntry>,
+    TransformingOptions {
+        fill_timestamps,
+        annotate_next_gap,
+        ..
+    }: TransformingOptions,
+) -> impl StreamingIterator<Item = LogEntry> {
+    let filled = input.inspect({
+        let mut last_timestamp = None;
+        move |entry| {
+            if fill_timestamps {
+                match entry.timestamp() {
+                    Some(timestamp) => last_timestamp = Some(timestamp),
+                    None => {
+                        if let Some(timestamp) = last_timestamp {
+                            entry.fill_timestamp(timestamp);
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    NextGapIter::new(filled, annotate_next_gap)
+}
+
+// Joins continuations of entries read in the given direction, before they are filtered, so that
+// fragments are kept or dropped along with their entry rather than appended to another one.
+pub fn joining_iter(
+    input: impl StreamingIterator<Item = LogEntry>,
+    join_continuations: bool,
+    direction: Direction,
+) -> impl StreamingIterator<Item = LogEntry> {
+    JoinIter::new(input, join_continuations, direction)
+}
+
+// Appends entries without a header (fragments of an entry split apart) to the entry preceding
+// them in the log. Reading forward this requires looking one entry ahead, reading in reverse
+// the fragments come first and are accumulated until their entry shows up.
+struct JoinIter<I> {
+    input: I,
+    enabled: bool,
+    direction: Direction,
+    started: bool,
+    current: Option<LogEntry>,
+    lookahead: Option<LogEntry>,
+}
+
+impl<I> JoinIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    fn new(input: I, enabled: bool, direction: Direction) -> Self {
+        JoinIter {
+            input,
+            enabled,
+            direction,
+            started: false,
+            current: None,
+            lookahead: None,
+        }
+    }
+
+    fn is_fragment(entry: &LogEntry) -> bool {
+        !entry.is_separator() && entry.timestamp().is_none() && entry.level().is_none()
+    }
+
+    fn append_fragment(entry: &mut LogEntry, fragment: &LogEntry) {
+        let contents = entry.contents_mut();
+        contents.truncate(eol::trim_eol(contents).len());
+        contents.extend_from_slice(eol::EOL);
+        contents.extend_from_slice(fragment.contents());
+        entry.set_end_offset(entry.end_offset().max(fragment.end_offset()));
+    }
+}
+
+impl<I> StreamingIterator for JoinIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        if !self.enabled {
+            self.input.advance();
+            return;
+        }
+
+        self.current = match self.direction {
+            Direction::Forward => {
+                if !self.started {
+                    self.started = true;
+                    self.lookahead = self.input.next().cloned();
+                }
+
+                let mut current = self.lookahead.take();
+                if let Some(current) = &mut current {
+                    while let Some(next) = self.input.next() {
+                        if Self::is_fragment(next) {
+                            Self::append_fragment(current, next);
+                        } else {
+                            self.lookahead = Some(next.clone());
+                            break;
+                        }
+                    }
+                }
+                current
+            }
+            Direction::Reverse => {
+                let mut current = self.input.next().cloned();
+                while let Some(fragment) = current.take() {
+                    if !Self::is_fragment(&fragment) {
+                        current = Some(fragment);
+                        break;
+                    }
+                    match self.input.next() {
+                        Some(previous) => {
+                            let mut previous = previous.clone();
+                            Self::append_fragment(&mut previous, &fragment);
+                            current = Some(previous);
+                        }
+                        None => {
+                            current = Some(fragment);
+                            break;
+                        }
+                    }
+                }
+                current
+            }
+        };
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.enabled {
+            self.current.as_ref()
+        } else {
+            self.input.get()
+        }
+    }
+}
+
+// Annotates every entry with the time until the following one, which requires reading one entry
+// ahead. Entries are passed through untouched when disabled.
+struct NextGapIter<I> {
+    input: I,
+    enabled: bool,
+    started: bool,
+    current: Option<LogEntry>,
+    lookahead: Option<LogEntry>,
+}
+
+impl<I> NextGapIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    fn new(input: I, enabled: bool) -> Self {
+        NextGapIter {
+            input,
+            enabled,
+            started: false,
+            current: None,
+            lookahead: None,
+        }
+    }
+}
+
+impl<I> StreamingIterator for NextGapIter<I>
+where
+    I: StreamingIterator<Item = LogEntry>,
+{
+    type Item = LogEntry;
+
+    fn advance(&mut self) {
+        if !self.enabled {
+            self.input.advance();
+            return;
+        }
+
+        if !self.started {
+            self.started = true;
+            self.lookahead = self.input.next().cloned();
+        }
+
+        self.current = self.lookahead.take();
+        self.lookahead = self.input.next().cloned();
+
+        if let Some(current) = &mut self.current {
+            let next_gap = match &self.lookahead {
+                Some(next) => match (current.timestamp(), next.timestamp()) {
+                    (Some(timestamp), Some(next_timestamp)) => {
+                        Some(NextGap::Gap(next_timestamp - timestamp))
+                    }
+                    _ => None,
+                },
+                None => Some(NextGap::Last),
+            };
+            current.set_next_gap(next_gap);
+        }
+    }
+
+    fn get(&self) -> Option<&Self::Item> {
+        if self.enabled {
+            self.current.as_ref()
+        } else {
+            self.input.get()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    const LOG_INPUT: &[&[u8]] = &[
+        b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1",
+        b"  at frame1",
+        b"-info:<16866> 2020-01-01 21:00:00.000 UTC [A]: Text2",
+        b"  at frame2",
+    ];
+
+    fn to_log_iter(
+        input_iter: impl Iterator<Item = &'static [u8]>,
+    ) -> impl StreamingIterator<Item = LogEntry> {
+        streaming_iterator::convert(input_iter.map(|lines| LogEntry::from_contents(lines.to_vec())))
+    }
+
+    #[test]
+    fn transforming_iter_fill_timestamps() {
+        let log_iter = to_log_iter(LOG_INPUT.iter().copied());
+        let log_iter = transforming_iter(
+            log_iter,
+            TransformingOptions {
+                fill_timestamps: true,
+                ..Default::default()
+            },
+        );
+
+        let timestamps: Vec<_> = log_iter.map(|entry| entry.timestamp()).owned().collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(20, 0, 0)),
+                Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(20, 0, 0)),
+                Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(21, 0, 0)),
+                Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(21, 0, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn transforming_iter_no_fill_timestamps() {
+        let log_iter = to_log_iter(LOG_INPUT.iter().copied());
+        let log_iter = transforming_iter(log_iter, TransformingOptions::default());
+
+        let timestamps: Vec<_> = log_iter.map(|entry| entry.timestamp()).owned().collect();
+        assert_eq!(timestamps[1], None);
+        assert_eq!(timestamps[3], None);
+    }
+
+    #[test]
+    fn transforming_iter_annotate_next_gap() {
+        let input: &[&[u8]] = &[
+            b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1",
+            b"-info:<16866> 2020-01-01 20:00:01.250 UTC [A]: Text2",
+            b"-info:<16866> 2020-01-01 20:01:01.250 UTC [A]: Text3",
+            b"-info:<16866> 2020-01-01 20:01:01.250 UTC [A]: Text4",
+        ];
+        let log_iter = to_log_iter(input.iter().copied());
+        let log_iter = transforming_iter(
+            log_iter,
+            TransformingOptions {
+                annotate_next_gap: true,
+                ..Default::default()
+            },
+        );
+
+        let next_gaps: Vec<_> = log_iter.map(|entry| entry.next_gap()).owned().collect();
+        assert_eq!(
+            next_gaps,
+            vec![
+                Some(NextGap::Gap(Duration::milliseconds(1250))),
+                Some(NextGap::Gap(Duration::seconds(60))),
+                Some(NextGap::Gap(Duration::zero())),
+                Some(NextGap::Last),
+            ]
+        );
+    }
+
+    #[test]
+    fn transforming_iter_annotate_next_gap_without_timestamp() {
+        let log_iter = to_log_iter(LOG_INPUT.iter().copied());
+        let log_iter = transforming_iter(
+            log_iter,
+            TransformingOptions {
+                annotate_next_gap: true,
+                ..Default::default()
+            },
+        );
+
+        let next_gaps: Vec<_> = log_iter.map(|entry| entry.next_gap()).owned().collect();
+        assert_eq!(next_gaps, vec![None, None, None, Some(NextGap::Last)]);
+    }
+
+    const FRAGMENTED_INPUT: &[&[u8]] = &[
+        b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1\n\n",
+        b"-critical:<16866> 2020-01-01 21:00:00.000 UTC [A]: Exception\n\n",
+        b"  at frame1\n\n",
+        b"  at frame2\n\n",
+        b"-info:<16866> 2020-01-01 22:00:00.000 UTC [A]: Text2\n\n",
+    ];
+
+    const JOINED_OUTPUT: &[&[u8]] = &[
+        b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Text1\n\n",
+        b"-critical:<16866> 2020-01-01 21:00:00.000 UTC [A]: Exception\n  at frame1\n  at frame2\n\n",
+        b"-info:<16866> 2020-01-01 22:00:00.000 UTC [A]: Text2\n\n",
+    ];
+
+    #[test]
+    fn joining_iter_join_continuations() {
+        let log_iter = to_log_iter(FRAGMENTED_INPUT.iter().copied());
+        let log_iter = joining_iter(log_iter, true, Direction::Forward);
+
+        assert_eq!(log_iter.owned().collect::<Vec<_>>(), JOINED_OUTPUT.to_vec());
+    }
+
+    #[test]
+    fn joining_iter_join_continuations_rev() {
+        let log_iter = to_log_iter(FRAGMENTED_INPUT.iter().copied().rev());
+        let log_iter = joining_iter(log_iter, true, Direction::Reverse);
+
+        assert_eq!(
+            log_iter.owned().collect::<Vec<_>>(),
+            JOINED_OUTPUT.iter().copied().rev().collect::<Vec<_>>()
+        );
+    }
+}
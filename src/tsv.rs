@@ -0,0 +1,115 @@
+use crate::cli::OutputOptions;
+use crate::eol::trim_eol;
+use crate::formatting::decode_escapes;
+use crate::log_entry::LogEntry;
+use crate::result::Result;
+use std::io::Write;
+use std::path::Path;
+
+const TIMESTAMP_FORMAT: &str = "%F %T.%3f";
+
+pub fn write_entry(
+    entry: &LogEntry,
+    source: Option<&Path>,
+    writer: &mut impl Write,
+    opts: &OutputOptions,
+) -> Result<()> {
+    if let Some(timestamp) = entry.timestamp() {
+        write!(writer, "{}", timestamp.format(TIMESTAMP_FORMAT))?;
+    }
+    writer.write_all(b"\t")?;
+
+    if let Some(level) = entry.level() {
+        writer.write_all(level.name().as_bytes())?;
+    }
+    writer.write_all(b"\t")?;
+
+    if let Some(category) = entry.category() {
+        write_field(category, writer)?;
+    }
+    writer.write_all(b"\t")?;
+
+    if let Some(pid) = entry.pid() {
+        write!(writer, "{}", pid)?;
+    }
+    writer.write_all(b"\t")?;
+
+    if let Some(source) = source {
+        write_field(source.display().to_string().as_bytes(), writer)?;
+        writer.write_all(b"\t")?;
+    }
+
+    let message = entry.message().unwrap_or_else(|| entry.contents());
+    let mut formatted = Vec::with_capacity(message.len());
+    decode_escapes(
+        message,
+        &mut formatted,
+        opts.escape_mode,
+        &opts.escape_table,
+        b"\n",
+        b"",
+    )?;
+    write_field(trim_eol(&formatted), writer)?;
+
+    Ok(())
+}
+
+// escapes characters which would break the line/column structure instead of quoting
+fn write_field(value: &[u8], writer: &mut impl Write) -> Result<()> {
+    let mut start = 0;
+    for (pos, c) in value.iter().enumerate() {
+        let escaped: &[u8] = match c {
+            b'\t' => b"\\t",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\\' => b"\\\\",
+            _ => continue,
+        };
+        writer.write_all(&value[start..pos])?;
+        writer.write_all(escaped)?;
+        start = pos + 1;
+    }
+    writer.write_all(&value[start..])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::EscapeMode;
+
+    fn to_tsv(entry: &LogEntry, escape_mode: EscapeMode) -> String {
+        let opts = OutputOptions {
+            escape_mode,
+            ..Default::default()
+        };
+        let mut out_buf = Vec::new();
+        write_entry(entry, None, &mut out_buf, &opts).unwrap();
+        String::from_utf8(out_buf).unwrap()
+    }
+
+    #[test]
+    fn write_entry_escaped_tab() {
+        let entry = LogEntry::from_contents(
+            b"-info:<16866> 2020-01-01 20:00:00.000 UTC [A]: Key\tvalue\\tescaped\nnext\n\n"
+                .to_vec(),
+        );
+        assert_eq!(
+            to_tsv(&entry, EscapeMode::Standard),
+            "2020-01-01 20:00:00.000\tinfo\tA\t16866\tKey\\tvalue\\tescaped\\nnext"
+        );
+        assert_eq!(
+            to_tsv(&entry, EscapeMode::None),
+            "2020-01-01 20:00:00.000\tinfo\tA\t16866\tKey\\tvalue\\\\tescaped\\nnext"
+        );
+    }
+
+    #[test]
+    fn write_entry_no_header() {
+        let entry = LogEntry::from_contents(b"MESSAGE Alphabet\n\n".to_vec());
+        assert_eq!(
+            to_tsv(&entry, EscapeMode::Standard),
+            "\t\t\t\tMESSAGE Alphabet"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+
+const ENTER_SCREEN: &[u8] = b"\x1B[?1049h\x1B[?25l"; // alternate screen, hidden cursor
+const LEAVE_SCREEN: &[u8] = b"\x1B[?25h\x1B[?1049l";
+const DEFAULT_SIZE: (usize, usize) = (80, 24);
+
+// The controlling terminal, also when standard input or output are redirected, switched to
+// reading single keystrokes without echo on an alternate screen until dropped.
+pub struct RawTerminal {
+    tty: File,
+    saved: libc::termios,
+}
+
+impl RawTerminal {
+    pub fn open() -> io::Result<Self> {
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let fd = tty.as_raw_fd();
+
+        let mut saved = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = saved;
+        // Ctrl-C is read as a key instead of interrupting, while output processing is kept
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut terminal = RawTerminal { tty, saved };
+        terminal.tty.write_all(ENTER_SCREEN)?;
+        Ok(terminal)
+    }
+
+    // columns and rows
+    pub fn size(&self) -> (usize, usize) {
+        let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+        let result = unsafe { libc::ioctl(self.tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+        if result != 0 || size.ws_col == 0 || size.ws_row == 0 {
+            DEFAULT_SIZE
+        } else {
+            (size.ws_col as usize, size.ws_row as usize)
+        }
+    }
+}
+
+impl Read for RawTerminal {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.tty.read(buf)
+    }
+}
+
+impl Write for RawTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tty.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.tty.flush()
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = self.tty.write_all(LEAVE_SCREEN);
+        unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.saved) };
+    }
+}
@@ -0,0 +1,155 @@
+use crate::histogram::{bucket_start, fill_buckets};
+use crate::log_entry::{LogEntry, LogLevel};
+use chrono::{Duration, NaiveDateTime};
+use std::collections::BTreeMap;
+use std::fmt;
+use streaming_iterator::StreamingIterator;
+
+const TIMESTAMP_FORMAT: &str = "%F %T%.3f";
+
+// entries without a level are counted only in the total
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BucketStats {
+    pub total: usize,
+    pub levels: [usize; LogLevel::ALL.len()], // indexed by level
+}
+
+impl BucketStats {
+    pub fn level(&self, level: LogLevel) -> usize {
+        self.levels[level as usize]
+    }
+}
+
+// numbers of entries of each level in consecutive time intervals aligned to multiples of
+// the interval length
+pub struct WindowStats {
+    interval_ms: i64,
+    buckets: BTreeMap<i64, BucketStats>,
+}
+
+impl WindowStats {
+    pub fn count(
+        mut log_entries: impl StreamingIterator<Item = LogEntry>,
+        interval: Duration,
+    ) -> Self {
+        let interval_ms = interval.num_milliseconds().max(1);
+        let mut buckets = BTreeMap::new();
+
+        while let Some(entry) = log_entries.next() {
+            if let Some(timestamp) = entry.timestamp() {
+                let stats: &mut BucketStats = buckets
+                    .entry(bucket_start(timestamp, interval_ms))
+                    .or_default();
+                stats.total += 1;
+                if let Some(level) = entry.level() {
+                    stats.levels[level as usize] += 1;
+                }
+            }
+        }
+
+        WindowStats {
+            interval_ms,
+            buckets,
+        }
+    }
+
+    pub fn buckets(&self) -> Vec<(NaiveDateTime, BucketStats)> {
+        fill_buckets(&self.buckets, self.interval_ms)
+    }
+}
+
+impl fmt::Display for WindowStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let buckets = self.buckets();
+        let max_total = buckets.iter().map(|(_, stats)| stats.total).max();
+        let count_width = max_total.unwrap_or(0).to_string().len();
+
+        for (start, stats) in buckets {
+            write!(
+                f,
+                "{} | total={:>width$}",
+                start.format(TIMESTAMP_FORMAT),
+                stats.total,
+                width = count_width
+            )?;
+            for &level in &LogLevel::ALL {
+                write!(
+                    f,
+                    " {}={:>width$}",
+                    level.name(),
+                    stats.level(level),
+                    width = count_width
+                )?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const LOG_INPUT: &[&[u8]] = &[
+        b"-info:<1> 2020-01-01 20:00:05.000 UTC [A]: Text1",
+        b"-warning:<1> 2020-01-01 20:00:10.000 UTC [A]: Text2",
+        b"-critical:<1> 2020-01-01 20:00:59.999 UTC [A]: Text3",
+        b"  continuation without timestamp",
+        b"-info:<1> 2020-01-01 20:01:00.000 UTC [A]: Text4",
+        b"-info:<1> 2020-01-01 20:01:30.000 UTC [A]: Text5",
+        b"-x:<1> 2020-01-01 20:01:31.000 UTC [A]: Text6",
+    ];
+
+    fn to_log_iter() -> impl StreamingIterator<Item = LogEntry> {
+        streaming_iterator::convert(
+            LOG_INPUT
+                .iter()
+                .map(|lines| LogEntry::from_contents(lines.to_vec())),
+        )
+    }
+
+    #[test]
+    fn window_stats_level_counts() {
+        let stats = WindowStats::count(to_log_iter(), Duration::minutes(1));
+        let minute = |min| NaiveDate::from_ymd(2020, 1, 1).and_hms(20, min, 0);
+        assert_eq!(
+            stats.buckets(),
+            vec![
+                (
+                    minute(0),
+                    BucketStats {
+                        total: 3,
+                        levels: [0, 1, 1, 1, 0]
+                    }
+                ),
+                (
+                    minute(1),
+                    BucketStats {
+                        total: 3,
+                        levels: [0, 2, 0, 0, 0]
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_stats_display() {
+        let stats = WindowStats::count(to_log_iter(), Duration::minutes(1));
+        assert_eq!(
+            stats.to_string(),
+            "2020-01-01 20:00:00.000 | total=3 debug=0 info=1 warning=1 critical=1 fatal=0\n\
+             2020-01-01 20:01:00.000 | total=3 debug=0 info=2 warning=0 critical=0 fatal=0\n"
+        );
+    }
+
+    #[test]
+    fn window_stats_empty() {
+        let stats = WindowStats::count(streaming_iterator::empty(), Duration::minutes(1));
+        assert!(stats.buckets().is_empty());
+        assert_eq!(stats.to_string(), "");
+    }
+}
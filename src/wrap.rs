@@ -0,0 +1,192 @@
+use crate::eol;
+use std::io::{self, Write};
+
+const ESC: u8 = 0x1B;
+const CODE_NORMAL: &[u8] = b"\x1B[0m";
+const TAB_WIDTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Text,
+    Escape,   // after ESC
+    Sequence, // after ESC [, until the final byte
+}
+
+// Hard-wraps lines longer than given number of columns, e.g. for archiving in fixed width. Escape
+// sequences take no columns and continuations of UTF-8 sequences are not split from their first
+// byte, unless every byte is a character (Latin-1). Colors set by SGR sequences on a wrapped line
+// are reset before the inserted line break and set again after it, like on every output line.
+pub struct WrappingWriter<W: Write> {
+    inner: W,
+    columns: Option<usize>,
+    single_byte_chars: bool,
+    column: usize,
+    state: State,
+    sequence: Vec<u8>, // escape sequence being written
+    style: Vec<u8>,    // SGR sequences written since the last reset
+}
+
+impl<W: Write> WrappingWriter<W> {
+    pub fn new(inner: W, columns: Option<usize>) -> Self {
+        WrappingWriter {
+            inner,
+            columns,
+            single_byte_chars: false,
+            column: 0,
+            state: State::Text,
+            sequence: Vec::new(),
+            style: Vec::new(),
+        }
+    }
+
+    // counts every byte as a character, e.g. for Latin-1 input
+    pub fn with_single_byte_chars(mut self, single_byte_chars: bool) -> Self {
+        self.single_byte_chars = single_byte_chars;
+        self
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    // tracks the byte, returning whether a line break has to be inserted before it
+    fn needs_break_before(&mut self, c: u8, columns: usize) -> bool {
+        match self.state {
+            State::Escape => {
+                self.sequence.push(c);
+                self.state = if c == b'[' {
+                    State::Sequence
+                } else {
+                    State::Text
+                };
+                false
+            }
+            State::Sequence => {
+                self.sequence.push(c);
+                if (0x40..=0x7E).contains(&c) {
+                    self.state = State::Text;
+                    if c == b'm' {
+                        self.record_style();
+                    }
+                }
+                false
+            }
+            State::Text => match c {
+                ESC => {
+                    self.state = State::Escape;
+                    self.sequence.clear();
+                    self.sequence.push(c);
+                    false
+                }
+                b'\n' | b'\r' => {
+                    self.column = 0;
+                    false
+                }
+                0x80..=0xBF if !self.single_byte_chars => false,
+                _ => {
+                    let width = if c == b'\t' {
+                        TAB_WIDTH - self.column % TAB_WIDTH
+                    } else {
+                        1
+                    };
+                    let wrap = self.column > 0 && self.column + width > columns;
+                    if wrap {
+                        self.column = 0;
+                    }
+                    self.column += width;
+                    wrap
+                }
+            },
+        }
+    }
+
+    fn record_style(&mut self) {
+        let params = &self.sequence[2..self.sequence.len() - 1];
+        let is_reset = params
+            .split(|&c| c == b';')
+            .all(|param| matches!(param, b"" | b"0"));
+        if is_reset {
+            self.style.clear();
+        } else {
+            self.style.extend_from_slice(&self.sequence);
+        }
+    }
+
+    fn write_break(&mut self) -> io::Result<()> {
+        if !self.style.is_empty() {
+            self.inner.write_all(CODE_NORMAL)?;
+        }
+        self.inner.write_all(eol::EOL)?;
+        self.inner.write_all(&self.style)
+    }
+}
+
+impl<W: Write> Write for WrappingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let columns = match self.columns {
+            Some(columns) => columns,
+            None => return self.inner.write(buf),
+        };
+
+        let mut start = 0;
+        for (pos, &c) in buf.iter().enumerate() {
+            if self.needs_break_before(c, columns) {
+                self.inner.write_all(&buf[start..pos])?;
+                self.write_break()?;
+                start = pos;
+            }
+        }
+        self.inner.write_all(&buf[start..])?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(chunks: &[&[u8]], columns: usize) -> io::Result<String> {
+        let mut out_buf = Vec::new();
+        let mut writer = WrappingWriter::new(&mut out_buf, Some(columns));
+        for chunk in chunks {
+            writer.write_all(chunk)?;
+        }
+        Ok(String::from_utf8(out_buf).expect("Invalid UTF-8 output"))
+    }
+
+    #[test]
+    fn wrapping_writer_long_line() -> io::Result<()> {
+        assert_eq!(
+            wrap(&[b"-info:<1> [A]: ", b"abcdefghij\n\nshort\n"], 10)?,
+            "-info:<1> \n[A]: abcde\nfghij\n\nshort\n"
+        );
+        assert_eq!(wrap(&[b"exactly 10\n"], 10)?, "exactly 10\n");
+        Ok(())
+    }
+
+    #[test]
+    fn wrapping_writer_multibyte_and_colors() -> io::Result<()> {
+        assert_eq!(
+            wrap(&["zażółć gęślą".as_bytes(), b"\n"], 5)?,
+            "zażół\nć gęś\nlą\n"
+        );
+        assert_eq!(
+            wrap(&[b"\x1B[33mwarning\x1B[0m\n"], 4)?,
+            "\x1B[33mwarn\x1B[0m\n\x1B[33ming\x1B[0m\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wrapping_writer_single_byte_chars() -> io::Result<()> {
+        let mut out_buf = Vec::new();
+        let mut writer = WrappingWriter::new(&mut out_buf, Some(2)).with_single_byte_chars(true);
+        writer.write_all(b"\xA9 caf\xE9\n")?;
+        assert_eq!(out_buf, b"\xA9 \nca\nf\xE9\n");
+        Ok(())
+    }
+}